@@ -9,4 +9,31 @@ cfg_alloc! {
     pub fn ax_dealloc(ptr: NonNull<u8>, layout: Layout) {
         axalloc::global_allocator().dealloc(ptr, layout)
     }
+
+    pub use axalloc::OomPolicy as AxOomPolicy;
+
+    pub fn ax_register_reclaimer(f: fn(usize) -> usize) -> crate::AxResult {
+        axalloc::register_reclaimer(f).map_err(|_| axerrno::AxError::NoMemory)
+    }
+
+    pub fn ax_set_oom_policy(policy: AxOomPolicy) {
+        axalloc::set_oom_policy(policy)
+    }
+}
+
+cfg_alloc_stats! {
+    pub use axalloc::AllocStats as AxAllocStats;
+    pub use axalloc::LiveAlloc as AxLiveAlloc;
+
+    pub fn ax_alloc_stats() -> AxAllocStats {
+        axalloc::alloc_stats()
+    }
+
+    pub fn ax_set_alloc_tracking(enable: bool) {
+        axalloc::set_live_tracking(enable)
+    }
+
+    pub fn ax_live_allocs() -> alloc::vec::Vec<AxLiveAlloc> {
+        axalloc::live_allocs()
+    }
 }
@@ -37,6 +37,5 @@ pub use self::mem::*;
 pub use self::stdio::*;
 pub use self::task::*;
 
-pub use axhal::misc::terminate as ax_terminate;
 pub use axhal::time::{current_time as ax_current_time, TimeValue as AxTimeValue};
 pub use axio::PollState as AxPollState;
@@ -16,7 +16,22 @@ pub fn ax_yield_now() {
     }
 }
 
+/// Writes the modified blocks in the cache of the filesystems back, before
+/// the current task or the whole system exits.
+fn sync_before_exit() {
+    #[cfg(feature = "fs")]
+    if let Err(e) = axfs::sync() {
+        axlog::warn!("failed to sync filesystems: {:?}", e);
+    }
+}
+
+pub fn ax_terminate() -> ! {
+    sync_before_exit();
+    axhal::misc::terminate()
+}
+
 pub fn ax_exit(_exit_code: i32) -> ! {
+    sync_before_exit();
     #[cfg(feature = "multitask")]
     axtask::exit(_exit_code);
     #[cfg(not(feature = "multitask"))]
@@ -26,6 +41,10 @@ pub fn ax_exit(_exit_code: i32) -> ! {
 cfg_task! {
     use core::time::Duration;
 
+    pub use axtask::CpuMask as AxCpuMask;
+    pub use axtask::TaskInfo as AxTaskInfo;
+    pub use axtask::TaskStats as AxTaskStats;
+
     /// A handle to a task.
     pub struct AxTaskHandle {
         inner: axtask::AxTaskRef,
@@ -67,10 +86,46 @@ cfg_task! {
         }
     }
 
+    pub fn ax_spawn_with_affinity<F>(
+        f: F,
+        name: alloc::string::String,
+        stack_size: usize,
+        cpumask: AxCpuMask,
+    ) -> AxTaskHandle
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let inner = axtask::spawn_raw_with_affinity(f, name, stack_size, cpumask);
+        AxTaskHandle {
+            id: inner.id().as_u64(),
+            inner,
+        }
+    }
+
     pub fn ax_wait_for_exit(task: AxTaskHandle) -> Option<i32> {
         task.inner.join()
     }
 
+    pub fn ax_current_task_stats() -> AxTaskStats {
+        axtask::current().stats()
+    }
+
+    pub fn ax_task_stats(task: &AxTaskHandle) -> AxTaskStats {
+        task.inner.stats()
+    }
+
+    pub fn ax_cpu_stats(cpu_id: usize) -> Option<AxTaskStats> {
+        axtask::cpu_stats(cpu_id)
+    }
+
+    pub fn ax_task_list() -> alloc::vec::Vec<AxTaskInfo> {
+        axtask::all_tasks().iter().map(|t| t.info()).collect()
+    }
+
+    pub fn ax_task_info(id: u64) -> Option<AxTaskInfo> {
+        axtask::find_task(id).map(|t| t.info())
+    }
+
     pub fn ax_set_current_priority(prio: isize) -> crate::AxResult {
         if axtask::set_priority(prio) {
             Ok(())
@@ -82,6 +137,17 @@ cfg_task! {
         }
     }
 
+    pub fn ax_set_current_affinity(cpumask: AxCpuMask) -> crate::AxResult {
+        if axtask::set_current_affinity(cpumask) {
+            Ok(())
+        } else {
+            axerrno::ax_err!(
+                InvalidInput,
+                "ax_set_current_affinity: empty CPU affinity"
+            )
+        }
+    }
+
     pub fn ax_wait_queue_wait(
         wq: &AxWaitQueueHandle,
         until_condition: impl Fn() -> bool,
@@ -64,6 +64,41 @@ pub mod mem {
         /// `layout`, which should be allocated by [`ax_alloc`].
         pub fn ax_dealloc(ptr: NonNull<u8>, layout: Layout);
     }
+
+    define_api_type! {
+        @cfg "alloc";
+        pub type AxOomPolicy;
+    }
+
+    define_api! {
+        @cfg "alloc";
+        /// Registers a callback to free memory (e.g., by dropping caches)
+        /// when the memory is exhausted. It takes the number of bytes
+        /// requested and returns the number of bytes freed.
+        pub fn ax_register_reclaimer(f: fn(usize) -> usize) -> crate::AxResult;
+        /// Sets what to do when an allocation fails even after reclaiming.
+        pub fn ax_set_oom_policy(policy: AxOomPolicy);
+    }
+
+    define_api_type! {
+        @cfg "alloc-stats";
+        pub type AxAllocStats;
+        pub type AxLiveAlloc;
+    }
+
+    define_api! {
+        @cfg "alloc-stats";
+        /// Returns the statistics of the global allocator, which can be
+        /// printed in the format of `/proc/meminfo`.
+        pub fn ax_alloc_stats() -> AxAllocStats;
+        /// Enables or disables tracking of live allocations. The records are
+        /// cleared when it's disabled.
+        pub fn ax_set_alloc_tracking(enable: bool);
+        /// Returns the allocations made since tracking is enabled that are
+        /// not deallocated yet, tagged with the IDs of the tasks that made
+        /// them.
+        pub fn ax_live_allocs() -> alloc::vec::Vec<AxLiveAlloc>;
+    }
 }
 
 /// Standard input and output.
@@ -85,6 +120,9 @@ pub mod task {
         @cfg "multitask";
         pub type AxTaskHandle;
         pub type AxWaitQueueHandle;
+        pub type AxCpuMask;
+        pub type AxTaskStats;
+        pub type AxTaskInfo;
     }
 
     define_api! {
@@ -114,11 +152,35 @@ pub mod task {
             name: alloc::string::String,
             stack_size: usize
         ) -> AxTaskHandle;
+        /// Spawns a new task which is only allowed to run on the given CPUs.
+        pub fn ax_spawn_with_affinity(
+            f: impl FnOnce() + Send + 'static,
+            name: alloc::string::String,
+            stack_size: usize,
+            cpumask: AxCpuMask,
+        ) -> AxTaskHandle;
         /// Waits for the given task to exit, and returns its exit code (the
         /// argument of [`ax_exit`]).
         pub fn ax_wait_for_exit(task: AxTaskHandle) -> Option<i32>;
+        /// Returns the scheduling statistics of the current task.
+        pub fn ax_current_task_stats() -> AxTaskStats;
+        /// Returns the scheduling statistics of the given task.
+        pub fn ax_task_stats(task: &AxTaskHandle) -> AxTaskStats;
+        /// Returns the scheduling statistics of all tasks that ran on the
+        /// given CPU, or [`None`] if the CPU is not online.
+        pub fn ax_cpu_stats(cpu_id: usize) -> Option<AxTaskStats>;
+        /// Returns the information of all tasks that are not dropped yet, in
+        /// the order of task IDs.
+        pub fn ax_task_list() -> alloc::vec::Vec<AxTaskInfo>;
+        /// Returns the information of the task with the given ID.
+        pub fn ax_task_info(id: u64) -> Option<AxTaskInfo>;
         /// Sets the priority of the current task.
         pub fn ax_set_current_priority(prio: isize) -> crate::AxResult;
+        /// Sets the CPU affinity of the current task.
+        ///
+        /// If the current CPU is not allowed, the current task will be
+        /// migrated to another CPU immediately.
+        pub fn ax_set_current_affinity(cpumask: AxCpuMask) -> crate::AxResult;
 
         /// Blocks the current task and put it into the wait queue, until the
         /// given condition becomes true, or the the given duration has elapsed
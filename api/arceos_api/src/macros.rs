@@ -62,6 +62,10 @@ macro_rules! cfg_alloc {
     ($($item:item)*) => { _cfg_common!{ "alloc" $($item)* } }
 }
 
+macro_rules! cfg_alloc_stats {
+    ($($item:item)*) => { _cfg_common!{ "alloc-stats" $($item)* } }
+}
+
 macro_rules! cfg_fs {
     ($($item:item)*) => { _cfg_common!{ "fs" $($item)* } }
 }
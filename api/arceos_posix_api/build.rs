@@ -1,18 +1,15 @@
 fn main() {
     use std::io::Write;
 
-    fn gen_pthread_mutex(out_file: &str) -> std::io::Result<()> {
-        // TODO: generate size and initial content automatically.
-        let (mutex_size, mutex_init) = if cfg!(feature = "multitask") {
-            if cfg!(feature = "smp") {
-                (6, "{0, 8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 6]>(axsync::Mutex::new(()))
-            } else {
-                (5, "{8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 5]>(axsync::Mutex::new(()))
-            }
-        } else {
-            (1, "{0}")
-        };
-
+    /// Generates a C header defining the opaque type `ty_name` with the same
+    /// size as the corresponding Rust type, and optionally a static
+    /// initializer macro `init_name`.
+    fn gen_sync_type(
+        out_file: &str,
+        ty_name: &str,
+        size: usize,
+        init: Option<(&str, &str)>,
+    ) -> std::io::Result<()> {
         let mut output = Vec::new();
         writeln!(
             output,
@@ -22,16 +19,86 @@ fn main() {
             output,
             r#"
 typedef struct {{
-    long __l[{mutex_size}];
-}} pthread_mutex_t;
-
-#define PTHREAD_MUTEX_INITIALIZER {{ .__l = {mutex_init}}}
+    long __l[{size}];
+}} {ty_name};
 "#
         )?;
+        if let Some((init_name, init_value)) = init {
+            writeln!(output, "#define {init_name} {{ .__l = {init_value}}}")?;
+        }
         std::fs::write(out_file, output)?;
         Ok(())
     }
 
+    fn gen_sync_types() -> std::io::Result<()> {
+        // TODO: generate size and initial content automatically.
+        //
+        // `WaitQueue` takes 5 words with `smp` and 4 words without it. Mutexes,
+        // condition variables, reader-writer locks and semaphores add one
+        // word after it, and barriers add three.
+        //
+        // Mutexes, condition variables and reader-writer locks are wrapped in
+        // `ZeroInit`, which adds one word and makes all zeros a valid
+        // initializer. Mutexes also add one word to tell whether priority
+        // inheritance is enabled.
+        let (mutex_size, mutex_init) = if cfg!(feature = "multitask") {
+            if cfg!(feature = "smp") {
+                (8, "{0}")
+            } else {
+                (7, "{0}")
+            }
+        } else {
+            (1, "{0}")
+        };
+        let (lock_size, barrier_size) = if cfg!(feature = "multitask") {
+            if cfg!(feature = "smp") {
+                (6, 8)
+            } else {
+                (5, 7)
+            }
+        } else {
+            (1, 1)
+        };
+        let zero_init_size = if cfg!(feature = "multitask") {
+            lock_size + 1
+        } else {
+            lock_size
+        };
+
+        let include_dir = "../../ulib/axlibc/include";
+        gen_sync_type(
+            &format!("{include_dir}/ax_pthread_mutex.h"),
+            "pthread_mutex_t",
+            mutex_size,
+            Some(("PTHREAD_MUTEX_INITIALIZER", mutex_init)),
+        )?;
+        gen_sync_type(
+            &format!("{include_dir}/ax_pthread_cond.h"),
+            "pthread_cond_t",
+            zero_init_size,
+            Some(("PTHREAD_COND_INITIALIZER", "{0}")),
+        )?;
+        gen_sync_type(
+            &format!("{include_dir}/ax_pthread_rwlock.h"),
+            "pthread_rwlock_t",
+            zero_init_size,
+            Some(("PTHREAD_RWLOCK_INITIALIZER", "{0}")),
+        )?;
+        gen_sync_type(
+            &format!("{include_dir}/ax_pthread_barrier.h"),
+            "pthread_barrier_t",
+            barrier_size,
+            None,
+        )?;
+        gen_sync_type(
+            &format!("{include_dir}/ax_semaphore.h"),
+            "sem_t",
+            lock_size,
+            None,
+        )?;
+        Ok(())
+    }
+
     fn gen_c_to_rust_bindings(in_file: &str, out_file: &str) {
         println!("cargo:rerun-if-changed={in_file}");
 
@@ -48,10 +115,20 @@ typedef struct {{
             "pthread_attr_t",
             "pthread_mutex_t",
             "pthread_mutexattr_t",
+            "pthread_cond_t",
+            "pthread_condattr_t",
+            "pthread_rwlock_t",
+            "pthread_rwlockattr_t",
+            "pthread_barrier_t",
+            "pthread_barrierattr_t",
+            "sem_t",
+            "cpu_set_t",
+            "sched_param",
             "epoll_event",
             "iovec",
             "clockid_t",
             "rlimit",
+            "rusage",
             "aibuf",
         ];
         let allow_vars = [
@@ -61,12 +138,24 @@ typedef struct {{
             "IPPROTO_.*",
             "FD_.*",
             "F_.*",
+            "AT_.*",
+            "UTIME_.*",
             "_SC_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
             "RLIMIT_.*",
+            "RUSAGE_.*",
+            "CLOCK_.*",
             "EAI_.*",
             "MAXADDRS",
+            "PTHREAD_BARRIER_SERIAL_THREAD",
+            "PTHREAD_PRIO_.*",
+            "SCHED_.*",
+            "PROT_.*",
+            "MAP_.*",
+            "MS_.*",
+            "MNT_.*",
+            "MADV_.*",
         ];
 
         #[derive(Debug)]
@@ -74,7 +163,9 @@ typedef struct {{
 
         impl bindgen::callbacks::ParseCallbacks for MyCallbacks {
             fn include_file(&self, fname: &str) {
-                if !fname.contains("ax_pthread_mutex.h") {
+                // Skip the headers generated by `gen_sync_types`.
+                let name = fname.rsplit('/').next().unwrap_or(fname);
+                if !name.starts_with("ax_pthread_") && name != "ax_semaphore.h" {
                     println!("cargo:rerun-if-changed={}", fname);
                 }
             }
@@ -101,6 +192,6 @@ typedef struct {{
             .expect("Couldn't write bindings!");
     }
 
-    gen_pthread_mutex("../../ulib/axlibc/include/ax_pthread_mutex.h").unwrap();
+    gen_sync_types().unwrap();
     gen_c_to_rust_bindings("ctypes.h", "src/ctypes_gen.rs");
 }
@@ -1,8 +1,10 @@
 use alloc::sync::Arc;
-use core::ffi::{c_char, c_int};
+use core::ffi::{c_char, c_int, c_long, c_ulong, c_void};
+use core::time::Duration;
 
 use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
+use axfs::api::{FileTimes, MountFlags};
+use axfs::fops::{FilePerm, OpenOptions};
 use axio::{PollState, SeekFrom};
 use axsync::Mutex;
 
@@ -24,12 +26,23 @@ impl File {
         super::fd_ops::add_file_like(Arc::new(self))
     }
 
-    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+    pub(super) fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
         let f = super::fd_ops::get_file_like(fd)?;
         f.into_any()
             .downcast::<Self>()
             .map_err(|_| LinuxError::EINVAL)
     }
+
+    /// Returns the backend of memory mappings of the file, which must be
+    /// opened for reading.
+    #[cfg(feature = "mmap")]
+    pub(super) fn mapping(&self) -> LinuxResult<Arc<dyn axmm::FileBackend>> {
+        let handle = self.inner.lock().handle()?;
+        if !handle.is_readable() {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(Arc::new(MappedFile(handle)))
+    }
 }
 
 impl FileLike for File {
@@ -42,21 +55,7 @@ impl FileLike for File {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        Ok(stat_from_attr(&self.inner.lock().get_attr()?))
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -75,6 +74,77 @@ impl FileLike for File {
     }
 }
 
+/// A file backing memory mappings. It accesses the node directly rather than
+/// locking the [`File`], which may be held by the faulting read or write.
+#[cfg(feature = "mmap")]
+struct MappedFile(axfs::fops::FileHandle);
+
+#[cfg(feature = "mmap")]
+impl axmm::FileBackend for MappedFile {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> axerrno::AxResult<usize> {
+        self.0.read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> axerrno::AxResult<usize> {
+        self.0.write_at(offset, buf)
+    }
+
+    fn size(&self) -> axerrno::AxResult<u64> {
+        Ok(self.0.get_attr()?.size())
+    }
+
+    fn is_writable(&self) -> bool {
+        self.0.is_writable()
+    }
+}
+
+/// Convert the attributes of a node to [`ctypes::stat`].
+fn stat_from_attr(attr: &axfs::fops::FileAttr) -> ctypes::stat {
+    let ty = attr.file_type() as u8;
+    let perm = attr.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    ctypes::stat {
+        st_ino: 1,
+        st_nlink: attr.nlink() as _,
+        st_mode,
+        st_uid: attr.uid(),
+        st_gid: attr.gid(),
+        st_size: attr.size() as _,
+        st_blocks: attr.blocks() as _,
+        st_blksize: 512,
+        st_atim: attr.atime().into(),
+        st_mtim: attr.mtime().into(),
+        st_ctim: attr.ctime().into(),
+        ..Default::default()
+    }
+}
+
+/// Convert the `times` argument of `utimensat` and `futimens`, where a null
+/// pointer sets both times to the current time.
+///
+/// Return `None` for a time with `UTIME_OMIT`, which is left unchanged.
+unsafe fn times_from_timespecs(
+    times: *const ctypes::timespec,
+) -> LinuxResult<(Option<Duration>, Option<Duration>)> {
+    let now = axhal::time::current_time();
+    if times.is_null() {
+        return Ok((Some(now), Some(now)));
+    }
+    let convert = |ts: &ctypes::timespec| {
+        if ts.tv_nsec == ctypes::UTIME_NOW as c_long {
+            Ok(Some(now))
+        } else if ts.tv_nsec == ctypes::UTIME_OMIT as c_long {
+            Ok(None)
+        } else if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+            Err(LinuxError::EINVAL)
+        } else {
+            Ok(Some(Duration::from(*ts)))
+        }
+    };
+    let times = unsafe { core::slice::from_raw_parts(times, 2) };
+    Ok((convert(&times[0])?, convert(&times[1])?))
+}
+
 /// Convert open flags to [`OpenOptions`].
 fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
@@ -143,11 +213,8 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let mut options = OpenOptions::new();
-        options.read(true);
-        let file = axfs::fops::File::open(path?, &options)?;
-        let st = File::new(file).stat()?;
-        unsafe { *buf = st };
+        let metadata = axfs::api::metadata(path?)?;
+        unsafe { *buf = stat_from_attr(metadata.raw_metadata()) };
         Ok(0)
     })
 }
@@ -177,7 +244,138 @@ pub unsafe fn sys_lstat(path: *const c_char, buf: *mut ctypes::stat) -> ctypes::
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        unsafe { *buf = Default::default() }; // TODO
+        let metadata = axfs::api::symlink_metadata(path?)?;
+        unsafe { *buf = stat_from_attr(metadata.raw_metadata()) };
+        Ok(0)
+    })
+}
+
+/// Change the permission bits of the file `path` to `mode`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_chmod(path: *const c_char, mode: ctypes::mode_t) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_chmod <= {:?} {:#o}", path, mode);
+    syscall_body!(sys_chmod, {
+        let perm = FilePerm::from_bits_truncate(mode as u16);
+        axfs::api::set_permissions(path?, perm)?;
+        Ok(0)
+    })
+}
+
+/// Change the permission bits of the file `fd` to `mode`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_fchmod(fd: c_int, mode: ctypes::mode_t) -> c_int {
+    debug!("sys_fchmod <= {} {:#o}", fd, mode);
+    syscall_body!(sys_fchmod, {
+        let perm = FilePerm::from_bits_truncate(mode as u16);
+        File::from_fd(fd)?.inner.lock().set_perm(perm)?;
+        Ok(0)
+    })
+}
+
+/// Change the access and modification times of the file `path`, or of the
+/// file `dirfd` if `path` is null.
+///
+/// A relative `path` is only supported with `AT_FDCWD`, as there are no
+/// directory file descriptors. Symlinks are not followed if `flags` has
+/// `AT_SYMLINK_NOFOLLOW`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub unsafe fn sys_utimensat(
+    dirfd: c_int,
+    path: *const c_char,
+    times: *const ctypes::timespec,
+    flags: c_int,
+) -> c_int {
+    debug!(
+        "sys_utimensat <= {} {:#x} {:#x} {:#x}",
+        dirfd, path as usize, times as usize, flags
+    );
+    syscall_body!(sys_utimensat, {
+        let (atime, mtime) = unsafe { times_from_timespecs(times)? };
+        if path.is_null() {
+            File::from_fd(dirfd)?.inner.lock().set_times(atime, mtime)?;
+            return Ok(0);
+        }
+        let path = char_ptr_to_str(path)?;
+        if dirfd != ctypes::AT_FDCWD && !path.starts_with('/') {
+            get_file_like(dirfd)?;
+            return Err(LinuxError::ENOTDIR);
+        }
+        let mut times = FileTimes::new();
+        if let Some(atime) = atime {
+            times = times.set_accessed(atime);
+        }
+        if let Some(mtime) = mtime {
+            times = times.set_modified(mtime);
+        }
+        if flags as u32 & ctypes::AT_SYMLINK_NOFOLLOW != 0 {
+            axfs::api::set_symlink_times(path, times)?;
+        } else {
+            axfs::api::set_times(path, times)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Change the access and modification times of the file `fd`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub unsafe fn sys_futimens(fd: c_int, times: *const ctypes::timespec) -> c_int {
+    debug!("sys_futimens <= {} {:#x}", fd, times as usize);
+    syscall_body!(sys_futimens, {
+        let (atime, mtime) = unsafe { times_from_timespecs(times)? };
+        File::from_fd(fd)?.inner.lock().set_times(atime, mtime)?;
+        Ok(0)
+    })
+}
+
+/// Create a symbolic link `linkpath`, which points to `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlink, {
+        let target = char_ptr_to_str(target)?;
+        let linkpath = char_ptr_to_str(linkpath)?;
+        debug!(
+            "sys_symlink <= target: {:?}, linkpath: {:?}",
+            target, linkpath
+        );
+        axfs::api::symlink(target, linkpath)?;
+        Ok(0)
+    })
+}
+
+/// Read the target of the symbolic link `path` into `buf`, which is not
+/// null-terminated and is truncated if `buf` is too small.
+///
+/// Return the number of bytes placed in `buf`.
+pub fn sys_readlink(path: *const c_char, buf: *mut c_char, bufsiz: usize) -> ctypes::ssize_t {
+    let path = char_ptr_to_str(path);
+    debug!("sys_readlink <= {:?} {:#x} {}", path, buf as usize, bufsiz);
+    syscall_body!(sys_readlink, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let target = axfs::api::read_link(path?)?;
+        let len = target.len().min(bufsiz);
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst.copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Create a hard link `new` to the file `old`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_link(old: *const c_char, new: *const c_char) -> c_int {
+    syscall_body!(sys_link, {
+        let old_path = char_ptr_to_str(old)?;
+        let new_path = char_ptr_to_str(new)?;
+        debug!("sys_link <= old: {:?}, new: {:?}", old_path, new_path);
+        axfs::api::hard_link(old_path, new_path)?;
         Ok(0)
     })
 }
@@ -215,3 +413,56 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
         Ok(0)
     })
 }
+
+/// Mount the filesystem `fstype` on the block device `source` (or the
+/// directory `source` for bind mounts) at the directory `target`.
+///
+/// Only `MS_RDONLY`, `MS_REMOUNT` and `MS_BIND` are supported in `flags`,
+/// and `data` is ignored.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_mount(
+    source: *const c_char,
+    target: *const c_char,
+    fstype: *const c_char,
+    flags: c_ulong,
+    data: *const c_void,
+) -> c_int {
+    syscall_body!(sys_mount, {
+        let source = if source.is_null() {
+            ""
+        } else {
+            char_ptr_to_str(source)?
+        };
+        let target = char_ptr_to_str(target)?;
+        let fstype = if fstype.is_null() {
+            ""
+        } else {
+            char_ptr_to_str(fstype)?
+        };
+        debug!(
+            "sys_mount <= source: {:?}, target: {:?}, fstype: {:?}, flags: {:#x}, data: {:#x}",
+            source, target, fstype, flags, data as usize
+        );
+        let flags = MountFlags::from_bits_truncate(flags as u32);
+        axfs::api::mount(source, target, fstype, flags)?;
+        Ok(0)
+    })
+}
+
+/// Unmount the filesystem mounted at `target`.
+///
+/// `MNT_FORCE` is accepted but has no effect, other flags are not supported.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_umount2(target: *const c_char, flags: c_int) -> c_int {
+    syscall_body!(sys_umount2, {
+        let target = char_ptr_to_str(target)?;
+        debug!("sys_umount2 <= target: {:?}, flags: {:#x}", target, flags);
+        if flags & !(ctypes::MNT_FORCE as c_int) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        axfs::api::umount(target)?;
+        Ok(0)
+    })
+}
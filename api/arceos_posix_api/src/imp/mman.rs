@@ -0,0 +1,244 @@
+use core::ffi::{c_int, c_void};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::mem::{VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::{MappingFlags, PageSize};
+use axmm::{Backend, WriteBack};
+
+use crate::ctypes;
+
+fn prot_to_flags(prot: c_int) -> LinuxResult<MappingFlags> {
+    let prot = prot as u32;
+    if prot & !(ctypes::PROT_READ | ctypes::PROT_WRITE | ctypes::PROT_EXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut flags = MappingFlags::empty();
+    if prot & ctypes::PROT_READ != 0 {
+        flags |= MappingFlags::READ;
+    }
+    if prot & ctypes::PROT_WRITE != 0 {
+        flags |= MappingFlags::WRITE;
+    }
+    if prot & ctypes::PROT_EXEC != 0 {
+        flags |= MappingFlags::EXECUTE;
+    }
+    Ok(flags)
+}
+
+/// Returns the page size of a `MAP_HUGETLB` mapping, which is 2M by default.
+fn huge_page_size(flags: u32) -> LinuxResult<PageSize> {
+    match (flags >> ctypes::MAP_HUGE_SHIFT) & ctypes::MAP_HUGE_MASK {
+        0 | 21 => Ok(PageSize::Size2M),
+        30 => Ok(PageSize::Size1G),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Checks that `addr` is page-aligned, and returns it with `len` rounded up
+/// to the page size.
+fn page_range(addr: *mut c_void, len: usize) -> LinuxResult<(VirtAddr, usize)> {
+    huge_page_range(addr, len, PageSize::Size4K)
+}
+
+/// Checks that `addr` is aligned to `page_size`, and returns it with `len`
+/// rounded up to `page_size`.
+fn huge_page_range(
+    addr: *mut c_void,
+    len: usize,
+    page_size: PageSize,
+) -> LinuxResult<(VirtAddr, usize)> {
+    let page_size = page_size as usize;
+    let start = VirtAddr::from(addr as usize);
+    if !start.is_aligned(page_size) {
+        return Err(LinuxError::EINVAL);
+    }
+    let size = len.checked_add(page_size - 1).ok_or(LinuxError::ENOMEM)? & !(page_size - 1);
+    if start.as_usize().checked_add(size).is_none() {
+        return Err(LinuxError::ENOMEM);
+    }
+    Ok((start, size))
+}
+
+#[cfg(feature = "fs")]
+fn file_backend(fd: c_int, offset: u64, shared: bool) -> LinuxResult<Backend> {
+    Ok(Backend::File {
+        file: super::fs::File::from_fd(fd)?.mapping()?,
+        offset,
+        shared,
+    })
+}
+
+#[cfg(not(feature = "fs"))]
+fn file_backend(_fd: c_int, _offset: u64, _shared: bool) -> LinuxResult<Backend> {
+    Err(LinuxError::ENODEV)
+}
+
+/// Map files or anonymous memory into the address space.
+///
+/// Pages are allocated (and read from the file) on the first access. As all
+/// tasks share the same address space, anonymous shared and private mappings
+/// behave the same, while modifications to shared file mappings are written
+/// back by `msync`, `madvise(MADV_DONTNEED)` and `munmap`. Anonymous mappings
+/// with `MAP_HUGETLB` are backed by 2M (or 1G with `MAP_HUGE_1GB`) pages.
+///
+/// The file must be opened for reading, and also for writing by shared
+/// mappings with `PROT_WRITE`. With `MAP_FIXED`, the existing mappings are
+/// kept if the arguments are invalid.
+///
+/// Return the start address of the mapping.
+pub fn sys_mmap(
+    addr: *mut c_void,
+    len: usize,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    off: ctypes::off_t,
+) -> *mut c_void {
+    debug!(
+        "sys_mmap <= addr: {:#x}, len: {:#x}, prot: {:#x}, flags: {:#x}, fd: {}, off: {:#x}",
+        addr as usize, len, prot, flags, fd, off
+    );
+    syscall_body!(sys_mmap, {
+        let flags = flags as u32;
+        let map_flags = prot_to_flags(prot)?;
+        let page_size = if flags & ctypes::MAP_HUGETLB != 0 {
+            if flags & ctypes::MAP_ANONYMOUS == 0 {
+                return Err(LinuxError::EINVAL); // no hugetlbfs
+            }
+            huge_page_size(flags)?
+        } else {
+            PageSize::Size4K
+        };
+        let size = huge_page_range(core::ptr::null_mut(), len, page_size)?.1;
+        if size == 0 || off < 0 || off as usize & (PAGE_SIZE_4K - 1) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let shared = match flags & ctypes::MAP_TYPE {
+            ctypes::MAP_SHARED | ctypes::MAP_SHARED_VALIDATE => true,
+            ctypes::MAP_PRIVATE => false,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let backend = if flags & ctypes::MAP_ANONYMOUS != 0 {
+            Backend::Alloc {
+                populate: flags & ctypes::MAP_POPULATE != 0,
+                page_size,
+            }
+        } else {
+            file_backend(fd, off as u64, shared)?
+        };
+
+        let mut aspace = axmm::kernel_aspace().lock();
+        if flags & ctypes::MAP_FIXED != 0 {
+            let start = huge_page_range(addr, len, page_size)?.0;
+            let write_back = aspace.map_fixed(start, size, map_flags, backend)?;
+            drop(aspace);
+            write_back.run()?;
+            return Ok(start.as_usize() as *mut c_void);
+        }
+        let hint = VirtAddr::from(addr as usize);
+        let start = aspace
+            .find_free_area(hint, size, page_size.into())
+            .or_else(|| aspace.find_free_area(aspace.base(), size, page_size.into()))
+            .ok_or(LinuxError::ENOMEM)?;
+        aspace.map(start, size, map_flags, backend)?;
+        Ok(start.as_usize() as *mut c_void)
+    })
+}
+
+/// Remove the mappings in the address range.
+///
+/// Modifications to shared file mappings are written back to the files.
+pub fn sys_munmap(addr: *mut c_void, len: usize) -> c_int {
+    debug!("sys_munmap <= addr: {:#x}, len: {:#x}", addr as usize, len);
+    syscall_body!(sys_munmap, {
+        let (start, size) = page_range(addr, len)?;
+        if size == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let write_back = axmm::kernel_aspace().lock().unmap(start, size)?;
+        write_back.run()?;
+        Ok(0)
+    })
+}
+
+/// Change the access protections of the mappings in the address range.
+pub fn sys_mprotect(addr: *mut c_void, len: usize, prot: c_int) -> c_int {
+    debug!(
+        "sys_mprotect <= addr: {:#x}, len: {:#x}, prot: {:#x}",
+        addr as usize, len, prot
+    );
+    syscall_body!(sys_mprotect, {
+        let flags = prot_to_flags(prot)?;
+        let (start, size) = page_range(addr, len)?;
+        if size == 0 {
+            return Ok(0);
+        }
+        let mut aspace = axmm::kernel_aspace().lock();
+        if !aspace.is_range_mapped(start, size) {
+            return Err(LinuxError::ENOMEM);
+        }
+        aspace.protect(start, size, flags)?;
+        Ok(0)
+    })
+}
+
+/// Write the modifications to shared file mappings in the address range back
+/// to the files.
+///
+/// The write-back is always synchronous, even if `MS_ASYNC` is specified.
+pub fn sys_msync(addr: *mut c_void, len: usize, flags: c_int) -> c_int {
+    debug!(
+        "sys_msync <= addr: {:#x}, len: {:#x}, flags: {:#x}",
+        addr as usize, len, flags
+    );
+    syscall_body!(sys_msync, {
+        let flags = flags as u32;
+        if flags & !(ctypes::MS_ASYNC | ctypes::MS_SYNC | ctypes::MS_INVALIDATE) != 0
+            || (flags & ctypes::MS_ASYNC != 0 && flags & ctypes::MS_SYNC != 0)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let (start, size) = page_range(addr, len)?;
+        if size == 0 {
+            return Ok(0);
+        }
+        let aspace = axmm::kernel_aspace().lock();
+        if !aspace.is_range_mapped(start, size) {
+            return Err(LinuxError::ENOMEM);
+        }
+        let write_back = aspace.sync(start, size)?;
+        drop(aspace);
+        write_back.run()?;
+        Ok(0)
+    })
+}
+
+/// Give advice about the use of memory in the address range.
+///
+/// Only `MADV_DONTNEED` takes effect, which frees the pages in the range.
+/// They are zero-filled (or read from the file again) on the next access.
+pub fn sys_madvise(addr: *mut c_void, len: usize, advice: c_int) -> c_int {
+    debug!(
+        "sys_madvise <= addr: {:#x}, len: {:#x}, advice: {}",
+        addr as usize, len, advice
+    );
+    syscall_body!(sys_madvise, {
+        let (start, size) = page_range(addr, len)?;
+        let mut aspace = axmm::kernel_aspace().lock();
+        if size != 0 && !aspace.is_range_mapped(start, size) {
+            return Err(LinuxError::ENOMEM);
+        }
+        let write_back = match advice as u32 {
+            ctypes::MADV_NORMAL
+            | ctypes::MADV_RANDOM
+            | ctypes::MADV_SEQUENTIAL
+            | ctypes::MADV_WILLNEED => WriteBack::default(),
+            ctypes::MADV_DONTNEED if size != 0 => aspace.discard(start, size)?,
+            ctypes::MADV_DONTNEED => WriteBack::default(),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        drop(aspace);
+        write_back.run()?;
+        Ok(0)
+    })
+}
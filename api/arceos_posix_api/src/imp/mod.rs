@@ -12,6 +12,8 @@ pub mod fd_ops;
 pub mod fs;
 #[cfg(any(feature = "select", feature = "epoll"))]
 pub mod io_mpx;
+#[cfg(feature = "mmap")]
+pub mod mman;
 #[cfg(feature = "net")]
 pub mod net;
 #[cfg(feature = "pipe")]
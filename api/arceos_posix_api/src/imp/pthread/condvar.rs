@@ -0,0 +1,176 @@
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::{Condvar, CondvarGuard};
+
+use core::ffi::c_int;
+use core::mem::{size_of, ManuallyDrop};
+use core::time::Duration;
+
+use super::mutex::{MutexKind, PthreadMutex};
+use super::zero_init::ZeroInit;
+
+static_assertions::const_assert_eq!(
+    size_of::<PthreadCond>(),
+    size_of::<ctypes::pthread_cond_t>()
+);
+
+/// A condition variable, which can be statically initialized with all zeros
+/// by `PTHREAD_COND_INITIALIZER`.
+#[repr(C)]
+pub struct PthreadCond(ZeroInit<Condvar>);
+
+impl PthreadCond {
+    const fn new() -> Self {
+        Self(ZeroInit::new(Condvar::new()))
+    }
+
+    fn inner(&self) -> &Condvar {
+        self.0.get_or_init(Condvar::new)
+    }
+
+    fn wait(&self, mutex: &PthreadMutex, timeout: Option<Duration>) -> LinuxResult {
+        let timed_out = match mutex.inner() {
+            MutexKind::Normal(m) => self.wait_guard(unsafe { m.make_guard_unchecked() }, timeout),
+            MutexKind::PrioInherit(m) => {
+                self.wait_guard(unsafe { m.make_guard_unchecked() }, timeout)
+            }
+        };
+        if timed_out {
+            Err(LinuxError::ETIMEDOUT)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Waits with the guard of the locked mutex, and keeps it locked after
+    /// returning. Returns whether it's timed out.
+    fn wait_guard<G: CondvarGuard>(&self, guard: G, timeout: Option<Duration>) -> bool {
+        match timeout {
+            Some(dur) => {
+                let (guard, res) = self.inner().wait_timeout(guard, dur);
+                let _guard = ManuallyDrop::new(guard);
+                res.timed_out()
+            }
+            None => {
+                let _guard = ManuallyDrop::new(self.inner().wait(guard));
+                false
+            }
+        }
+    }
+
+    fn signal(&self) -> LinuxResult {
+        self.inner().notify_one();
+        Ok(())
+    }
+
+    fn broadcast(&self) -> LinuxResult {
+        self.inner().notify_all();
+        Ok(())
+    }
+}
+
+/// Initialize a condition variable.
+pub fn sys_pthread_cond_init(
+    cond: *mut ctypes::pthread_cond_t,
+    _attr: *const ctypes::pthread_condattr_t,
+) -> c_int {
+    debug!("sys_pthread_cond_init <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_init, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            cond.cast::<PthreadCond>().write(PthreadCond::new());
+        }
+        Ok(0)
+    })
+}
+
+/// Destroy a condition variable.
+pub fn sys_pthread_cond_destroy(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_destroy <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_destroy, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            cond.cast::<PthreadCond>().drop_in_place();
+        }
+        Ok(0)
+    })
+}
+
+/// Unlock the given mutex and wait on the condition variable, then lock the
+/// mutex again.
+pub fn sys_pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    debug!(
+        "sys_pthread_cond_wait <= {:#x}, {:#x}",
+        cond as usize, mutex as usize
+    );
+    syscall_body!(sys_pthread_cond_wait, {
+        check_null_mut_ptr(cond)?;
+        check_null_mut_ptr(mutex)?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).wait(&*mutex.cast::<PthreadMutex>(), None)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Like [`sys_pthread_cond_wait`], but returns `ETIMEDOUT` if the absolute
+/// time `abstime` has passed before the condition variable is notified.
+pub unsafe fn sys_pthread_cond_timedwait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!(
+        "sys_pthread_cond_timedwait <= {:#x}, {:#x}",
+        cond as usize, mutex as usize
+    );
+    syscall_body!(sys_pthread_cond_timedwait, {
+        check_null_mut_ptr(cond)?;
+        check_null_mut_ptr(mutex)?;
+        if abstime.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let abstime = unsafe { *abstime };
+        if abstime.tv_sec < 0 || abstime.tv_nsec < 0 || abstime.tv_nsec > 999999999 {
+            return Err(LinuxError::EINVAL);
+        }
+        let deadline = Duration::from(abstime);
+        let now = axhal::time::current_time();
+        if deadline <= now {
+            return Err(LinuxError::ETIMEDOUT);
+        }
+        unsafe {
+            (*cond.cast::<PthreadCond>())
+                .wait(&*mutex.cast::<PthreadMutex>(), Some(deadline - now))?;
+        }
+        Ok(0)
+    })
+}
+
+/// Wake up one task waiting on the condition variable.
+pub fn sys_pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_signal <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_signal, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).signal()?;
+        }
+        Ok(0)
+    })
+}
+
+/// Wake up all tasks waiting on the condition variable.
+pub fn sys_pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_broadcast <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_broadcast, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).broadcast()?;
+        }
+        Ok(0)
+    })
+}
@@ -8,7 +8,13 @@ use spin::RwLock;
 
 use crate::ctypes;
 
+pub mod barrier;
+pub mod condvar;
 pub mod mutex;
+pub mod rwlock;
+pub mod semaphore;
+
+mod zero_init;
 
 lazy_static::lazy_static! {
     static ref TID_TO_PTHREAD: RwLock<BTreeMap<u64, ForceSendSync<ctypes::pthread_t>>> = {
@@ -147,6 +153,28 @@ pub unsafe fn sys_pthread_join(thread: ctypes::pthread_t, retval: *mut *mut c_vo
     })
 }
 
+/// Set the CPU affinity of the given thread.
+///
+/// Only the current thread is supported for now.
+pub unsafe fn sys_pthread_setaffinity_np(
+    thread: ctypes::pthread_t,
+    cpusetsize: usize,
+    cpuset: *const ctypes::cpu_set_t,
+) -> c_int {
+    debug!(
+        "sys_pthread_setaffinity_np <= {:#x} {:#x}",
+        thread as usize, cpuset as usize
+    );
+    syscall_body!(sys_pthread_setaffinity_np, {
+        let bits = crate::imp::task::cpu_set_to_mask(cpusetsize, cpuset)?;
+        if !core::ptr::eq(thread, Pthread::current_ptr() as _) {
+            return Err(LinuxError::ESRCH);
+        }
+        axtask::set_current_affinity(axtask::CpuMask::from_bits(bits));
+        Ok(0)
+    })
+}
+
 #[derive(Clone, Copy)]
 struct ForceSendSync<T>(T);
 
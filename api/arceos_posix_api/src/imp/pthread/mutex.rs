@@ -1,45 +1,146 @@
 use crate::{ctypes, utils::check_null_mut_ptr};
 
-use axerrno::LinuxResult;
-use axsync::Mutex;
+use axerrno::{LinuxError, LinuxResult};
+use axsync::{Mutex, PiMutex};
 
 use core::ffi::c_int;
 use core::mem::{size_of, ManuallyDrop};
 
+use super::zero_init::ZeroInit;
+
 static_assertions::const_assert_eq!(
     size_of::<PthreadMutex>(),
     size_of::<ctypes::pthread_mutex_t>()
 );
 
+/// The bit in `pthread_mutexattr_t` indicating the `PTHREAD_PRIO_INHERIT`
+/// protocol, the same as musl.
+const MUTEXATTR_PRIO_INHERIT: u32 = 8;
+
+/// The mutex of the protocol chosen by `pthread_mutexattr_setprotocol`.
+pub(super) enum MutexKind {
+    /// `PTHREAD_PRIO_NONE`
+    Normal(Mutex<()>),
+    /// `PTHREAD_PRIO_INHERIT`
+    PrioInherit(PiMutex<()>),
+}
+
+/// A mutex which is still all zeros (`PTHREAD_MUTEX_INITIALIZER`) is
+/// constructed as a [`MutexKind::Normal`] on first use.
 #[repr(C)]
-pub struct PthreadMutex(Mutex<()>);
+pub struct PthreadMutex(ZeroInit<MutexKind>);
 
 impl PthreadMutex {
     const fn new() -> Self {
-        Self(Mutex::new(()))
+        Self(ZeroInit::new(MutexKind::Normal(Mutex::new(()))))
+    }
+
+    const fn new_priority_inherit() -> Self {
+        Self(ZeroInit::new(MutexKind::PrioInherit(PiMutex::new(()))))
+    }
+
+    pub(super) fn inner(&self) -> &MutexKind {
+        self.0.get_or_init(|| MutexKind::Normal(Mutex::new(())))
     }
 
     fn lock(&self) -> LinuxResult {
-        let _guard = ManuallyDrop::new(self.0.lock());
+        match self.inner() {
+            MutexKind::Normal(m) => {
+                let _guard = ManuallyDrop::new(m.lock());
+            }
+            MutexKind::PrioInherit(m) => {
+                let _guard = ManuallyDrop::new(m.lock());
+            }
+        }
         Ok(())
     }
 
     fn unlock(&self) -> LinuxResult {
-        unsafe { self.0.force_unlock() };
+        match self.inner() {
+            MutexKind::Normal(m) => unsafe { m.force_unlock() },
+            MutexKind::PrioInherit(m) => unsafe { m.force_unlock() },
+        }
         Ok(())
     }
 }
 
+/// Initialize a mutex attributes object with the default attributes.
+pub fn sys_pthread_mutexattr_init(attr: *mut ctypes::pthread_mutexattr_t) -> c_int {
+    debug!("sys_pthread_mutexattr_init <= {:#x}", attr as usize);
+    syscall_body!(sys_pthread_mutexattr_init, {
+        check_null_mut_ptr(attr)?;
+        unsafe {
+            attr.write(ctypes::pthread_mutexattr_t::default());
+        }
+        Ok(0)
+    })
+}
+
+/// Set the protocol of a mutex attributes object.
+///
+/// Only `PTHREAD_PRIO_NONE` and `PTHREAD_PRIO_INHERIT` are supported.
+pub fn sys_pthread_mutexattr_setprotocol(
+    attr: *mut ctypes::pthread_mutexattr_t,
+    protocol: c_int,
+) -> c_int {
+    debug!(
+        "sys_pthread_mutexattr_setprotocol <= {:#x}, {}",
+        attr as usize, protocol
+    );
+    syscall_body!(sys_pthread_mutexattr_setprotocol, {
+        check_null_mut_ptr(attr)?;
+        let attr = unsafe { &mut *attr };
+        match protocol as u32 {
+            ctypes::PTHREAD_PRIO_NONE => attr.__attr &= !MUTEXATTR_PRIO_INHERIT,
+            ctypes::PTHREAD_PRIO_INHERIT => attr.__attr |= MUTEXATTR_PRIO_INHERIT,
+            ctypes::PTHREAD_PRIO_PROTECT => return Err(LinuxError::EOPNOTSUPP),
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
+
+/// Get the protocol of a mutex attributes object.
+pub unsafe fn sys_pthread_mutexattr_getprotocol(
+    attr: *const ctypes::pthread_mutexattr_t,
+    protocol: *mut c_int,
+) -> c_int {
+    debug!("sys_pthread_mutexattr_getprotocol <= {:#x}", attr as usize);
+    syscall_body!(sys_pthread_mutexattr_getprotocol, {
+        if attr.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        check_null_mut_ptr(protocol)?;
+        unsafe {
+            *protocol = if (*attr).__attr & MUTEXATTR_PRIO_INHERIT != 0 {
+                ctypes::PTHREAD_PRIO_INHERIT as c_int
+            } else {
+                ctypes::PTHREAD_PRIO_NONE as c_int
+            };
+        }
+        Ok(0)
+    })
+}
+
 /// Initialize a mutex.
-pub fn sys_pthread_mutex_init(
+///
+/// If the protocol of `attr` is `PTHREAD_PRIO_INHERIT`, the owner of the mutex
+/// will inherit the priority of the highest-priority thread blocked on it.
+pub unsafe fn sys_pthread_mutex_init(
     mutex: *mut ctypes::pthread_mutex_t,
-    _attr: *const ctypes::pthread_mutexattr_t,
+    attr: *const ctypes::pthread_mutexattr_t,
 ) -> c_int {
     debug!("sys_pthread_mutex_init <= {:#x}", mutex as usize);
     syscall_body!(sys_pthread_mutex_init, {
         check_null_mut_ptr(mutex)?;
+        let inherit = !attr.is_null() && unsafe { (*attr).__attr } & MUTEXATTR_PRIO_INHERIT != 0;
+        let new_mutex = if inherit {
+            PthreadMutex::new_priority_inherit()
+        } else {
+            PthreadMutex::new()
+        };
         unsafe {
-            mutex.cast::<PthreadMutex>().write(PthreadMutex::new());
+            mutex.cast::<PthreadMutex>().write(new_mutex);
         }
         Ok(0)
     })
@@ -0,0 +1,152 @@
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::RwLock;
+
+use core::ffi::c_int;
+use core::mem::{size_of, ManuallyDrop};
+
+use super::zero_init::ZeroInit;
+
+static_assertions::const_assert_eq!(
+    size_of::<PthreadRwlock>(),
+    size_of::<ctypes::pthread_rwlock_t>()
+);
+
+/// A reader-writer lock, which can be statically initialized with all zeros
+/// by `PTHREAD_RWLOCK_INITIALIZER`.
+#[repr(C)]
+pub struct PthreadRwlock(ZeroInit<RwLock<()>>);
+
+impl PthreadRwlock {
+    const fn new() -> Self {
+        Self(ZeroInit::new(RwLock::new(())))
+    }
+
+    fn inner(&self) -> &RwLock<()> {
+        self.0.get_or_init(|| RwLock::new(()))
+    }
+
+    fn rdlock(&self) -> LinuxResult {
+        let _guard = ManuallyDrop::new(self.inner().read());
+        Ok(())
+    }
+
+    fn tryrdlock(&self) -> LinuxResult {
+        let guard = self.inner().try_read().ok_or(LinuxError::EBUSY)?;
+        let _guard = ManuallyDrop::new(guard);
+        Ok(())
+    }
+
+    fn wrlock(&self) -> LinuxResult {
+        let _guard = ManuallyDrop::new(self.inner().write());
+        Ok(())
+    }
+
+    fn trywrlock(&self) -> LinuxResult {
+        let guard = self.inner().try_write().ok_or(LinuxError::EBUSY)?;
+        let _guard = ManuallyDrop::new(guard);
+        Ok(())
+    }
+
+    fn unlock(&self) -> LinuxResult {
+        let lock = self.inner();
+        if lock.writer_count() != 0 {
+            unsafe { lock.force_write_unlock() };
+        } else if lock.reader_count() != 0 {
+            unsafe { lock.force_read_decrement() };
+        } else {
+            return Err(LinuxError::EPERM);
+        }
+        Ok(())
+    }
+}
+
+/// Initialize a reader-writer lock.
+pub fn sys_pthread_rwlock_init(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    _attr: *const ctypes::pthread_rwlockattr_t,
+) -> c_int {
+    debug!("sys_pthread_rwlock_init <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_init, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            rwlock.cast::<PthreadRwlock>().write(PthreadRwlock::new());
+        }
+        Ok(0)
+    })
+}
+
+/// Destroy a reader-writer lock.
+pub fn sys_pthread_rwlock_destroy(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_destroy <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_destroy, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            rwlock.cast::<PthreadRwlock>().drop_in_place();
+        }
+        Ok(0)
+    })
+}
+
+/// Lock the given reader-writer lock for reading.
+pub fn sys_pthread_rwlock_rdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_rdlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_rdlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            (*rwlock.cast::<PthreadRwlock>()).rdlock()?;
+        }
+        Ok(0)
+    })
+}
+
+/// Try to lock the given reader-writer lock for reading, returns `EBUSY` if
+/// a writer holds the lock.
+pub fn sys_pthread_rwlock_tryrdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_tryrdlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_tryrdlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            (*rwlock.cast::<PthreadRwlock>()).tryrdlock()?;
+        }
+        Ok(0)
+    })
+}
+
+/// Lock the given reader-writer lock for writing.
+pub fn sys_pthread_rwlock_wrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_wrlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_wrlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            (*rwlock.cast::<PthreadRwlock>()).wrlock()?;
+        }
+        Ok(0)
+    })
+}
+
+/// Try to lock the given reader-writer lock for writing, returns `EBUSY` if
+/// it is already locked.
+pub fn sys_pthread_rwlock_trywrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_trywrlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_trywrlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            (*rwlock.cast::<PthreadRwlock>()).trywrlock()?;
+        }
+        Ok(0)
+    })
+}
+
+/// Unlock the given reader-writer lock.
+pub fn sys_pthread_rwlock_unlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    debug!("sys_pthread_rwlock_unlock <= {:#x}", rwlock as usize);
+    syscall_body!(sys_pthread_rwlock_unlock, {
+        check_null_mut_ptr(rwlock)?;
+        unsafe {
+            (*rwlock.cast::<PthreadRwlock>()).unlock()?;
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,137 @@
+use crate::{ctypes, utils::check_null_mut_ptr};
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Semaphore;
+
+use core::ffi::{c_int, c_uint};
+use core::mem::size_of;
+use core::time::Duration;
+
+static_assertions::const_assert_eq!(size_of::<PthreadSem>(), size_of::<ctypes::sem_t>());
+
+#[repr(C)]
+pub struct PthreadSem(Semaphore);
+
+impl PthreadSem {
+    fn wait(&self, timeout: Option<Duration>) -> LinuxResult {
+        match timeout {
+            Some(dur) => {
+                if !self.0.acquire_timeout(dur) {
+                    return Err(LinuxError::ETIMEDOUT);
+                }
+            }
+            None => self.0.acquire(),
+        }
+        Ok(())
+    }
+}
+
+/// Initialize an unnamed semaphore with the initial count `value`.
+///
+/// Semaphores shared between processes are not supported, `pshared` is
+/// ignored.
+pub fn sys_sem_init(sem: *mut ctypes::sem_t, _pshared: c_int, value: c_uint) -> c_int {
+    debug!("sys_sem_init <= {:#x}, {}", sem as usize, value);
+    syscall_body!(sys_sem_init, {
+        check_null_mut_ptr(sem)?;
+        unsafe {
+            sem.cast::<PthreadSem>()
+                .write(PthreadSem(Semaphore::new(value as usize)));
+        }
+        Ok(0)
+    })
+}
+
+/// Destroy an unnamed semaphore.
+pub fn sys_sem_destroy(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_destroy <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_destroy, {
+        check_null_mut_ptr(sem)?;
+        unsafe {
+            sem.cast::<PthreadSem>().drop_in_place();
+        }
+        Ok(0)
+    })
+}
+
+/// Decrement the semaphore, blocking until its value is positive.
+pub fn sys_sem_wait(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_wait <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_wait, {
+        check_null_mut_ptr(sem)?;
+        unsafe {
+            (*sem.cast::<PthreadSem>()).wait(None)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Decrement the semaphore if its value is positive, otherwise returns
+/// `EAGAIN`.
+pub fn sys_sem_trywait(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_trywait <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_trywait, {
+        check_null_mut_ptr(sem)?;
+        if unsafe { (*sem.cast::<PthreadSem>()).0.try_acquire() } {
+            Ok(0)
+        } else {
+            Err(LinuxError::EAGAIN)
+        }
+    })
+}
+
+/// Like [`sys_sem_wait`], but returns `ETIMEDOUT` if the absolute time
+/// `abstime` has passed before the semaphore can be decremented.
+pub unsafe fn sys_sem_timedwait(
+    sem: *mut ctypes::sem_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!("sys_sem_timedwait <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_timedwait, {
+        check_null_mut_ptr(sem)?;
+        let sem = unsafe { &*sem.cast::<PthreadSem>() };
+        // Never blocks if the semaphore can be decremented immediately.
+        if sem.0.try_acquire() {
+            return Ok(0);
+        }
+        if abstime.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let abstime = unsafe { *abstime };
+        if abstime.tv_sec < 0 || abstime.tv_nsec < 0 || abstime.tv_nsec > 999999999 {
+            return Err(LinuxError::EINVAL);
+        }
+        let deadline = Duration::from(abstime);
+        let now = axhal::time::current_time();
+        if deadline <= now {
+            return Err(LinuxError::ETIMEDOUT);
+        }
+        sem.wait(Some(deadline - now))?;
+        Ok(0)
+    })
+}
+
+/// Increment the semaphore, and wake up one thread blocked on it.
+pub fn sys_sem_post(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_post <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_post, {
+        check_null_mut_ptr(sem)?;
+        unsafe {
+            (*sem.cast::<PthreadSem>()).0.release();
+        }
+        Ok(0)
+    })
+}
+
+/// Store the current value of the semaphore in `sval`.
+pub unsafe fn sys_sem_getvalue(sem: *mut ctypes::sem_t, sval: *mut c_int) -> c_int {
+    debug!("sys_sem_getvalue <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_getvalue, {
+        check_null_mut_ptr(sem)?;
+        check_null_mut_ptr(sval)?;
+        unsafe {
+            *sval = (*sem.cast::<PthreadSem>()).0.count() as c_int;
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,64 @@
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const UNINIT: usize = 0;
+const INITIALIZING: usize = 1;
+const READY: usize = 2;
+
+/// A synchronization object whose all-zero bytes are a valid (not yet
+/// constructed) state.
+///
+/// C code initializes statically allocated objects with all zeros by the
+/// `PTHREAD_*_INITIALIZER` macros, and the inner object is constructed in
+/// place on first use. So the initializers do not depend on the layout of the
+/// inner Rust type, only on its size.
+#[repr(C)]
+pub struct ZeroInit<T> {
+    state: AtomicUsize,
+    inner: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send + Sync> Sync for ZeroInit<T> {}
+unsafe impl<T: Send> Send for ZeroInit<T> {}
+
+impl<T> ZeroInit<T> {
+    /// Creates a constructed object.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            state: AtomicUsize::new(READY),
+            inner: UnsafeCell::new(MaybeUninit::new(inner)),
+        }
+    }
+
+    /// Returns the inner object, constructing it by `init` first if it's
+    /// still all zeros.
+    pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        loop {
+            match self.state.compare_exchange_weak(
+                UNINIT,
+                INITIALIZING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    unsafe { (*self.inner.get()).write(init()) };
+                    self.state.store(READY, Ordering::Release);
+                    break;
+                }
+                Err(READY) => break,
+                // Being constructed by another task, or a spurious failure.
+                Err(_) => core::hint::spin_loop(),
+            }
+        }
+        unsafe { (*self.inner.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Drop for ZeroInit<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            unsafe { self.inner.get_mut().assume_init_drop() };
+        }
+    }
+}
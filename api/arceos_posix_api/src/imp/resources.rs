@@ -1,6 +1,6 @@
-use crate::ctypes;
+use crate::{ctypes, utils::check_null_mut_ptr};
 use axerrno::LinuxError;
-use core::ffi::c_int;
+use core::ffi::{c_int, c_long};
 
 /// Get resource limitations
 ///
@@ -49,3 +49,28 @@ pub unsafe fn sys_setrlimit(resource: c_int, rlimits: *mut crate::ctypes::rlimit
         Ok(0)
     })
 }
+
+/// Get resource usage
+///
+/// `RUSAGE_SELF` reports all threads, and `RUSAGE_THREAD` reports the calling
+/// thread. As there are no child processes, `RUSAGE_CHILDREN` reports nothing.
+///
+/// Only the CPU time and the numbers of context switches are filled, and all
+/// CPU time is counted as user time.
+pub unsafe fn sys_getrusage(who: c_int, usage: *mut ctypes::rusage) -> c_int {
+    debug!("sys_getrusage <= {} {:#x}", who, usage as usize);
+    syscall_body!(sys_getrusage, {
+        check_null_mut_ptr(usage)?;
+        let mut ru = ctypes::rusage::default();
+        if who == ctypes::RUSAGE_SELF as c_int || who == ctypes::RUSAGE_THREAD as c_int {
+            let cpu = super::task::cpu_usage(who == ctypes::RUSAGE_THREAD as c_int);
+            ru.ru_utime = cpu.cpu_time.into();
+            ru.ru_nvcsw = cpu.nvcsw as c_long;
+            ru.ru_nivcsw = cpu.nivcsw as c_long;
+        } else if who != ctypes::RUSAGE_CHILDREN {
+            return Err(LinuxError::EINVAL);
+        }
+        unsafe { *usage = ru };
+        Ok(0)
+    })
+}
@@ -1,4 +1,9 @@
 use core::ffi::c_int;
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+
+use crate::{ctypes, utils::check_null_ptr};
 
 /// Relinquish the CPU, and switches to another task.
 ///
@@ -16,6 +21,165 @@ pub fn sys_sched_yield() -> c_int {
     0
 }
 
+/// Converts the C `cpu_set_t` of `cpusetsize` bytes to the list of allowed
+/// CPUs. CPUs that do not exist are ignored.
+pub(crate) unsafe fn cpu_set_to_mask(
+    cpusetsize: usize,
+    mask: *const ctypes::cpu_set_t,
+) -> LinuxResult<usize> {
+    if mask.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let bytes = core::slice::from_raw_parts(mask as *const u8, cpusetsize);
+    let mut bits = 0;
+    for cpu_id in 0..axconfig::SMP.min(cpusetsize * 8) {
+        if bytes[cpu_id / 8] & (1 << (cpu_id % 8)) != 0 {
+            bits |= 1 << cpu_id;
+        }
+    }
+    if bits == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(bits)
+}
+
+/// Set the CPU affinity of the thread `pid`.
+///
+/// Only the current thread is supported, i.e., `pid` must be 0 or the ID of
+/// the current thread.
+pub unsafe fn sys_sched_setaffinity(
+    pid: c_int,
+    cpusetsize: usize,
+    mask: *const ctypes::cpu_set_t,
+) -> c_int {
+    debug!("sys_sched_setaffinity <= {} {:#x}", pid, mask as usize);
+    syscall_body!(sys_sched_setaffinity, {
+        let bits = cpu_set_to_mask(cpusetsize, mask)?;
+        if pid != 0 && pid != sys_getpid() {
+            return Err(LinuxError::ESRCH);
+        }
+        #[cfg(feature = "multitask")]
+        axtask::set_current_affinity(axtask::CpuMask::from_bits(bits));
+        #[cfg(not(feature = "multitask"))]
+        if bits & 1 == 0 {
+            // Only the primary CPU is running.
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(0)
+    })
+}
+
+/// Returns the range of static priorities of the scheduling `policy`.
+fn sched_priority_range(policy: c_int) -> LinuxResult<(c_int, c_int)> {
+    match policy as u32 {
+        ctypes::SCHED_OTHER => Ok((0, 0)),
+        ctypes::SCHED_FIFO | ctypes::SCHED_RR => Ok((1, 99)),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Set the scheduling policy and priority of the thread `pid`.
+///
+/// Only the current thread is supported, i.e., `pid` must be 0 or the ID of
+/// the current thread. `SCHED_FIFO` and `SCHED_RR` require the real-time
+/// scheduler (the `sched_rt` feature), where the static priority 1 to 99 is
+/// mapped to the scheduler level 98 to 0, and `SCHED_OTHER` is the lowest
+/// level 99.
+pub unsafe fn sys_sched_setscheduler(
+    pid: c_int,
+    policy: c_int,
+    param: *const ctypes::sched_param,
+) -> c_int {
+    debug!(
+        "sys_sched_setscheduler <= {} {} {:#x}",
+        pid, policy, param as usize
+    );
+    syscall_body!(sys_sched_setscheduler, {
+        check_null_ptr(param)?;
+        let prio = (*param).sched_priority;
+        let (min, max) = sched_priority_range(policy)?;
+        if !(min..=max).contains(&prio) {
+            return Err(LinuxError::EINVAL);
+        }
+        if pid != 0 && pid != sys_getpid() {
+            return Err(LinuxError::ESRCH);
+        }
+        #[cfg(feature = "sched_rt")]
+        {
+            let rt_policy = if policy as u32 == ctypes::SCHED_FIFO {
+                axtask::RTPolicy::Fifo
+            } else {
+                axtask::RTPolicy::RoundRobin
+            };
+            if !axtask::set_current_policy(rt_policy, (99 - prio) as isize) {
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        #[cfg(not(feature = "sched_rt"))]
+        if policy as u32 != ctypes::SCHED_OTHER {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(0)
+    })
+}
+
+/// Get the maximum static priority of the scheduling `policy`.
+pub fn sys_sched_get_priority_max(policy: c_int) -> c_int {
+    debug!("sys_sched_get_priority_max <= {}", policy);
+    syscall_body!(sys_sched_get_priority_max, {
+        sched_priority_range(policy).map(|(_, max)| max)
+    })
+}
+
+/// Get the minimum static priority of the scheduling `policy`.
+pub fn sys_sched_get_priority_min(policy: c_int) -> c_int {
+    debug!("sys_sched_get_priority_min <= {}", policy);
+    syscall_body!(sys_sched_get_priority_min, {
+        sched_priority_range(policy).map(|(min, _)| min)
+    })
+}
+
+/// CPU usage of a thread or all threads, see [`cpu_usage`].
+#[derive(Default)]
+pub(crate) struct CpuUsage {
+    pub cpu_time: Duration,
+    pub nvcsw: u64,
+    pub nivcsw: u64,
+}
+
+/// Returns the CPU usage of the current thread if `thread` is true, or of
+/// all threads (excluding the idle ones) otherwise.
+///
+/// For single-threaded configuration, the only thread has been running since
+/// booting.
+pub(crate) fn cpu_usage(thread: bool) -> CpuUsage {
+    #[cfg(feature = "multitask")]
+    {
+        let mut usage = CpuUsage::default();
+        let mut add = |stats: axtask::TaskStats| {
+            usage.cpu_time += stats.run_time;
+            usage.nvcsw += stats.nvcsw;
+            usage.nivcsw += stats.nivcsw;
+        };
+        if thread {
+            add(axtask::current().stats());
+        } else {
+            (0..axconfig::SMP)
+                .filter_map(axtask::cpu_stats)
+                .for_each(add);
+        }
+        usage
+    }
+    #[cfg(not(feature = "multitask"))]
+    {
+        let _ = thread;
+        CpuUsage {
+            cpu_time: axhal::time::current_time(),
+            ..Default::default()
+        }
+    }
+}
+
 /// Get current thread ID.
 pub fn sys_getpid() -> c_int {
     syscall_body!(sys_getpid,
@@ -33,6 +197,10 @@ pub fn sys_getpid() -> c_int {
 /// Exit current task
 pub fn sys_exit(exit_code: c_int) -> ! {
     debug!("sys_exit <= {}", exit_code);
+    #[cfg(feature = "fs")]
+    if let Err(e) = axfs::sync() {
+        warn!("failed to sync filesystems: {:?}", e);
+    }
     #[cfg(feature = "multitask")]
     axtask::exit(exit_code);
     #[cfg(not(feature = "multitask"))]
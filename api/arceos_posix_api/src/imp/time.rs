@@ -35,12 +35,20 @@ impl From<Duration> for ctypes::timeval {
 }
 
 /// Get clock time since booting
-pub unsafe fn sys_clock_gettime(_clk: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
+///
+/// `CLOCK_THREAD_CPUTIME_ID` and `CLOCK_PROCESS_CPUTIME_ID` give the CPU time
+/// consumed by the calling thread and by all threads, respectively.
+pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
     syscall_body!(sys_clock_gettime, {
         if ts.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let now = axhal::time::current_time().into();
+        let now = match clk as u32 {
+            ctypes::CLOCK_THREAD_CPUTIME_ID => super::task::cpu_usage(true).cpu_time,
+            ctypes::CLOCK_PROCESS_CPUTIME_ID => super::task::cpu_usage(false).cpu_time,
+            _ => axhal::time::current_time(),
+        }
+        .into();
         unsafe { *ts = now };
         debug!("sys_clock_gettime: {}.{:09}s", now.tv_sec, now.tv_nsec);
         Ok(0)
@@ -33,19 +33,28 @@ pub mod config {
 pub mod ctypes;
 
 pub use imp::io::{sys_read, sys_write, sys_writev};
-pub use imp::resources::{sys_getrlimit, sys_setrlimit};
+pub use imp::resources::{sys_getrlimit, sys_getrusage, sys_setrlimit};
 pub use imp::sys::sys_sysconf;
-pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
+pub use imp::task::{
+    sys_exit, sys_getpid, sys_sched_get_priority_max, sys_sched_get_priority_min,
+    sys_sched_setaffinity, sys_sched_setscheduler, sys_sched_yield,
+};
 pub use imp::time::{sys_clock_gettime, sys_nanosleep};
 
 #[cfg(feature = "fd")]
 pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::fs::{
+    sys_chmod, sys_fchmod, sys_fstat, sys_futimens, sys_getcwd, sys_link, sys_lseek, sys_lstat,
+    sys_mount, sys_open, sys_readlink, sys_rename, sys_stat, sys_symlink, sys_umount2,
+    sys_utimensat,
+};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
 #[cfg(feature = "epoll")]
 pub use imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+#[cfg(feature = "mmap")]
+pub use imp::mman::{sys_madvise, sys_mmap, sys_mprotect, sys_msync, sys_munmap};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
@@ -55,8 +64,33 @@ pub use imp::net::{
 #[cfg(feature = "pipe")]
 pub use imp::pipe::sys_pipe;
 #[cfg(feature = "multitask")]
+pub use imp::pthread::barrier::{
+    sys_pthread_barrier_destroy, sys_pthread_barrier_init, sys_pthread_barrier_wait,
+};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::condvar::{
+    sys_pthread_cond_broadcast, sys_pthread_cond_destroy, sys_pthread_cond_init,
+    sys_pthread_cond_signal, sys_pthread_cond_timedwait, sys_pthread_cond_wait,
+};
+#[cfg(feature = "multitask")]
 pub use imp::pthread::mutex::{
     sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_unlock,
+    sys_pthread_mutexattr_getprotocol, sys_pthread_mutexattr_init,
+    sys_pthread_mutexattr_setprotocol,
 };
 #[cfg(feature = "multitask")]
-pub use imp::pthread::{sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_self};
+pub use imp::pthread::rwlock::{
+    sys_pthread_rwlock_destroy, sys_pthread_rwlock_init, sys_pthread_rwlock_rdlock,
+    sys_pthread_rwlock_tryrdlock, sys_pthread_rwlock_trywrlock, sys_pthread_rwlock_unlock,
+    sys_pthread_rwlock_wrlock,
+};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::semaphore::{
+    sys_sem_destroy, sys_sem_getvalue, sys_sem_init, sys_sem_post, sys_sem_timedwait,
+    sys_sem_trywait, sys_sem_wait,
+};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::{
+    sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_self,
+    sys_pthread_setaffinity_np,
+};
@@ -12,6 +12,14 @@
 //!     - `alloc-tlsf`: Use the TLSF allocator.
 //!     - `alloc-slab`: Use the slab allocator.
 //!     - `alloc-buddy`: Use the buddy system allocator.
+//!     - `alloc-page-buddy`: Use the buddy system page allocator instead of
+//!       the bitmap one.
+//!     - `alloc-percpu-cache`: Cache small objects per CPU to reduce lock
+//!       contention of the allocator.
+//!     - `alloc-stats`: Record memory usage statistics and track live
+//!       allocations to find memory leaks.
+//!     - `alloc-debug`: Detect heap corruptions with redzones, poisoning and
+//!       double-free checks.
 //!     - `paging`: Enable page table manipulation.
 //!     - `tls`: Enable thread-local storage.
 //! - Task management
@@ -19,9 +27,12 @@
 //!     - `sched_fifo`: Use the FIFO cooperative scheduler.
 //!     - `sched_rr`: Use the Round-robin preemptive scheduler.
 //!     - `sched_cfs`: Use the Completely Fair Scheduler (CFS) preemptive scheduler.
+//!     - `sched_rt`: Use the real-time fixed-priority preemptive scheduler.
+//!     - `sched_edf`: Use the Earliest Deadline First (EDF) preemptive scheduler.
 //! - Upperlayer stacks (fs, net, display)
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `ext4`: Use ext4 (also ext2/ext3) as the main filesystem instead of FAT.
 //!     - `net`: Enable networking support.
 //!     - `display`: Enable graphics support.
 //! - Device drivers
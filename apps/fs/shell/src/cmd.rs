@@ -24,6 +24,7 @@ const CMD_TABLE: &[(&str, CmdHandler)] = &[
     ("help", do_help),
     ("ls", do_ls),
     ("mkdir", do_mkdir),
+    ("ps", do_ps),
     ("pwd", do_pwd),
     ("rm", do_rm),
     ("uname", do_uname),
@@ -243,6 +244,60 @@ fn do_pwd(_args: &str) {
     println!("{}", path_to_str!(pwd));
 }
 
+fn do_ps(_args: &str) {
+    fn status_of(tid: &str) -> io::Result<Vec<(String, String)>> {
+        let status = fs::read_to_string(&(String::from("/proc/") + tid + "/status"))?;
+        Ok(status
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (String::from(key), String::from(value.trim())))
+            .collect())
+    }
+
+    let mut tids = match fs::read_dir("/proc") {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| String::from(path_to_str!(e.file_name())))
+            .filter_map(|name| Some((name.parse::<u64>().ok()?, name)))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            print_err!("ps", "/proc", e);
+            return;
+        }
+    };
+    tids.sort();
+
+    println!(
+        "{:>5} {:<8} {:>4} {:>3} {:>11} NAME",
+        "TID", "STATE", "PRIO", "CPU", "STACK(kB)"
+    );
+    for (_, tid) in tids {
+        let status = match status_of(&tid) {
+            Ok(status) => status,
+            // The task may have exited.
+            Err(_) => continue,
+        };
+        let field = |key: &str| {
+            status
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or("-", |(_, v)| v.as_str())
+        };
+        let stack = String::from(field("StackUsed").trim_end_matches(" kB"))
+            + "/"
+            + field("StackSize").trim_end_matches(" kB");
+        println!(
+            "{:>5} {:<8} {:>4} {:>3} {:>11} {}",
+            tid,
+            field("State"),
+            field("Priority"),
+            field("Cpu"),
+            stack,
+            field("Name"),
+        );
+    }
+}
+
 fn do_uname(_args: &str) {
     let arch = option_env!("AX_ARCH").unwrap_or("");
     let platform = option_env!("AX_PLATFORM").unwrap_or("");
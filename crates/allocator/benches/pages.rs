@@ -0,0 +1,85 @@
+mod utils;
+
+use allocator::{BaseAllocator, BitmapPageAllocator, BuddyPageAllocator, PageAllocator};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use self::utils::MemoryPool;
+
+const POOL_SIZE: usize = 1024 * 1024 * 128;
+const PAGE_SIZE: usize = 0x1000;
+
+fn single_pages(n: usize, alloc: &mut impl PageAllocator) {
+    let mut pages = Vec::with_capacity(n);
+    for _ in 0..n {
+        pages.push(alloc.alloc_pages(1, PAGE_SIZE).unwrap());
+    }
+    for pos in pages {
+        alloc.dealloc_pages(pos, 1);
+    }
+}
+
+fn huge_pages(n: usize, alloc: &mut impl PageAllocator) {
+    const HUGE_PAGES: usize = 0x20_0000 / PAGE_SIZE; // 2M
+    let mut pages = Vec::with_capacity(n);
+    for _ in 0..n {
+        pages.push(alloc.alloc_pages(HUGE_PAGES, 0x20_0000).unwrap());
+    }
+    for pos in pages {
+        alloc.dealloc_pages(pos, HUGE_PAGES);
+    }
+}
+
+/// Allocates (2/3) and frees (1/3) regions of 1 to 512 pages randomly. When
+/// the memory is exhausted, frees a random region instead.
+fn mixed_pages(n: usize, alloc: &mut impl PageAllocator) {
+    let mut rng = SmallRng::seed_from_u64(0xdead_beef);
+    let mut blocks = Vec::new();
+    for _ in 0..n {
+        if rng.gen_ratio(2, 3) || blocks.is_empty() {
+            let num_pages = rng.gen_range(1..=512);
+            if let Ok(pos) = alloc.alloc_pages(num_pages, PAGE_SIZE) {
+                blocks.push((pos, num_pages));
+                continue;
+            }
+        }
+        if !blocks.is_empty() {
+            let (pos, num_pages) = blocks.swap_remove(rng.gen_range(0..blocks.len()));
+            alloc.dealloc_pages(pos, num_pages);
+        }
+    }
+    for (pos, num_pages) in blocks {
+        alloc.dealloc_pages(pos, num_pages);
+    }
+}
+
+fn bench(c: &mut Criterion, alloc_name: &str, alloc: &mut impl PageAllocator) {
+    let mut g = c.benchmark_group(alloc_name);
+    g.bench_function("single_pages_20K", |b| {
+        b.iter(|| single_pages(black_box(20_000), alloc));
+    });
+    g.bench_function("huge_pages_2M_50", |b| {
+        b.iter(|| huge_pages(black_box(50), alloc));
+    });
+    g.sample_size(10);
+    g.bench_function("mixed_pages_1_512_100K", |b| {
+        b.iter(|| mixed_pages(black_box(100_000), alloc));
+    });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut pool = MemoryPool::new(POOL_SIZE);
+    let region = pool.as_slice();
+    let (start, size) = (region.as_mut_ptr() as usize, region.len());
+
+    let mut bitmap = Box::new(BitmapPageAllocator::<PAGE_SIZE>::new());
+    bitmap.init(start, size);
+    bench(c, "bitmap", bitmap.as_mut());
+
+    let mut buddy = BuddyPageAllocator::<PAGE_SIZE>::new();
+    buddy.init(start, size);
+    bench(c, "buddy", &mut buddy);
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
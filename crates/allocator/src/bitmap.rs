@@ -9,6 +9,9 @@ use crate::{AllocError, AllocResult, BaseAllocator, PageAllocator};
 // Support max 1M * 4096 = 4GB memory.
 type BitAllocUsed = bitmap_allocator::BitAlloc1M;
 
+/// The maximum alignment supported by [`BitmapPageAllocator::alloc_pages`].
+const MAX_ALIGN_1GB: usize = 0x4000_0000;
+
 /// A page-granularity memory allocator based on the [bitmap_allocator].
 ///
 /// It internally uses a bitmap, each bit indicates whether a page has been
@@ -16,9 +19,19 @@ type BitAllocUsed = bitmap_allocator::BitAlloc1M;
 ///
 /// The `PAGE_SIZE` must be a power of two.
 ///
+/// Allocated regions are aligned to `align_pow2` in absolute addresses (up to
+/// 1GB), so that they can be mapped as huge pages. The maximum alignment may
+/// be smaller if the memory region is too large for the bitmap to cover it
+/// from the previous 1GB boundary. Pages beyond the bitmap capacity are not
+/// used.
+///
 /// [bitmap_allocator]: https://github.com/rcore-os/bitmap-allocator
 pub struct BitmapPageAllocator<const PAGE_SIZE: usize> {
+    /// The address of the first bit in the bitmap, which is aligned to
+    /// `max_align`.
     base: usize,
+    /// The maximum alignment supported by [`PageAllocator::alloc_pages`].
+    max_align: usize,
     total_pages: usize,
     used_pages: usize,
     inner: BitAllocUsed,
@@ -29,6 +42,7 @@ impl<const PAGE_SIZE: usize> BitmapPageAllocator<PAGE_SIZE> {
     pub const fn new() -> Self {
         Self {
             base: 0,
+            max_align: MAX_ALIGN_1GB,
             total_pages: 0,
             used_pages: 0,
             inner: BitAllocUsed::DEFAULT,
@@ -41,9 +55,19 @@ impl<const PAGE_SIZE: usize> BaseAllocator for BitmapPageAllocator<PAGE_SIZE> {
         assert!(PAGE_SIZE.is_power_of_two());
         let end = super::align_down(start + size, PAGE_SIZE);
         let start = super::align_up(start, PAGE_SIZE);
-        self.base = start;
+        let capacity = BitAllocUsed::CAP.saturating_mul(PAGE_SIZE);
+        let end = end.min(start.saturating_add(capacity));
         self.total_pages = (end - start) / PAGE_SIZE;
-        self.inner.insert(0..self.total_pages);
+        // Alignments of bit indices are the same as those of the addresses, if
+        // the base is aligned. Align it as much as the capacity allows.
+        let mut align = MAX_ALIGN_1GB;
+        while align > PAGE_SIZE && end - super::align_down(start, align) > capacity {
+            align /= 2;
+        }
+        self.base = super::align_down(start, align);
+        self.max_align = align;
+        let start_idx = (start - self.base) / PAGE_SIZE;
+        self.inner.insert(start_idx..start_idx + self.total_pages);
     }
 
     fn add_memory(&mut self, _start: usize, _size: usize) -> AllocResult {
@@ -55,7 +79,7 @@ impl<const PAGE_SIZE: usize> PageAllocator for BitmapPageAllocator<PAGE_SIZE> {
     const PAGE_SIZE: usize = PAGE_SIZE;
 
     fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        if align_pow2 % PAGE_SIZE != 0 {
+        if align_pow2 % PAGE_SIZE != 0 || align_pow2 > self.max_align {
             return Err(AllocError::InvalidParam);
         }
         let align_pow2 = align_pow2 / PAGE_SIZE;
@@ -78,7 +102,8 @@ impl<const PAGE_SIZE: usize> PageAllocator for BitmapPageAllocator<PAGE_SIZE> {
     fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
         // TODO: not decrease `used_pages` if deallocation failed
         self.used_pages -= num_pages;
-        self.inner.dealloc((pos - self.base) / PAGE_SIZE)
+        let idx = (pos - self.base) / PAGE_SIZE;
+        self.inner.insert(idx..idx + num_pages)
     }
 
     fn total_pages(&self) -> usize {
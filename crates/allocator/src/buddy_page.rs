@@ -0,0 +1,212 @@
+//! Buddy system allocation in page-granularity.
+
+use crate::{AllocError, AllocResult, BaseAllocator, PageAllocator};
+
+/// Blocks contain at most 2<sup>`MAX_ORDER`</sup> pages (1GB with 4K pages).
+const MAX_ORDER: usize = 18;
+
+/// Marks the first page of a free block in the page metadata, the lower bits
+/// store the order of the block.
+const FREE_HEAD: u8 = 0x80;
+
+/// The null link of the free lists.
+const NIL: usize = usize::MAX;
+
+/// Links of a free block, stored in its first page.
+struct FreeBlock {
+    prev: usize,
+    next: usize,
+}
+
+/// A page-granularity memory allocator based on the buddy system.
+///
+/// Free blocks of 2<sup>order</sup> pages are kept in per-order free lists,
+/// which are linked through the free pages themselves. Therefore, unlike
+/// [`BitmapPageAllocator`], it accesses the managed memory, and the first few
+/// pages of the region are used to store one byte of metadata per page.
+///
+/// Both allocation and deallocation take `O(MAX_ORDER)` time regardless of
+/// the number of pages. Blocks are aligned to their size in absolute
+/// addresses (up to 1GB), so that they can be mapped as huge pages. The
+/// unused pages of a block (when `num_pages` is not a power of two) are given
+/// back to the free lists.
+///
+/// The `PAGE_SIZE` must be a power of two.
+///
+/// [`BitmapPageAllocator`]: crate::BitmapPageAllocator
+pub struct BuddyPageAllocator<const PAGE_SIZE: usize> {
+    /// Start address of the allocatable pages.
+    start: usize,
+    /// End address of the allocatable pages.
+    end: usize,
+    /// Address of the metadata array, one byte per page in `[start, end)`.
+    meta: usize,
+    total_pages: usize,
+    used_pages: usize,
+    free_lists: [usize; MAX_ORDER + 1],
+}
+
+impl<const PAGE_SIZE: usize> BuddyPageAllocator<PAGE_SIZE> {
+    /// Creates a new empty `BuddyPageAllocator`.
+    pub const fn new() -> Self {
+        Self {
+            start: 0,
+            end: 0,
+            meta: 0,
+            total_pages: 0,
+            used_pages: 0,
+            free_lists: [NIL; MAX_ORDER + 1],
+        }
+    }
+
+    const fn block_size(order: usize) -> usize {
+        PAGE_SIZE << order
+    }
+
+    fn meta_mut(&mut self, addr: usize) -> &mut u8 {
+        debug_assert!(self.start <= addr && addr < self.end);
+        let idx = (addr - self.start) / PAGE_SIZE;
+        unsafe { &mut *(self.meta as *mut u8).add(idx) }
+    }
+
+    fn node(addr: usize) -> &'static mut FreeBlock {
+        unsafe { &mut *(addr as *mut FreeBlock) }
+    }
+
+    fn push(&mut self, addr: usize, order: usize) {
+        *self.meta_mut(addr) = FREE_HEAD | order as u8;
+        let head = self.free_lists[order];
+        if head != NIL {
+            Self::node(head).prev = addr;
+        }
+        *Self::node(addr) = FreeBlock {
+            prev: NIL,
+            next: head,
+        };
+        self.free_lists[order] = addr;
+    }
+
+    fn remove(&mut self, addr: usize, order: usize) {
+        *self.meta_mut(addr) = 0;
+        let FreeBlock { prev, next } = *Self::node(addr);
+        if prev != NIL {
+            Self::node(prev).next = next;
+        } else {
+            self.free_lists[order] = next;
+        }
+        if next != NIL {
+            Self::node(next).prev = prev;
+        }
+    }
+
+    /// Whether `addr` is the start of a free block of the given order.
+    fn is_free_block(&mut self, addr: usize, order: usize) -> bool {
+        addr >= self.start
+            && addr + Self::block_size(order) <= self.end
+            && *self.meta_mut(addr) == FREE_HEAD | order as u8
+    }
+
+    /// Frees a block, and merges it with its buddies as much as possible.
+    fn free_block(&mut self, mut addr: usize, mut order: usize) {
+        while order < MAX_ORDER {
+            let buddy = addr ^ Self::block_size(order);
+            if !self.is_free_block(buddy, order) {
+                break;
+            }
+            self.remove(buddy, order);
+            addr = addr.min(buddy);
+            order += 1;
+        }
+        self.push(addr, order);
+    }
+
+    /// Frees all pages in `[start, end)` as the largest possible blocks.
+    fn free_range(&mut self, mut start: usize, end: usize) {
+        while start < end {
+            let mut order = (start.trailing_zeros() as usize - PAGE_SIZE.trailing_zeros() as usize)
+                .min(MAX_ORDER);
+            while start + Self::block_size(order) > end {
+                order -= 1;
+            }
+            self.free_block(start, order);
+            start += Self::block_size(order);
+        }
+    }
+}
+
+impl<const PAGE_SIZE: usize> BaseAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    fn init(&mut self, start: usize, size: usize) {
+        assert!(PAGE_SIZE.is_power_of_two() && PAGE_SIZE >= core::mem::size_of::<FreeBlock>());
+        let end = super::align_down(start + size, PAGE_SIZE);
+        let start = super::align_up(start, PAGE_SIZE);
+        // The metadata occupies the first pages of the region.
+        let num_pages = (end - start) / PAGE_SIZE;
+        let meta_size = super::align_up(num_pages, PAGE_SIZE);
+        assert!(meta_size < end - start);
+        self.meta = start;
+        self.start = start + meta_size;
+        self.end = end;
+        self.total_pages = (self.end - self.start) / PAGE_SIZE;
+        unsafe { core::ptr::write_bytes(self.meta as *mut u8, 0, self.total_pages) };
+        self.free_range(self.start, self.end);
+    }
+
+    fn add_memory(&mut self, _start: usize, _size: usize) -> AllocResult {
+        Err(AllocError::NoMemory) // unsupported
+    }
+}
+
+impl<const PAGE_SIZE: usize> PageAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    const PAGE_SIZE: usize = PAGE_SIZE;
+
+    fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        if align_pow2 % PAGE_SIZE != 0 || !(align_pow2 / PAGE_SIZE).is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        if num_pages == 0 {
+            return Err(AllocError::InvalidParam);
+        }
+        let order = num_pages
+            .next_power_of_two()
+            .max(align_pow2 / PAGE_SIZE)
+            .trailing_zeros() as usize;
+        if order > MAX_ORDER {
+            return Err(AllocError::InvalidParam);
+        }
+
+        let mut cur = (order..=MAX_ORDER)
+            .find(|&o| self.free_lists[o] != NIL)
+            .ok_or(AllocError::NoMemory)?;
+        let addr = self.free_lists[cur];
+        self.remove(addr, cur);
+        // Split the block, and give back the upper halves.
+        while cur > order {
+            cur -= 1;
+            self.push(addr + Self::block_size(cur), cur);
+        }
+        // Give back the pages beyond `num_pages`.
+        let used_end = addr + num_pages * PAGE_SIZE;
+        self.free_range(used_end, addr + Self::block_size(order));
+        self.used_pages += num_pages;
+        Ok(addr)
+    }
+
+    fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
+        let end = pos + num_pages * PAGE_SIZE;
+        debug_assert!(self.start <= pos && end <= self.end && pos % PAGE_SIZE == 0);
+        self.used_pages -= num_pages;
+        self.free_range(pos, end);
+    }
+
+    fn total_pages(&self) -> usize {
+        self.total_pages
+    }
+
+    fn used_pages(&self) -> usize {
+        self.used_pages
+    }
+
+    fn available_pages(&self) -> usize {
+        self.total_pages - self.used_pages
+    }
+}
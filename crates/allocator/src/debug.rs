@@ -0,0 +1,267 @@
+//! Heap debugging with redzones, poisoning and double-free detection.
+
+use super::{AllocResult, BaseAllocator, ByteAllocator};
+use core::alloc::Layout;
+use core::mem::size_of;
+use core::ptr::NonNull;
+
+/// Minimum size of the redzones before and after each allocation.
+const REDZONE_SIZE: usize = 64;
+/// Number of freed blocks held back before given to the inner allocator.
+const QUARANTINE_LEN: usize = 64;
+
+/// Fills the redzones.
+const REDZONE_BYTE: u8 = 0xfd;
+/// Fills newly allocated memory.
+const ALLOC_BYTE: u8 = 0xcd;
+/// Fills freed memory.
+const FREE_BYTE: u8 = 0xdd;
+
+const STATE_ALLOCATED: usize = 0xa110_c8ed_a110_c8ed_u64 as usize;
+const STATE_FREED: usize = 0xf4ee_d0ff_f4ee_d0ff_u64 as usize;
+
+/// Metadata stored at the end of the front redzone.
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Header {
+    size: usize,
+    align: usize,
+    state: usize,
+}
+
+/// A heap corruption detected by [`DebugByteAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapError {
+    /// The block has already been freed.
+    DoubleFree,
+    /// The pointer is not allocated by this allocator, or the metadata in
+    /// front of it is overwritten.
+    InvalidPointer,
+    /// The layout passed to `dealloc` differs from the one used in `alloc`.
+    LayoutMismatch {
+        /// Layout used in `alloc`.
+        expected: Layout,
+    },
+    /// The front redzone is overwritten, i.e., a buffer underflow.
+    FrontRedzone,
+    /// The back redzone is overwritten, i.e., a buffer overflow.
+    BackRedzone,
+    /// The block is written after it was freed.
+    UseAfterFree,
+}
+
+/// A wrapper of a [`ByteAllocator`] that detects heap corruptions.
+///
+/// Each allocation is surrounded by redzones filled with a known pattern,
+/// and the size and alignment of the allocation are stored in front of it.
+/// When the block is deallocated, the stored layout and the redzones are
+/// checked. Freed blocks are filled with another pattern and kept in a
+/// quarantine of the last 64 freed blocks, so that double frees and writes
+/// after free are detected before the memory is reused.
+///
+/// Errors are reported through the [`log`] crate, with the address and the
+/// layout passed to `dealloc`. Double frees and invalid pointers are ignored,
+/// while the other blocks are still freed with the layout used in `alloc`.
+/// The most recent error is also available from
+/// [`last_error`](Self::last_error).
+pub struct DebugByteAllocator<A: ByteAllocator> {
+    inner: A,
+    quarantine: [usize; QUARANTINE_LEN],
+    quarantine_head: usize,
+    quarantine_len: usize,
+    error_count: usize,
+    last_error: Option<HeapError>,
+}
+
+impl<A: ByteAllocator> DebugByteAllocator<A> {
+    /// Creates a new [`DebugByteAllocator`] that wraps `inner`.
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            quarantine: [0; QUARANTINE_LEN],
+            quarantine_head: 0,
+            quarantine_len: 0,
+            error_count: 0,
+            last_error: None,
+        }
+    }
+
+    /// Returns the number of heap errors detected so far.
+    pub fn error_count(&self) -> usize {
+        self.error_count
+    }
+
+    /// Returns the most recently detected heap error.
+    pub fn last_error(&self) -> Option<HeapError> {
+        self.last_error
+    }
+
+    /// Gives back all blocks in the quarantine to the inner allocator.
+    pub fn flush_quarantine(&mut self) {
+        while self.quarantine_len > 0 {
+            self.evict();
+        }
+    }
+
+    /// Size of the front redzone, including the header.
+    const fn front_size(align: usize) -> usize {
+        if align > REDZONE_SIZE {
+            align
+        } else {
+            REDZONE_SIZE
+        }
+    }
+
+    /// The layout of the whole block allocated from the inner allocator.
+    fn inner_layout(size: usize, align: usize) -> Layout {
+        let size = Self::front_size(align) + size + REDZONE_SIZE;
+        let align = align.max(core::mem::align_of::<Header>());
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    fn header(addr: usize) -> *mut Header {
+        (addr - size_of::<Header>()) as *mut Header
+    }
+
+    fn report(&mut self, err: HeapError, addr: usize, layout: Layout) {
+        log::error!(
+            "heap error: {:?} at {:#x}, size: {}, align: {}",
+            err,
+            addr,
+            layout.size(),
+            layout.align()
+        );
+        self.error_count += 1;
+        self.last_error = Some(err);
+    }
+
+    /// Checks the header and redzones of an allocated block.
+    fn check_allocated(&mut self, addr: usize, layout: Layout) -> Option<Header> {
+        let header = unsafe { Self::header(addr).read() };
+        match header.state {
+            STATE_ALLOCATED => {}
+            STATE_FREED => {
+                self.report(HeapError::DoubleFree, addr, layout);
+                return None;
+            }
+            _ => {
+                self.report(HeapError::InvalidPointer, addr, layout);
+                return None;
+            }
+        }
+        if header.size != layout.size() || header.align != layout.align() {
+            // The stored layout is trusted to free the block correctly.
+            let expected = Layout::from_size_align(header.size, header.align).unwrap();
+            self.report(HeapError::LayoutMismatch { expected }, addr, layout);
+        }
+        let front_size = Self::front_size(header.align) - size_of::<Header>();
+        let front = unsafe {
+            core::slice::from_raw_parts(
+                (addr - Self::front_size(header.align)) as *const u8,
+                front_size,
+            )
+        };
+        if front.iter().any(|&b| b != REDZONE_BYTE) {
+            self.report(HeapError::FrontRedzone, addr, layout);
+        }
+        let back =
+            unsafe { core::slice::from_raw_parts((addr + header.size) as *const u8, REDZONE_SIZE) };
+        if back.iter().any(|&b| b != REDZONE_BYTE) {
+            self.report(HeapError::BackRedzone, addr, layout);
+        }
+        Some(header)
+    }
+
+    /// Gives back the oldest block in the quarantine to the inner allocator.
+    fn evict(&mut self) {
+        let idx = (self.quarantine_head + QUARANTINE_LEN - self.quarantine_len) % QUARANTINE_LEN;
+        let addr = self.quarantine[idx];
+        self.quarantine_len -= 1;
+
+        let header = unsafe { Self::header(addr).read() };
+        let layout = Layout::from_size_align(header.size, header.align);
+        let (STATE_FREED, Ok(layout)) = (header.state, layout) else {
+            // The header is overwritten, the block can't be freed safely.
+            self.report(HeapError::UseAfterFree, addr, Layout::new::<u8>());
+            return;
+        };
+        let body = unsafe { core::slice::from_raw_parts(addr as *const u8, header.size) };
+        if body.iter().any(|&b| b != FREE_BYTE) {
+            self.report(HeapError::UseAfterFree, addr, layout);
+        }
+        let start = addr - Self::front_size(header.align);
+        let inner_layout = Self::inner_layout(header.size, header.align);
+        self.inner.dealloc(
+            unsafe { NonNull::new_unchecked(start as *mut u8) },
+            inner_layout,
+        );
+    }
+}
+
+impl<A: ByteAllocator> BaseAllocator for DebugByteAllocator<A> {
+    fn init(&mut self, start: usize, size: usize) {
+        self.inner.init(start, size)
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        self.inner.add_memory(start, size)
+    }
+}
+
+impl<A: ByteAllocator> ByteAllocator for DebugByteAllocator<A> {
+    fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        let inner_layout = Self::inner_layout(layout.size(), layout.align());
+        let start = match self.inner.alloc(inner_layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(_) if self.quarantine_len > 0 => {
+                self.flush_quarantine();
+                self.inner.alloc(inner_layout)?.as_ptr()
+            }
+            Err(e) => return Err(e),
+        };
+        let front_size = Self::front_size(layout.align());
+        unsafe {
+            let addr = start.add(front_size);
+            start.write_bytes(REDZONE_BYTE, front_size - size_of::<Header>());
+            Self::header(addr as usize).write(Header {
+                size: layout.size(),
+                align: layout.align(),
+                state: STATE_ALLOCATED,
+            });
+            addr.write_bytes(ALLOC_BYTE, layout.size());
+            addr.add(layout.size())
+                .write_bytes(REDZONE_BYTE, REDZONE_SIZE);
+            Ok(NonNull::new_unchecked(addr))
+        }
+    }
+
+    fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
+        let addr = pos.as_ptr() as usize;
+        let Some(mut header) = self.check_allocated(addr, layout) else {
+            return;
+        };
+        header.state = STATE_FREED;
+        unsafe {
+            Self::header(addr).write(header);
+            pos.as_ptr().write_bytes(FREE_BYTE, header.size);
+        }
+        if self.quarantine_len == QUARANTINE_LEN {
+            self.evict();
+        }
+        self.quarantine[self.quarantine_head] = addr;
+        self.quarantine_head = (self.quarantine_head + 1) % QUARANTINE_LEN;
+        self.quarantine_len += 1;
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.inner.total_bytes()
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.inner.used_bytes()
+    }
+
+    fn available_bytes(&self) -> usize {
+        self.inner.available_bytes()
+    }
+}
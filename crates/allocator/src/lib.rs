@@ -5,8 +5,11 @@
 //! - [`ByteAllocator`]: Byte-granularity memory allocator. (e.g.,
 //!   [`BuddyByteAllocator`], [`SlabByteAllocator`])
 //! - [`PageAllocator`]: Page-granularity memory allocator. (e.g.,
-//!   [`BitmapPageAllocator`])
+//!   [`BitmapPageAllocator`], [`BuddyPageAllocator`])
 //! - [`IdAllocator`]: Used to allocate unique IDs.
+//!
+//! A byte allocator can be wrapped in a [`DebugByteAllocator`] (with the
+//! `debug` feature) to detect heap corruptions.
 
 #![no_std]
 #![feature(result_option_inspect)]
@@ -17,6 +20,11 @@ mod bitmap;
 #[cfg(feature = "bitmap")]
 pub use bitmap::BitmapPageAllocator;
 
+#[cfg(feature = "buddy_page")]
+mod buddy_page;
+#[cfg(feature = "buddy_page")]
+pub use buddy_page::BuddyPageAllocator;
+
 #[cfg(feature = "buddy")]
 mod buddy;
 #[cfg(feature = "buddy")]
@@ -32,6 +40,11 @@ mod tlsf;
 #[cfg(feature = "tlsf")]
 pub use tlsf::TlsfByteAllocator;
 
+#[cfg(feature = "debug")]
+mod debug;
+#[cfg(feature = "debug")]
+pub use debug::{DebugByteAllocator, HeapError};
+
 use core::alloc::Layout;
 use core::ptr::NonNull;
 
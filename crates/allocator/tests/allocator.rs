@@ -5,7 +5,10 @@ use std::alloc::{Allocator, Layout};
 use std::collections::BTreeMap;
 use std::io::Write;
 
-use allocator::{AllocatorRc, BuddyByteAllocator, SlabByteAllocator, TlsfByteAllocator};
+use allocator::{
+    AllocError, AllocatorRc, BaseAllocator, BitmapPageAllocator, BuddyByteAllocator,
+    BuddyPageAllocator, PageAllocator, SlabByteAllocator, TlsfByteAllocator,
+};
 use rand::{prelude::SliceRandom, Rng};
 
 const POOL_SIZE: usize = 1024 * 1024 * 128;
@@ -84,6 +87,60 @@ pub fn test_alignment(n: usize, alloc: &(impl Allocator + Clone)) {
     }
 }
 
+pub fn test_huge_pages<A: PageAllocator>(alloc: &mut A) {
+    const HUGE_SIZE: usize = 0x20_0000; // 2M
+    let page_size = A::PAGE_SIZE;
+    let total_pages = alloc.available_pages();
+    let mut huge_pages = vec![];
+    while let Ok(pos) = alloc.alloc_pages(HUGE_SIZE / page_size, HUGE_SIZE) {
+        assert_eq!(pos % HUGE_SIZE, 0);
+        huge_pages.push(pos);
+    }
+    assert!(!huge_pages.is_empty());
+    for pos in huge_pages {
+        alloc.dealloc_pages(pos, HUGE_SIZE / page_size);
+    }
+    assert_eq!(alloc.available_pages(), total_pages);
+    // All pages of the huge pages are freed.
+    for _ in 0..total_pages {
+        alloc.alloc_pages(1, page_size).unwrap();
+    }
+    assert!(alloc.alloc_pages(1, page_size).is_err());
+}
+
+pub fn test_mixed_pages<A: PageAllocator>(n: usize, alloc: &mut A) {
+    let page_size = A::PAGE_SIZE;
+    let mut rng = rand::thread_rng();
+    let mut blocks: Vec<(usize, usize, u8)> = vec![];
+    for i in 0..n {
+        if rng.gen_ratio(2, 3) || blocks.len() == 0 {
+            // allocate and fill a region
+            let num_pages = rng.gen_range(1..=512);
+            let align = page_size << rng.gen_range(0..4);
+            if let Ok(pos) = alloc.alloc_pages(num_pages, align) {
+                assert_eq!(pos % align, 0);
+                let region = unsafe {
+                    core::slice::from_raw_parts_mut(pos as *mut u8, num_pages * page_size)
+                };
+                region.fill(i as u8);
+                blocks.push((pos, num_pages, i as u8));
+                continue;
+            }
+        }
+        // check and free a region
+        let idx = rng.gen_range(0..blocks.len());
+        let (pos, num_pages, byte) = blocks.swap_remove(idx);
+        let region =
+            unsafe { core::slice::from_raw_parts(pos as *const u8, num_pages * page_size) };
+        assert!(region.iter().all(|&b| b == byte));
+        alloc.dealloc_pages(pos, num_pages);
+    }
+    for (pos, num_pages, _) in blocks {
+        alloc.dealloc_pages(pos, num_pages);
+    }
+    assert_eq!(alloc.used_pages(), 0);
+}
+
 fn run_test(f: impl FnOnce(&mut [u8])) {
     let layout = Layout::from_size_align(POOL_SIZE, 4096).unwrap();
     let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
@@ -141,3 +198,44 @@ fn tlsf_alloc() {
         test_btree_map(50_000, &alloc);
     })
 }
+
+#[test]
+fn bitmap_page_alloc() {
+    // The page allocator does not access the memory, use an unaligned region.
+    let mut alloc = BitmapPageAllocator::<4096>::new();
+    alloc.init(0x8020_1000, 0x400_0000);
+    test_huge_pages(&mut alloc);
+}
+
+#[test]
+fn bitmap_page_alloc_large() {
+    // The bitmap can only cover the region from the previous 2M boundary.
+    let mut alloc = BitmapPageAllocator::<4096>::new();
+    alloc.init(0x8020_1000, 0xfff0_0000);
+    assert_eq!(alloc.total_pages(), 0xfff00);
+    assert!(matches!(
+        alloc.alloc_pages(1, 0x40_0000),
+        Err(AllocError::InvalidParam)
+    ));
+    assert_eq!(alloc.alloc_pages(0x200, 0x20_0000).ok(), Some(0x8040_0000));
+
+    // Pages beyond the capacity are not used.
+    let mut alloc = BitmapPageAllocator::<4096>::new();
+    alloc.init(0x8020_1000, 0x1_0000_1000);
+    assert_eq!(alloc.total_pages(), 1 << 20);
+    assert!(matches!(
+        alloc.alloc_pages(2, 0x2000),
+        Err(AllocError::InvalidParam)
+    ));
+    assert_eq!(alloc.alloc_pages(2, 0x1000).ok(), Some(0x8020_1000));
+}
+
+#[test]
+fn buddy_page_alloc() {
+    run_test(|pool| {
+        let mut alloc = BuddyPageAllocator::<4096>::new();
+        alloc.init(pool.as_mut_ptr() as usize, pool.len());
+        test_mixed_pages(10_000, &mut alloc);
+        test_huge_pages(&mut alloc);
+    })
+}
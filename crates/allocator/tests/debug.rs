@@ -0,0 +1,144 @@
+use std::alloc::Layout;
+use std::ptr::NonNull;
+
+use allocator::{BaseAllocator, ByteAllocator, DebugByteAllocator, HeapError, TlsfByteAllocator};
+
+const POOL_SIZE: usize = 1024 * 1024 * 16;
+
+fn run_test(f: impl FnOnce(&mut DebugByteAllocator<TlsfByteAllocator>)) {
+    let layout = Layout::from_size_align(POOL_SIZE, 4096).unwrap();
+    let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+
+    let mut alloc = DebugByteAllocator::new(TlsfByteAllocator::new());
+    alloc.init(ptr as usize, POOL_SIZE);
+    f(&mut alloc);
+
+    unsafe { std::alloc::dealloc(ptr, layout) };
+}
+
+fn write(ptr: NonNull<u8>, offset: isize, val: u8) {
+    unsafe { ptr.as_ptr().offset(offset).write(val) };
+}
+
+#[test]
+fn debug_alloc() {
+    run_test(|alloc| {
+        let mut blocks = Vec::new();
+        for i in 0..1000 {
+            let size = (i * 37) % 3000 + 1;
+            let align = 1 << (i % 13);
+            let layout = Layout::from_size_align(size, align).unwrap();
+            let ptr = alloc.alloc(layout).unwrap();
+            assert_eq!(ptr.as_ptr() as usize % align, 0);
+            unsafe { ptr.as_ptr().write_bytes(0xab, size) };
+            blocks.push((ptr, layout));
+        }
+        for (ptr, layout) in blocks {
+            alloc.dealloc(ptr, layout);
+        }
+        alloc.flush_quarantine();
+        assert_eq!(alloc.error_count(), 0);
+        assert_eq!(alloc.used_bytes(), 0);
+    });
+}
+
+#[test]
+fn debug_poison() {
+    run_test(|alloc| {
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let ptr = alloc.alloc(layout).unwrap();
+        let bytes = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), 64) };
+        assert!(bytes.iter().all(|&b| b == 0xcd));
+        alloc.dealloc(ptr, layout);
+        let bytes = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), 64) };
+        assert!(bytes.iter().all(|&b| b == 0xdd));
+        assert_eq!(alloc.error_count(), 0);
+    });
+}
+
+#[test]
+fn debug_redzones() {
+    run_test(|alloc| {
+        let layout = Layout::from_size_align(100, 16).unwrap();
+        let ptr = alloc.alloc(layout).unwrap();
+        write(ptr, 100, 0); // overflow by one byte
+        alloc.dealloc(ptr, layout);
+        assert_eq!(alloc.last_error(), Some(HeapError::BackRedzone));
+
+        let ptr = alloc.alloc(layout).unwrap();
+        write(ptr, -30, 0);
+        alloc.dealloc(ptr, layout);
+        assert_eq!(alloc.last_error(), Some(HeapError::FrontRedzone));
+        assert_eq!(alloc.error_count(), 2);
+
+        // Corrupted blocks are still freed.
+        alloc.flush_quarantine();
+        assert_eq!(alloc.used_bytes(), 0);
+    });
+}
+
+#[test]
+fn debug_double_free() {
+    run_test(|alloc| {
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        let ptr = alloc.alloc(layout).unwrap();
+        alloc.dealloc(ptr, layout);
+        alloc.dealloc(ptr, layout);
+        assert_eq!(alloc.last_error(), Some(HeapError::DoubleFree));
+        assert_eq!(alloc.error_count(), 1);
+
+        // Only freed once.
+        alloc.flush_quarantine();
+        assert_eq!(alloc.used_bytes(), 0);
+    });
+}
+
+#[test]
+fn debug_layout_mismatch() {
+    run_test(|alloc| {
+        let layout = Layout::from_size_align(48, 8).unwrap();
+        let ptr = alloc.alloc(layout).unwrap();
+        alloc.dealloc(ptr, Layout::from_size_align(64, 8).unwrap());
+        assert_eq!(
+            alloc.last_error(),
+            Some(HeapError::LayoutMismatch { expected: layout })
+        );
+        assert_eq!(alloc.error_count(), 1);
+
+        // Freed with the original layout.
+        alloc.flush_quarantine();
+        assert_eq!(alloc.used_bytes(), 0);
+    });
+}
+
+#[test]
+fn debug_use_after_free() {
+    run_test(|alloc| {
+        let layout = Layout::from_size_align(128, 8).unwrap();
+        let ptr = alloc.alloc(layout).unwrap();
+        alloc.dealloc(ptr, layout);
+        write(ptr, 10, 0);
+        assert_eq!(alloc.error_count(), 0);
+
+        // Detected when the block leaves the quarantine.
+        for _ in 0..64 {
+            let p = alloc.alloc(layout).unwrap();
+            alloc.dealloc(p, layout);
+        }
+        assert_eq!(alloc.last_error(), Some(HeapError::UseAfterFree));
+        assert_eq!(alloc.error_count(), 1);
+    });
+}
+
+#[test]
+fn debug_invalid_pointer() {
+    run_test(|alloc| {
+        let layout = Layout::from_size_align(256, 8).unwrap();
+        let ptr = alloc.alloc(layout).unwrap();
+        let inner = unsafe { NonNull::new_unchecked(ptr.as_ptr().add(64)) };
+        alloc.dealloc(inner, Layout::from_size_align(16, 8).unwrap());
+        assert_eq!(alloc.last_error(), Some(HeapError::InvalidPointer));
+        alloc.dealloc(ptr, layout);
+        assert_eq!(alloc.error_count(), 1);
+    });
+}
@@ -41,8 +41,12 @@ pub enum AxError {
     ConnectionRefused,
     /// The connection was reset by the remote server.
     ConnectionReset,
+    /// A link or rename crosses filesystems.
+    CrossesDevices,
     /// A non-empty directory was specified where an empty directory was expected.
     DirectoryNotEmpty,
+    /// Too many symbolic links were encountered in resolving a path.
+    FilesystemLoop,
     /// Data not valid for the operation were encountered.
     ///
     /// Unlike [`InvalidInput`], this typically means that the operation
@@ -70,6 +74,8 @@ pub enum AxError {
     NotFound,
     /// The operation lacked the necessary privileges to complete.
     PermissionDenied,
+    /// The filesystem or storage medium is read-only, but a write operation was attempted.
+    ReadOnlyFilesystem,
     /// Device or resource is busy.
     ResourceBusy,
     /// The underlying storage (typically, a filesystem) is full.
@@ -194,7 +200,9 @@ impl AxError {
             AlreadyExists => "Entity already exists",
             ConnectionRefused => "Connection refused",
             ConnectionReset => "Connection reset",
+            CrossesDevices => "Cross-device link",
             DirectoryNotEmpty => "Directory not empty",
+            FilesystemLoop => "Too many levels of symbolic links",
             InvalidData => "Invalid data",
             InvalidInput => "Invalid input parameter",
             Io => "I/O error",
@@ -204,6 +212,7 @@ impl AxError {
             NotConnected => "Not connected",
             NotFound => "Entity not found",
             PermissionDenied => "Permission denied",
+            ReadOnlyFilesystem => "Read-only filesystem",
             ResourceBusy => "Resource busy",
             StorageFull => "No storage space",
             UnexpectedEof => "Unexpected end of file",
@@ -247,7 +256,9 @@ impl From<AxError> for LinuxError {
             BadAddress | BadState => LinuxError::EFAULT,
             ConnectionRefused => LinuxError::ECONNREFUSED,
             ConnectionReset => LinuxError::ECONNRESET,
+            CrossesDevices => LinuxError::EXDEV,
             DirectoryNotEmpty => LinuxError::ENOTEMPTY,
+            FilesystemLoop => LinuxError::ELOOP,
             InvalidInput | InvalidData => LinuxError::EINVAL,
             Io => LinuxError::EIO,
             IsADirectory => LinuxError::EISDIR,
@@ -256,6 +267,7 @@ impl From<AxError> for LinuxError {
             NotConnected => LinuxError::ENOTCONN,
             NotFound => LinuxError::ENOENT,
             PermissionDenied => LinuxError::EACCES,
+            ReadOnlyFilesystem => LinuxError::EROFS,
             ResourceBusy => LinuxError::EBUSY,
             StorageFull => LinuxError::ENOSPC,
             Unsupported => LinuxError::ENOSYS,
@@ -283,7 +295,7 @@ mod tests {
     #[test]
     fn test_try_from() {
         let max_code = core::mem::variant_count::<AxError>() as i32;
-        assert_eq!(max_code, 22);
+        assert_eq!(max_code, 25);
         assert_eq!(max_code, AxError::WriteZero.code());
 
         assert_eq!(AxError::AddrInUse.code(), 1);
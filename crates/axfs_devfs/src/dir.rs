@@ -2,6 +2,7 @@ use alloc::collections::BTreeMap;
 use alloc::sync::{Arc, Weak};
 use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
 use axfs_vfs::{VfsError, VfsResult};
+use core::time::Duration;
 use spin::RwLock;
 
 /// The directory node in the device filesystem.
@@ -10,6 +11,8 @@ use spin::RwLock;
 pub struct DirNode {
     parent: RwLock<Weak<dyn VfsNodeOps>>,
     children: RwLock<BTreeMap<&'static str, VfsNodeRef>>,
+    /// Time when the directory is created or the last node is added.
+    mtime: RwLock<Duration>,
 }
 
 impl DirNode {
@@ -18,6 +21,7 @@ impl DirNode {
         Arc::new(Self {
             parent: RwLock::new(parent),
             children: RwLock::new(BTreeMap::new()),
+            mtime: RwLock::new(axfs_vfs::current_time()),
         })
     }
 
@@ -29,19 +33,26 @@ impl DirNode {
     pub fn mkdir(self: &Arc<Self>, name: &'static str) -> Arc<Self> {
         let parent = self.clone() as VfsNodeRef;
         let node = Self::new(Some(&parent));
-        self.children.write().insert(name, node.clone());
+        self.add(name, node.clone());
         node
     }
 
     /// Add a node to this directory.
     pub fn add(&self, name: &'static str, node: VfsNodeRef) {
         self.children.write().insert(name, node);
+        *self.mtime.write() = axfs_vfs::current_time();
     }
 }
 
 impl VfsNodeOps for DirNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_dir(4096, 0))
+        let children = self.children.read();
+        let subdirs = children.values().filter(|n| n.as_any().is::<DirNode>());
+        let mtime = *self.mtime.read();
+        let mut attr = VfsNodeAttr::new_dir(4096, 0);
+        attr.set_nlink(2 + subdirs.count() as u64);
+        attr.set_times(mtime, mtime, mtime);
+        Ok(attr)
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
@@ -112,4 +112,6 @@ fn test_devfs() {
 
     test_devfs_ops(&devfs).unwrap();
     test_get_parent(&devfs).unwrap();
+
+    assert_eq!(devfs.root_dir().get_attr().unwrap().nlink(), 3);
 }
@@ -0,0 +1,293 @@
+//! Allocation of blocks and inodes with the bitmaps of the groups.
+
+use alloc::{vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::crc::crc32c;
+use crate::ext4::Ext4;
+use crate::layout::*;
+
+fn test_bit(bitmap: &[u8], bit: u32) -> bool {
+    bitmap[bit as usize / 8] & (1 << (bit % 8)) != 0
+}
+
+fn set_bit(bitmap: &mut [u8], bit: u32) {
+    bitmap[bit as usize / 8] |= 1 << (bit % 8);
+}
+
+fn clear_bit(bitmap: &mut [u8], bit: u32) {
+    bitmap[bit as usize / 8] &= !(1 << (bit % 8));
+}
+
+/// Returns the first zero bit in `start..end`.
+fn find_zero(bitmap: &[u8], start: u32, end: u32) -> Option<u32> {
+    (start..end).find(|&bit| !test_bit(bitmap, bit))
+}
+
+fn is_power_of(mut n: u32, base: u32) -> bool {
+    while n > 1 && n.is_multiple_of(base) {
+        n /= base;
+    }
+    n == 1
+}
+
+impl Ext4 {
+    pub fn group_of_block(&self, block: u64) -> u32 {
+        ((block - self.sb.first_data_block() as u64) / self.sb.blocks_per_group() as u64) as u32
+    }
+
+    pub fn group_first_block(&self, g: u32) -> u64 {
+        self.sb.first_data_block() as u64 + g as u64 * self.sb.blocks_per_group() as u64
+    }
+
+    fn blocks_in_group(&self, g: u32) -> u32 {
+        let rest = self.sb.blocks_count() - self.group_first_block(g);
+        rest.min(self.sb.blocks_per_group() as u64) as u32
+    }
+
+    fn has_super_backup(&self, g: u32) -> bool {
+        if self.sb.has_compat(COMPAT_SPARSE_SUPER2) {
+            return g == 0 || self.sb.backup_bgs().contains(&g);
+        }
+        g <= 1
+            || !self.sb.has_ro_compat(RO_COMPAT_SPARSE_SUPER)
+            || is_power_of(g, 3)
+            || is_power_of(g, 5)
+            || is_power_of(g, 7)
+    }
+
+    /// Computes the block bitmap of a group with `BLOCK_UNINIT`, where only
+    /// the metadata blocks are in use.
+    fn init_block_bitmap(&self, g: u32) -> Vec<u8> {
+        let mut bitmap = vec![0; self.block_size];
+        let start = self.group_first_block(g);
+        let count = self.blocks_in_group(g);
+        if self.has_super_backup(g) {
+            let desc_per_block = (self.block_size / self.sb.desc_size()) as u32;
+            let gdt_blocks = self.group_count().div_ceil(desc_per_block);
+            for bit in 0..1 + gdt_blocks + self.sb.reserved_gdt_blocks() {
+                set_bit(&mut bitmap, bit);
+            }
+        }
+        // The bitmaps and inode tables of any group may be placed here with
+        // `flex_bg`.
+        let itable_blocks =
+            (self.sb.inodes_per_group() as usize * self.inode_size).div_ceil(self.block_size);
+        let mut mark = |block: u64| {
+            if block >= start && block < start + count as u64 {
+                set_bit(&mut bitmap, (block - start) as u32);
+            }
+        };
+        for desc in &self.groups {
+            mark(desc.block_bitmap());
+            mark(desc.inode_bitmap());
+            for i in 0..itable_blocks as u64 {
+                mark(desc.inode_table() + i);
+            }
+        }
+        for bit in count..self.block_size as u32 * 8 {
+            set_bit(&mut bitmap, bit);
+        }
+        bitmap
+    }
+
+    fn read_block_bitmap(&mut self, g: u32) -> VfsResult<Vec<u8>> {
+        let desc = &self.groups[g as usize];
+        if self.has_group_csum() && desc.flags() & BG_BLOCK_UNINIT != 0 {
+            Ok(self.init_block_bitmap(g))
+        } else {
+            self.read_block(desc.block_bitmap())
+        }
+    }
+
+    fn read_inode_bitmap(&mut self, g: u32) -> VfsResult<Vec<u8>> {
+        let desc = &self.groups[g as usize];
+        if self.has_group_csum() && desc.flags() & BG_INODE_UNINIT != 0 {
+            let mut bitmap = vec![0; self.block_size];
+            for bit in self.sb.inodes_per_group()..self.block_size as u32 * 8 {
+                set_bit(&mut bitmap, bit);
+            }
+            Ok(bitmap)
+        } else {
+            self.read_block(desc.inode_bitmap())
+        }
+    }
+
+    /// Writes the block bitmap of group `g` back, the group descriptor must
+    /// be written after this.
+    fn write_block_bitmap(&mut self, g: u32, bitmap: &[u8]) -> VfsResult {
+        let desc = &mut self.groups[g as usize];
+        desc.set_flags(desc.flags() & !BG_BLOCK_UNINIT);
+        if self.sb.has_ro_compat(RO_COMPAT_METADATA_CSUM) {
+            let len = self.sb.blocks_per_group() as usize / 8;
+            desc.set_block_bitmap_csum(crc32c(self.csum_seed, &bitmap[..len]));
+        }
+        let block = desc.block_bitmap();
+        self.write_block(block, bitmap)
+    }
+
+    /// Writes the inode bitmap of group `g` back, the group descriptor must
+    /// be written after this.
+    fn write_inode_bitmap(&mut self, g: u32, bitmap: &[u8]) -> VfsResult {
+        let desc = &mut self.groups[g as usize];
+        desc.set_flags(desc.flags() & !BG_INODE_UNINIT);
+        if self.sb.has_ro_compat(RO_COMPAT_METADATA_CSUM) {
+            let len = self.sb.inodes_per_group() as usize / 8;
+            desc.set_inode_bitmap_csum(crc32c(self.csum_seed, &bitmap[..len]));
+        }
+        let block = desc.inode_bitmap();
+        self.write_block(block, bitmap)
+    }
+
+    /// Allocates at most `count` contiguous blocks, as close to `goal` as
+    /// possible. Returns the first block and the number of blocks.
+    pub fn alloc_blocks(&mut self, goal: u64, count: u32) -> VfsResult<(u64, u32)> {
+        self.check_writable()?;
+        let first_data_block = self.sb.first_data_block() as u64;
+        let goal = if goal < first_data_block || goal >= self.sb.blocks_count() {
+            first_data_block
+        } else {
+            goal
+        };
+        let group_count = self.group_count();
+        let goal_group = self.group_of_block(goal);
+        for i in 0..group_count {
+            let g = (goal_group + i) % group_count;
+            if self.groups[g as usize].free_blocks() == 0 {
+                continue;
+            }
+            let mut bitmap = self.read_block_bitmap(g)?;
+            let end = self.blocks_in_group(g);
+            let start = if i == 0 {
+                (goal - self.group_first_block(g)) as u32
+            } else {
+                0
+            };
+            let Some(bit) = find_zero(&bitmap, start, end).or_else(|| find_zero(&bitmap, 0, start))
+            else {
+                continue;
+            };
+            let mut len = 0;
+            while len < count && bit + len < end && !test_bit(&bitmap, bit + len) {
+                set_bit(&mut bitmap, bit + len);
+                len += 1;
+            }
+            self.write_block_bitmap(g, &bitmap)?;
+            let desc = &mut self.groups[g as usize];
+            desc.set_free_blocks(desc.free_blocks() - len);
+            self.write_group(g)?;
+            self.sb.set_free_blocks(self.sb.free_blocks() - len as u64);
+            self.write_super()?;
+            return Ok((self.group_first_block(g) + bit as u64, len));
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    /// Frees `count` contiguous blocks starting from `start`.
+    pub fn free_blocks(&mut self, mut start: u64, mut count: u32) -> VfsResult {
+        self.check_writable()?;
+        if start < self.sb.first_data_block() as u64
+            || start + count as u64 > self.sb.blocks_count()
+        {
+            warn!("ext4: freeing invalid blocks {}+{}", start, count);
+            return Err(VfsError::InvalidData);
+        }
+        while count > 0 {
+            let g = self.group_of_block(start);
+            let bit = (start - self.group_first_block(g)) as u32;
+            let len = count.min(self.blocks_in_group(g) - bit);
+            let mut bitmap = self.read_block_bitmap(g)?;
+            for i in bit..bit + len {
+                if !test_bit(&bitmap, i) {
+                    warn!(
+                        "ext4: freeing free block {}",
+                        self.group_first_block(g) + i as u64
+                    );
+                }
+                clear_bit(&mut bitmap, i);
+            }
+            self.write_block_bitmap(g, &bitmap)?;
+            let desc = &mut self.groups[g as usize];
+            desc.set_free_blocks(desc.free_blocks() + len);
+            self.write_group(g)?;
+            self.sb.set_free_blocks(self.sb.free_blocks() + len as u64);
+            start += len as u64;
+            count -= len;
+        }
+        self.write_super()
+    }
+
+    /// Frees all blocks in the list, merging the contiguous ones.
+    pub fn free_block_list(&mut self, blocks: &mut [u64]) -> VfsResult {
+        blocks.sort_unstable();
+        let mut i = 0;
+        while i < blocks.len() {
+            let mut len = 1;
+            while i + len < blocks.len() && blocks[i + len] == blocks[i] + len as u64 {
+                len += 1;
+            }
+            self.free_blocks(blocks[i], len as u32)?;
+            i += len;
+        }
+        Ok(())
+    }
+
+    /// Allocates an inode, preferably in the group of its parent directory.
+    pub fn alloc_inode(&mut self, parent: u32, is_dir: bool) -> VfsResult<u32> {
+        self.check_writable()?;
+        let inodes_per_group = self.sb.inodes_per_group();
+        let group_count = self.group_count();
+        let parent_group = (parent - 1) / inodes_per_group;
+        for i in 0..group_count {
+            let g = (parent_group + i) % group_count;
+            if self.groups[g as usize].free_inodes() == 0 {
+                continue;
+            }
+            let mut bitmap = self.read_inode_bitmap(g)?;
+            let first_bit = if g == 0 { self.sb.first_ino() - 1 } else { 0 };
+            let Some(bit) = find_zero(&bitmap, first_bit, inodes_per_group) else {
+                continue;
+            };
+            set_bit(&mut bitmap, bit);
+            self.write_inode_bitmap(g, &bitmap)?;
+
+            let has_group_csum = self.has_group_csum();
+            let desc = &mut self.groups[g as usize];
+            desc.set_free_inodes(desc.free_inodes() - 1);
+            if is_dir {
+                desc.set_used_dirs(desc.used_dirs() + 1);
+            }
+            // The inodes after the used ones are not initialized.
+            if has_group_csum && bit >= inodes_per_group - desc.itable_unused() {
+                desc.set_itable_unused(inodes_per_group - bit - 1);
+            }
+            self.write_group(g)?;
+            self.sb.set_free_inodes(self.sb.free_inodes() - 1);
+            self.write_super()?;
+            return Ok(g * inodes_per_group + bit + 1);
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    /// Frees an inode.
+    pub fn free_inode(&mut self, ino: u32, is_dir: bool) -> VfsResult {
+        self.check_writable()?;
+        let inodes_per_group = self.sb.inodes_per_group();
+        let g = (ino - 1) / inodes_per_group;
+        let bit = (ino - 1) % inodes_per_group;
+        let mut bitmap = self.read_inode_bitmap(g)?;
+        if !test_bit(&bitmap, bit) {
+            warn!("ext4: freeing free inode {}", ino);
+        }
+        clear_bit(&mut bitmap, bit);
+        self.write_inode_bitmap(g, &bitmap)?;
+        let desc = &mut self.groups[g as usize];
+        desc.set_free_inodes(desc.free_inodes() + 1);
+        if is_dir {
+            desc.set_used_dirs(desc.used_dirs().saturating_sub(1));
+        }
+        self.write_group(g)?;
+        self.sb.set_free_inodes(self.sb.free_inodes() + 1);
+        self.write_super()
+    }
+}
@@ -0,0 +1,186 @@
+use alloc::sync::Arc;
+
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsNodePerm, VfsResult};
+use core::time::Duration;
+
+use crate::ext4::Ext4;
+use crate::layout::ROOT_INO;
+use crate::{new_node, FileNode, Volume};
+
+/// The directory node in the ext2/ext4 filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct DirNode {
+    vol: Arc<Volume>,
+    ino: u32,
+}
+
+impl DirNode {
+    pub(crate) const fn new(vol: Arc<Volume>, ino: u32) -> Self {
+        Self { vol, ino }
+    }
+
+    /// Returns the inode number of the directory.
+    pub fn ino(&self) -> u32 {
+        self.ino
+    }
+
+    /// Returns the child node with `name`, which contains no `/`.
+    fn lookup_child(&self, name: &str) -> VfsResult<VfsNodeRef> {
+        match name {
+            "" | "." => Ok(Arc::new(Self::new(self.vol.clone(), self.ino))),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => {
+                let mut fs = self.vol.ext4.lock();
+                let (ino, ft) = fs.lookup(self.ino, name)?.ok_or(VfsError::NotFound)?;
+                let ty = fs.entry_type(ino, ft)?;
+                Ok(new_node(&self.vol, ino, ty))
+            }
+        }
+    }
+
+    /// Resolves the parent directory of `path` inside this filesystem,
+    /// returns its inode number and the last component.
+    fn resolve_parent<'a>(&self, fs: &mut Ext4, path: &'a str) -> VfsResult<(u32, &'a str)> {
+        let path = path.trim_matches('/');
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let mut ino = self.ino;
+        for comp in dir.split('/').filter(|c| !c.is_empty() && *c != ".") {
+            let (child, _) = fs.lookup(ino, comp)?.ok_or(VfsError::NotFound)?;
+            ino = child;
+        }
+        Ok((ino, name))
+    }
+}
+
+impl VfsNodeOps for DirNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let mut fs = self.vol.ext4.lock();
+        let block_size = fs.block_size;
+        Ok(fs.read_inode(self.ino)?.attr(block_size))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.vol.ext4.lock().set_perm(self.ino, perm.bits())
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.vol.ext4.lock().set_times(self.ino, atime, mtime)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if self.ino == ROOT_INO {
+            return self.vol.parent.get().cloned();
+        }
+        let (ino, _) = self.vol.ext4.lock().lookup(self.ino, "..").ok()??;
+        Some(Arc::new(Self::new(self.vol.clone(), ino)))
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node = self.lookup_child(name)?;
+        if let Some(rest) = rest {
+            node.lookup(rest)
+        } else {
+            Ok(node)
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let mut fs = self.vol.ext4.lock();
+        let list = fs.list_dir(self.ino)?;
+        let mut count = 0;
+        for ((name, ino, ft), ent) in list.iter().skip(start_idx).zip(dirents.iter_mut()) {
+            *ent = VfsDirEntry::new(truncate_name(name), fs.entry_type(*ino, *ft)?);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at ext4: {}", ty, path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.lookup_child(name)?.create(rest, ty)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Ok(()) // already exists
+        } else {
+            self.vol.ext4.lock().create_node(self.ino, name, ty)
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at ext4: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.lookup_child(name)?.remove(rest)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::InvalidInput) // remove '.' or '..
+        } else {
+            self.vol.ext4.lock().remove_node(self.ino, name)
+        }
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        // `src_path` and `dst_path` should in the same mounted fs
+        debug!(
+            "rename at ext4, src_path: {}, dst_path: {}",
+            src_path, dst_path
+        );
+        let mut fs = self.vol.ext4.lock();
+        let (src_dir, src_name) = self.resolve_parent(&mut fs, src_path)?;
+        let (dst_dir, dst_name) = self.resolve_parent(&mut fs, dst_path)?;
+        fs.rename(src_dir, src_name, dst_dir, dst_name)
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        debug!("symlink at ext4: {} -> {}", path, target);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.lookup_child(name)?.symlink(rest, target)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.vol.ext4.lock().create_symlink(self.ino, name, target)
+        }
+    }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        debug!("link at ext4: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.lookup_child(name)?.link(rest, node)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else if node.as_any().is::<Self>() {
+            Err(VfsError::PermissionDenied)
+        } else {
+            let file = node
+                .as_any()
+                .downcast_ref::<FileNode>()
+                .filter(|f| f.in_volume(&self.vol))
+                .ok_or(VfsError::CrossesDevices)?;
+            self.vol.ext4.lock().link(self.ino, name, file.ino())
+        }
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
+
+/// Truncates a name to fit in [`VfsDirEntry`].
+fn truncate_name(name: &str) -> &str {
+    const MAX_LEN: usize = 63;
+    let mut len = name.len().min(MAX_LEN);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    &name[..len]
+}
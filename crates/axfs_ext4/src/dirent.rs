@@ -0,0 +1,358 @@
+//! Directory entries, stored in linear directory blocks.
+//!
+//! Hashed (`dir_index`) directories are read as linear ones, since their
+//! index blocks look like empty entries.
+
+use alloc::{string::String, vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+
+use crate::crc::crc32c;
+use crate::ext4::Ext4;
+use crate::inode::Inode;
+use crate::layout::*;
+
+/// Size of the checksum tail at the end of each block with `metadata_csum`.
+const TAIL_SIZE: usize = 12;
+const TAIL_FT: u8 = 0xde;
+const ENTRY_HEADER_SIZE: usize = 8;
+
+/// The on-disk size of an entry with a name of `name_len` bytes.
+fn entry_size(name_len: usize) -> usize {
+    (ENTRY_HEADER_SIZE + name_len).next_multiple_of(4)
+}
+
+/// A parsed directory entry in a block.
+struct DirEntry {
+    offset: usize,
+    rec_len: usize,
+    ino: u32,
+    name_len: usize,
+    file_type: u8,
+}
+
+impl DirEntry {
+    fn name<'a>(&self, block: &'a [u8]) -> &'a [u8] {
+        &block[self.offset + ENTRY_HEADER_SIZE..][..self.name_len]
+    }
+
+    /// The size actually used by the entry, 0 for an empty entry.
+    fn used_size(&self) -> usize {
+        if self.ino == 0 {
+            0
+        } else {
+            entry_size(self.name_len)
+        }
+    }
+}
+
+fn write_entry(block: &mut [u8], offset: usize, ino: u32, rec_len: usize, name: &[u8], ft: u8) {
+    let entry = &mut block[offset..offset + rec_len];
+    write_u32(entry, 0x0, ino);
+    write_u16(entry, 0x4, encode_rec_len(rec_len));
+    entry[0x6] = name.len() as u8;
+    entry[0x7] = ft;
+    entry[ENTRY_HEADER_SIZE..][..name.len()].copy_from_slice(name);
+}
+
+/// `rec_len` of 65536 is stored as 65535 for 64KB blocks.
+fn encode_rec_len(rec_len: usize) -> u16 {
+    rec_len.min(u16::MAX as usize) as u16
+}
+
+fn decode_rec_len(raw: u16, block_size: usize) -> usize {
+    if block_size >= 65536 && (raw == u16::MAX || raw == 0) {
+        block_size
+    } else {
+        raw as usize
+    }
+}
+
+/// Converts the mode of an inode to a file type in directory entries.
+pub fn file_type_of(mode: u16) -> u8 {
+    match mode & S_IFMT {
+        S_IFREG => FT_REG_FILE,
+        S_IFDIR => FT_DIR,
+        S_IFCHR => FT_CHRDEV,
+        S_IFBLK => FT_BLKDEV,
+        S_IFIFO => FT_FIFO,
+        S_IFSOCK => FT_SOCK,
+        S_IFLNK => FT_SYMLINK,
+        _ => FT_UNKNOWN,
+    }
+}
+
+impl Ext4 {
+    fn has_filetype(&self) -> bool {
+        self.sb.has_incompat(INCOMPAT_FILETYPE)
+    }
+
+    /// Whether the directory block has a checksum tail. Index blocks of
+    /// hashed directories have their own checksums instead.
+    fn has_dir_tail(&self, block: &[u8]) -> bool {
+        let tail = self.block_size - TAIL_SIZE;
+        self.has_metadata_csum()
+            && read_u32(block, tail) == 0
+            && read_u16(block, tail + 0x4) == TAIL_SIZE as u16
+            && block[tail + 0x6] == 0
+            && block[tail + 0x7] == TAIL_FT
+    }
+
+    /// The end of the entries in a directory block.
+    fn dir_block_limit(&self, block: &[u8]) -> usize {
+        if self.has_dir_tail(block) {
+            self.block_size - TAIL_SIZE
+        } else {
+            self.block_size
+        }
+    }
+
+    /// Returns an empty directory block, with the checksum tail if needed,
+    /// and the end of the entries in it.
+    fn new_dir_block(&self) -> (Vec<u8>, usize) {
+        let mut block = vec![0; self.block_size];
+        if self.has_metadata_csum() {
+            let tail = self.block_size - TAIL_SIZE;
+            write_u16(&mut block, tail + 0x4, TAIL_SIZE as u16);
+            block[tail + 0x7] = TAIL_FT;
+            (block, tail)
+        } else {
+            (block, self.block_size)
+        }
+    }
+
+    /// Parses all entries in a directory block.
+    fn parse_dir_block(&self, block: &[u8]) -> VfsResult<Vec<DirEntry>> {
+        let limit = self.dir_block_limit(block);
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < limit {
+            if offset + ENTRY_HEADER_SIZE > limit {
+                warn!("ext4: corrupted directory entry at {}", offset);
+                return Err(VfsError::InvalidData);
+            }
+            let rec_len = decode_rec_len(read_u16(block, offset + 0x4), self.block_size);
+            let name_len = block[offset + 0x6] as usize;
+            if rec_len < ENTRY_HEADER_SIZE
+                || rec_len % 4 != 0
+                || offset + rec_len > limit
+                || ENTRY_HEADER_SIZE + name_len > rec_len
+            {
+                warn!("ext4: corrupted directory entry at {}", offset);
+                return Err(VfsError::InvalidData);
+            }
+            entries.push(DirEntry {
+                offset,
+                rec_len,
+                ino: read_u32(block, offset),
+                name_len,
+                file_type: if self.has_filetype() {
+                    block[offset + 0x7]
+                } else {
+                    FT_UNKNOWN
+                },
+            });
+            offset += rec_len;
+        }
+        Ok(entries)
+    }
+
+    /// Writes a directory block back, with the checksum tail updated.
+    fn write_dir_block(&mut self, dir: &Inode, block: u64, data: &mut [u8]) -> VfsResult {
+        if self.has_metadata_csum() {
+            if !self.has_dir_tail(data) {
+                return Err(VfsError::Unsupported); // index blocks
+            }
+            let tail = self.block_size - TAIL_SIZE;
+            let csum = crc32c(self.inode_csum_seed(dir), &data[..tail]);
+            write_u32(data, tail + 0x8, csum);
+        }
+        self.write_block(block, data)
+    }
+
+    /// Reads each block of the directory, and calls `f` with the physical
+    /// block, the data and the entries until it returns `Some`.
+    fn for_each_dir_block<T>(
+        &mut self,
+        dir: &Inode,
+        mut f: impl FnMut(&mut Self, u64, &mut Vec<u8>, Vec<DirEntry>) -> VfsResult<Option<T>>,
+    ) -> VfsResult<Option<T>> {
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let map = self.load_map(dir)?;
+        let blocks = dir.size().div_ceil(self.block_size as u64);
+        for lblk in 0..blocks as u32 {
+            let Some(block) = self.map_block(dir, &map, lblk)? else {
+                continue;
+            };
+            let mut data = self.read_block(block)?;
+            let entries = self.parse_dir_block(&data)?;
+            if let Some(res) = f(self, block, &mut data, entries)? {
+                return Ok(Some(res));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Finds the entry with `name`, returns the inode number and the file
+    /// type.
+    pub fn dir_lookup(&mut self, dir: &Inode, name: &str) -> VfsResult<Option<(u32, u8)>> {
+        self.for_each_dir_block(dir, |_, _, data, entries| {
+            Ok(entries
+                .iter()
+                .find(|e| e.ino != 0 && e.name(data) == name.as_bytes())
+                .map(|e| (e.ino, e.file_type)))
+        })
+    }
+
+    /// Returns the names, inode numbers and file types of all entries,
+    /// including `.` and `..`.
+    pub fn dir_list(&mut self, dir: &Inode) -> VfsResult<Vec<(String, u32, u8)>> {
+        let mut list = Vec::new();
+        self.for_each_dir_block(dir, |_, _, data, entries| {
+            for e in entries.iter().filter(|e| e.ino != 0) {
+                let name = String::from_utf8_lossy(e.name(data)).into_owned();
+                list.push((name, e.ino, e.file_type));
+            }
+            Ok(None::<()>)
+        })?;
+        Ok(list)
+    }
+
+    /// Returns the node type of an entry, the inode is read if the type is
+    /// not stored in the entry.
+    pub fn entry_type(&mut self, ino: u32, file_type: u8) -> VfsResult<VfsNodeType> {
+        Ok(match file_type {
+            FT_REG_FILE => VfsNodeType::File,
+            FT_DIR => VfsNodeType::Dir,
+            FT_CHRDEV => VfsNodeType::CharDevice,
+            FT_BLKDEV => VfsNodeType::BlockDevice,
+            FT_FIFO => VfsNodeType::Fifo,
+            FT_SOCK => VfsNodeType::Socket,
+            FT_SYMLINK => VfsNodeType::SymLink,
+            _ => self.read_inode(ino)?.node_type(),
+        })
+    }
+
+    /// Whether the directory has no entries other than `.` and `..`.
+    pub fn dir_is_empty(&mut self, dir: &Inode) -> VfsResult<bool> {
+        let found = self.for_each_dir_block(dir, |_, _, data, entries| {
+            Ok(entries
+                .iter()
+                .any(|e| e.ino != 0 && !matches!(e.name(data), b"." | b".."))
+                .then_some(()))
+        })?;
+        Ok(found.is_none())
+    }
+
+    /// Adds an entry to the directory. The directory inode may be changed,
+    /// and must be written after this.
+    pub fn dir_add(&mut self, dir: &mut Inode, name: &str, ino: u32, file_type: u8) -> VfsResult {
+        if name.is_empty() || name.len() > NAME_LEN {
+            return Err(VfsError::InvalidInput);
+        }
+        if dir.flags() & INODE_FL_INDEX != 0 {
+            // The index is not updated, so the directory becomes a linear
+            // one. Its index blocks have no checksum tails, which is invalid
+            // with `metadata_csum`.
+            if self.has_metadata_csum() {
+                return Err(VfsError::Unsupported);
+            }
+            dir.set_flags(dir.flags() & !INODE_FL_INDEX);
+        }
+        let ft = if self.has_filetype() { file_type } else { 0 };
+        let needed = entry_size(name.len());
+        let dir_ref = &*dir;
+        let inserted = self.for_each_dir_block(dir_ref, |fs, block, data, entries| {
+            let Some(e) = entries.iter().find(|e| e.rec_len - e.used_size() >= needed) else {
+                return Ok(None);
+            };
+            let used = e.used_size();
+            if used > 0 {
+                write_u16(data, e.offset + 0x4, encode_rec_len(used));
+            }
+            write_entry(
+                data,
+                e.offset + used,
+                ino,
+                e.rec_len - used,
+                name.as_bytes(),
+                ft,
+            );
+            fs.write_dir_block(dir_ref, block, data)?;
+            Ok(Some(()))
+        })?;
+        if inserted.is_some() {
+            return Ok(());
+        }
+
+        // Appends a new block.
+        let size = dir.size();
+        let mut map = self.load_map(dir)?;
+        let (block, _) = self.map_alloc(dir, &mut map, (size / self.block_size as u64) as u32)?;
+        self.store_map(dir, &mut map)?;
+        dir.set_size(size + self.block_size as u64);
+        let (mut data, limit) = self.new_dir_block();
+        write_entry(&mut data, 0, ino, limit, name.as_bytes(), ft);
+        self.write_dir_block(dir, block, &mut data)
+    }
+
+    /// Removes the entry with `name`, returns its inode number.
+    pub fn dir_remove(&mut self, dir: &Inode, name: &str) -> VfsResult<u32> {
+        let removed = self.for_each_dir_block(dir, |fs, block, data, entries| {
+            let Some(i) = entries
+                .iter()
+                .position(|e| e.ino != 0 && e.name(data) == name.as_bytes())
+            else {
+                return Ok(None);
+            };
+            let e = &entries[i];
+            if i > 0 {
+                // Merged into the previous entry.
+                let prev = &entries[i - 1];
+                write_u16(
+                    data,
+                    prev.offset + 0x4,
+                    encode_rec_len(prev.rec_len + e.rec_len),
+                );
+            } else {
+                write_u32(data, e.offset, 0);
+            }
+            fs.write_dir_block(dir, block, data)?;
+            Ok(Some(e.ino))
+        })?;
+        removed.ok_or(VfsError::NotFound)
+    }
+
+    /// Points the existing entry with `name` to another inode.
+    pub fn dir_set_entry(&mut self, dir: &Inode, name: &str, ino: u32, file_type: u8) -> VfsResult {
+        let ft = if self.has_filetype() { file_type } else { 0 };
+        let found = self.for_each_dir_block(dir, |fs, block, data, entries| {
+            let Some(e) = entries
+                .iter()
+                .find(|e| e.ino != 0 && e.name(data) == name.as_bytes())
+            else {
+                return Ok(None);
+            };
+            write_u32(data, e.offset, ino);
+            data[e.offset + 0x7] = ft;
+            fs.write_dir_block(dir, block, data)?;
+            Ok(Some(()))
+        })?;
+        found.ok_or(VfsError::NotFound)
+    }
+
+    /// Allocates the first block of a new directory with `.` and `..`.
+    pub fn dir_init(&mut self, dir: &mut Inode, parent: u32) -> VfsResult {
+        let ft = if self.has_filetype() { FT_DIR } else { 0 };
+        let mut map = self.load_map(dir)?;
+        let (block, _) = self.map_alloc(dir, &mut map, 0)?;
+        self.store_map(dir, &mut map)?;
+        dir.set_size(self.block_size as u64);
+        let (mut data, limit) = self.new_dir_block();
+        let dot_len = entry_size(1);
+        write_entry(&mut data, 0, dir.ino, dot_len, b".", ft);
+        write_entry(&mut data, dot_len, parent, limit - dot_len, b"..", ft);
+        self.write_dir_block(dir, block, &mut data)
+    }
+}
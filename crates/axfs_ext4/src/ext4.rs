@@ -0,0 +1,185 @@
+//! The state of a mounted filesystem and the accesses to its metadata.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::crc::{crc16, crc32c};
+use crate::layout::*;
+use crate::BlockDevice;
+
+/// A mounted ext2/ext4 filesystem, all operations on it are serialized by
+/// the lock in [`Volume`](crate::Volume).
+pub struct Ext4 {
+    dev: Box<dyn BlockDevice>,
+    pub sb: SuperBlock,
+    pub groups: Vec<GroupDesc>,
+    pub block_size: usize,
+    pub inode_size: usize,
+    /// Seed of the `metadata_csum` checksums.
+    pub csum_seed: u32,
+    /// Some features are not supported to write.
+    read_only: bool,
+}
+
+impl Ext4 {
+    /// Reads the superblock and group descriptors of the filesystem.
+    pub fn open(mut dev: Box<dyn BlockDevice>) -> VfsResult<Self> {
+        let mut raw = vec![0; SUPER_BLOCK_SIZE];
+        dev.read_at(SUPER_BLOCK_OFFSET, &mut raw)?;
+        let sb = SuperBlock { raw };
+        if sb.magic() != EXT4_MAGIC {
+            warn!("ext4: bad magic number {:#x}", sb.magic());
+            return Err(VfsError::InvalidData);
+        }
+        let unsupported = sb.feature_incompat() & !INCOMPAT_SUPPORTED;
+        if unsupported != 0 {
+            warn!("ext4: unsupported incompatible features {:#x}", unsupported);
+            return Err(VfsError::Unsupported);
+        }
+        if sb.has_incompat(INCOMPAT_RECOVER) {
+            warn!("ext4: the journal needs recovery");
+            return Err(VfsError::Unsupported);
+        }
+        let read_only = sb.feature_ro_compat() & !RO_COMPAT_SUPPORTED != 0;
+        if read_only {
+            warn!(
+                "ext4: mounted read-only for unsupported features {:#x}",
+                sb.feature_ro_compat() & !RO_COMPAT_SUPPORTED
+            );
+        }
+
+        let log_block_size = sb.log_block_size();
+        let inode_size = sb.inode_size();
+        let desc_size = sb.desc_size();
+        if log_block_size > 6
+            || inode_size < GOOD_OLD_INODE_SIZE
+            || desc_size < 32
+            || sb.blocks_per_group() == 0
+            || sb.inodes_per_group() == 0
+        {
+            warn!("ext4: invalid superblock");
+            return Err(VfsError::InvalidData);
+        }
+        let block_size = 1024 << log_block_size;
+        let csum_seed = if sb.has_incompat(INCOMPAT_CSUM_SEED) {
+            sb.checksum_seed()
+        } else {
+            crc32c(!0, sb.uuid())
+        };
+
+        let mut fs = Self {
+            dev,
+            sb,
+            groups: Vec::new(),
+            block_size,
+            inode_size,
+            csum_seed,
+            read_only,
+        };
+        let gdt_pos = fs.gdt_block() * block_size as u64;
+        for g in 0..fs.group_count() {
+            let mut raw = vec![0; desc_size];
+            fs.read_bytes(gdt_pos + (g as usize * desc_size) as u64, &mut raw)?;
+            fs.groups.push(GroupDesc { raw });
+        }
+        info!(
+            "ext4: block size {}, {} blocks, {} inodes, {} groups",
+            block_size,
+            fs.sb.blocks_count(),
+            fs.sb.inodes_count(),
+            fs.groups.len()
+        );
+        Ok(fs)
+    }
+
+    /// Returns [`VfsError::PermissionDenied`] if the filesystem can not be
+    /// modified.
+    pub fn check_writable(&self) -> VfsResult {
+        if self.read_only {
+            Err(VfsError::PermissionDenied)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn has_metadata_csum(&self) -> bool {
+        self.sb.has_ro_compat(RO_COMPAT_METADATA_CSUM)
+    }
+
+    /// Whether the group descriptors have checksums, which also enables the
+    /// uninitialized flags of the groups.
+    pub fn has_group_csum(&self) -> bool {
+        self.sb
+            .has_ro_compat(RO_COMPAT_GDT_CSUM | RO_COMPAT_METADATA_CSUM)
+    }
+
+    pub fn group_count(&self) -> u32 {
+        let data_blocks = self.sb.blocks_count() - self.sb.first_data_block() as u64;
+        data_blocks.div_ceil(self.sb.blocks_per_group() as u64) as u32
+    }
+
+    /// The first block of the group descriptor table.
+    pub fn gdt_block(&self) -> u64 {
+        self.sb.first_data_block() as u64 + 1
+    }
+
+    pub fn read_bytes(&mut self, pos: u64, buf: &mut [u8]) -> VfsResult {
+        self.dev.read_at(pos, buf)
+    }
+
+    pub fn write_bytes(&mut self, pos: u64, buf: &[u8]) -> VfsResult {
+        self.dev.write_at(pos, buf)
+    }
+
+    pub fn read_block(&mut self, block: u64) -> VfsResult<Vec<u8>> {
+        let mut buf = vec![0; self.block_size];
+        self.read_bytes(block * self.block_size as u64, &mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn write_block(&mut self, block: u64, buf: &[u8]) -> VfsResult {
+        self.write_bytes(block * self.block_size as u64, buf)
+    }
+
+    /// Writes the cached data back to the device.
+    pub fn flush(&mut self) -> VfsResult {
+        self.dev.flush()
+    }
+
+    /// Writes the superblock back.
+    pub fn write_super(&mut self) -> VfsResult {
+        self.sb.update_checksum();
+        self.dev.write_at(SUPER_BLOCK_OFFSET, &self.sb.raw)
+    }
+
+    /// Writes the descriptor of group `g` back, with the checksum updated.
+    pub fn write_group(&mut self, g: u32) -> VfsResult {
+        let csum = self.group_checksum(g);
+        let desc = &mut self.groups[g as usize];
+        if let Some(csum) = csum {
+            desc.set_checksum(csum);
+        }
+        let desc_size = desc.raw.len();
+        let pos = self.gdt_block() * self.block_size as u64 + (g as usize * desc_size) as u64;
+        self.dev.write_at(pos, &self.groups[g as usize].raw)
+    }
+
+    fn group_checksum(&self, g: u32) -> Option<u16> {
+        let raw = &self.groups[g as usize].raw;
+        // The checksum field at 0x1e is excluded.
+        if self.has_metadata_csum() {
+            let mut crc = crc32c(self.csum_seed, &g.to_le_bytes());
+            crc = crc32c(crc, &raw[..0x1e]);
+            crc = crc32c(crc, &[0; 2]);
+            crc = crc32c(crc, &raw[0x20..]);
+            Some(crc as u16)
+        } else if self.sb.has_ro_compat(RO_COMPAT_GDT_CSUM) {
+            let mut crc = crc16(!0, self.sb.uuid());
+            crc = crc16(crc, &g.to_le_bytes());
+            crc = crc16(crc, &raw[..0x1e]);
+            Some(crc16(crc, &raw[0x20..]))
+        } else {
+            None
+        }
+    }
+}
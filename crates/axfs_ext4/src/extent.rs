@@ -0,0 +1,287 @@
+//! Extent trees, which map the blocks of files on ext4.
+//!
+//! The whole tree of a file is loaded as a sorted list of extents, and the
+//! tree is rebuilt from the list when it's changed.
+
+use alloc::{vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::crc::crc32c;
+use crate::ext4::Ext4;
+use crate::inode::Inode;
+use crate::layout::*;
+
+const EXTENT_MAGIC: u16 = 0xf30a;
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 12;
+/// Number of entries in the root node in the inode.
+const ROOT_ENTRIES: usize = 4;
+const MAX_DEPTH: u16 = 5;
+const MAX_INIT_LEN: u32 = 32768;
+const MAX_UNINIT_LEN: u32 = 32767;
+
+/// A contiguous range of blocks.
+#[derive(Debug, Clone, Copy)]
+pub struct Extent {
+    lblk: u32,
+    len: u32,
+    pblk: u64,
+    /// Allocated but not initialized, which reads as zeros.
+    uninit: bool,
+}
+
+impl Extent {
+    fn end(&self) -> u32 {
+        self.lblk + self.len
+    }
+
+    /// Whether `next` can be merged to the end of this extent.
+    fn can_merge(&self, next: &Extent) -> bool {
+        let max_len = if self.uninit {
+            MAX_UNINIT_LEN
+        } else {
+            MAX_INIT_LEN
+        };
+        self.end() == next.lblk
+            && self.pblk + self.len as u64 == next.pblk
+            && self.uninit == next.uninit
+            && self.len + next.len <= max_len
+    }
+}
+
+/// The extents of a file and the blocks of the tree nodes.
+pub struct ExtentTree {
+    extents: Vec<Extent>,
+    nodes: Vec<u64>,
+    dirty: bool,
+}
+
+fn write_header(node: &mut [u8], entries: usize, max: usize, depth: u16) {
+    write_u16(node, 0x0, EXTENT_MAGIC);
+    write_u16(node, 0x2, entries as u16);
+    write_u16(node, 0x4, max as u16);
+    write_u16(node, 0x6, depth);
+    write_u32(node, 0x8, 0);
+}
+
+impl ExtentTree {
+    /// Initializes an empty tree in `i_block` of an inode.
+    pub fn init_root(i_block: &mut [u8]) {
+        i_block.fill(0);
+        write_header(i_block, 0, ROOT_ENTRIES, 0);
+    }
+
+    /// Returns the physical block of the logical block, and whether it's
+    /// uninitialized.
+    pub fn lookup(&self, lblk: u32) -> Option<(u64, bool)> {
+        let idx = self.extents.partition_point(|e| e.lblk <= lblk);
+        let e = self.extents.get(idx.checked_sub(1)?)?;
+        (lblk < e.end()).then(|| (e.pblk + (lblk - e.lblk) as u64, e.uninit))
+    }
+
+    /// Maps the unmapped logical block to the physical block.
+    pub fn insert(&mut self, lblk: u32, pblk: u64) {
+        let idx = self.extents.partition_point(|e| e.lblk <= lblk);
+        let new = Extent {
+            lblk,
+            len: 1,
+            pblk,
+            uninit: false,
+        };
+        self.dirty = true;
+        if idx > 0 && self.extents[idx - 1].can_merge(&new) {
+            self.extents[idx - 1].len += 1;
+        } else if idx < self.extents.len() && new.can_merge(&self.extents[idx]) {
+            let next = &mut self.extents[idx];
+            next.lblk -= 1;
+            next.pblk -= 1;
+            next.len += 1;
+        } else {
+            self.extents.insert(idx, new);
+        }
+    }
+
+    /// Marks the mapped logical block as initialized.
+    pub fn mark_init(&mut self, lblk: u32) {
+        let idx = self.extents.partition_point(|e| e.lblk <= lblk) - 1;
+        let e = self.extents[idx];
+        let offset = lblk - e.lblk;
+        let mut parts = Vec::with_capacity(3);
+        if offset > 0 {
+            parts.push(Extent { len: offset, ..e });
+        }
+        parts.push(Extent {
+            lblk,
+            len: 1,
+            pblk: e.pblk + offset as u64,
+            uninit: false,
+        });
+        if offset + 1 < e.len {
+            parts.push(Extent {
+                lblk: lblk + 1,
+                len: e.len - offset - 1,
+                pblk: e.pblk + offset as u64 + 1,
+                uninit: true,
+            });
+        }
+        self.extents.splice(idx..idx + 1, parts);
+        self.dirty = true;
+    }
+
+    /// Unmaps all blocks from the logical block `start`, and appends them to
+    /// `freed`.
+    pub fn truncate(&mut self, start: u32, freed: &mut Vec<u64>) {
+        self.extents.retain_mut(|e| {
+            let keep = start.saturating_sub(e.lblk).min(e.len);
+            freed.extend((keep..e.len).map(|i| e.pblk + i as u64));
+            e.len = keep;
+            keep > 0
+        });
+        self.dirty = true;
+    }
+
+    fn merge(&mut self) {
+        let mut merged: Vec<Extent> = Vec::with_capacity(self.extents.len());
+        for e in self.extents.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.can_merge(&e) => last.len += e.len,
+                _ => merged.push(e),
+            }
+        }
+        self.extents = merged;
+    }
+}
+
+impl Ext4 {
+    fn extent_tail_offset(&self) -> usize {
+        HEADER_SIZE + (self.block_size - HEADER_SIZE) / ENTRY_SIZE * ENTRY_SIZE
+    }
+
+    pub fn load_extents(&mut self, inode: &Inode) -> VfsResult<ExtentTree> {
+        let mut tree = ExtentTree {
+            extents: Vec::new(),
+            nodes: Vec::new(),
+            dirty: false,
+        };
+        self.load_extent_node(inode.i_block(), MAX_DEPTH, &mut tree)?;
+        Ok(tree)
+    }
+
+    fn load_extent_node(
+        &mut self,
+        node: &[u8],
+        max_depth: u16,
+        tree: &mut ExtentTree,
+    ) -> VfsResult {
+        let entries = read_u16(node, 0x2) as usize;
+        let depth = read_u16(node, 0x6);
+        if read_u16(node, 0x0) != EXTENT_MAGIC
+            || depth > max_depth
+            || HEADER_SIZE + entries * ENTRY_SIZE > node.len()
+        {
+            warn!("ext4: corrupted extent tree");
+            return Err(VfsError::InvalidData);
+        }
+        for i in 0..entries {
+            let entry = &node[HEADER_SIZE + i * ENTRY_SIZE..][..ENTRY_SIZE];
+            if depth == 0 {
+                let len = read_u16(entry, 0x4) as u32;
+                let (len, uninit) = if len > MAX_INIT_LEN {
+                    (len - MAX_INIT_LEN, true)
+                } else {
+                    (len, false)
+                };
+                tree.extents.push(Extent {
+                    lblk: read_u32(entry, 0x0),
+                    len,
+                    pblk: read_u32(entry, 0x8) as u64 | (read_u16(entry, 0x6) as u64) << 32,
+                    uninit,
+                });
+            } else {
+                let child = read_u32(entry, 0x4) as u64 | (read_u16(entry, 0x8) as u64) << 32;
+                tree.nodes.push(child);
+                let block = self.read_block(child)?;
+                self.load_extent_node(&block, depth - 1, tree)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuilds the extent tree if it's changed. The inode must be written
+    /// after this.
+    pub fn store_extents(&mut self, inode: &mut Inode, tree: &mut ExtentTree) -> VfsResult {
+        if !tree.dirty {
+            return Ok(());
+        }
+        tree.merge();
+
+        // Entries of the current level, with their first logical blocks.
+        let mut entries: Vec<(u32, [u8; ENTRY_SIZE])> = tree
+            .extents
+            .iter()
+            .map(|e| {
+                let mut entry = [0; ENTRY_SIZE];
+                let len = if e.uninit {
+                    e.len + MAX_INIT_LEN
+                } else {
+                    e.len
+                };
+                write_u32(&mut entry, 0x0, e.lblk);
+                write_u16(&mut entry, 0x4, len as u16);
+                write_u16(&mut entry, 0x6, (e.pblk >> 32) as u16);
+                write_u32(&mut entry, 0x8, e.pblk as u32);
+                (e.lblk, entry)
+            })
+            .collect();
+
+        let per_block = (self.block_size - HEADER_SIZE) / ENTRY_SIZE;
+        let tail_offset = self.extent_tail_offset();
+        let csum_seed = self.inode_csum_seed(inode);
+        let mut old_nodes = core::mem::take(&mut tree.nodes);
+        let mut depth = 0;
+        while entries.len() > ROOT_ENTRIES {
+            let mut parents = Vec::with_capacity(entries.len().div_ceil(per_block));
+            for chunk in entries.chunks(per_block) {
+                let block = match old_nodes.pop() {
+                    Some(block) => block,
+                    None => {
+                        let (block, _) = self.alloc_blocks(self.inode_goal(inode), 1)?;
+                        inode.add_blocks(1, self.block_size);
+                        block
+                    }
+                };
+                tree.nodes.push(block);
+
+                let mut node = vec![0; self.block_size];
+                write_header(&mut node, chunk.len(), per_block, depth);
+                for (i, (_, entry)) in chunk.iter().enumerate() {
+                    node[HEADER_SIZE + i * ENTRY_SIZE..][..ENTRY_SIZE].copy_from_slice(entry);
+                }
+                if self.has_metadata_csum() {
+                    let csum = crc32c(csum_seed, &node[..tail_offset]);
+                    write_u32(&mut node, tail_offset, csum);
+                }
+                self.write_block(block, &node)?;
+
+                let mut index = [0; ENTRY_SIZE];
+                write_u32(&mut index, 0x0, chunk[0].0);
+                write_u32(&mut index, 0x4, block as u32);
+                write_u16(&mut index, 0x8, (block >> 32) as u16);
+                parents.push((chunk[0].0, index));
+            }
+            entries = parents;
+            depth += 1;
+        }
+
+        let root = inode.i_block_mut();
+        ExtentTree::init_root(root);
+        write_header(root, entries.len(), ROOT_ENTRIES, depth);
+        for (i, (_, entry)) in entries.iter().enumerate() {
+            root[HEADER_SIZE + i * ENTRY_SIZE..][..ENTRY_SIZE].copy_from_slice(entry);
+        }
+        inode.add_blocks(-(old_nodes.len() as i64), self.block_size);
+        self.free_block_list(&mut old_nodes)?;
+        tree.dirty = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use alloc::sync::Arc;
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use axfs_vfs::{VfsError, VfsNodePerm, VfsNodeType};
+use core::time::Duration;
+
+use crate::Volume;
+
+/// The file node in the ext2/ext4 filesystem, also used for other
+/// non-directory nodes such as symbolic links.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct FileNode {
+    vol: Arc<Volume>,
+    ino: u32,
+}
+
+impl FileNode {
+    pub(crate) const fn new(vol: Arc<Volume>, ino: u32) -> Self {
+        Self { vol, ino }
+    }
+
+    /// Returns the inode number of the file.
+    pub fn ino(&self) -> u32 {
+        self.ino
+    }
+
+    /// Whether the file is in the filesystem of `vol`.
+    pub(crate) fn in_volume(&self, vol: &Arc<Volume>) -> bool {
+        Arc::ptr_eq(&self.vol, vol)
+    }
+}
+
+impl VfsNodeOps for FileNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let mut fs = self.vol.ext4.lock();
+        let block_size = fs.block_size;
+        Ok(fs.read_inode(self.ino)?.attr(block_size))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.vol.ext4.lock().set_perm(self.ino, perm.bits())
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.vol.ext4.lock().set_times(self.ino, atime, mtime)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut fs = self.vol.ext4.lock();
+        let inode = fs.read_inode(self.ino)?;
+        fs.read_data(&inode, offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut fs = self.vol.ext4.lock();
+        let mut inode = fs.read_inode(self.ino)?;
+        inode.set_modified(axfs_vfs::current_time());
+        fs.write_data(&mut inode, offset, buf)
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        let mut fs = self.vol.ext4.lock();
+        let mut inode = fs.read_inode(self.ino)?;
+        inode.set_modified(axfs_vfs::current_time());
+        fs.truncate(&mut inode, size)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.vol.ext4.lock().flush()
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut fs = self.vol.ext4.lock();
+        let inode = fs.read_inode(self.ino)?;
+        if inode.node_type() != VfsNodeType::SymLink {
+            return Err(VfsError::InvalidInput);
+        }
+        let target = fs.read_link(&inode)?;
+        let len = target.len().min(buf.len());
+        buf[..len].copy_from_slice(&target[..len]);
+        Ok(len)
+    }
+
+    impl_vfs_non_dir_default! {}
+}
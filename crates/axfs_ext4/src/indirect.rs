@@ -0,0 +1,180 @@
+//! Indirect block maps, which map the blocks of files on ext2/ext3.
+//!
+//! `i_block` holds 12 direct blocks, followed by a single, a double and a
+//! triple indirect block.
+
+use alloc::{vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::ext4::Ext4;
+use crate::inode::Inode;
+use crate::layout::*;
+
+const DIRECT_BLOCKS: u64 = 12;
+
+impl Ext4 {
+    fn addrs_per_block(&self) -> u64 {
+        self.block_size as u64 / 4
+    }
+
+    /// Returns the depth of indirection, and the indices at each level, where
+    /// the first one is the index in `i_block`.
+    fn indirect_path(&self, lblk: u32) -> VfsResult<(usize, [usize; 4])> {
+        let apb = self.addrs_per_block();
+        let mut n = lblk as u64;
+        if n < DIRECT_BLOCKS {
+            return Ok((0, [n as usize, 0, 0, 0]));
+        }
+        n -= DIRECT_BLOCKS;
+        let mut span = apb;
+        for depth in 1..=3 {
+            if n < span {
+                let mut path = [DIRECT_BLOCKS as usize + depth - 1, 0, 0, 0];
+                for level in (1..=depth).rev() {
+                    path[level] = (n % apb) as usize;
+                    n /= apb;
+                }
+                return Ok((depth, path));
+            }
+            n -= span;
+            span *= apb;
+        }
+        Err(VfsError::InvalidInput) // too large
+    }
+
+    pub fn indirect_lookup(&mut self, inode: &Inode, lblk: u32) -> VfsResult<Option<u64>> {
+        let (depth, path) = self.indirect_path(lblk)?;
+        let mut block = read_u32(inode.i_block(), path[0] * 4) as u64;
+        for &idx in &path[1..=depth] {
+            if block == 0 {
+                break;
+            }
+            let mut addr = [0; 4];
+            self.read_bytes(block * self.block_size as u64 + idx as u64 * 4, &mut addr)?;
+            block = u32::from_le_bytes(addr) as u64;
+        }
+        Ok((block != 0).then_some(block))
+    }
+
+    /// Maps the logical block, allocates the data block and the indirect
+    /// blocks if they are not allocated. Returns the physical block and
+    /// whether it's newly allocated.
+    pub fn indirect_alloc(
+        &mut self,
+        inode: &mut Inode,
+        lblk: u32,
+        goal: u64,
+    ) -> VfsResult<(u64, bool)> {
+        let (depth, path) = self.indirect_path(lblk)?;
+        let block_size = self.block_size as u64;
+        // Position of the pointer in the indirect block, `None` for `i_block`.
+        let mut parent: Option<u64> = None;
+        let mut is_new = false;
+        for (level, &idx) in path[..=depth].iter().enumerate() {
+            let addr_pos = parent.map(|b| b * block_size + idx as u64 * 4);
+            let mut block = match addr_pos {
+                Some(pos) => {
+                    let mut addr = [0; 4];
+                    self.read_bytes(pos, &mut addr)?;
+                    u32::from_le_bytes(addr) as u64
+                }
+                None => read_u32(inode.i_block(), idx * 4) as u64,
+            };
+            is_new = block == 0;
+            if is_new {
+                let (new_block, _) = self.alloc_blocks(goal, 1)?;
+                if new_block > u32::MAX as u64 {
+                    self.free_blocks(new_block, 1)?;
+                    return Err(VfsError::StorageFull);
+                }
+                block = new_block;
+                inode.add_blocks(1, self.block_size);
+                if level < depth {
+                    self.write_block(block, &vec![0; self.block_size])?;
+                }
+                match addr_pos {
+                    Some(pos) => self.write_bytes(pos, &(block as u32).to_le_bytes())?,
+                    None => write_u32(inode.i_block_mut(), idx * 4, block as u32),
+                }
+            }
+            parent = Some(block);
+        }
+        Ok((parent.unwrap(), is_new))
+    }
+
+    /// Unmaps all blocks from the logical block `start`, and appends them
+    /// (including the freed indirect blocks) to `freed`.
+    pub fn indirect_truncate(
+        &mut self,
+        inode: &mut Inode,
+        start: u32,
+        freed: &mut Vec<u64>,
+    ) -> VfsResult {
+        let start = start as u64;
+        let apb = self.addrs_per_block();
+        for idx in start.min(DIRECT_BLOCKS)..DIRECT_BLOCKS {
+            let block = read_u32(inode.i_block(), idx as usize * 4);
+            if block != 0 {
+                freed.push(block as u64);
+                write_u32(inode.i_block_mut(), idx as usize * 4, 0);
+            }
+        }
+        let mut base = DIRECT_BLOCKS;
+        let mut span = apb;
+        for depth in 1..=3 {
+            let slot = DIRECT_BLOCKS as usize + depth - 1;
+            let block = read_u32(inode.i_block(), slot * 4) as u64;
+            if block != 0 && start < base + span {
+                let start = start.saturating_sub(base);
+                if self.truncate_indirect_block(block, depth, start, freed)? {
+                    write_u32(inode.i_block_mut(), slot * 4, 0);
+                }
+            }
+            base += span;
+            span *= apb;
+        }
+        Ok(())
+    }
+
+    /// Unmaps the blocks from `start` (relative to the range of this
+    /// indirect block). Returns true if the indirect block itself is freed.
+    fn truncate_indirect_block(
+        &mut self,
+        block: u64,
+        depth: usize,
+        start: u64,
+        freed: &mut Vec<u64>,
+    ) -> VfsResult<bool> {
+        let apb = self.addrs_per_block();
+        let child_span = apb.pow(depth as u32 - 1);
+        let mut data = self.read_block(block)?;
+        let mut changed = false;
+        for idx in 0..apb {
+            let child = read_u32(&data, idx as usize * 4) as u64;
+            let child_start = idx * child_span;
+            if child == 0 || child_start + child_span <= start {
+                continue;
+            }
+            let child_freed = if depth == 1 {
+                freed.push(child);
+                true
+            } else {
+                let start = start.saturating_sub(child_start);
+                self.truncate_indirect_block(child, depth - 1, start, freed)?
+            };
+            if child_freed {
+                write_u32(&mut data, idx as usize * 4, 0);
+                changed = true;
+            }
+        }
+        if start == 0 {
+            freed.push(block);
+            Ok(true)
+        } else {
+            if changed {
+                self.write_block(block, &data)?;
+            }
+            Ok(false)
+        }
+    }
+}
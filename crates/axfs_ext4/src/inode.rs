@@ -0,0 +1,575 @@
+//! Inodes and the data of files.
+
+use alloc::{vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodePerm, VfsNodeType, VfsResult};
+use core::time::Duration;
+
+use crate::crc::crc32c;
+use crate::ext4::Ext4;
+use crate::extent::ExtentTree;
+use crate::layout::*;
+
+/// A raw inode, only the used fields have accessors.
+pub struct Inode {
+    pub ino: u32,
+    raw: Vec<u8>,
+}
+
+impl Inode {
+    pub fn mode(&self) -> u16 {
+        read_u16(&self.raw, 0x0)
+    }
+
+    pub fn set_mode(&mut self, mode: u16) {
+        write_u16(&mut self.raw, 0x0, mode)
+    }
+
+    pub fn node_type(&self) -> VfsNodeType {
+        match self.mode() & S_IFMT {
+            S_IFIFO => VfsNodeType::Fifo,
+            S_IFCHR => VfsNodeType::CharDevice,
+            S_IFDIR => VfsNodeType::Dir,
+            S_IFBLK => VfsNodeType::BlockDevice,
+            S_IFLNK => VfsNodeType::SymLink,
+            S_IFSOCK => VfsNodeType::Socket,
+            _ => VfsNodeType::File,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode() & S_IFMT == S_IFDIR
+    }
+
+    pub fn size(&self) -> u64 {
+        read_u32(&self.raw, 0x4) as u64 | (read_u32(&self.raw, 0x6c) as u64) << 32
+    }
+
+    pub fn set_size(&mut self, size: u64) {
+        write_u32(&mut self.raw, 0x4, size as u32);
+        write_u32(&mut self.raw, 0x6c, (size >> 32) as u32);
+    }
+
+    pub fn uid(&self) -> u32 {
+        read_u16(&self.raw, 0x2) as u32 | (read_u16(&self.raw, 0x78) as u32) << 16
+    }
+
+    pub fn gid(&self) -> u32 {
+        read_u16(&self.raw, 0x18) as u32 | (read_u16(&self.raw, 0x7a) as u32) << 16
+    }
+
+    /// Whether the field at `offset` is in the extra space of a large inode.
+    fn has_extra(&self, offset: usize) -> bool {
+        self.raw.len() > GOOD_OLD_INODE_SIZE
+            && offset + 4 <= GOOD_OLD_INODE_SIZE + read_u16(&self.raw, 0x80) as usize
+    }
+
+    /// Reads a timestamp, whose seconds are at `offset`. The epoch bits and
+    /// the nanoseconds are at `extra` if the inode has the extra space.
+    fn time(&self, offset: usize, extra: usize) -> Duration {
+        let mut secs = read_u32(&self.raw, offset) as i32 as i64;
+        let mut nanos = 0;
+        if self.has_extra(extra) {
+            let extra = read_u32(&self.raw, extra);
+            secs += ((extra & 3) as i64) << 32;
+            nanos = (extra >> 2).min(999_999_999);
+        }
+        if secs < 0 {
+            return Duration::ZERO;
+        }
+        Duration::new(secs as u64, nanos)
+    }
+
+    fn set_time(&mut self, offset: usize, extra: usize, time: Duration) {
+        let secs = time.as_secs() as i64;
+        write_u32(&mut self.raw, offset, secs as u32);
+        if self.has_extra(extra) {
+            let epoch = ((secs - secs as i32 as i64) >> 32) as u32 & 3;
+            write_u32(&mut self.raw, extra, epoch | time.subsec_nanos() << 2);
+        }
+    }
+
+    pub fn atime(&self) -> Duration {
+        self.time(0x8, 0x8c)
+    }
+
+    pub fn set_atime(&mut self, time: Duration) {
+        self.set_time(0x8, 0x8c, time)
+    }
+
+    pub fn ctime(&self) -> Duration {
+        self.time(0xc, 0x84)
+    }
+
+    pub fn set_ctime(&mut self, time: Duration) {
+        self.set_time(0xc, 0x84, time)
+    }
+
+    pub fn mtime(&self) -> Duration {
+        self.time(0x10, 0x88)
+    }
+
+    pub fn set_mtime(&mut self, time: Duration) {
+        self.set_time(0x10, 0x88, time)
+    }
+
+    /// Sets the modification and change times after the data is changed.
+    pub fn set_modified(&mut self, time: Duration) {
+        self.set_mtime(time);
+        self.set_ctime(time);
+    }
+
+    pub fn set_dtime(&mut self, time: Duration) {
+        write_u32(&mut self.raw, 0x14, time.as_secs() as u32)
+    }
+
+    pub fn links(&self) -> u16 {
+        read_u16(&self.raw, 0x1a)
+    }
+
+    pub fn set_links(&mut self, links: u16) {
+        write_u16(&mut self.raw, 0x1a, links)
+    }
+
+    pub fn flags(&self) -> u32 {
+        read_u32(&self.raw, 0x20)
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        write_u32(&mut self.raw, 0x20, flags)
+    }
+
+    /// The 60-byte `i_block` area, i.e., the block map or the root of the
+    /// extent tree.
+    pub fn i_block(&self) -> &[u8] {
+        &self.raw[0x28..0x64]
+    }
+
+    pub fn i_block_mut(&mut self) -> &mut [u8] {
+        &mut self.raw[0x28..0x64]
+    }
+
+    pub fn generation(&self) -> u32 {
+        read_u32(&self.raw, 0x64)
+    }
+
+    fn file_acl(&self) -> u64 {
+        read_u32(&self.raw, 0x68) as u64 | (read_u16(&self.raw, 0x76) as u64) << 32
+    }
+
+    fn set_file_acl(&mut self, block: u64) {
+        write_u32(&mut self.raw, 0x68, block as u32);
+        write_u16(&mut self.raw, 0x76, (block >> 32) as u16);
+    }
+
+    fn raw_blocks(&self) -> u64 {
+        read_u32(&self.raw, 0x1c) as u64 | (read_u16(&self.raw, 0x74) as u64) << 32
+    }
+
+    /// Number of 512-byte sectors used by the inode.
+    pub fn sectors(&self, block_size: usize) -> u64 {
+        if self.flags() & INODE_FL_HUGE_FILE != 0 {
+            self.raw_blocks() * (block_size / 512) as u64
+        } else {
+            self.raw_blocks()
+        }
+    }
+
+    /// Adds `delta` blocks to the number of blocks used by the inode.
+    pub fn add_blocks(&mut self, delta: i64, block_size: usize) {
+        let unit = if self.flags() & INODE_FL_HUGE_FILE != 0 {
+            1
+        } else {
+            (block_size / 512) as i64
+        };
+        let blocks = (self.raw_blocks() as i64 + delta * unit).max(0) as u64;
+        write_u32(&mut self.raw, 0x1c, blocks as u32);
+        write_u16(&mut self.raw, 0x74, (blocks >> 32) as u16);
+    }
+
+    /// Whether the data is stored in `i_block` directly, i.e., fast symbolic
+    /// links, device files, FIFOs and sockets.
+    fn has_no_blocks(&self) -> bool {
+        match self.mode() & S_IFMT {
+            S_IFREG | S_IFDIR => false,
+            S_IFLNK => self.size() < 60 && self.flags() & INODE_FL_EXTENTS == 0,
+            _ => true,
+        }
+    }
+
+    pub fn attr(&self, block_size: usize) -> VfsNodeAttr {
+        let mut attr = VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(self.mode() & 0o777),
+            self.node_type(),
+            self.size(),
+            self.sectors(block_size),
+        );
+        attr.set_nlink(self.links() as u64);
+        attr.set_owner(self.uid(), self.gid());
+        attr.set_times(self.atime(), self.mtime(), self.ctime());
+        attr
+    }
+}
+
+/// The mapping from logical blocks of a file to physical blocks, loaded for
+/// an operation on the file data.
+pub enum BlockMap {
+    Extents(ExtentTree),
+    Indirect,
+}
+
+impl Ext4 {
+    fn inode_pos(&self, ino: u32) -> VfsResult<u64> {
+        if ino == 0 || ino > self.sb.inodes_count() {
+            warn!("ext4: invalid inode number {}", ino);
+            return Err(VfsError::InvalidData);
+        }
+        let inodes_per_group = self.sb.inodes_per_group();
+        let table = self.groups[((ino - 1) / inodes_per_group) as usize].inode_table();
+        let index = ((ino - 1) % inodes_per_group) as u64;
+        Ok(table * self.block_size as u64 + index * self.inode_size as u64)
+    }
+
+    pub fn read_inode(&mut self, ino: u32) -> VfsResult<Inode> {
+        let pos = self.inode_pos(ino)?;
+        let mut raw = vec![0; self.inode_size];
+        self.read_bytes(pos, &mut raw)?;
+        Ok(Inode { ino, raw })
+    }
+
+    pub fn write_inode(&mut self, inode: &mut Inode) -> VfsResult {
+        self.check_writable()?;
+        if self.has_metadata_csum() {
+            self.update_inode_checksum(inode);
+        }
+        let pos = self.inode_pos(inode.ino)?;
+        self.write_bytes(pos, &inode.raw)
+    }
+
+    /// Creates an in-memory inode with no data, it's not written to the disk.
+    pub fn new_inode(&self, ino: u32, mode: u16) -> Inode {
+        let mut inode = Inode {
+            ino,
+            raw: vec![0; self.inode_size],
+        };
+        write_u16(&mut inode.raw, 0x0, mode);
+        if self.inode_size > GOOD_OLD_INODE_SIZE {
+            let extra_isize = (self.inode_size - GOOD_OLD_INODE_SIZE).min(32);
+            write_u16(&mut inode.raw, 0x80, extra_isize as u16);
+        }
+        let now = axfs_vfs::current_time();
+        inode.set_atime(now);
+        inode.set_modified(now);
+        if inode.has_extra(0x94) {
+            inode.set_time(0x90, 0x94, now); // creation time
+        }
+        if self.sb.has_incompat(INCOMPAT_EXTENTS) && matches!(mode & S_IFMT, S_IFREG | S_IFDIR) {
+            inode.set_flags(INODE_FL_EXTENTS);
+            ExtentTree::init_root(inode.i_block_mut());
+        }
+        inode
+    }
+
+    /// Seed of the checksums of the blocks owned by the inode.
+    pub fn inode_csum_seed(&self, inode: &Inode) -> u32 {
+        let crc = crc32c(self.csum_seed, &inode.ino.to_le_bytes());
+        crc32c(crc, &inode.generation().to_le_bytes())
+    }
+
+    fn update_inode_checksum(&self, inode: &mut Inode) {
+        // `i_checksum_lo` at 0x7c and `i_checksum_hi` at 0x82 are excluded.
+        let has_hi = self.inode_size > GOOD_OLD_INODE_SIZE && read_u16(&inode.raw, 0x80) >= 4;
+        let raw = &inode.raw;
+        let mut crc = crc32c(self.inode_csum_seed(inode), &raw[..0x7c]);
+        crc = crc32c(crc, &[0; 2]);
+        if has_hi {
+            crc = crc32c(crc, &raw[0x7e..0x82]);
+            crc = crc32c(crc, &[0; 2]);
+            crc = crc32c(crc, &raw[0x84..]);
+        } else {
+            crc = crc32c(crc, &raw[0x7e..]);
+        }
+        write_u16(&mut inode.raw, 0x7c, crc as u16);
+        if has_hi {
+            write_u16(&mut inode.raw, 0x82, (crc >> 16) as u16);
+        }
+    }
+
+    /// A block near the inode, from which blocks are allocated for it.
+    pub fn inode_goal(&self, inode: &Inode) -> u64 {
+        let g = (inode.ino - 1) / self.sb.inodes_per_group();
+        self.group_first_block(g)
+    }
+
+    pub fn load_map(&mut self, inode: &Inode) -> VfsResult<BlockMap> {
+        if inode.flags() & INODE_FL_INLINE_DATA != 0 {
+            return Err(VfsError::Unsupported);
+        }
+        if inode.flags() & INODE_FL_EXTENTS != 0 {
+            Ok(BlockMap::Extents(self.load_extents(inode)?))
+        } else {
+            Ok(BlockMap::Indirect)
+        }
+    }
+
+    /// Returns the physical block of the logical block, or `None` if it's
+    /// not allocated or not initialized.
+    pub fn map_block(
+        &mut self,
+        inode: &Inode,
+        map: &BlockMap,
+        lblk: u32,
+    ) -> VfsResult<Option<u64>> {
+        match map {
+            BlockMap::Extents(tree) => Ok(tree.lookup(lblk).filter(|e| !e.1).map(|e| e.0)),
+            BlockMap::Indirect => self.indirect_lookup(inode, lblk),
+        }
+    }
+
+    /// Returns the physical block of the logical block, allocates one if it
+    /// is not allocated. The second return value is true if the content of
+    /// the block is not initialized.
+    ///
+    /// [`store_map`](Self::store_map) must be called after all blocks are
+    /// mapped.
+    pub fn map_alloc(
+        &mut self,
+        inode: &mut Inode,
+        map: &mut BlockMap,
+        lblk: u32,
+    ) -> VfsResult<(u64, bool)> {
+        let goal = match lblk.checked_sub(1) {
+            Some(prev) => self.map_block(inode, map, prev)?.map(|b| b + 1),
+            None => None,
+        }
+        .unwrap_or_else(|| self.inode_goal(inode));
+        match map {
+            BlockMap::Extents(tree) => match tree.lookup(lblk) {
+                Some((block, false)) => Ok((block, false)),
+                Some((block, true)) => {
+                    tree.mark_init(lblk);
+                    Ok((block, true))
+                }
+                None => {
+                    let (block, _) = self.alloc_blocks(goal, 1)?;
+                    inode.add_blocks(1, self.block_size);
+                    tree.insert(lblk, block);
+                    Ok((block, true))
+                }
+            },
+            BlockMap::Indirect => self.indirect_alloc(inode, lblk, goal),
+        }
+    }
+
+    /// Writes the changed block map back, the inode must be written after
+    /// this.
+    pub fn store_map(&mut self, inode: &mut Inode, map: &mut BlockMap) -> VfsResult {
+        match map {
+            BlockMap::Extents(tree) => self.store_extents(inode, tree),
+            BlockMap::Indirect => Ok(()),
+        }
+    }
+
+    /// Frees all blocks from the logical block `start`.
+    fn truncate_blocks(&mut self, inode: &mut Inode, map: &mut BlockMap, start: u32) -> VfsResult {
+        let mut freed = Vec::new();
+        match map {
+            BlockMap::Extents(tree) => tree.truncate(start, &mut freed),
+            BlockMap::Indirect => self.indirect_truncate(inode, start, &mut freed)?,
+        }
+        inode.add_blocks(-(freed.len() as i64), self.block_size);
+        self.free_block_list(&mut freed)?;
+        self.store_map(inode, map)
+    }
+
+    /// Reads the file data at `offset`, returns the number of bytes read.
+    pub fn read_data(&mut self, inode: &Inode, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let size = inode.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let map = self.load_map(inode)?;
+        let block_size = self.block_size as u64;
+        let mut read_len = 0;
+        while read_len < len {
+            let pos = offset + read_len as u64;
+            let block_offset = (pos % block_size) as usize;
+            let count = (self.block_size - block_offset).min(len - read_len);
+            let dst = &mut buf[read_len..read_len + count];
+            match self.map_block(inode, &map, (pos / block_size) as u32)? {
+                Some(block) => self.read_bytes(block * block_size + block_offset as u64, dst)?,
+                None => dst.fill(0), // hole
+            }
+            read_len += count;
+        }
+        Ok(len)
+    }
+
+    /// Writes the file data at `offset`, and writes the inode back.
+    pub fn write_data(&mut self, inode: &mut Inode, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.check_writable()?;
+        let block_size = self.block_size as u64;
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .ok_or(VfsError::InvalidInput)?;
+        if end.div_ceil(block_size) > u32::MAX as u64 {
+            return Err(VfsError::InvalidInput);
+        }
+        let mut map = self.load_map(inode)?;
+        let mut write_len = 0;
+        let res = loop {
+            if write_len == buf.len() {
+                break Ok(());
+            }
+            let pos = offset + write_len as u64;
+            let block_offset = (pos % block_size) as usize;
+            let count = (self.block_size - block_offset).min(buf.len() - write_len);
+            let src = &buf[write_len..write_len + count];
+            let (block, uninit) = match self.map_alloc(inode, &mut map, (pos / block_size) as u32) {
+                Ok(res) => res,
+                Err(e) => break Err(e),
+            };
+            let res = if uninit && count < self.block_size {
+                let mut data = vec![0; self.block_size];
+                data[block_offset..block_offset + count].copy_from_slice(src);
+                self.write_block(block, &data)
+            } else {
+                self.write_bytes(block * block_size + block_offset as u64, src)
+            };
+            if let Err(e) = res {
+                break Err(e);
+            }
+            write_len += count;
+        };
+        // Keep the blocks allocated so far even if it fails.
+        self.store_map(inode, &mut map)?;
+        let end = offset + write_len as u64;
+        if end > inode.size() {
+            inode.set_size(end);
+        }
+        self.write_inode(inode)?;
+        match res {
+            Err(e) if write_len == 0 => Err(e),
+            _ => Ok(write_len),
+        }
+    }
+
+    /// Reads the target of a symbolic link.
+    pub fn read_link(&mut self, inode: &Inode) -> VfsResult<Vec<u8>> {
+        let size = inode.size();
+        if size > self.block_size as u64 {
+            return Err(VfsError::InvalidData);
+        }
+        if inode.has_no_blocks() {
+            return Ok(inode.i_block()[..size as usize].to_vec());
+        }
+        let mut buf = vec![0; size as usize];
+        self.read_data(inode, 0, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Stores the target of a new symbolic link, in `i_block` if it's short
+    /// enough, and writes the inode back.
+    pub fn write_link(&mut self, inode: &mut Inode, target: &[u8]) -> VfsResult {
+        if target.len() < inode.i_block().len() {
+            inode.i_block_mut()[..target.len()].copy_from_slice(target);
+            inode.set_size(target.len() as u64);
+            return self.write_inode(inode);
+        }
+        if self.sb.has_incompat(INCOMPAT_EXTENTS) {
+            inode.set_flags(INODE_FL_EXTENTS);
+            ExtentTree::init_root(inode.i_block_mut());
+        }
+        if self.write_data(inode, 0, target)? < target.len() {
+            return Err(VfsError::StorageFull);
+        }
+        Ok(())
+    }
+
+    /// Sets the file size, and writes the inode back.
+    pub fn truncate(&mut self, inode: &mut Inode, size: u64) -> VfsResult {
+        self.check_writable()?;
+        let block_size = self.block_size as u64;
+        if size.div_ceil(block_size) > u32::MAX as u64 {
+            return Err(VfsError::InvalidInput);
+        }
+        if size < inode.size() {
+            let mut map = self.load_map(inode)?;
+            // Data after the end of file must be zero when it's extended.
+            let tail = (size % block_size) as usize;
+            if tail != 0 {
+                if let Some(block) = self.map_block(inode, &map, (size / block_size) as u32)? {
+                    let zeros = vec![0; self.block_size - tail];
+                    self.write_bytes(block * block_size + tail as u64, &zeros)?;
+                }
+            }
+            self.truncate_blocks(inode, &mut map, size.div_ceil(block_size) as u32)?;
+        }
+        inode.set_size(size);
+        self.write_inode(inode)
+    }
+
+    /// Sets the permission of the inode `ino`.
+    pub fn set_perm(&mut self, ino: u32, perm: u16) -> VfsResult {
+        self.check_writable()?;
+        let mut inode = self.read_inode(ino)?;
+        inode.set_mode(inode.mode() & S_IFMT | perm & 0o777);
+        inode.set_ctime(axfs_vfs::current_time());
+        self.write_inode(&mut inode)
+    }
+
+    /// Sets the access and modification times of the inode `ino`, a time of
+    /// `None` is left unchanged.
+    pub fn set_times(
+        &mut self,
+        ino: u32,
+        atime: Option<Duration>,
+        mtime: Option<Duration>,
+    ) -> VfsResult {
+        self.check_writable()?;
+        let mut inode = self.read_inode(ino)?;
+        if let Some(atime) = atime {
+            inode.set_atime(atime);
+        }
+        if let Some(mtime) = mtime {
+            inode.set_mtime(mtime);
+        }
+        inode.set_ctime(axfs_vfs::current_time());
+        self.write_inode(&mut inode)
+    }
+
+    /// Frees all blocks of the inode, including the extended attribute
+    /// block. The inode is not written back.
+    pub fn free_data(&mut self, inode: &mut Inode) -> VfsResult {
+        if !inode.has_no_blocks() {
+            let mut map = self.load_map(inode)?;
+            self.truncate_blocks(inode, &mut map, 0)?;
+            inode.set_size(0);
+        }
+        let acl = inode.file_acl();
+        if acl != 0 {
+            self.release_xattr_block(acl)?;
+            inode.set_file_acl(0);
+            inode.add_blocks(-1, self.block_size);
+        }
+        Ok(())
+    }
+
+    /// Drops a reference to the shared extended attribute block.
+    fn release_xattr_block(&mut self, block: u64) -> VfsResult {
+        let mut data = self.read_block(block)?;
+        let refcount = read_u32(&data, 0x4);
+        if refcount > 1 {
+            write_u32(&mut data, 0x4, refcount - 1);
+            if self.has_metadata_csum() {
+                write_u32(&mut data, 0x10, 0);
+                let crc = crc32c(self.csum_seed, &block.to_le_bytes());
+                let csum = crc32c(crc, &data);
+                write_u32(&mut data, 0x10, csum);
+            }
+            self.write_block(block, &data)
+        } else {
+            self.free_blocks(block, 1)
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! On-disk structures and constants.
+
+use alloc::vec::Vec;
+
+pub const SUPER_BLOCK_OFFSET: u64 = 1024;
+pub const SUPER_BLOCK_SIZE: usize = 1024;
+pub const EXT4_MAGIC: u16 = 0xef53;
+
+pub const ROOT_INO: u32 = 2;
+pub const GOOD_OLD_INODE_SIZE: usize = 128;
+pub const GOOD_OLD_FIRST_INO: u32 = 11;
+
+pub const COMPAT_SPARSE_SUPER2: u32 = 0x200;
+
+pub const INCOMPAT_FILETYPE: u32 = 0x2;
+pub const INCOMPAT_RECOVER: u32 = 0x4;
+pub const INCOMPAT_EXTENTS: u32 = 0x40;
+pub const INCOMPAT_64BIT: u32 = 0x80;
+pub const INCOMPAT_FLEX_BG: u32 = 0x200;
+pub const INCOMPAT_CSUM_SEED: u32 = 0x2000;
+pub const INCOMPAT_LARGEDIR: u32 = 0x4000;
+/// Incompatible features that are supported.
+pub const INCOMPAT_SUPPORTED: u32 = INCOMPAT_FILETYPE
+    | INCOMPAT_EXTENTS
+    | INCOMPAT_64BIT
+    | INCOMPAT_FLEX_BG
+    | INCOMPAT_CSUM_SEED
+    | INCOMPAT_LARGEDIR;
+
+pub const RO_COMPAT_SPARSE_SUPER: u32 = 0x1;
+pub const RO_COMPAT_LARGE_FILE: u32 = 0x2;
+pub const RO_COMPAT_HUGE_FILE: u32 = 0x8;
+pub const RO_COMPAT_GDT_CSUM: u32 = 0x10;
+pub const RO_COMPAT_DIR_NLINK: u32 = 0x20;
+pub const RO_COMPAT_EXTRA_ISIZE: u32 = 0x40;
+pub const RO_COMPAT_METADATA_CSUM: u32 = 0x400;
+/// Read-only compatible features that are supported to write.
+pub const RO_COMPAT_SUPPORTED: u32 = RO_COMPAT_SPARSE_SUPER
+    | RO_COMPAT_LARGE_FILE
+    | RO_COMPAT_HUGE_FILE
+    | RO_COMPAT_GDT_CSUM
+    | RO_COMPAT_DIR_NLINK
+    | RO_COMPAT_EXTRA_ISIZE
+    | RO_COMPAT_METADATA_CSUM;
+
+pub const BG_INODE_UNINIT: u16 = 0x1;
+pub const BG_BLOCK_UNINIT: u16 = 0x2;
+
+pub const S_IFMT: u16 = 0o170000;
+pub const S_IFIFO: u16 = 0o010000;
+pub const S_IFCHR: u16 = 0o020000;
+pub const S_IFDIR: u16 = 0o040000;
+pub const S_IFBLK: u16 = 0o060000;
+pub const S_IFREG: u16 = 0o100000;
+pub const S_IFLNK: u16 = 0o120000;
+pub const S_IFSOCK: u16 = 0o140000;
+
+pub const INODE_FL_INDEX: u32 = 0x1000;
+pub const INODE_FL_HUGE_FILE: u32 = 0x40000;
+pub const INODE_FL_EXTENTS: u32 = 0x80000;
+pub const INODE_FL_INLINE_DATA: u32 = 0x1000_0000;
+
+pub const FT_UNKNOWN: u8 = 0;
+pub const FT_REG_FILE: u8 = 1;
+pub const FT_DIR: u8 = 2;
+pub const FT_CHRDEV: u8 = 3;
+pub const FT_BLKDEV: u8 = 4;
+pub const FT_FIFO: u8 = 5;
+pub const FT_SOCK: u8 = 6;
+pub const FT_SYMLINK: u8 = 7;
+
+pub const NAME_LEN: usize = 255;
+
+pub fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+pub fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn write_u16(buf: &mut [u8], offset: usize, val: u16) {
+    buf[offset..offset + 2].copy_from_slice(&val.to_le_bytes());
+}
+
+pub fn write_u32(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+/// Raw superblock, only the used fields have accessors.
+pub struct SuperBlock {
+    pub raw: Vec<u8>,
+}
+
+impl SuperBlock {
+    pub fn inodes_count(&self) -> u32 {
+        read_u32(&self.raw, 0x0)
+    }
+
+    pub fn blocks_count(&self) -> u64 {
+        read_u32(&self.raw, 0x4) as u64 | (self.hi32(0x150) << 32)
+    }
+
+    pub fn free_blocks(&self) -> u64 {
+        read_u32(&self.raw, 0xc) as u64 | (self.hi32(0x158) << 32)
+    }
+
+    pub fn set_free_blocks(&mut self, val: u64) {
+        write_u32(&mut self.raw, 0xc, val as u32);
+        if self.has_incompat(INCOMPAT_64BIT) {
+            write_u32(&mut self.raw, 0x158, (val >> 32) as u32);
+        }
+    }
+
+    pub fn free_inodes(&self) -> u32 {
+        read_u32(&self.raw, 0x10)
+    }
+
+    pub fn set_free_inodes(&mut self, val: u32) {
+        write_u32(&mut self.raw, 0x10, val);
+    }
+
+    pub fn first_data_block(&self) -> u32 {
+        read_u32(&self.raw, 0x14)
+    }
+
+    pub fn log_block_size(&self) -> u32 {
+        read_u32(&self.raw, 0x18)
+    }
+
+    pub fn blocks_per_group(&self) -> u32 {
+        read_u32(&self.raw, 0x20)
+    }
+
+    pub fn inodes_per_group(&self) -> u32 {
+        read_u32(&self.raw, 0x28)
+    }
+
+    pub fn magic(&self) -> u16 {
+        read_u16(&self.raw, 0x38)
+    }
+
+    pub fn rev_level(&self) -> u32 {
+        read_u32(&self.raw, 0x4c)
+    }
+
+    pub fn first_ino(&self) -> u32 {
+        if self.rev_level() == 0 {
+            GOOD_OLD_FIRST_INO
+        } else {
+            read_u32(&self.raw, 0x54)
+        }
+    }
+
+    pub fn inode_size(&self) -> usize {
+        if self.rev_level() == 0 {
+            GOOD_OLD_INODE_SIZE
+        } else {
+            read_u16(&self.raw, 0x58) as usize
+        }
+    }
+
+    pub fn has_compat(&self, feature: u32) -> bool {
+        read_u32(&self.raw, 0x5c) & feature != 0
+    }
+
+    pub fn feature_incompat(&self) -> u32 {
+        read_u32(&self.raw, 0x60)
+    }
+
+    pub fn has_incompat(&self, feature: u32) -> bool {
+        self.feature_incompat() & feature != 0
+    }
+
+    pub fn feature_ro_compat(&self) -> u32 {
+        read_u32(&self.raw, 0x64)
+    }
+
+    pub fn has_ro_compat(&self, feature: u32) -> bool {
+        self.feature_ro_compat() & feature != 0
+    }
+
+    pub fn uuid(&self) -> &[u8] {
+        &self.raw[0x68..0x78]
+    }
+
+    pub fn reserved_gdt_blocks(&self) -> u32 {
+        read_u16(&self.raw, 0xce) as u32
+    }
+
+    /// Groups with backup superblocks with `sparse_super2`.
+    pub fn backup_bgs(&self) -> [u32; 2] {
+        [read_u32(&self.raw, 0x24c), read_u32(&self.raw, 0x250)]
+    }
+
+    pub fn desc_size(&self) -> usize {
+        if self.has_incompat(INCOMPAT_64BIT) {
+            read_u16(&self.raw, 0xfe) as usize
+        } else {
+            32
+        }
+    }
+
+    pub fn checksum_seed(&self) -> u32 {
+        read_u32(&self.raw, 0x270)
+    }
+
+    /// Updates the checksum of the superblock.
+    pub fn update_checksum(&mut self) {
+        if self.has_ro_compat(RO_COMPAT_METADATA_CSUM) {
+            let csum = crate::crc::crc32c(!0, &self.raw[..0x3fc]);
+            write_u32(&mut self.raw, 0x3fc, csum);
+        }
+    }
+
+    fn hi32(&self, offset: usize) -> u64 {
+        if self.has_incompat(INCOMPAT_64BIT) {
+            read_u32(&self.raw, offset) as u64
+        } else {
+            0
+        }
+    }
+}
+
+/// Raw block group descriptor of 32 or 64 bytes.
+pub struct GroupDesc {
+    pub raw: Vec<u8>,
+}
+
+impl GroupDesc {
+    fn read_lo_hi32(&self, lo: usize, hi: usize) -> u64 {
+        let hi = if self.raw.len() >= 64 {
+            read_u32(&self.raw, hi) as u64
+        } else {
+            0
+        };
+        read_u32(&self.raw, lo) as u64 | (hi << 32)
+    }
+
+    fn read_lo_hi16(&self, lo: usize, hi: usize) -> u32 {
+        let hi = if self.raw.len() >= 64 {
+            read_u16(&self.raw, hi) as u32
+        } else {
+            0
+        };
+        read_u16(&self.raw, lo) as u32 | (hi << 16)
+    }
+
+    fn write_lo_hi16(&mut self, lo: usize, hi: usize, val: u32) {
+        write_u16(&mut self.raw, lo, val as u16);
+        if self.raw.len() >= 64 {
+            write_u16(&mut self.raw, hi, (val >> 16) as u16);
+        }
+    }
+
+    pub fn block_bitmap(&self) -> u64 {
+        self.read_lo_hi32(0x0, 0x20)
+    }
+
+    pub fn inode_bitmap(&self) -> u64 {
+        self.read_lo_hi32(0x4, 0x24)
+    }
+
+    pub fn inode_table(&self) -> u64 {
+        self.read_lo_hi32(0x8, 0x28)
+    }
+
+    pub fn free_blocks(&self) -> u32 {
+        self.read_lo_hi16(0xc, 0x2c)
+    }
+
+    pub fn set_free_blocks(&mut self, val: u32) {
+        self.write_lo_hi16(0xc, 0x2c, val)
+    }
+
+    pub fn free_inodes(&self) -> u32 {
+        self.read_lo_hi16(0xe, 0x2e)
+    }
+
+    pub fn set_free_inodes(&mut self, val: u32) {
+        self.write_lo_hi16(0xe, 0x2e, val)
+    }
+
+    pub fn used_dirs(&self) -> u32 {
+        self.read_lo_hi16(0x10, 0x30)
+    }
+
+    pub fn set_used_dirs(&mut self, val: u32) {
+        self.write_lo_hi16(0x10, 0x30, val)
+    }
+
+    pub fn flags(&self) -> u16 {
+        read_u16(&self.raw, 0x12)
+    }
+
+    pub fn set_flags(&mut self, val: u16) {
+        write_u16(&mut self.raw, 0x12, val)
+    }
+
+    pub fn itable_unused(&self) -> u32 {
+        self.read_lo_hi16(0x1c, 0x32)
+    }
+
+    pub fn set_itable_unused(&mut self, val: u32) {
+        self.write_lo_hi16(0x1c, 0x32, val)
+    }
+
+    pub fn set_block_bitmap_csum(&mut self, csum: u32) {
+        self.write_lo_hi16(0x18, 0x38, csum)
+    }
+
+    pub fn set_inode_bitmap_csum(&mut self, csum: u32) {
+        self.write_lo_hi16(0x1a, 0x3a, csum)
+    }
+
+    pub fn set_checksum(&mut self, csum: u16) {
+        write_u16(&mut self.raw, 0x1e, csum)
+    }
+}
@@ -0,0 +1,115 @@
+//! ext2/ext4 filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
+//!
+//! The implementation is based on [`axfs_vfs`], and works on any block device
+//! that implements [`BlockDevice`].
+//!
+//! Supported features:
+//!
+//! - ext2/ext3 block maps and ext4 extent trees.
+//! - `64bit`, `flex_bg`, `huge_file`, `dir_nlink` and `large_dir`.
+//! - `metadata_csum` and `gdt_csum` checksums, which are updated on writes.
+//!
+//! The journal is not used, so the filesystem must be cleanly unmounted
+//! before. Directories with hash indexes are read as linear ones, and new
+//! entries can not be added to them with `metadata_csum`. Filesystems with
+//! other read-only compatible features are mounted read-only, and those with
+//! other incompatible features (e.g., `inline_data`, `encrypt`) are refused.
+//! Access times are not updated by reads.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+#[macro_use]
+extern crate log;
+
+mod bitmap;
+mod crc;
+mod dir;
+mod dirent;
+mod ext4;
+mod extent;
+mod file;
+mod indirect;
+mod inode;
+mod layout;
+mod namei;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::dir::DirNode;
+pub use self::file::FileNode;
+
+use alloc::{boxed::Box, sync::Arc};
+use axfs_vfs::{VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use spin::{once::Once, Mutex};
+
+use self::ext4::Ext4;
+
+/// The block device that an ext2/ext4 filesystem is on.
+pub trait BlockDevice: Send {
+    /// Reads `buf.len()` bytes at byte offset `pos`.
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> VfsResult;
+
+    /// Writes `buf` at byte offset `pos`.
+    fn write_at(&mut self, pos: u64, buf: &[u8]) -> VfsResult;
+
+    /// Writes the cached data back to the device.
+    fn flush(&mut self) -> VfsResult {
+        Ok(())
+    }
+}
+
+/// States shared by all nodes of a filesystem.
+pub(crate) struct Volume {
+    ext4: Mutex<Ext4>,
+    parent: Once<VfsNodeRef>,
+}
+
+/// An ext2/ext4 filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct Ext4FileSystem {
+    vol: Arc<Volume>,
+}
+
+impl Ext4FileSystem {
+    /// Opens the filesystem on the block device.
+    pub fn new(dev: impl BlockDevice + 'static) -> VfsResult<Self> {
+        let ext4 = Ext4::open(Box::new(dev))?;
+        Ok(Self {
+            vol: Arc::new(Volume {
+                ext4: Mutex::new(ext4),
+                parent: Once::new(),
+            }),
+        })
+    }
+
+    /// Returns the root directory node in [`Arc<DirNode>`](DirNode).
+    pub fn root_dir_node(&self) -> Arc<DirNode> {
+        Arc::new(DirNode::new(self.vol.clone(), layout::ROOT_INO))
+    }
+}
+
+impl VfsOps for Ext4FileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        if let Some(parent) = mount_point.parent() {
+            self.vol.parent.call_once(|| parent);
+        }
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        self.vol.ext4.lock().flush()
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root_dir_node()
+    }
+}
+
+/// Creates the node of an inode.
+fn new_node(vol: &Arc<Volume>, ino: u32, ty: VfsNodeType) -> VfsNodeRef {
+    match ty {
+        VfsNodeType::Dir => Arc::new(DirNode::new(vol.clone(), ino)),
+        _ => Arc::new(FileNode::new(vol.clone(), ino)),
+    }
+}
@@ -0,0 +1,269 @@
+//! Operations on the names of nodes: creating, linking, removing and
+//! renaming.
+
+use alloc::{string::String, vec::Vec};
+use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+
+use crate::dirent::file_type_of;
+use crate::ext4::Ext4;
+use crate::inode::Inode;
+use crate::layout::*;
+
+/// Maximum number of links to an inode.
+const LINK_MAX: u16 = 65000;
+
+impl Ext4 {
+    fn read_dir_inode(&mut self, ino: u32) -> VfsResult<Inode> {
+        let dir = self.read_inode(ino)?;
+        if dir.is_dir() {
+            Ok(dir)
+        } else {
+            Err(VfsError::NotADirectory)
+        }
+    }
+
+    /// Increases the link count of a directory for a new subdirectory.
+    fn inc_dir_links(&self, dir: &mut Inode) -> VfsResult {
+        let links = dir.links();
+        if links == 1 {
+            // Already too many subdirectories with `dir_nlink`.
+        } else if links + 1 < LINK_MAX {
+            dir.set_links(links + 1);
+        } else if self.sb.has_ro_compat(RO_COMPAT_DIR_NLINK) {
+            dir.set_links(1);
+        } else {
+            return Err(VfsError::StorageFull);
+        }
+        Ok(())
+    }
+
+    fn dec_dir_links(dir: &mut Inode) {
+        if dir.links() > 2 {
+            dir.set_links(dir.links() - 1);
+        }
+    }
+
+    /// Looks up `name` in the directory `dir_ino`.
+    pub fn lookup(&mut self, dir_ino: u32, name: &str) -> VfsResult<Option<(u32, u8)>> {
+        let dir = self.read_dir_inode(dir_ino)?;
+        self.dir_lookup(&dir, name)
+    }
+
+    /// Returns all entries in the directory `dir_ino`.
+    pub fn list_dir(&mut self, dir_ino: u32) -> VfsResult<Vec<(String, u32, u8)>> {
+        let dir = self.read_dir_inode(dir_ino)?;
+        self.dir_list(&dir)
+    }
+
+    /// Creates a node in the directory `dir_ino`, does nothing if it already
+    /// exists.
+    pub fn create_node(&mut self, dir_ino: u32, name: &str, ty: VfsNodeType) -> VfsResult {
+        self.check_writable()?;
+        let mut dir = self.read_dir_inode(dir_ino)?;
+        if self.dir_lookup(&dir, name)?.is_some() {
+            return Ok(());
+        }
+        let mode = match ty {
+            VfsNodeType::File => S_IFREG | 0o644,
+            VfsNodeType::Dir => S_IFDIR | 0o755,
+            _ => return Err(VfsError::Unsupported),
+        };
+        self.add_inode(&mut dir, name, mode, |fs, inode| {
+            if ty.is_dir() {
+                fs.dir_init(inode, dir_ino)
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// Creates a symbolic link in the directory `dir_ino`, which points to
+    /// `target`.
+    pub fn create_symlink(&mut self, dir_ino: u32, name: &str, target: &str) -> VfsResult {
+        self.check_writable()?;
+        let mut dir = self.read_dir_inode(dir_ino)?;
+        if self.dir_lookup(&dir, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+        if target.is_empty() || target.len() >= self.block_size || target.contains('\0') {
+            return Err(VfsError::InvalidInput);
+        }
+        self.add_inode(&mut dir, name, S_IFLNK | 0o777, |fs, inode| {
+            fs.write_link(inode, target.as_bytes())
+        })
+    }
+
+    /// Allocates an inode with `mode` and adds it to the directory `dir` as
+    /// `name`. The data of the new inode is filled by `init`.
+    fn add_inode<F>(&mut self, dir: &mut Inode, name: &str, mode: u16, init: F) -> VfsResult
+    where
+        F: FnOnce(&mut Self, &mut Inode) -> VfsResult,
+    {
+        if name.len() > NAME_LEN {
+            return Err(VfsError::InvalidInput);
+        }
+        let is_dir = mode & S_IFMT == S_IFDIR;
+        if is_dir {
+            self.inc_dir_links(dir)?;
+        }
+
+        let ino = self.alloc_inode(dir.ino, is_dir)?;
+        let mut inode = self.new_inode(ino, mode);
+        inode.set_links(if is_dir { 2 } else { 1 });
+        let res = init(self, &mut inode)
+            .and_then(|_| self.write_inode(&mut inode))
+            .and_then(|_| self.dir_add(dir, name, ino, file_type_of(mode)));
+        if let Err(e) = res {
+            self.delete_inode(&mut inode)?;
+            return Err(e);
+        }
+        dir.set_modified(axfs_vfs::current_time());
+        self.write_inode(dir)
+    }
+
+    /// Adds a hard link `name` in the directory `dir_ino` to the inode `ino`.
+    pub fn link(&mut self, dir_ino: u32, name: &str, ino: u32) -> VfsResult {
+        self.check_writable()?;
+        let mut dir = self.read_dir_inode(dir_ino)?;
+        if self.dir_lookup(&dir, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+        if name.len() > NAME_LEN {
+            return Err(VfsError::InvalidInput);
+        }
+        let mut inode = self.read_inode(ino)?;
+        if inode.links() == 0 {
+            return Err(VfsError::NotFound); // already deleted
+        } else if inode.is_dir() {
+            return Err(VfsError::PermissionDenied);
+        } else if inode.links() >= LINK_MAX {
+            return Err(VfsError::StorageFull);
+        }
+        let now = axfs_vfs::current_time();
+        self.dir_add(&mut dir, name, ino, file_type_of(inode.mode()))?;
+        dir.set_modified(now);
+        self.write_inode(&mut dir)?;
+        inode.set_links(inode.links() + 1);
+        inode.set_ctime(now);
+        self.write_inode(&mut inode)
+    }
+
+    /// Removes the node `name` in the directory `dir_ino`. A directory must
+    /// be empty.
+    pub fn remove_node(&mut self, dir_ino: u32, name: &str) -> VfsResult {
+        self.check_writable()?;
+        if name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        let mut dir = self.read_dir_inode(dir_ino)?;
+        let (ino, _) = self.dir_lookup(&dir, name)?.ok_or(VfsError::NotFound)?;
+        let mut inode = self.read_inode(ino)?;
+        let is_dir = inode.is_dir();
+        if is_dir && !self.dir_is_empty(&inode)? {
+            return Err(VfsError::DirectoryNotEmpty);
+        }
+        self.dir_remove(&dir, name)?;
+        let now = axfs_vfs::current_time();
+        if is_dir {
+            Self::dec_dir_links(&mut dir);
+            inode.set_links(0);
+        } else {
+            inode.set_links(inode.links().saturating_sub(1));
+        }
+        dir.set_modified(now);
+        self.write_inode(&mut dir)?;
+        if inode.links() > 0 {
+            inode.set_ctime(now);
+            return self.write_inode(&mut inode);
+        }
+        self.delete_inode(&mut inode)
+    }
+
+    /// Frees the inode and its data.
+    fn delete_inode(&mut self, inode: &mut Inode) -> VfsResult {
+        let is_dir = inode.is_dir();
+        self.free_data(inode)?;
+        inode.set_links(0);
+        // A deleted inode must have either a deletion time or a zero mode,
+        // and the clock may still read zero.
+        inode.set_dtime(axfs_vfs::current_time());
+        inode.set_mode(0);
+        self.write_inode(inode)?;
+        self.free_inode(inode.ino, is_dir)
+    }
+
+    /// Whether the directory `ino` is `ancestor` or in its subtree.
+    fn is_in_subtree(&mut self, mut ino: u32, ancestor: u32) -> VfsResult<bool> {
+        loop {
+            if ino == ancestor {
+                return Ok(true);
+            }
+            if ino == ROOT_INO {
+                return Ok(false);
+            }
+            let (parent, _) = self.lookup(ino, "..")?.ok_or(VfsError::InvalidData)?;
+            if parent == ino {
+                return Ok(false);
+            }
+            ino = parent;
+        }
+    }
+
+    /// Moves the node `src_name` in the directory `src_dir` to `dst_name` in
+    /// `dst_dir`, an existing destination is replaced.
+    pub fn rename(
+        &mut self,
+        src_dir: u32,
+        src_name: &str,
+        dst_dir: u32,
+        dst_name: &str,
+    ) -> VfsResult {
+        self.check_writable()?;
+        for name in [src_name, dst_name] {
+            if name.is_empty() || name == "." || name == ".." {
+                return Err(VfsError::InvalidInput);
+            }
+        }
+        let (ino, _) = self.lookup(src_dir, src_name)?.ok_or(VfsError::NotFound)?;
+        if src_dir == dst_dir && src_name == dst_name {
+            return Ok(());
+        }
+        let mut inode = self.read_inode(ino)?;
+        let is_dir = inode.is_dir();
+        if is_dir && self.is_in_subtree(dst_dir, ino)? {
+            return Err(VfsError::InvalidInput);
+        }
+
+        if let Some((dst_ino, _)) = self.lookup(dst_dir, dst_name)? {
+            if dst_ino == ino {
+                return Ok(()); // hard links to the same inode
+            }
+            let dst = self.read_inode(dst_ino)?;
+            match (is_dir, dst.is_dir()) {
+                (true, false) => return Err(VfsError::NotADirectory),
+                (false, true) => return Err(VfsError::IsADirectory),
+                _ => self.remove_node(dst_dir, dst_name)?,
+            }
+        }
+
+        let now = axfs_vfs::current_time();
+        let ft = file_type_of(inode.mode());
+        let mut dst = self.read_dir_inode(dst_dir)?;
+        if is_dir && src_dir != dst_dir {
+            self.inc_dir_links(&mut dst)?;
+        }
+        self.dir_add(&mut dst, dst_name, ino, ft)?;
+        dst.set_modified(now);
+        self.write_inode(&mut dst)?;
+        let mut src = self.read_dir_inode(src_dir)?;
+        self.dir_remove(&src, src_name)?;
+        if is_dir && src_dir != dst_dir {
+            self.dir_set_entry(&inode, "..", dst_dir, FT_DIR)?;
+            Self::dec_dir_links(&mut src);
+        }
+        src.set_modified(now);
+        self.write_inode(&mut src)?;
+        inode.set_ctime(now);
+        self.write_inode(&mut inode)
+    }
+}
@@ -0,0 +1,313 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+
+use crate::*;
+
+const IMG_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../modules/axfs/resources/ext4.img"
+);
+
+/// A disk image in memory, shared by the filesystems opened on it.
+#[derive(Clone)]
+struct MemDisk(Arc<Mutex<Vec<u8>>>);
+
+impl BlockDevice for MemDisk {
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> VfsResult {
+        let data = self.0.lock().unwrap();
+        let src = data
+            .get(pos as usize..pos as usize + buf.len())
+            .ok_or(VfsError::UnexpectedEof)?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+
+    fn write_at(&mut self, pos: u64, buf: &[u8]) -> VfsResult {
+        let mut data = self.0.lock().unwrap();
+        let dst = data
+            .get_mut(pos as usize..pos as usize + buf.len())
+            .ok_or(VfsError::UnexpectedEof)?;
+        dst.copy_from_slice(buf);
+        Ok(())
+    }
+}
+
+fn load_disk() -> MemDisk {
+    let data = std::fs::read(IMG_PATH).expect("failed to load disk image");
+    MemDisk(Arc::new(Mutex::new(data)))
+}
+
+fn free_counts(fs: &Ext4FileSystem) -> (u64, u32) {
+    let ext4 = fs.vol.ext4.lock();
+    (ext4.sb.free_blocks(), ext4.sb.free_inodes())
+}
+
+fn ino_of(node: &VfsNodeRef) -> u32 {
+    node.as_any().downcast_ref::<DirNode>().unwrap().ino()
+}
+
+fn read_all(node: &VfsNodeRef) -> VfsResult<Vec<u8>> {
+    let mut buf = vec![0; node.get_attr()?.size() as usize];
+    assert_eq!(node.read_at(0, &mut buf)?, buf.len());
+    Ok(buf)
+}
+
+fn test_read(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+    assert!(root.get_attr()?.is_dir());
+
+    let long = root.clone().lookup("long.txt")?;
+    assert_eq!(long.get_attr()?.file_type(), VfsNodeType::File);
+    assert_eq!(read_all(&long)?, "Rust is cool!\n".repeat(1000).as_bytes());
+
+    let node = root
+        .clone()
+        .lookup("///very/long//.././long/./path/test.txt")?;
+    assert_eq!(read_all(&node)?, b"Rust is cool!\n");
+    assert_eq!(
+        root.clone().lookup("short.txt/").err(),
+        Some(VfsError::NotADirectory)
+    );
+    assert_eq!(
+        root.clone().lookup("not-exist").err(),
+        Some(VfsError::NotFound)
+    );
+
+    let mut dirents: [VfsDirEntry; 8] = core::array::from_fn(|_| VfsDirEntry::default());
+    let n = root.read_dir(0, &mut dirents)?;
+    let names = dirents[..n]
+        .iter()
+        .map(|e| String::from_utf8(e.name_as_bytes().to_vec()).unwrap())
+        .collect::<Vec<_>>();
+    for name in [
+        ".",
+        "..",
+        "long.txt",
+        "short.txt",
+        "very",
+        "very-long-dir-name",
+    ] {
+        assert!(names.contains(&name.into()));
+    }
+    Ok(())
+}
+
+fn test_write(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+    root.create("foo", VfsNodeType::Dir)?;
+    root.create("foo/f1", VfsNodeType::File)?;
+    root.create("/foo/f2", VfsNodeType::File)?;
+    assert_eq!(
+        root.create("not-exist/f1", VfsNodeType::File).err(),
+        Some(VfsError::NotFound)
+    );
+
+    // Interleaved writes fragment the files, which needs a deeper extent
+    // tree.
+    let f1 = root.clone().lookup("foo/f1")?;
+    let f2 = root.clone().lookup("foo/f2")?;
+    let block = [0x5a; 1024];
+    for i in 0..64 {
+        f1.write_at(i * 1024, &block)?;
+        f2.write_at(i * 1024, &block)?;
+    }
+    assert_eq!(f1.get_attr()?.size(), 64 * 1024);
+    assert_eq!(read_all(&f1)?, [0x5a; 64 * 1024]);
+
+    // Holes read as zeros.
+    f2.write_at(200 * 1024 + 10, b"hole")?;
+    let mut buf = [1; 16];
+    assert_eq!(f2.read_at(100 * 1024, &mut buf)?, 16);
+    assert_eq!(buf, [0; 16]);
+    assert_eq!(f2.read_at(200 * 1024 + 8, &mut buf)?, 6);
+    assert_eq!(&buf[..6], b"\0\0hole");
+
+    f1.truncate(1000)?;
+    assert_eq!(read_all(&f1)?, [0x5a; 1000]);
+    f1.truncate(2000)?;
+    let data = read_all(&f1)?;
+    assert_eq!(data[..1000], [0x5a; 1000]);
+    assert_eq!(data[1000..], [0; 1000]);
+    f1.truncate(1000)?;
+    Ok(())
+}
+
+fn test_dir_ops(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+    // Many entries need more directory blocks.
+    root.create("foo/bar", VfsNodeType::Dir)?;
+    for i in 0..100 {
+        let path = format!("foo/bar/file-with-a-long-name-{}", i);
+        root.create(&path, VfsNodeType::File)?;
+    }
+    let bar = root.clone().lookup("foo/bar")?;
+    assert!(bar.get_attr()?.size() > 1024);
+    assert_eq!(
+        ino_of(&bar.parent().unwrap()),
+        ino_of(&root.clone().lookup("foo")?)
+    );
+
+    assert_eq!(root.remove("foo").err(), Some(VfsError::DirectoryNotEmpty));
+    assert_eq!(root.remove("foo/..").err(), Some(VfsError::InvalidInput));
+    for i in (0..100).step_by(2) {
+        root.remove(&format!("foo/bar/file-with-a-long-name-{}", i))?;
+    }
+    assert_eq!(
+        root.clone().lookup("foo/bar/file-with-a-long-name-0").err(),
+        Some(VfsError::NotFound)
+    );
+    assert!(root
+        .clone()
+        .lookup("foo/bar/file-with-a-long-name-1")
+        .is_ok());
+
+    root.rename("foo/f1", "foo/bar/f1")?;
+    root.rename("foo/f2", "/f2")?;
+    root.rename("foo/bar", "/bar")?;
+    assert_eq!(
+        root.rename("bar", "bar/baz").err(),
+        Some(VfsError::InvalidInput)
+    );
+    let bar = root.clone().lookup("bar")?;
+    assert_eq!(ino_of(&bar.clone().lookup("..")?), ino_of(&root));
+    assert_eq!(read_all(&bar.lookup("f1")?)?, [0x5a; 1000]);
+    assert!(root.clone().lookup("f2").is_ok());
+    Ok(())
+}
+
+fn read_link(node: &VfsNodeRef) -> VfsResult<Vec<u8>> {
+    let mut buf = vec![0; node.get_attr()?.size() as usize];
+    assert_eq!(node.readlink(&mut buf)?, buf.len());
+    Ok(buf)
+}
+
+fn test_links(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+    // Short targets are stored in the inode, and long ones in a block.
+    let long_target = "very-long-dir-name/".repeat(10);
+    root.symlink("bar/fast", "../very/long/path")?;
+    root.symlink("bar/slow", &long_target)?;
+    assert_eq!(
+        root.symlink("bar/fast", "f1").err(),
+        Some(VfsError::AlreadyExists)
+    );
+    let fast = root.clone().lookup("bar/fast")?;
+    assert_eq!(fast.get_attr()?.file_type(), VfsNodeType::SymLink);
+    assert_eq!(read_link(&fast)?, b"../very/long/path");
+    assert_eq!(
+        read_link(&root.clone().lookup("bar/slow")?)?,
+        long_target.as_bytes()
+    );
+    assert_eq!(
+        root.readlink(&mut [0; 8]).err(),
+        Some(VfsError::InvalidInput)
+    );
+
+    let long = root.clone().lookup("long.txt")?;
+    root.link("bar/hard", &long)?;
+    assert_eq!(
+        read_all(&root.clone().lookup("bar/hard")?)?,
+        read_all(&long)?
+    );
+    assert_eq!(
+        root.link("bar/dir", &root.clone().lookup("bar")?).err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(
+        root.link("bar/hard", &long).err(),
+        Some(VfsError::AlreadyExists)
+    );
+    Ok(())
+}
+
+/// A clock that ticks one second on each read, it starts after 2038 so the
+/// epoch bits of the timestamps are used.
+fn clock() -> Duration {
+    static SECS: AtomicU64 = AtomicU64::new(3_000_000_000);
+    Duration::from_secs(SECS.fetch_add(1, Ordering::Relaxed))
+}
+
+const ATIME: Duration = Duration::new(5_000_000_000, 123_456_789);
+
+fn test_times(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+    let f2 = root.clone().lookup("f2")?;
+    let attr = f2.get_attr()?;
+    assert_eq!(attr.nlink(), 1);
+    assert!(attr.mtime() >= Duration::from_secs(3_000_000_000));
+    f2.write_at(0, b"time")?;
+    let written = f2.get_attr()?;
+    assert!(written.mtime() > attr.mtime());
+    assert_eq!(written.ctime(), written.mtime());
+    assert_eq!(written.atime(), attr.atime());
+
+    // Adding and removing entries modify the directory.
+    assert_eq!(root.clone().lookup("long.txt")?.get_attr()?.nlink(), 2);
+    let bar = root.clone().lookup("bar")?;
+    let bar_mtime = bar.get_attr()?.mtime();
+    root.symlink("bar/tmp", "f1")?;
+    root.remove("bar/tmp")?;
+    assert!(bar.get_attr()?.mtime() > bar_mtime);
+
+    f2.set_times(Some(ATIME), None)?;
+    assert_eq!(f2.get_attr()?.atime(), ATIME);
+    assert_eq!(f2.get_attr()?.mtime(), written.mtime());
+    f2.set_perm(VfsNodePerm::from_bits_truncate(0o600))?;
+    let attr = f2.get_attr()?;
+    assert_eq!(attr.perm().mode(), 0o600);
+    assert_eq!(attr.file_type(), VfsNodeType::File);
+    assert!(attr.ctime() > written.ctime());
+    Ok(())
+}
+
+fn test_remove_all(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+    for name in ["fast", "slow", "hard"] {
+        root.remove(&format!("bar/{}", name))?;
+    }
+    assert!(root.clone().lookup("long.txt").is_ok());
+    for i in (1..100).step_by(2) {
+        root.remove(&format!("bar/file-with-a-long-name-{}", i))?;
+    }
+    root.remove("bar/f1")?;
+    root.remove("bar")?;
+    root.remove("f2")?;
+    root.remove("foo")?;
+    Ok(())
+}
+
+#[test]
+fn test_ext4() {
+    axfs_vfs::set_clock(clock);
+    let disk = load_disk();
+    let fs = Ext4FileSystem::new(disk.clone()).unwrap();
+    let free = free_counts(&fs);
+    test_read(&fs).unwrap();
+    test_write(&fs).unwrap();
+    test_dir_ops(&fs).unwrap();
+    test_links(&fs).unwrap();
+    test_times(&fs).unwrap();
+    fs.umount().unwrap();
+
+    // Changes are persistent.
+    let fs = Ext4FileSystem::new(disk).unwrap();
+    test_read(&fs).unwrap();
+    let root = fs.root_dir();
+    assert_eq!(
+        read_all(&root.clone().lookup("bar/f1").unwrap()).unwrap(),
+        [0x5a; 1000]
+    );
+    assert_eq!(
+        read_link(&root.clone().lookup("bar/fast").unwrap()).unwrap(),
+        b"../very/long/path"
+    );
+    let f2 = root.lookup("f2").unwrap().get_attr().unwrap();
+    assert_eq!((f2.atime(), f2.perm().mode()), (ATIME, 0o600));
+
+    // All blocks and inodes are freed.
+    test_remove_all(&fs).unwrap();
+    assert_eq!(free_counts(&fs), free);
+}
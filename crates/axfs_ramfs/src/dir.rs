@@ -3,10 +3,13 @@ use alloc::sync::{Arc, Weak};
 use alloc::{string::String, vec::Vec};
 
 use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
-use axfs_vfs::{VfsError, VfsResult};
+use axfs_vfs::{VfsError, VfsNodePerm, VfsResult};
+use core::time::Duration;
 use spin::RwLock;
 
 use crate::file::FileNode;
+use crate::meta::Metadata;
+use crate::symlink::SymlinkNode;
 
 /// The directory node in the RAM filesystem.
 ///
@@ -15,6 +18,7 @@ pub struct DirNode {
     this: Weak<DirNode>,
     parent: RwLock<Weak<dyn VfsNodeOps>>,
     children: RwLock<BTreeMap<String, VfsNodeRef>>,
+    meta: RwLock<Metadata>,
 }
 
 impl DirNode {
@@ -23,6 +27,7 @@ impl DirNode {
             this: this.clone(),
             parent: RwLock::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
             children: RwLock::new(BTreeMap::new()),
+            meta: RwLock::new(Metadata::new(VfsNodePerm::default_dir())),
         })
     }
 
@@ -52,9 +57,51 @@ impl DirNode {
             _ => return Err(VfsError::Unsupported),
         };
         self.children.write().insert(name.into(), node);
+        self.meta.write().modify();
         Ok(())
     }
 
+    /// Creates a symbolic link with the given name in this directory, which
+    /// points to `target`.
+    pub fn create_symlink(&self, name: &str, target: &str) -> VfsResult {
+        if self.exist(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        let node = Arc::new(SymlinkNode::new(target));
+        self.children.write().insert(name.into(), node);
+        self.meta.write().modify();
+        Ok(())
+    }
+
+    /// Adds a hard link with the given name in this directory to `node`.
+    pub fn link_node(&self, name: &str, node: &VfsNodeRef) -> VfsResult {
+        if self.exist(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        if node.as_any().is::<DirNode>() {
+            return Err(VfsError::PermissionDenied);
+        }
+        let meta = link_meta(node).ok_or(VfsError::CrossesDevices)?;
+        self.children.write().insert(name.into(), node.clone());
+        meta.write().add_link();
+        self.meta.write().modify();
+        Ok(())
+    }
+
+    /// Returns the subdirectory to look up the rest of a path in.
+    fn subdir(&self, name: &str) -> VfsResult<VfsNodeRef> {
+        match name {
+            "" | "." => Ok(self.this.upgrade().ok_or(VfsError::NotFound)?),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => self
+                .children
+                .read()
+                .get(name)
+                .cloned()
+                .ok_or(VfsError::NotFound),
+        }
+    }
+
     /// Removes a node by the given name in this directory.
     pub fn remove_node(&self, name: &str) -> VfsResult {
         let mut children = self.children.write();
@@ -63,15 +110,42 @@ impl DirNode {
             if !dir.children.read().is_empty() {
                 return Err(VfsError::DirectoryNotEmpty);
             }
+        } else if let Some(meta) = link_meta(node) {
+            meta.write().remove_link();
         }
         children.remove(name);
+        self.meta.write().modify();
         Ok(())
     }
 }
 
+/// Returns the metadata of a non-directory node in the RAM filesystem.
+fn link_meta(node: &VfsNodeRef) -> Option<&RwLock<Metadata>> {
+    let any = node.as_any();
+    if let Some(file) = any.downcast_ref::<FileNode>() {
+        Some(&file.meta)
+    } else {
+        any.downcast_ref::<SymlinkNode>().map(|link| &link.meta)
+    }
+}
+
 impl VfsNodeOps for DirNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_dir(4096, 0))
+        let children = self.children.read();
+        let subdirs = children.values().filter(|n| n.as_any().is::<DirNode>());
+        let mut attr = self.meta.read().attr(VfsNodeType::Dir, 4096);
+        attr.set_nlink(2 + subdirs.count() as u64);
+        Ok(attr)
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.meta.write().set_perm(perm);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.meta.write().set_times(atime, mtime);
+        Ok(())
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
@@ -99,6 +173,7 @@ impl VfsNodeOps for DirNode {
     }
 
     fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        self.meta.write().access();
         let children = self.children.read();
         let mut children = children.iter().skip(start_idx.max(2) - 2);
         for (i, ent) in dirents.iter_mut().enumerate() {
@@ -121,19 +196,7 @@ impl VfsNodeOps for DirNode {
         log::debug!("create {:?} at ramfs: {}", ty, path);
         let (name, rest) = split_path(path);
         if let Some(rest) = rest {
-            match name {
-                "" | "." => self.create(rest, ty),
-                ".." => self.parent().ok_or(VfsError::NotFound)?.create(rest, ty),
-                _ => {
-                    let subdir = self
-                        .children
-                        .read()
-                        .get(name)
-                        .ok_or(VfsError::NotFound)?
-                        .clone();
-                    subdir.create(rest, ty)
-                }
-            }
+            self.subdir(name)?.create(rest, ty)
         } else if name.is_empty() || name == "." || name == ".." {
             Ok(()) // already exists
         } else {
@@ -145,19 +208,7 @@ impl VfsNodeOps for DirNode {
         log::debug!("remove at ramfs: {}", path);
         let (name, rest) = split_path(path);
         if let Some(rest) = rest {
-            match name {
-                "" | "." => self.remove(rest),
-                ".." => self.parent().ok_or(VfsError::NotFound)?.remove(rest),
-                _ => {
-                    let subdir = self
-                        .children
-                        .read()
-                        .get(name)
-                        .ok_or(VfsError::NotFound)?
-                        .clone();
-                    subdir.remove(rest)
-                }
-            }
+            self.subdir(name)?.remove(rest)
         } else if name.is_empty() || name == "." || name == ".." {
             Err(VfsError::InvalidInput) // remove '.' or '..
         } else {
@@ -165,6 +216,30 @@ impl VfsNodeOps for DirNode {
         }
     }
 
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        log::debug!("symlink at ramfs: {} -> {}", path, target);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.subdir(name)?.symlink(rest, target)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.create_symlink(name, target)
+        }
+    }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        log::debug!("link at ramfs: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.subdir(name)?.link(rest, node)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.link_node(name, node)
+        }
+    }
+
     axfs_vfs::impl_vfs_dir_default! {}
 }
 
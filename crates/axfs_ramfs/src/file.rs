@@ -1,25 +1,42 @@
 use alloc::vec::Vec;
 use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use axfs_vfs::{VfsNodePerm, VfsNodeType};
+use core::time::Duration;
 use spin::RwLock;
 
+use crate::meta::Metadata;
+
 /// The file node in the RAM filesystem.
 ///
 /// It implements [`axfs_vfs::VfsNodeOps`].
 pub struct FileNode {
     content: RwLock<Vec<u8>>,
+    pub(crate) meta: RwLock<Metadata>,
 }
 
 impl FileNode {
-    pub(super) const fn new() -> Self {
+    pub(super) fn new() -> Self {
         Self {
             content: RwLock::new(Vec::new()),
+            meta: RwLock::new(Metadata::new(VfsNodePerm::default_file())),
         }
     }
 }
 
 impl VfsNodeOps for FileNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_file(self.content.read().len() as _, 0))
+        let size = self.content.read().len() as u64;
+        Ok(self.meta.read().attr(VfsNodeType::File, size))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.meta.write().set_perm(perm);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.meta.write().set_times(atime, mtime);
+        Ok(())
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
@@ -29,6 +46,7 @@ impl VfsNodeOps for FileNode {
         } else {
             content.resize(size as _, 0);
         }
+        self.meta.write().modify();
         Ok(())
     }
 
@@ -38,6 +56,7 @@ impl VfsNodeOps for FileNode {
         let end = content.len().min(offset as usize + buf.len());
         let src = &content[start..end];
         buf[..src.len()].copy_from_slice(src);
+        self.meta.write().access();
         Ok(src.len())
     }
 
@@ -49,6 +68,7 @@ impl VfsNodeOps for FileNode {
         }
         let dst = &mut content[offset..offset + buf.len()];
         dst.copy_from_slice(&buf[..dst.len()]);
+        self.meta.write().modify();
         Ok(buf.len())
     }
 
@@ -8,12 +8,15 @@ extern crate alloc;
 
 mod dir;
 mod file;
+mod meta;
+mod symlink;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::dir::DirNode;
 pub use self::file::FileNode;
+pub use self::symlink::SymlinkNode;
 
 use alloc::sync::Arc;
 use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
@@ -0,0 +1,68 @@
+use axfs_vfs::{VfsNodeAttr, VfsNodePerm, VfsNodeType};
+use core::time::Duration;
+
+/// The permission, link count and timestamps of a node.
+pub(crate) struct Metadata {
+    perm: VfsNodePerm,
+    nlink: u64,
+    atime: Duration,
+    mtime: Duration,
+    ctime: Duration,
+}
+
+impl Metadata {
+    pub fn new(perm: VfsNodePerm) -> Self {
+        let now = axfs_vfs::current_time();
+        Self {
+            perm,
+            nlink: 1,
+            atime: now,
+            mtime: now,
+            ctime: now,
+        }
+    }
+
+    /// Updates the access time.
+    pub fn access(&mut self) {
+        self.atime = axfs_vfs::current_time();
+    }
+
+    /// Updates the modification and change times after the data is changed.
+    pub fn modify(&mut self) {
+        self.mtime = axfs_vfs::current_time();
+        self.ctime = self.mtime;
+    }
+
+    /// Updates the change time after the attributes are changed.
+    pub fn change(&mut self) {
+        self.ctime = axfs_vfs::current_time();
+    }
+
+    pub fn set_perm(&mut self, perm: VfsNodePerm) {
+        self.perm = perm;
+        self.change();
+    }
+
+    pub fn set_times(&mut self, atime: Option<Duration>, mtime: Option<Duration>) {
+        self.atime = atime.unwrap_or(self.atime);
+        self.mtime = mtime.unwrap_or(self.mtime);
+        self.change();
+    }
+
+    pub fn add_link(&mut self) {
+        self.nlink += 1;
+        self.change();
+    }
+
+    pub fn remove_link(&mut self) {
+        self.nlink = self.nlink.saturating_sub(1);
+        self.change();
+    }
+
+    pub fn attr(&self, ty: VfsNodeType, size: u64) -> VfsNodeAttr {
+        let mut attr = VfsNodeAttr::new(self.perm, ty, size, 0);
+        attr.set_nlink(self.nlink);
+        attr.set_times(self.atime, self.mtime, self.ctime);
+        attr
+    }
+}
@@ -0,0 +1,45 @@
+use alloc::string::String;
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use axfs_vfs::{VfsNodePerm, VfsNodeType};
+use core::time::Duration;
+use spin::RwLock;
+
+use crate::meta::Metadata;
+
+/// The symbolic link node in the RAM filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct SymlinkNode {
+    target: String,
+    pub(crate) meta: RwLock<Metadata>,
+}
+
+impl SymlinkNode {
+    pub(super) fn new(target: &str) -> Self {
+        Self {
+            target: target.into(),
+            meta: RwLock::new(Metadata::new(VfsNodePerm::from_bits_truncate(0o777))),
+        }
+    }
+}
+
+impl VfsNodeOps for SymlinkNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.target.len() as u64;
+        Ok(self.meta.read().attr(VfsNodeType::SymLink, size))
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.meta.write().set_times(atime, mtime);
+        Ok(())
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let len = self.target.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.target.as_bytes()[..len]);
+        self.meta.write().access();
+        Ok(len)
+    }
+
+    impl_vfs_non_dir_default! {}
+}
@@ -1,6 +1,8 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+use axfs_vfs::{VfsError, VfsNodePerm, VfsNodeType, VfsResult};
 
 use crate::*;
 
@@ -88,6 +90,95 @@ fn test_get_parent(devfs: &RamFileSystem) -> VfsResult {
     Ok(())
 }
 
+fn test_links(devfs: &RamFileSystem) -> VfsResult {
+    let root = devfs.root_dir();
+    root.symlink("foo/l1", "../f2")?;
+    root.symlink("/l2", "a-long-path/that-does-not-exist")?;
+    assert_eq!(
+        root.symlink("foo/bar", "f1").err(),
+        Some(VfsError::AlreadyExists)
+    );
+
+    let l1 = root.clone().lookup("foo/l1")?;
+    assert_eq!(l1.get_attr()?.file_type(), VfsNodeType::SymLink);
+    assert_eq!(l1.get_attr()?.size(), 5);
+    let mut buf = [0; 32];
+    assert_eq!(l1.readlink(&mut buf)?, 5);
+    assert_eq!(&buf[..5], b"../f2");
+    assert_eq!(l1.readlink(&mut buf[..2])?, 2);
+    assert_eq!(&buf[..2], b"..");
+    assert_eq!(l1.lookup("x").err(), Some(VfsError::NotADirectory));
+    assert_eq!(root.readlink(&mut buf).err(), Some(VfsError::InvalidInput));
+
+    // Hard links share the same node.
+    let f2 = root.clone().lookup("f2")?;
+    root.link("foo/bar/f5", &f2)?;
+    f2.write_at(0, b"hello")?;
+    let f5 = root.clone().lookup("foo/bar/f5")?;
+    assert!(Arc::ptr_eq(&f2, &f5));
+    assert_eq!(
+        root.link("f5", &root.clone().lookup("foo")?).err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(root.link("f2", &f5).err(), Some(VfsError::AlreadyExists));
+    root.remove("f2")?;
+    assert_eq!(f5.read_at(0, &mut buf)?, 5);
+    assert_eq!(&buf[..5], b"hello");
+    Ok(())
+}
+
+/// A clock that ticks one second on each read.
+fn clock() -> Duration {
+    static SECS: AtomicU64 = AtomicU64::new(1000);
+    Duration::from_secs(SECS.fetch_add(1, Ordering::Relaxed))
+}
+
+fn test_times(devfs: &RamFileSystem) -> VfsResult {
+    let root = devfs.root_dir();
+    let f5 = root.clone().lookup("foo/bar/f5")?;
+    let attr = f5.get_attr()?;
+    assert_eq!(attr.nlink(), 1);
+    assert!(attr.mtime() >= Duration::from_secs(1000));
+    assert!(attr.ctime() >= attr.mtime());
+
+    f5.write_at(5, b" world")?;
+    let written = f5.get_attr()?;
+    assert!(written.mtime() > attr.ctime());
+    assert_eq!(written.ctime(), written.mtime());
+    assert_eq!(written.atime(), attr.atime());
+    let mut buf = [0; 16];
+    f5.read_at(0, &mut buf)?;
+    assert!(f5.get_attr()?.atime() > written.mtime());
+
+    // Linking and unlinking change the node and its directory.
+    let bar = root.clone().lookup("foo/bar")?;
+    let bar_mtime = bar.get_attr()?.mtime();
+    bar.link("f6", &f5)?;
+    assert_eq!(f5.get_attr()?.nlink(), 2);
+    assert!(f5.get_attr()?.ctime() > written.ctime());
+    assert!(bar.get_attr()?.mtime() > bar_mtime);
+    bar.remove("f6")?;
+    assert_eq!(f5.get_attr()?.nlink(), 1);
+    assert_eq!(root.clone().lookup("foo")?.get_attr()?.nlink(), 3);
+    assert_eq!(bar.get_attr()?.nlink(), 2);
+
+    let before = f5.get_attr()?;
+    f5.set_times(Some(Duration::from_secs(1)), None)?;
+    let attr = f5.get_attr()?;
+    assert_eq!(attr.atime(), Duration::from_secs(1));
+    assert_eq!(attr.mtime(), before.mtime());
+    assert!(attr.ctime() > before.ctime());
+    f5.set_times(None, Some(Duration::new(2, 500)))?;
+    assert_eq!(f5.get_attr()?.atime(), Duration::from_secs(1));
+    assert_eq!(f5.get_attr()?.mtime(), Duration::new(2, 500));
+
+    f5.set_perm(VfsNodePerm::from_bits_truncate(0o400))?;
+    assert_eq!(f5.get_attr()?.perm().mode(), 0o400);
+    bar.set_perm(VfsNodePerm::from_bits_truncate(0o700))?;
+    assert_eq!(bar.get_attr()?.perm().mode(), 0o700);
+    Ok(())
+}
+
 #[test]
 fn test_ramfs() {
     // .
@@ -98,6 +189,7 @@ fn test_ramfs() {
     // ├── f1
     // └── f2
 
+    axfs_vfs::set_clock(clock);
     let ramfs = RamFileSystem::new();
     let root = ramfs.root_dir();
     root.create("f1", VfsNodeType::File).unwrap();
@@ -117,10 +209,14 @@ fn test_ramfs() {
 
     test_ramfs_ops(&ramfs).unwrap();
     test_get_parent(&ramfs).unwrap();
+    test_links(&ramfs).unwrap();
+    test_times(&ramfs).unwrap();
 
     let root = ramfs.root_dir();
     assert_eq!(root.remove("f1"), Ok(()));
-    assert_eq!(root.remove("//f2"), Ok(()));
+    assert_eq!(root.remove("l2"), Ok(()));
+    assert_eq!(root.remove("foo/l1"), Ok(()));
+    assert_eq!(root.remove("foo/bar/f5"), Ok(()));
     assert_eq!(root.remove("f3").err(), Some(VfsError::NotFound));
     assert_eq!(root.remove("foo").err(), Some(VfsError::DirectoryNotEmpty));
     assert_eq!(root.remove("foo/..").err(), Some(VfsError::InvalidInput));
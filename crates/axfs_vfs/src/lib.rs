@@ -1,10 +1,9 @@
 //! Virtual filesystem interfaces used by [ArceOS](https://github.com/rcore-os/arceos).
 //!
-//! A filesystem is a set of files and directories (symbol links are not
-//! supported currently), collectively referred to as **nodes**, which are
-//! conceptually similar to [inodes] in Linux. A file system needs to implement
-//! the [`VfsOps`] trait, its files and directories need to implement the
-//! [`VfsNodeOps`] trait.
+//! A filesystem is a set of files, directories and symbolic links,
+//! collectively referred to as **nodes**, which are conceptually similar to
+//! [inodes] in Linux. A file system needs to implement the [`VfsOps`] trait,
+//! its nodes need to implement the [`VfsNodeOps`] trait.
 //!
 //! The [`VfsOps`] trait provides the following operations on a filesystem:
 //!
@@ -14,14 +13,16 @@
 //! - [`statfs()`](VfsOps::statfs): Get the attributes of the filesystem.
 //! - [`root_dir()`](VfsOps::root_dir): Get root directory of the filesystem.
 //!
-//! The [`VfsNodeOps`] trait provides the following operations on a file or a
-//! directory:
+//! The [`VfsNodeOps`] trait provides the following operations on a file, a
+//! directory or a symbolic link:
 //!
-//! | Operation | Description | file/directory |
+//! | Operation | Description | file/directory/symlink |
 //! | --- | --- | --- |
-//! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | both |
-//! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | both |
-//! | [`get_attr()`](VfsNodeOps::get_attr) | Get the attributes of the node | both |
+//! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | all |
+//! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | all |
+//! | [`get_attr()`](VfsNodeOps::get_attr) | Get the attributes of the node | all |
+//! | [`set_perm()`](VfsNodeOps::set_perm) | Set the permission of the node | all |
+//! | [`set_times()`](VfsNodeOps::set_times) | Set the access and modification times | all |
 //! | [`read_at()`](VfsNodeOps::read_at) | Read data from the file | file |
 //! | [`write_at()`](VfsNodeOps::write_at) | Write data to the file | file |
 //! | [`fsync()`](VfsNodeOps::fsync) | Synchronize the file data to disk | file |
@@ -31,6 +32,13 @@
 //! | [`create()`](VfsNodeOps::create) | Create a new node with the given path | directory |
 //! | [`remove()`](VfsNodeOps::remove) | Remove the node with the given path | directory |
 //! | [`read_dir()`](VfsNodeOps::read_dir) | Read directory entries | directory |
+//! | [`rename()`](VfsNodeOps::rename) | Rename or move a node | directory |
+//! | [`symlink()`](VfsNodeOps::symlink) | Create a symbolic link with the given path | directory |
+//! | [`link()`](VfsNodeOps::link) | Create a hard link with the given path | directory |
+//! | [`readlink()`](VfsNodeOps::readlink) | Read the target of the symbolic link | symlink |
+//!
+//! Filesystems timestamp their nodes with [`current_time()`], which reads
+//! the clock registered by [`set_clock()`].
 //!
 //! [inodes]: https://en.wikipedia.org/wiki/Inode
 
@@ -45,6 +53,8 @@ pub mod path;
 
 use alloc::sync::Arc;
 use axerrno::{ax_err, AxError, AxResult};
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::time::Duration;
 
 pub use self::structs::{FileSystemInfo, VfsDirEntry, VfsNodeAttr, VfsNodePerm, VfsNodeType};
 
@@ -57,6 +67,25 @@ pub type VfsError = AxError;
 /// Alias of [`AxResult`].
 pub type VfsResult<T = ()> = AxResult<T>;
 
+static CLOCK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Sets the clock used to timestamp the nodes of all filesystems.
+pub fn set_clock(clock: fn() -> Duration) {
+    CLOCK.store(clock as *mut (), Ordering::Release);
+}
+
+/// Returns the current time read from the clock set by [`set_clock()`], or
+/// zero if no clock is set.
+pub fn current_time() -> Duration {
+    let clock = CLOCK.load(Ordering::Acquire);
+    if clock.is_null() {
+        return Duration::ZERO;
+    }
+    // SAFETY: only function pointers are stored in `CLOCK`.
+    let clock = unsafe { core::mem::transmute::<*mut (), fn() -> Duration>(clock) };
+    clock()
+}
+
 /// Filesystem operations.
 pub trait VfsOps: Send + Sync {
     /// Do something when the filesystem is mounted.
@@ -100,6 +129,19 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Set the permission of the node.
+    fn set_perm(&self, _perm: VfsNodePerm) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Set the access and modification times of the node, a time of `None`
+    /// is left unchanged.
+    ///
+    /// The change time is set to the current time.
+    fn set_times(&self, _atime: Option<Duration>, _mtime: Option<Duration>) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
     // file operations:
 
     /// Read data from the file at the given offset.
@@ -160,6 +202,29 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Create a symbolic link with the given `path` in the directory, which
+    /// points to `target`.
+    fn symlink(&self, _path: &str, _target: &str) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Create a hard link with the given `path` in the directory to `node`.
+    ///
+    /// The `node` must be a non-directory node in the same filesystem.
+    fn link(&self, _path: &str, _node: &VfsNodeRef) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    // symbolic link operations:
+
+    /// Read the target of the symbolic link into `buf`.
+    ///
+    /// Return the number of bytes read, the target is truncated if `buf` is
+    /// too small.
+    fn readlink(&self, _buf: &mut [u8]) -> VfsResult<usize> {
+        ax_err!(InvalidInput)
+    }
+
     /// Convert `&self` to [`&dyn Any`][1] that can use
     /// [`Any::downcast_ref`][2].
     ///
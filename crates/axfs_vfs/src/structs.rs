@@ -1,3 +1,5 @@
+use core::time::Duration;
+
 /// Filesystem attributes.
 ///
 /// Currently not used.
@@ -16,6 +18,18 @@ pub struct VfsNodeAttr {
     size: u64,
     /// Number of 512B blocks allocated.
     blocks: u64,
+    /// Number of hard links.
+    nlink: u64,
+    /// User ID of the owner.
+    uid: u32,
+    /// Group ID of the owner.
+    gid: u32,
+    /// Time of the last access.
+    atime: Duration,
+    /// Time of the last modification of the data.
+    mtime: Duration,
+    /// Time of the last change of the data or the attributes.
+    ctime: Duration,
 }
 
 bitflags::bitflags! {
@@ -199,34 +213,33 @@ impl VfsNodeType {
 impl VfsNodeAttr {
     /// Creates a new `VfsNodeAttr` with the given permission mode, type, size
     /// and number of blocks.
+    ///
+    /// The node has one link, is owned by root, and all its timestamps are
+    /// zero.
     pub const fn new(mode: VfsNodePerm, ty: VfsNodeType, size: u64, blocks: u64) -> Self {
         Self {
             mode,
             ty,
             size,
             blocks,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            atime: Duration::ZERO,
+            mtime: Duration::ZERO,
+            ctime: Duration::ZERO,
         }
     }
 
     /// Creates a new `VfsNodeAttr` for a file, with the default file permission.
     pub const fn new_file(size: u64, blocks: u64) -> Self {
-        Self {
-            mode: VfsNodePerm::default_file(),
-            ty: VfsNodeType::File,
-            size,
-            blocks,
-        }
+        Self::new(VfsNodePerm::default_file(), VfsNodeType::File, size, blocks)
     }
 
     /// Creates a new `VfsNodeAttr` for a directory, with the default directory
     /// permission.
     pub const fn new_dir(size: u64, blocks: u64) -> Self {
-        Self {
-            mode: VfsNodePerm::default_dir(),
-            ty: VfsNodeType::Dir,
-            size,
-            blocks,
-        }
+        Self::new(VfsNodePerm::default_dir(), VfsNodeType::Dir, size, blocks)
     }
 
     /// Returns the size of the node.
@@ -249,6 +262,54 @@ impl VfsNodeAttr {
         self.mode = perm
     }
 
+    /// Returns the number of hard links to the node.
+    pub const fn nlink(&self) -> u64 {
+        self.nlink
+    }
+
+    /// Sets the number of hard links to the node.
+    pub fn set_nlink(&mut self, nlink: u64) {
+        self.nlink = nlink
+    }
+
+    /// Returns the user ID of the owner.
+    pub const fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns the group ID of the owner.
+    pub const fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Sets the user ID and group ID of the owner.
+    pub fn set_owner(&mut self, uid: u32, gid: u32) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    /// Returns the time of the last access.
+    pub const fn atime(&self) -> Duration {
+        self.atime
+    }
+
+    /// Returns the time of the last modification of the data.
+    pub const fn mtime(&self) -> Duration {
+        self.mtime
+    }
+
+    /// Returns the time of the last change of the data or the attributes.
+    pub const fn ctime(&self) -> Duration {
+        self.ctime
+    }
+
+    /// Sets the access, modification and change times of the node.
+    pub fn set_times(&mut self, atime: Duration, mtime: Duration, ctime: Duration) {
+        self.atime = atime;
+        self.mtime = mtime;
+        self.ctime = ctime;
+    }
+
     /// Returns the type of the node.
     pub const fn file_type(&self) -> VfsNodeType {
         self.ty
@@ -263,6 +324,11 @@ impl VfsNodeAttr {
     pub const fn is_dir(&self) -> bool {
         self.ty.is_dir()
     }
+
+    /// Whether the node is a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.ty.is_symlink()
+    }
 }
 
 impl VfsDirEntry {
@@ -131,6 +131,38 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
         Ok(size)
     }
 
+    /// Splits the huge page containing `vaddr` into smaller pages, until
+    /// `vaddr` is mapped by a 4K page. The target and flags of the mapping are
+    /// not changed.
+    ///
+    /// Each huge page entry is replaced by a filled next-level table at once,
+    /// so the other addresses in the huge page stay mapped. Stale TLB entries
+    /// of the huge page are not flushed.
+    ///
+    /// Returns [`Err(PagingError::NotMapped)`](PagingError::NotMapped) if the
+    /// mapping is not present.
+    pub fn split_huge(&mut self, vaddr: VirtAddr) -> PagingResult {
+        loop {
+            let (entry, size) = self.get_entry_mut(vaddr)?;
+            if entry.is_unused() {
+                return Err(PagingError::NotMapped);
+            }
+            let sub_size = match size {
+                PageSize::Size4K => return Ok(()),
+                PageSize::Size2M => PageSize::Size4K,
+                PageSize::Size1G => PageSize::Size2M,
+            };
+            let (paddr, flags) = (entry.paddr(), entry.flags());
+            let table_paddr = Self::alloc_table()?;
+            let table = self.table_of_mut(table_paddr);
+            for (i, e) in table.iter_mut().enumerate() {
+                *e = GenericPTE::new_page(paddr + i * sub_size as usize, flags, sub_size.is_huge());
+            }
+            *entry = GenericPTE::new_table(table_paddr);
+            self.intrm_tables.push(table_paddr);
+        }
+    }
+
     /// Map a contiguous virtual memory region to a contiguous physical memory
     /// region with the given mapping `flags`.
     ///
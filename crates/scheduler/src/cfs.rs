@@ -173,6 +173,18 @@ impl<T> BaseScheduler for CFScheduler<T> {
             .insert((prev.clone().get_vruntime(), taskid), prev);
     }
 
+    fn steal_task<F>(&mut self, filter: F) -> Option<Self::SchedItem>
+    where
+        F: Fn(&Self::SchedItem) -> bool,
+    {
+        let task = self.ready_queue.values().find(|t| filter(t))?.clone();
+        self.remove_task(&task)
+    }
+
+    fn nr_ready(&self) -> usize {
+        self.ready_queue.len()
+    }
+
     fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
         current.task_tick();
         self.min_vruntime.is_none()
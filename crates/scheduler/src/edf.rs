@@ -0,0 +1,352 @@
+use alloc::{collections::BTreeMap, collections::VecDeque, sync::Arc, vec::Vec};
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::BaseScheduler;
+
+/// Fixed-point shift of the bandwidth (`runtime / period`) of deadline tasks.
+const BW_SHIFT: u32 = 20;
+/// The total bandwidth of a CPU, i.e., `1.0` in fixed-point.
+const BW_UNIT: u64 = 1 << BW_SHIFT;
+
+/// Parameters of a deadline task, all in timer ticks.
+///
+/// In every `period`, the task may run for at most `runtime`, and should
+/// finish its work within `deadline` since the period starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EDFParams {
+    /// Execution budget in each period.
+    pub runtime: u64,
+    /// Relative deadline.
+    pub deadline: u64,
+    /// Length of the period.
+    pub period: u64,
+}
+
+impl EDFParams {
+    /// Whether `0 < runtime <= deadline <= period` holds.
+    pub const fn is_valid(&self) -> bool {
+        0 < self.runtime && self.runtime <= self.deadline && self.deadline <= self.period
+    }
+
+    fn bandwidth(&self) -> u64 {
+        (self.runtime << BW_SHIFT).div_ceil(self.period)
+    }
+}
+
+/// A task wrapper for the [`EDFScheduler`].
+///
+/// It adds the deadline parameters and the state of the current job (i.e.,
+/// the work in the current period). A task without parameters is a
+/// best-effort task.
+pub struct EDFTask<T> {
+    inner: T,
+    /// `runtime`, `deadline` and `period` of [`EDFParams`], all zeros for a
+    /// best-effort task.
+    runtime: AtomicU64,
+    deadline: AtomicU64,
+    period: AtomicU64,
+    /// Start time of the current job.
+    release: AtomicU64,
+    /// Remaining budget of the current job.
+    budget: AtomicU64,
+    /// Sequence number to order tasks with the same deadline.
+    seq: AtomicU64,
+}
+
+impl<T> EDFTask<T> {
+    /// Creates a new best-effort [`EDFTask`] from the inner task struct.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            runtime: AtomicU64::new(0),
+            deadline: AtomicU64::new(0),
+            period: AtomicU64::new(0),
+            release: AtomicU64::new(0),
+            budget: AtomicU64::new(0),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the deadline parameters, or [`None`] for a best-effort task.
+    pub fn params(&self) -> Option<EDFParams> {
+        let runtime = self.runtime.load(Ordering::Acquire);
+        if runtime == 0 {
+            return None;
+        }
+        Some(EDFParams {
+            runtime,
+            deadline: self.deadline.load(Ordering::Acquire),
+            period: self.period.load(Ordering::Acquire),
+        })
+    }
+
+    /// Returns the absolute deadline of the current job, or [`None`] for a
+    /// best-effort task.
+    pub fn abs_deadline(&self) -> Option<u64> {
+        self.params()
+            .map(|p| self.release.load(Ordering::Acquire) + p.deadline)
+    }
+
+    /// Returns the remaining budget of the current job.
+    pub fn budget(&self) -> u64 {
+        self.budget.load(Ordering::Acquire)
+    }
+
+    fn is_deadline(&self) -> bool {
+        self.runtime.load(Ordering::Acquire) != 0
+    }
+
+    fn store_params(&self, params: Option<EDFParams>) {
+        let p = params.unwrap_or(EDFParams {
+            runtime: 0,
+            deadline: 0,
+            period: 0,
+        });
+        self.runtime.store(p.runtime, Ordering::Release);
+        self.deadline.store(p.deadline, Ordering::Release);
+        self.period.store(p.period, Ordering::Release);
+    }
+
+    /// Starts a new job at `now` with a full budget.
+    fn start_job(&self, now: u64) {
+        self.release.store(now, Ordering::Release);
+        self.budget
+            .store(self.runtime.load(Ordering::Acquire), Ordering::Release);
+    }
+
+    /// Returns the time when the budget is replenished.
+    fn next_release(&self) -> u64 {
+        self.release.load(Ordering::Acquire) + self.period.load(Ordering::Acquire)
+    }
+
+    /// Returns the key in the ready queue.
+    fn key(&self) -> (u64, u64) {
+        (
+            self.release.load(Ordering::Acquire) + self.deadline.load(Ordering::Acquire),
+            self.seq.load(Ordering::Acquire),
+        )
+    }
+
+    /// Returns a reference to the inner task struct.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> Deref for EDFTask<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// An [Earliest Deadline First][EDF] (EDF) preemptive scheduler for periodic
+/// real-time tasks.
+///
+/// Each deadline task has [`EDFParams`], and runs a job of at most `runtime`
+/// ticks in every `period`. The ready task with the earliest absolute
+/// deadline is always picked next, and preempts the current task at the
+/// timer tick. Best-effort tasks (those without parameters) run in FIFO
+/// order only when no deadline task is ready.
+///
+/// - **Admission control**: [`EDFScheduler::set_params`] refuses new
+///   parameters if the total bandwidth (`runtime / period`) of the deadline
+///   tasks would exceed the CPU, so that all deadlines can be met.
+/// - **Budget enforcement**: the budget of the current job is charged at
+///   each tick. A task that used up its budget is throttled until its next
+///   period, even if it's still runnable.
+///
+/// A task woken up after the deadline of its job starts a new job. Time is
+/// measured in ticks counted by [`task_tick`](BaseScheduler::task_tick) and
+/// [`EDFScheduler::idle_tick`].
+///
+/// The bandwidth and the clock are local to each scheduler (i.e., each CPU),
+/// so deadline tasks are never handed out by
+/// [`steal_task`](BaseScheduler::steal_task) and stay on the CPU that admitted
+/// them. Throttled tasks are not counted by
+/// [`nr_ready`](BaseScheduler::nr_ready).
+///
+/// [EDF]: https://en.wikipedia.org/wiki/Earliest_deadline_first_scheduling
+pub struct EDFScheduler<T> {
+    ready_queue: BTreeMap<(u64, u64), Arc<EDFTask<T>>>,
+    best_effort: VecDeque<Arc<EDFTask<T>>>,
+    throttled: Vec<Arc<EDFTask<T>>>,
+    total_bw: u64,
+    now: u64,
+    seq: u64,
+}
+
+impl<T> EDFScheduler<T> {
+    /// Creates a new empty [`EDFScheduler`].
+    pub const fn new() -> Self {
+        Self {
+            ready_queue: BTreeMap::new(),
+            best_effort: VecDeque::new(),
+            throttled: Vec::new(),
+            total_bw: 0,
+            now: 0,
+            seq: 0,
+        }
+    }
+    /// get the name of scheduler
+    pub fn scheduler_name() -> &'static str {
+        "Earliest Deadline First"
+    }
+
+    /// Returns the current time in ticks.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Advances the clock when no task is running, which may release
+    /// throttled tasks.
+    pub fn idle_tick(&mut self) {
+        self.now += 1;
+        self.replenish();
+    }
+
+    /// Sets the deadline parameters of `task`, or makes it a best-effort task
+    /// if `params` is [`None`]. The task starts a new job immediately.
+    ///
+    /// Returns `false` if `params` is invalid, or admitting it would exceed
+    /// the bandwidth of the CPU.
+    pub fn set_params(&mut self, task: &Arc<EDFTask<T>>, params: Option<EDFParams>) -> bool {
+        let old_bw = task.params().map_or(0, |p| p.bandwidth());
+        let new_bw = match params {
+            Some(p) if !p.is_valid() => return false,
+            Some(p) => p.bandwidth(),
+            None => 0,
+        };
+        let total_bw = self.total_bw.saturating_sub(old_bw) + new_bw;
+        if total_bw > BW_UNIT {
+            return false;
+        }
+        self.total_bw = total_bw;
+
+        let queued = self.remove_task(task);
+        task.store_params(params);
+        task.start_job(self.now);
+        if let Some(task) = queued {
+            self.add_task(task);
+        }
+        true
+    }
+
+    fn enqueue(&mut self, task: Arc<EDFTask<T>>) {
+        self.seq += 1;
+        task.seq.store(self.seq, Ordering::Release);
+        self.ready_queue.insert(task.key(), task);
+    }
+
+    /// Moves throttled tasks whose budgets are replenished to the ready
+    /// queue.
+    fn replenish(&mut self) {
+        let mut i = 0;
+        while i < self.throttled.len() {
+            if self.throttled[i].next_release() <= self.now {
+                let task = self.throttled.swap_remove(i);
+                task.start_job(self.now);
+                self.enqueue(task);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl<T> BaseScheduler for EDFScheduler<T> {
+    type SchedItem = Arc<EDFTask<T>>;
+
+    fn init(&mut self) {}
+
+    fn add_task(&mut self, task: Self::SchedItem) {
+        if !task.is_deadline() {
+            self.best_effort.push_back(task);
+        } else if task.budget() == 0 {
+            if task.next_release() <= self.now {
+                task.start_job(self.now);
+                self.enqueue(task);
+            } else {
+                self.throttled.push(task);
+            }
+        } else {
+            if task.abs_deadline().unwrap() <= self.now {
+                // Missed the deadline while sleeping, start a new job.
+                task.start_job(self.now);
+            }
+            self.enqueue(task);
+        }
+    }
+
+    fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        let key = task.key();
+        if self
+            .ready_queue
+            .get(&key)
+            .is_some_and(|t| Arc::ptr_eq(t, task))
+        {
+            return self.ready_queue.remove(&key);
+        }
+        if let Some(idx) = self.throttled.iter().position(|t| Arc::ptr_eq(t, task)) {
+            return Some(self.throttled.swap_remove(idx));
+        }
+        self.best_effort
+            .iter()
+            .position(|t| Arc::ptr_eq(t, task))
+            .and_then(|idx| self.best_effort.remove(idx))
+    }
+
+    fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
+        self.ready_queue
+            .pop_first()
+            .map(|(_, t)| t)
+            .or_else(|| self.best_effort.pop_front())
+    }
+
+    fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool) {
+        if prev.is_deadline() {
+            self.add_task(prev);
+        } else if preempt {
+            self.best_effort.push_front(prev);
+        } else {
+            self.best_effort.push_back(prev);
+        }
+    }
+
+    fn steal_task<F>(&mut self, filter: F) -> Option<Self::SchedItem>
+    where
+        F: Fn(&Self::SchedItem) -> bool,
+    {
+        // Deadline tasks are pinned, since they are admitted with the
+        // bandwidth of this CPU, and their jobs are timed by its clock.
+        let idx = self.best_effort.iter().position(filter)?;
+        self.best_effort.remove(idx)
+    }
+
+    fn nr_ready(&self) -> usize {
+        self.ready_queue.len() + self.best_effort.len()
+    }
+
+    fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
+        self.now += 1;
+        self.replenish();
+        match current.abs_deadline() {
+            Some(deadline) => {
+                let budget = current.budget().saturating_sub(1);
+                current.budget.store(budget, Ordering::Release);
+                budget == 0
+                    || self
+                        .ready_queue
+                        .first_key_value()
+                        .is_some_and(|(&(d, _), _)| d < deadline)
+            }
+            None => !self.ready_queue.is_empty(),
+        }
+    }
+
+    fn set_priority(&mut self, _task: &Self::SchedItem, _prio: isize) -> bool {
+        false
+    }
+}
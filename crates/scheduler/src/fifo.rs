@@ -56,6 +56,7 @@ impl<T> Deref for FifoTask<T> {
 /// It internally uses a linked list as the ready queue.
 pub struct FifoScheduler<T> {
     ready_queue: List<Arc<FifoTask<T>>>,
+    len: usize,
 }
 
 impl<T> FifoScheduler<T> {
@@ -63,6 +64,7 @@ impl<T> FifoScheduler<T> {
     pub const fn new() -> Self {
         Self {
             ready_queue: List::new(),
+            len: 0,
         }
     }
     /// get the name of scheduler
@@ -78,18 +80,47 @@ impl<T> BaseScheduler for FifoScheduler<T> {
 
     fn add_task(&mut self, task: Self::SchedItem) {
         self.ready_queue.push_back(task);
+        self.len += 1;
     }
 
     fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
-        unsafe { self.ready_queue.remove(task) }
+        let task = unsafe { self.ready_queue.remove(task) };
+        self.len -= task.is_some() as usize;
+        task
     }
 
     fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
-        self.ready_queue.pop_front()
+        let task = self.ready_queue.pop_front();
+        self.len -= task.is_some() as usize;
+        task
     }
 
     fn put_prev_task(&mut self, prev: Self::SchedItem, _preempt: bool) {
         self.ready_queue.push_back(prev);
+        self.len += 1;
+    }
+
+    fn steal_task<F>(&mut self, filter: F) -> Option<Self::SchedItem>
+    where
+        F: Fn(&Self::SchedItem) -> bool,
+    {
+        // The linked list can only be walked by reference, so rebuild it.
+        let mut stolen = None;
+        let mut rest = List::new();
+        while let Some(task) = self.ready_queue.pop_front() {
+            if stolen.is_none() && filter(&task) {
+                stolen = Some(task);
+            } else {
+                rest.push_back(task);
+            }
+        }
+        self.ready_queue = rest;
+        self.len -= stolen.is_some() as usize;
+        stolen
+    }
+
+    fn nr_ready(&self) -> usize {
+        self.len
     }
 
     fn task_tick(&mut self, _current: &Self::SchedItem) -> bool {
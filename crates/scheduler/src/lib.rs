@@ -5,13 +5,17 @@
 //! - [`FifoScheduler`]: FIFO (First-In-First-Out) scheduler (cooperative).
 //! - [`RRScheduler`]: Round-robin scheduler (preemptive).
 //! - [`CFScheduler`]: Completely Fair Scheduler (preemptive).
+//! - [`RTScheduler`]: Multi-level fixed-priority scheduler (preemptive).
+//! - [`EDFScheduler`]: Earliest Deadline First scheduler (preemptive).
 
 #![cfg_attr(not(test), no_std)]
 #![feature(const_mut_refs)]
 
 mod cfs;
+mod edf;
 mod fifo;
 mod round_robin;
+mod rt;
 
 #[cfg(test)]
 mod tests;
@@ -19,8 +23,10 @@ mod tests;
 extern crate alloc;
 
 pub use cfs::{CFSTask, CFScheduler};
+pub use edf::{EDFParams, EDFScheduler, EDFTask};
 pub use fifo::{FifoScheduler, FifoTask};
 pub use round_robin::{RRScheduler, RRTask};
+pub use rt::{RTPolicy, RTScheduler, RTTask, RT_PRIO_LEVELS};
 
 /// The base scheduler trait that all schedulers should implement.
 ///
@@ -58,6 +64,19 @@ pub trait BaseScheduler {
     /// ready queue.
     fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool);
 
+    /// Removes the first task (in the order they would be picked) that
+    /// `filter` returns `true` for, to migrate it to another scheduler. The
+    /// order of the remaining tasks is kept.
+    ///
+    /// Returns [`None`] if there is no such task.
+    fn steal_task<F>(&mut self, filter: F) -> Option<Self::SchedItem>
+    where
+        F: Fn(&Self::SchedItem) -> bool;
+
+    /// Returns the number of tasks that can be picked by
+    /// [`pick_next_task`](Self::pick_next_task).
+    fn nr_ready(&self) -> usize;
+
     /// Advances the scheduler state at each timer tick. Returns `true` if
     /// re-scheduling is required.
     ///
@@ -102,6 +102,20 @@ impl<T, const S: usize> BaseScheduler for RRScheduler<T, S> {
         }
     }
 
+    fn steal_task<F>(&mut self, filter: F) -> Option<Self::SchedItem>
+    where
+        F: Fn(&Self::SchedItem) -> bool,
+    {
+        self.ready_queue
+            .iter()
+            .position(filter)
+            .and_then(|idx| self.ready_queue.remove(idx))
+    }
+
+    fn nr_ready(&self) -> usize {
+        self.ready_queue.len()
+    }
+
     fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
         let old_slice = current.time_slice.fetch_sub(1, Ordering::Release);
         old_slice <= 1
@@ -0,0 +1,237 @@
+use alloc::{collections::VecDeque, sync::Arc};
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+use crate::BaseScheduler;
+
+/// Number of priority levels of the [`RTScheduler`].
+///
+/// Priorities range from `0` (the highest) to `RT_PRIO_LEVELS - 1` (the
+/// lowest), which is also the default priority of new tasks.
+pub const RT_PRIO_LEVELS: usize = 100;
+
+/// How tasks of the same priority share the CPU in the [`RTScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RTPolicy {
+    /// The task runs until it blocks, yields, or is preempted by a task with
+    /// a higher priority.
+    Fifo,
+    /// Like [`RTPolicy::Fifo`], but the task is also preempted when its time
+    /// slice is used up, and then placed at the end of its priority level.
+    RoundRobin,
+}
+
+/// A task wrapper for the [`RTScheduler`].
+///
+/// It adds a priority, a scheduling policy and a time slice counter.
+pub struct RTTask<T, const MAX_TIME_SLICE: usize> {
+    inner: T,
+    prio: AtomicUsize,
+    fifo: AtomicBool,
+    time_slice: AtomicIsize,
+}
+
+impl<T, const S: usize> RTTask<T, S> {
+    /// Creates a new [`RTTask`] from the inner task struct.
+    ///
+    /// The task has the lowest priority and the [`RTPolicy::RoundRobin`]
+    /// policy at first.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            prio: AtomicUsize::new(RT_PRIO_LEVELS - 1),
+            fifo: AtomicBool::new(false),
+            time_slice: AtomicIsize::new(S as isize),
+        }
+    }
+
+    fn prio(&self) -> usize {
+        self.prio.load(Ordering::Acquire)
+    }
+
+    /// Returns the scheduling policy of the task.
+    pub fn policy(&self) -> RTPolicy {
+        if self.fifo.load(Ordering::Acquire) {
+            RTPolicy::Fifo
+        } else {
+            RTPolicy::RoundRobin
+        }
+    }
+
+    /// Sets the scheduling policy of the task.
+    ///
+    /// It takes effect the next time the task is put back to the scheduler.
+    pub fn set_policy(&self, policy: RTPolicy) {
+        self.fifo.store(policy == RTPolicy::Fifo, Ordering::Release);
+    }
+
+    fn time_slice(&self) -> isize {
+        self.time_slice.load(Ordering::Acquire)
+    }
+
+    fn reset_time_slice(&self) {
+        self.time_slice.store(S as isize, Ordering::Release);
+    }
+
+    /// Returns a reference to the inner task struct.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, const S: usize> Deref for RTTask<T, S> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// A multi-level fixed-priority preemptive scheduler, like the real-time
+/// scheduling classes of Linux.
+///
+/// There are [`RT_PRIO_LEVELS`] priority levels, each with its own ready
+/// queue. The head of the highest non-empty level is always picked next. A
+/// running task is preempted at the timer tick once a task with a higher
+/// priority becomes ready.
+///
+/// Tasks of the same priority are scheduled according to their
+/// [`RTPolicy`]: [`Fifo`](RTPolicy::Fifo) tasks keep the CPU until they give
+/// it up, while [`RoundRobin`](RTPolicy::RoundRobin) tasks are rotated when
+/// their time slices run out, as in the [`RRScheduler`].
+///
+/// A bitmap of non-empty levels makes picking the next task O(1). Removing a
+/// task may take O(n) time, where n is the length of its level.
+///
+/// [`RRScheduler`]: crate::RRScheduler
+pub struct RTScheduler<T, const MAX_TIME_SLICE: usize> {
+    ready_queues: [VecDeque<Arc<RTTask<T, MAX_TIME_SLICE>>>; RT_PRIO_LEVELS],
+    bitmap: u128,
+    len: usize,
+}
+
+impl<T, const S: usize> RTScheduler<T, S> {
+    const EMPTY_QUEUE: VecDeque<Arc<RTTask<T, S>>> = VecDeque::new();
+
+    /// Creates a new empty [`RTScheduler`].
+    pub const fn new() -> Self {
+        Self {
+            ready_queues: [Self::EMPTY_QUEUE; RT_PRIO_LEVELS],
+            bitmap: 0,
+            len: 0,
+        }
+    }
+    /// get the name of scheduler
+    pub fn scheduler_name() -> &'static str {
+        "Real-time"
+    }
+
+    fn push(&mut self, task: Arc<RTTask<T, S>>, front: bool) {
+        let prio = task.prio();
+        if front {
+            self.ready_queues[prio].push_front(task);
+        } else {
+            self.ready_queues[prio].push_back(task);
+        }
+        self.bitmap |= 1 << prio;
+        self.len += 1;
+    }
+
+    fn remove_at(&mut self, prio: usize, task: &Arc<RTTask<T, S>>) -> Option<Arc<RTTask<T, S>>> {
+        let queue = &mut self.ready_queues[prio];
+        let task = queue
+            .iter()
+            .position(|t| Arc::ptr_eq(t, task))
+            .and_then(|idx| queue.remove(idx));
+        if queue.is_empty() {
+            self.bitmap &= !(1 << prio);
+        }
+        self.len -= task.is_some() as usize;
+        task
+    }
+
+    /// Returns whether there is a ready task with a priority higher than
+    /// `prio`.
+    fn has_higher_than(&self, prio: usize) -> bool {
+        self.bitmap & ((1 << prio) - 1) != 0
+    }
+}
+
+impl<T, const S: usize> BaseScheduler for RTScheduler<T, S> {
+    type SchedItem = Arc<RTTask<T, S>>;
+
+    fn init(&mut self) {}
+
+    fn add_task(&mut self, task: Self::SchedItem) {
+        self.push(task, false);
+    }
+
+    fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        self.remove_at(task.prio(), task)
+    }
+
+    fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
+        if self.bitmap == 0 {
+            return None;
+        }
+        let prio = self.bitmap.trailing_zeros() as usize;
+        let queue = &mut self.ready_queues[prio];
+        let task = queue.pop_front();
+        if queue.is_empty() {
+            self.bitmap &= !(1 << prio);
+        }
+        self.len -= task.is_some() as usize;
+        task
+    }
+
+    fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool) {
+        // A preempted task keeps its place at the head of its level, unless a
+        // round-robin task has used up its time slice.
+        let front = preempt && (prev.policy() == RTPolicy::Fifo || prev.time_slice() > 0);
+        if !front {
+            prev.reset_time_slice();
+        }
+        self.push(prev, front);
+    }
+
+    fn steal_task<F>(&mut self, filter: F) -> Option<Self::SchedItem>
+    where
+        F: Fn(&Self::SchedItem) -> bool,
+    {
+        let task = self
+            .ready_queues
+            .iter()
+            .flatten()
+            .find(|t| filter(t))?
+            .clone();
+        self.remove_task(&task)
+    }
+
+    fn nr_ready(&self) -> usize {
+        self.len
+    }
+
+    fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
+        if self.has_higher_than(current.prio()) {
+            return true;
+        }
+        match current.policy() {
+            RTPolicy::Fifo => false,
+            RTPolicy::RoundRobin => current.time_slice.fetch_sub(1, Ordering::Release) <= 1,
+        }
+    }
+
+    fn set_priority(&mut self, task: &Self::SchedItem, prio: isize) -> bool {
+        if !(0..RT_PRIO_LEVELS as isize).contains(&prio) {
+            return false;
+        }
+        let old_prio = task.prio.swap(prio as usize, Ordering::AcqRel);
+        if old_prio != prio as usize {
+            // Move the task to its new level if it's in the ready queue.
+            if let Some(task) = self.remove_at(old_prio, task) {
+                self.push(task, false);
+            }
+        }
+        true
+    }
+}
@@ -24,10 +24,33 @@ macro_rules! def_test_sched {
                 let mut n = 0;
                 while scheduler.pick_next_task().is_some() {
                     n += 1;
+                    assert_eq!(scheduler.nr_ready(), NUM_TASKS - n);
                 }
                 assert_eq!(n, NUM_TASKS);
             }
 
+            #[test]
+            fn test_steal() {
+                const NUM_TASKS: usize = 11;
+
+                let mut scheduler = <$scheduler>::new();
+                for i in 0..NUM_TASKS {
+                    scheduler.add_task(Arc::new(<$task>::new(i)));
+                }
+
+                let stolen = scheduler.steal_task(|t| *t.inner() % 4 == 3).unwrap();
+                assert_eq!(*stolen.inner(), 3);
+                assert!(scheduler.steal_task(|t| *t.inner() >= NUM_TASKS).is_none());
+                assert_eq!(scheduler.nr_ready(), NUM_TASKS - 1);
+
+                // The order of the other tasks is kept.
+                let order: Vec<_> = core::iter::from_fn(|| scheduler.pick_next_task())
+                    .map(|t| *t.inner())
+                    .collect();
+                let expected: Vec<_> = (0..NUM_TASKS).filter(|&i| i != 3).collect();
+                assert_eq!(order, expected);
+            }
+
             #[test]
             fn bench_yield() {
                 const NUM_TASKS: usize = 1_000_000;
@@ -82,3 +105,188 @@ macro_rules! def_test_sched {
 def_test_sched!(fifo, FifoScheduler::<usize>, FifoTask::<usize>);
 def_test_sched!(rr, RRScheduler::<usize, 5>, RRTask::<usize, 5>);
 def_test_sched!(cfs, CFScheduler::<usize>, CFSTask::<usize>);
+def_test_sched!(rt, RTScheduler::<usize, 5>, RTTask::<usize, 5>);
+
+mod rt_prio {
+    use crate::*;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn test_priority_order() {
+        let mut scheduler = RTScheduler::<usize, 5>::new();
+        let tasks: Vec<_> = (0..4)
+            .map(|i| Arc::new(RTTask::<usize, 5>::new(i)))
+            .collect();
+        for t in &tasks {
+            scheduler.add_task(t.clone());
+        }
+        assert!(scheduler.set_priority(&tasks[2], 10));
+        assert!(scheduler.set_priority(&tasks[3], 10));
+        assert!(scheduler.set_priority(&tasks[1], 0));
+        assert!(!scheduler.set_priority(&tasks[0], RT_PRIO_LEVELS as isize));
+        assert!(!scheduler.set_priority(&tasks[0], -1));
+
+        let order: Vec<_> = core::iter::from_fn(|| scheduler.pick_next_task())
+            .map(|t| *t.inner())
+            .collect();
+        assert_eq!(order, [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_preempt() {
+        let mut scheduler = RTScheduler::<usize, 5>::new();
+        let low = Arc::new(RTTask::<usize, 5>::new(0));
+        let high = Arc::new(RTTask::<usize, 5>::new(1));
+        scheduler.set_priority(&high, 1);
+        low.set_policy(RTPolicy::Fifo);
+
+        // A FIFO task is never rotated by ticks alone.
+        scheduler.add_task(low.clone());
+        let curr = scheduler.pick_next_task().unwrap();
+        for _ in 0..100 {
+            assert!(!scheduler.task_tick(&curr));
+        }
+
+        // A ready higher-priority task preempts it, and it goes back to the
+        // head of its level.
+        scheduler.add_task(high.clone());
+        assert!(scheduler.task_tick(&curr));
+        scheduler.put_prev_task(curr, true);
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &high));
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &low));
+        assert!(scheduler.pick_next_task().is_none());
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut scheduler = RTScheduler::<usize, 5>::new();
+        for i in 0..2 {
+            scheduler.add_task(Arc::new(RTTask::<usize, 5>::new(i)));
+        }
+        let curr = scheduler.pick_next_task().unwrap();
+        for _ in 0..4 {
+            assert!(!scheduler.task_tick(&curr));
+        }
+        assert!(scheduler.task_tick(&curr));
+        scheduler.put_prev_task(curr, true);
+        assert_eq!(*scheduler.pick_next_task().unwrap().inner(), 1);
+        assert_eq!(*scheduler.pick_next_task().unwrap().inner(), 0);
+    }
+}
+def_test_sched!(edf, EDFScheduler::<usize>, EDFTask::<usize>);
+
+mod edf_params {
+    use crate::*;
+    use alloc::sync::Arc;
+
+    const fn params(runtime: u64, deadline: u64, period: u64) -> Option<EDFParams> {
+        Some(EDFParams {
+            runtime,
+            deadline,
+            period,
+        })
+    }
+
+    #[test]
+    fn test_admission() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        let tasks: Vec<_> = (0..3).map(|i| Arc::new(EDFTask::new(i))).collect();
+
+        assert!(!scheduler.set_params(&tasks[0], params(0, 10, 10)));
+        assert!(!scheduler.set_params(&tasks[0], params(5, 4, 10)));
+        assert!(!scheduler.set_params(&tasks[0], params(5, 20, 10)));
+
+        assert!(scheduler.set_params(&tasks[0], params(5, 10, 10)));
+        assert!(scheduler.set_params(&tasks[1], params(3, 10, 10)));
+        assert!(!scheduler.set_params(&tasks[2], params(3, 10, 10)));
+        // Changing the parameters of an admitted task releases its old
+        // bandwidth first.
+        assert!(scheduler.set_params(&tasks[1], params(5, 10, 10)));
+        assert!(scheduler.set_params(&tasks[0], None));
+        assert!(scheduler.set_params(&tasks[2], params(5, 10, 10)));
+        assert_eq!(tasks[0].params(), None);
+    }
+
+    #[test]
+    fn test_earliest_deadline_first() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        let tasks: Vec<_> = (0..4).map(|i| Arc::new(EDFTask::new(i))).collect();
+        for t in &tasks {
+            scheduler.add_task(t.clone());
+        }
+        assert!(scheduler.set_params(&tasks[1], params(1, 30, 100)));
+        assert!(scheduler.set_params(&tasks[2], params(1, 10, 100)));
+        assert!(scheduler.set_params(&tasks[3], params(1, 20, 100)));
+
+        let order: Vec<_> = core::iter::from_fn(|| scheduler.pick_next_task())
+            .map(|t| *t.inner())
+            .collect();
+        assert_eq!(order, [2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_budget_enforcement() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        let rt = Arc::new(EDFTask::new(0));
+        let be = Arc::new(EDFTask::new(1));
+        scheduler.add_task(be.clone());
+        scheduler.add_task(rt.clone());
+        assert!(scheduler.set_params(&rt, params(2, 5, 5)));
+
+        for period in 0..3 {
+            // The deadline task runs first, until its budget is used up.
+            let curr = scheduler.pick_next_task().unwrap();
+            assert!(Arc::ptr_eq(&curr, &rt));
+            assert!(!scheduler.task_tick(&curr));
+            assert!(scheduler.task_tick(&curr));
+            scheduler.put_prev_task(curr, true);
+
+            // Then it's throttled, and the best-effort task runs for the
+            // rest of the period, preempted once the budget is replenished.
+            let curr = scheduler.pick_next_task().unwrap();
+            assert!(Arc::ptr_eq(&curr, &be));
+            assert!(!scheduler.task_tick(&curr));
+            assert!(!scheduler.task_tick(&curr));
+            assert!(scheduler.task_tick(&curr));
+            scheduler.put_prev_task(curr, true);
+            assert_eq!(scheduler.now(), (period + 1) * 5);
+            assert_eq!(rt.abs_deadline(), Some(scheduler.now() + 5));
+        }
+    }
+
+    #[test]
+    fn test_idle_tick() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        let rt = Arc::new(EDFTask::new(0));
+        assert!(scheduler.set_params(&rt, params(1, 3, 3)));
+        scheduler.add_task(rt.clone());
+
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(scheduler.task_tick(&curr));
+        scheduler.put_prev_task(curr, true);
+        assert!(scheduler.pick_next_task().is_none());
+
+        scheduler.idle_tick();
+        assert!(scheduler.pick_next_task().is_none());
+        assert_eq!(scheduler.nr_ready(), 0);
+        scheduler.idle_tick();
+        assert_eq!(scheduler.nr_ready(), 1);
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &rt));
+        assert_eq!(rt.budget(), 1);
+    }
+
+    #[test]
+    fn test_steal_pinned() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        let rt = Arc::new(EDFTask::new(0));
+        let be = Arc::new(EDFTask::new(1));
+        scheduler.add_task(rt.clone());
+        scheduler.add_task(be.clone());
+        assert!(scheduler.set_params(&rt, params(1, 5, 5)));
+
+        // Only the best-effort task can migrate.
+        assert!(Arc::ptr_eq(&scheduler.steal_task(|_| true).unwrap(), &be));
+        assert!(scheduler.steal_task(|_| true).is_none());
+        assert_eq!(scheduler.nr_ready(), 1);
+    }
+}
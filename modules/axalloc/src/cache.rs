@@ -0,0 +1,311 @@
+//! Per-CPU caches of small objects in front of the shared byte allocator.
+//!
+//! Allocations up to [`MAX_CACHED_SIZE`] bytes are rounded up to power-of-two
+//! size classes. Each CPU keeps a free list (bin) per size class, so that most
+//! allocations and deallocations do not take the lock of the byte allocator.
+//! An empty bin is refilled with a batch of objects at once, and a bin that
+//! grows too big is flushed back to the byte allocator by half. When the heap
+//! is exhausted, the caches of all CPUs are drained before the allocation
+//! fails.
+
+use core::alloc::Layout;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use allocator::{AllocResult, ByteAllocator};
+use kernel_guard::NoPreemptIrqSave;
+use spinlock::SpinRaw;
+
+use crate::GlobalAllocator;
+
+const MIN_CLASS_SHIFT: usize = 4; // 16 B
+const MAX_CLASS_SHIFT: usize = 11; // 2 KB
+const NUM_CLASSES: usize = MAX_CLASS_SHIFT - MIN_CLASS_SHIFT + 1;
+
+/// The maximum size (and alignment) of allocations served by the caches.
+const MAX_CACHED_SIZE: usize = 1 << MAX_CLASS_SHIFT;
+
+/// Number of bytes to fetch from the byte allocator when a bin is empty.
+const REFILL_BYTES: usize = 0x1000;
+/// A bin is flushed by half when it holds more bytes than this.
+const MAX_BIN_BYTES: usize = 0x10000;
+
+/// A free list of objects of the same size class, linked through the first
+/// word of each object.
+struct Bin {
+    head: usize,
+    len: usize,
+}
+
+impl Bin {
+    const fn new() -> Self {
+        Self { head: 0, len: 0 }
+    }
+
+    fn push(&mut self, ptr: NonNull<u8>) {
+        unsafe { *(ptr.as_ptr() as *mut usize) = self.head };
+        self.head = ptr.as_ptr() as usize;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<NonNull<u8>> {
+        let ptr = NonNull::new(self.head as *mut u8)?;
+        self.head = unsafe { *(ptr.as_ptr() as *const usize) };
+        self.len -= 1;
+        Some(ptr)
+    }
+}
+
+/// The bins of one CPU.
+///
+/// They are only used by the owner CPU with preemption and IRQs disabled, but
+/// other CPUs may take all objects away when the heap is exhausted, so they
+/// are protected by an (uncontended) lock.
+struct CpuCache {
+    bins: SpinRaw<[Bin; NUM_CLASSES]>,
+    registered: AtomicBool,
+    /// The next cache in the list of [`CACHES`].
+    next: AtomicPtr<CpuCache>,
+}
+
+impl CpuCache {
+    const fn new() -> Self {
+        Self {
+            bins: SpinRaw::new(EMPTY_BINS),
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Adds the cache to [`CACHES`], so that it can be drained by other CPUs.
+    fn register(&'static self) {
+        if self.registered.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let mut head = CACHES.load(Ordering::Relaxed);
+        loop {
+            self.next.store(head, Ordering::Relaxed);
+            match CACHES.compare_exchange_weak(
+                head,
+                self as *const _ as *mut _,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(h) => head = h,
+            }
+        }
+    }
+
+    /// Takes all objects out of the cache.
+    fn take(&self) -> [Bin; NUM_CLASSES] {
+        core::mem::replace(&mut *self.bins.lock(), EMPTY_BINS)
+    }
+}
+
+const EMPTY_BINS: [Bin; NUM_CLASSES] = {
+    const EMPTY: Bin = Bin::new();
+    [EMPTY; NUM_CLASSES]
+};
+
+#[percpu::def_percpu]
+static CPU_CACHE: CpuCache = CpuCache::new();
+
+/// The list of caches that have been used, linked through [`CpuCache::next`].
+/// Caches are never removed from it.
+static CACHES: AtomicPtr<CpuCache> = AtomicPtr::new(ptr::null_mut());
+
+/// Returns the cache of the current CPU. Preemption must be disabled.
+fn current() -> &'static CpuCache {
+    let cache = unsafe { &*CPU_CACHE.current_ptr() };
+    if !cache.registered.load(Ordering::Relaxed) {
+        cache.register();
+    }
+    cache
+}
+
+/// Returns the size class of `layout`, or [`None`] if it's too large to be
+/// cached.
+///
+/// Nothing is cached with the `debug` feature, as cached objects are not
+/// checked by the debug allocator.
+pub(crate) fn size_class(layout: Layout) -> Option<usize> {
+    if cfg!(feature = "debug") {
+        return None;
+    }
+    let size = layout.size().max(layout.align()).max(1 << MIN_CLASS_SHIFT);
+    if size > MAX_CACHED_SIZE {
+        return None;
+    }
+    Some(size.next_power_of_two().trailing_zeros() as usize - MIN_CLASS_SHIFT)
+}
+
+/// The layout used to allocate objects of `class` from the byte allocator.
+/// Objects are aligned to their size, so they can serve any layout in the
+/// class.
+fn class_layout(class: usize) -> Layout {
+    let size = 1 << (class + MIN_CLASS_SHIFT);
+    Layout::from_size_align(size, size).unwrap()
+}
+
+pub(crate) fn alloc(ga: &GlobalAllocator, class: usize) -> AllocResult<NonNull<u8>> {
+    let _guard = NoPreemptIrqSave::new();
+    match alloc_from(ga, current(), class) {
+        Ok(ptr) => Ok(ptr),
+        Err(_) => {
+            // The memory may be held by the caches of all CPUs.
+            drain_all(ga);
+            alloc_from(ga, current(), class)
+        }
+    }
+}
+
+fn alloc_from(ga: &GlobalAllocator, cache: &CpuCache, class: usize) -> AllocResult<NonNull<u8>> {
+    let mut bins = cache.bins.lock();
+    if let Some(ptr) = bins[class].pop() {
+        return Ok(ptr);
+    }
+
+    let layout = class_layout(class);
+    let mut balloc = ga.balloc.lock();
+    let ptr = ga.heap_alloc(&mut balloc, layout)?;
+    // Refill the bin, it's fine to fail.
+    for _ in 1..REFILL_BYTES / layout.size() {
+        match balloc.alloc(layout) {
+            Ok(obj) => bins[class].push(obj),
+            Err(_) => break,
+        }
+    }
+    Ok(ptr)
+}
+
+pub(crate) fn dealloc(ga: &GlobalAllocator, ptr: NonNull<u8>, class: usize) {
+    let _guard = NoPreemptIrqSave::new();
+    dealloc_to(ga, current(), ptr, class)
+}
+
+fn dealloc_to(ga: &GlobalAllocator, cache: &CpuCache, ptr: NonNull<u8>, class: usize) {
+    let mut bins = cache.bins.lock();
+    let bin = &mut bins[class];
+    bin.push(ptr);
+    let max_len = MAX_BIN_BYTES / class_layout(class).size();
+    if bin.len > max_len {
+        // Give back half of the objects to the byte allocator.
+        let mut balloc = ga.balloc.lock();
+        while bin.len > max_len / 2 {
+            balloc.dealloc(bin.pop().unwrap(), class_layout(class));
+        }
+    }
+}
+
+/// Gives back the objects in `bins` to the byte allocator.
+fn free_bins(ga: &GlobalAllocator, mut bins: [Bin; NUM_CLASSES]) {
+    let mut balloc = ga.balloc.lock();
+    for (class, bin) in bins.iter_mut().enumerate() {
+        while let Some(ptr) = bin.pop() {
+            balloc.dealloc(ptr, class_layout(class));
+        }
+    }
+}
+
+/// Gives back all objects in the cache of the current CPU to the byte
+/// allocator.
+pub(crate) fn flush(ga: &GlobalAllocator) {
+    let _guard = NoPreemptIrqSave::new();
+    free_bins(ga, current().take());
+}
+
+/// Gives back all objects in the caches of all CPUs to the byte allocator.
+///
+/// The caches are emptied one by one, and the byte allocator is not locked
+/// at the same time, as the owner CPUs lock their caches first.
+pub(crate) fn drain_all(ga: &GlobalAllocator) {
+    let _guard = NoPreemptIrqSave::new();
+    let mut next = CACHES.load(Ordering::Acquire);
+    while let Some(cache) = unsafe { next.as_ref() } {
+        free_bins(ga, cache.take());
+        next = cache.next.load(Ordering::Relaxed);
+    }
+}
+
+// Nothing is cached with the `debug` feature.
+#[cfg(all(test, not(feature = "debug")))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::PAGE_SIZE;
+
+    fn new_allocator(size: usize) -> GlobalAllocator {
+        let layout = Layout::from_size_align(size, PAGE_SIZE).unwrap();
+        let start = unsafe { std::alloc::alloc(layout) } as usize;
+        let ga = GlobalAllocator::new();
+        ga.init(start, size);
+        ga
+    }
+
+    fn bin_len(cache: &CpuCache, class: usize) -> usize {
+        cache.bins.lock()[class].len
+    }
+
+    #[test]
+    fn test_refill_and_flush() {
+        let ga = new_allocator(0x10_0000);
+        let cache = CpuCache::new();
+        let class = size_class(Layout::new::<[u64; 8]>()).unwrap();
+        let size = class_layout(class).size();
+        assert_eq!(size, 64);
+
+        // An empty bin is refilled with a batch of objects.
+        let ptr = alloc_from(&ga, &cache, class).unwrap();
+        assert_eq!(bin_len(&cache, class), REFILL_BYTES / size - 1);
+        assert_eq!(ga.used_bytes(), REFILL_BYTES);
+        dealloc_to(&ga, &cache, ptr, class);
+        assert_eq!(bin_len(&cache, class), REFILL_BYTES / size);
+
+        // A bin that grows too big is flushed by half.
+        let max_len = MAX_BIN_BYTES / size;
+        let ptrs: std::vec::Vec<_> = (0..=max_len)
+            .map(|_| alloc_from(&ga, &cache, class).unwrap())
+            .collect();
+        for ptr in ptrs {
+            dealloc_to(&ga, &cache, ptr, class);
+            assert!(bin_len(&cache, class) <= max_len);
+        }
+        assert!(bin_len(&cache, class) >= max_len / 2);
+
+        // All objects are given back.
+        free_bins(&ga, cache.take());
+        assert_eq!(bin_len(&cache, class), 0);
+        assert_eq!(ga.used_bytes(), 0);
+    }
+
+    #[test]
+    fn test_drain_all() {
+        static CACHE0: CpuCache = CpuCache::new();
+        static CACHE1: CpuCache = CpuCache::new();
+        CACHE0.register();
+        CACHE1.register();
+
+        let ga = new_allocator(0x1_0000);
+        let class = NUM_CLASSES - 1;
+        assert!(ga.available_bytes() + ga.available_pages() * PAGE_SIZE <= MAX_BIN_BYTES);
+
+        // The whole heap is held by the cache of CPU 1.
+        let mut ptrs = std::vec::Vec::new();
+        while let Ok(ptr) = alloc_from(&ga, &CACHE1, class) {
+            ptrs.push(ptr);
+        }
+        for ptr in ptrs {
+            dealloc_to(&ga, &CACHE1, ptr, class);
+        }
+        assert!(bin_len(&CACHE1, class) > 0);
+        assert!(alloc_from(&ga, &CACHE0, class).is_err());
+
+        // CPU 0 can still allocate after draining the caches.
+        drain_all(&ga);
+        assert_eq!(bin_len(&CACHE1, class), 0);
+        assert_eq!(ga.used_bytes(), 0);
+        assert!(alloc_from(&ga, &CACHE0, class).is_ok());
+    }
+}
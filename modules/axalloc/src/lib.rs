@@ -4,6 +4,29 @@
 //! [`core::alloc::GlobalAlloc`]. A static global variable of type
 //! [`GlobalAllocator`] is defined with the `#[global_allocator]` attribute, to
 //! be registered as the standard library’s default allocator.
+//!
+//! When the memory is exhausted, the callbacks registered by
+//! [`register_reclaimer`] are called to free memory (e.g., caches) before the
+//! allocation fails. A failed allocation of [`core::alloc::GlobalAlloc`] is
+//! then handled by the [`OomPolicy`] set by [`set_oom_policy`].
+//!
+//! # Cargo Features
+//!
+//! - `tlsf`, `slab`, `buddy`: Select the byte allocator. TLSF by default.
+//! - `page-buddy`: Use the buddy system page allocator instead of the bitmap
+//!   one.
+//! - `percpu-cache`: Cache small objects per CPU, so that most allocations do
+//!   not contend for the lock of the byte allocator on multi-core systems.
+//! - `stats`: Record allocation counts, peak usage and a size histogram, and
+//!   optionally track live allocations to find memory leaks. The
+//!   [`AllocStatsIf`] interface must be implemented to tag the allocations
+//!   with task IDs.
+//! - `debug`: Wrap the byte allocator in a [`DebugByteAllocator`], which
+//!   surrounds allocations with redzones and poisons freed memory, to report
+//!   heap corruptions, double frees and mismatched layouts. The per-CPU caches
+//!   are bypassed, so that every deallocation is checked.
+//!
+//! [`DebugByteAllocator`]: allocator::DebugByteAllocator
 
 #![no_std]
 
@@ -11,25 +34,63 @@
 extern crate log;
 extern crate alloc;
 
+#[cfg(feature = "percpu-cache")]
+mod cache;
+mod oom;
 mod page;
+#[cfg(feature = "stats")]
+mod stats;
 
-use allocator::{AllocResult, BaseAllocator, BitmapPageAllocator, ByteAllocator, PageAllocator};
+use allocator::{AllocResult, BaseAllocator, ByteAllocator, PageAllocator};
 use core::alloc::{GlobalAlloc, Layout};
+use core::fmt;
 use core::ptr::NonNull;
 use spinlock::SpinNoIrq;
 
 const PAGE_SIZE: usize = 0x1000;
 const MIN_HEAP_SIZE: usize = 0x8000; // 32 K
 
+pub use oom::{
+    oom_policy, register_reclaimer, set_oom_killer, set_oom_policy, OomPolicy, ReclaimFn,
+};
 pub use page::GlobalPage;
+#[cfg(feature = "stats")]
+pub use stats::{
+    alloc_stats, live_allocs, set_live_tracking, AllocStats, AllocStatsIf, LiveAlloc,
+    NUM_SIZE_BUCKETS,
+};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "slab")] {
-        use allocator::SlabByteAllocator as DefaultByteAllocator;
+        use allocator::SlabByteAllocator as InnerByteAllocator;
     } else if #[cfg(feature = "buddy")] {
-        use allocator::BuddyByteAllocator as DefaultByteAllocator;
+        use allocator::BuddyByteAllocator as InnerByteAllocator;
     } else if #[cfg(feature = "tlsf")] {
-        use allocator::TlsfByteAllocator as DefaultByteAllocator;
+        use allocator::TlsfByteAllocator as InnerByteAllocator;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "debug")] {
+        type DefaultByteAllocator = allocator::DebugByteAllocator<InnerByteAllocator>;
+
+        const fn new_byte_allocator() -> DefaultByteAllocator {
+            DefaultByteAllocator::new(InnerByteAllocator::new())
+        }
+    } else {
+        type DefaultByteAllocator = InnerByteAllocator;
+
+        const fn new_byte_allocator() -> DefaultByteAllocator {
+            DefaultByteAllocator::new()
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "page-buddy")] {
+        use allocator::BuddyPageAllocator as DefaultPageAllocator;
+    } else {
+        use allocator::BitmapPageAllocator as DefaultPageAllocator;
     }
 }
 
@@ -40,21 +101,23 @@ cfg_if::cfg_if! {
 /// there is no memory, asks the page allocator for more memory and adds it to
 /// the byte allocator.
 ///
-/// Currently, [`TlsfByteAllocator`] is used as the byte allocator, while
-/// [`BitmapPageAllocator`] is used as the page allocator.
+/// By default, [`TlsfByteAllocator`] is used as the byte allocator, while
+/// [`BitmapPageAllocator`] is used as the page allocator. The latter can be
+/// replaced by `BuddyPageAllocator` with the `page-buddy` feature.
 ///
 /// [`TlsfByteAllocator`]: allocator::TlsfByteAllocator
+/// [`BitmapPageAllocator`]: allocator::BitmapPageAllocator
 pub struct GlobalAllocator {
     balloc: SpinNoIrq<DefaultByteAllocator>,
-    palloc: SpinNoIrq<BitmapPageAllocator<PAGE_SIZE>>,
+    palloc: SpinNoIrq<DefaultPageAllocator<PAGE_SIZE>>,
 }
 
 impl GlobalAllocator {
     /// Creates an empty [`GlobalAllocator`].
     pub const fn new() -> Self {
         Self {
-            balloc: SpinNoIrq::new(DefaultByteAllocator::new()),
-            palloc: SpinNoIrq::new(BitmapPageAllocator::new()),
+            balloc: SpinNoIrq::new(new_byte_allocator()),
+            palloc: SpinNoIrq::new(DefaultPageAllocator::new()),
         }
     }
 
@@ -81,7 +144,7 @@ impl GlobalAllocator {
         let init_heap_size = MIN_HEAP_SIZE;
         self.palloc.lock().init(start_vaddr, size);
         let heap_ptr = self
-            .alloc_pages(init_heap_size / PAGE_SIZE, PAGE_SIZE)
+            .alloc_pages_inner(init_heap_size / PAGE_SIZE, PAGE_SIZE)
             .unwrap();
         self.balloc.lock().init(heap_ptr, init_heap_size);
     }
@@ -102,9 +165,42 @@ impl GlobalAllocator {
     ///
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     ///  aligned to it.
+    ///
+    /// With the `percpu-cache` feature, small allocations are served from the
+    /// cache of the current CPU first.
+    ///
+    /// If there is still no memory, the callbacks registered by
+    /// [`register_reclaimer`] are called to free memory before it fails.
     pub fn alloc(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        let res = oom::with_reclaim(layout.size(), || self.alloc_inner(layout));
+        #[cfg(feature = "stats")]
+        stats::record_alloc(&res, layout.size());
+        res
+    }
+
+    fn alloc_inner(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        #[cfg(feature = "percpu-cache")]
+        if let Some(class) = cache::size_class(layout) {
+            return cache::alloc(self, class);
+        }
+        let res = self.heap_alloc(&mut self.balloc.lock(), layout);
+        #[cfg(feature = "percpu-cache")]
+        if res.is_err() {
+            // The memory may be held by the caches of all CPUs.
+            cache::drain_all(self);
+            return self.heap_alloc(&mut self.balloc.lock(), layout);
+        }
+        res
+    }
+
+    /// Allocates from the locked byte allocator, and expands the heap if
+    /// there is no memory.
+    fn heap_alloc(
+        &self,
+        balloc: &mut DefaultByteAllocator,
+        layout: Layout,
+    ) -> AllocResult<NonNull<u8>> {
         // simple two-level allocator: if no heap memory, allocate from the page allocator.
-        let mut balloc = self.balloc.lock();
         loop {
             if let Ok(ptr) = balloc.alloc(layout) {
                 return Ok(ptr);
@@ -114,7 +210,7 @@ impl GlobalAllocator {
                     .max(layout.size())
                     .next_power_of_two()
                     .max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+                let heap_ptr = self.alloc_pages_inner(expand_size / PAGE_SIZE, PAGE_SIZE)?;
                 debug!(
                     "expand heap memory: [{:#x}, {:#x})",
                     heap_ptr,
@@ -133,17 +229,51 @@ impl GlobalAllocator {
     ///
     /// [`alloc`]: GlobalAllocator::alloc
     pub fn dealloc(&self, pos: NonNull<u8>, layout: Layout) {
+        #[cfg(feature = "stats")]
+        stats::record_dealloc(pos, layout.size());
+        #[cfg(feature = "percpu-cache")]
+        if let Some(class) = cache::size_class(layout) {
+            return cache::dealloc(self, pos, class);
+        }
         self.balloc.lock().dealloc(pos, layout)
     }
 
+    /// Gives back the objects cached by the current CPU to the byte
+    /// allocator.
+    ///
+    /// It's done automatically when a cache grows too big (and for the caches
+    /// of all CPUs when the heap is exhausted), but can also be called when a
+    /// CPU becomes idle.
+    #[cfg(feature = "percpu-cache")]
+    pub fn flush_percpu_cache(&self) {
+        cache::flush(self);
+    }
+
     /// Allocates contiguous pages.
     ///
     /// It allocates `num_pages` pages from the page allocator.
     ///
     /// `align_pow2` must be a power of 2, and the returned region bound will be
-    /// aligned to it.
+    /// aligned to it. Alignments up to 1G are supported, so that the region
+    /// can be used as a huge page (see [`GlobalPage::alloc_huge`]).
+    ///
+    /// Like [`alloc`], the callbacks registered by [`register_reclaimer`] are
+    /// called if there is no memory.
+    ///
+    /// [`alloc`]: GlobalAllocator::alloc
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        self.palloc.lock().alloc_pages(num_pages, align_pow2)
+        oom::with_reclaim(num_pages * PAGE_SIZE, || {
+            self.alloc_pages_inner(num_pages, align_pow2)
+        })
+    }
+
+    /// Allocates pages without reclaiming, which is used when the byte
+    /// allocator is locked, as the reclaim callbacks may free memory to it.
+    fn alloc_pages_inner(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        let res = self.palloc.lock().alloc_pages(num_pages, align_pow2);
+        #[cfg(feature = "stats")]
+        stats::record_alloc_pages(&res, num_pages);
+        res
     }
 
     /// Gives back the allocated pages starts from `pos` to the page allocator.
@@ -154,10 +284,14 @@ impl GlobalAllocator {
     ///
     /// [`alloc_pages`]: GlobalAllocator::alloc_pages
     pub fn dealloc_pages(&self, pos: usize, num_pages: usize) {
-        self.palloc.lock().dealloc_pages(pos, num_pages)
+        self.palloc.lock().dealloc_pages(pos, num_pages);
+        #[cfg(feature = "stats")]
+        stats::record_dealloc_pages(num_pages);
     }
 
     /// Returns the number of allocated bytes in the byte allocator.
+    ///
+    /// Objects in the per-CPU caches are counted as allocated.
     pub fn used_bytes(&self) -> usize {
         self.balloc.lock().used_bytes()
     }
@@ -176,6 +310,40 @@ impl GlobalAllocator {
     pub fn available_pages(&self) -> usize {
         self.palloc.lock().available_pages()
     }
+
+    /// Writes the memory usage in the format of `/proc/meminfo`.
+    ///
+    /// With the `stats` feature, the statistics returned by [`alloc_stats`]
+    /// are appended.
+    pub fn write_meminfo(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        let used_pages = self.used_pages();
+        let total_pages = used_pages + self.available_pages();
+        let used_bytes = self.used_bytes();
+        let total_bytes = used_bytes + self.available_bytes();
+        let lines = [
+            ("MemTotal", total_pages * PAGE_SIZE),
+            ("MemFree", (total_pages - used_pages) * PAGE_SIZE),
+            ("HeapTotal", total_bytes),
+            ("HeapUsed", used_bytes),
+        ];
+        for (name, bytes) in lines {
+            write_meminfo_line(w, name, bytes / 1024, " kB")?;
+        }
+        #[cfg(feature = "stats")]
+        write!(w, "{}", alloc_stats())?;
+        Ok(())
+    }
+}
+
+/// Writes a `/proc/meminfo` line, with the values right-aligned.
+fn write_meminfo_line(
+    w: &mut impl fmt::Write,
+    name: &str,
+    value: impl fmt::Display,
+    unit: &str,
+) -> fmt::Result {
+    let width = 24usize.saturating_sub(name.len());
+    writeln!(w, "{}:{:>width$}{}", name, value, unit)
 }
 
 unsafe impl GlobalAlloc for GlobalAllocator {
@@ -183,7 +351,7 @@ unsafe impl GlobalAlloc for GlobalAllocator {
         if let Ok(ptr) = GlobalAllocator::alloc(self, layout) {
             ptr.as_ptr()
         } else {
-            alloc::alloc::handle_alloc_error(layout)
+            oom::handle_oom(layout)
         }
     }
 
@@ -202,10 +370,11 @@ pub fn global_allocator() -> &'static GlobalAllocator {
 
 /// Initializes the global allocator with the given memory region.
 ///
-/// Note that the memory region bounds are just numbers, and the allocator
-/// does not actually access the region. Users should ensure that the region
-/// is valid and not being used by others, so that the allocated memory is also
-/// valid.
+/// Note that the allocator keeps its metadata in the region itself (e.g., the
+/// byte allocator headers and the free lists of the `page-buddy` allocator), so
+/// the region must be mapped and writable before this function is called.
+/// Users should also ensure that the region is not being used by others, so
+/// that the allocated memory is also valid.
 ///
 /// This function should be called only once, and before any allocation.
 pub fn global_init(start_vaddr: usize, size: usize) {
@@ -0,0 +1,162 @@
+//! Memory pressure callbacks and the out-of-memory policy.
+
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use allocator::{AllocError, AllocResult};
+use kernel_guard::NoPreempt;
+use spinlock::SpinNoIrq;
+
+/// Maximum number of reclaim callbacks that can be registered.
+const MAX_RECLAIMERS: usize = 16;
+
+/// A callback to free memory under memory pressure, e.g., by dropping
+/// caches.
+///
+/// It takes the number of bytes that the failed allocation requested, and
+/// returns the number of bytes freed (0 if nothing is freed). It may be
+/// called in any context where memory is allocated with preemption disabled,
+/// so it must not block or wait for locks that the allocating code may hold
+/// (use `try_lock` instead). Its own allocations do not trigger reclaiming
+/// again.
+pub type ReclaimFn = fn(usize) -> usize;
+
+/// What to do when an allocation through [`core::alloc::GlobalAlloc`] fails
+/// even after reclaiming.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OomPolicy {
+    /// Call the allocation error handler, which panics. This is the default.
+    Panic = 0,
+    /// Mark the allocating task to be killed with the callback set by
+    /// [`set_oom_killer`], and return a null pointer. The task exits at its
+    /// next safe point, as it may hold locks when allocating. As with
+    /// [`OomPolicy::ReturnNull`], Rust collections still call the allocation
+    /// error handler. Panic if the callback is not set.
+    KillTask = 1,
+    /// Return a null pointer. Rust collections still call the allocation
+    /// error handler, but C programs get `NULL` from `malloc`.
+    ReturnNull = 2,
+}
+
+static RECLAIMERS: SpinNoIrq<[Option<ReclaimFn>; MAX_RECLAIMERS]> =
+    SpinNoIrq::new([None; MAX_RECLAIMERS]);
+/// The CPU that is calling the reclaim callbacks, or [`NO_OWNER`].
+static RECLAIM_OWNER: AtomicUsize = AtomicUsize::new(NO_OWNER);
+const NO_OWNER: usize = usize::MAX;
+
+static POLICY: AtomicU8 = AtomicU8::new(OomPolicy::Panic as u8);
+static KILLER: SpinNoIrq<Option<fn()>> = SpinNoIrq::new(None);
+
+/// Registers a callback to free memory when an allocation fails.
+///
+/// The callbacks are called in the order of registration, and the
+/// allocation is retried after each callback that freed some memory.
+///
+/// Returns [`AllocError::NoMemory`] if there are too many callbacks.
+pub fn register_reclaimer(f: ReclaimFn) -> AllocResult {
+    let mut reclaimers = RECLAIMERS.lock();
+    let slot = reclaimers
+        .iter_mut()
+        .find(|r| r.is_none())
+        .ok_or(AllocError::NoMemory)?;
+    *slot = Some(f);
+    Ok(())
+}
+
+/// Sets the policy when an allocation fails.
+pub fn set_oom_policy(policy: OomPolicy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// Returns the policy when an allocation fails.
+pub fn oom_policy() -> OomPolicy {
+    match POLICY.load(Ordering::Relaxed) {
+        1 => OomPolicy::KillTask,
+        2 => OomPolicy::ReturnNull,
+        _ => OomPolicy::Panic,
+    }
+}
+
+/// Sets the callback to kill the current task under the
+/// [`OomPolicy::KillTask`] policy.
+///
+/// It's usually set by the runtime when multitasking is enabled. It's called
+/// in the allocator, so it must not exit the task right away, but only mark
+/// it to exit at a safe point.
+pub fn set_oom_killer(f: fn()) {
+    *KILLER.lock() = Some(f);
+}
+
+/// Calls `f` to allocate memory, and calls the reclaim callbacks then
+/// retries if it fails.
+pub(crate) fn with_reclaim<T>(
+    size: usize,
+    mut f: impl FnMut() -> AllocResult<T>,
+) -> AllocResult<T> {
+    let mut res = f();
+    if res.is_ok() {
+        return res;
+    }
+    // The owner must stay on its CPU until it's done.
+    let _guard = NoPreempt::new();
+    let cpu = this_cpu();
+    while let Err(owner) =
+        RECLAIM_OWNER.compare_exchange(NO_OWNER, cpu, Ordering::Acquire, Ordering::Relaxed)
+    {
+        if owner == cpu {
+            // Reclaiming does not nest, the allocations in the callbacks (or
+            // in interrupt handlers meanwhile) fail directly.
+            return res;
+        }
+        // Wait for the other CPU to finish reclaiming, then retry since some
+        // memory may have been freed.
+        while RECLAIM_OWNER.load(Ordering::Relaxed) != NO_OWNER {
+            core::hint::spin_loop();
+        }
+        res = f();
+        if res.is_ok() {
+            return res;
+        }
+    }
+    let reclaimers = *RECLAIMERS.lock();
+    for reclaim in reclaimers.iter().flatten() {
+        let freed = reclaim(size);
+        if freed > 0 {
+            debug!("reclaimed {} bytes for {} bytes", freed, size);
+            res = f();
+            if res.is_ok() {
+                break;
+            }
+        }
+    }
+    RECLAIM_OWNER.store(NO_OWNER, Ordering::Release);
+    res
+}
+
+/// Returns an identifier of the current CPU, i.e., the base address of its
+/// per-CPU data area.
+fn this_cpu() -> usize {
+    if cfg!(target_os = "none") {
+        percpu::get_local_thread_pointer()
+    } else {
+        0
+    }
+}
+
+/// Handles a failed allocation of [`core::alloc::GlobalAlloc`] according to
+/// the policy.
+pub(crate) fn handle_oom(layout: core::alloc::Layout) -> *mut u8 {
+    warn!("out of memory: {:?}", layout);
+    match oom_policy() {
+        OomPolicy::ReturnNull => return core::ptr::null_mut(),
+        OomPolicy::KillTask => {
+            let killer = *KILLER.lock();
+            if let Some(kill) = killer {
+                kill();
+                return core::ptr::null_mut();
+            }
+        }
+        OomPolicy::Panic => {}
+    }
+    alloc::alloc::handle_alloc_error(layout)
+}
@@ -6,7 +6,9 @@ use crate::{global_allocator, PAGE_SIZE};
 
 /// A RAII wrapper of contiguous 4K-sized pages.
 ///
-/// It will automatically deallocate the pages when dropped.
+/// It can also hold a huge page (e.g., 2M or 1G), which is a naturally aligned
+/// contiguous region of 4K-sized pages. It will automatically deallocate the
+/// pages when dropped.
 #[derive(Debug)]
 pub struct GlobalPage {
     start_vaddr: VirtAddr,
@@ -43,6 +45,24 @@ impl GlobalPage {
             .map_err(alloc_err_to_ax_err)
     }
 
+    /// Allocate one huge page of `page_size` bytes (e.g., 2M or 1G), whose
+    /// start address is aligned to `page_size`.
+    ///
+    /// `page_size` must be a power of two and a multiple of 4K.
+    pub fn alloc_huge(page_size: usize) -> AxResult<Self> {
+        if !page_size.is_power_of_two() || page_size < PAGE_SIZE {
+            return Err(AxError::InvalidInput);
+        }
+        Self::alloc_contiguous(page_size / PAGE_SIZE, page_size)
+    }
+
+    /// Allocate one huge page of `page_size` bytes and fill with zero.
+    pub fn alloc_huge_zero(page_size: usize) -> AxResult<Self> {
+        let mut p = Self::alloc_huge(page_size)?;
+        p.zero();
+        Ok(p)
+    }
+
     /// Get the start virtual address of this page.
     pub fn start_vaddr(&self) -> VirtAddr {
         self.start_vaddr
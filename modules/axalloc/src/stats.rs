@@ -0,0 +1,380 @@
+//! Memory usage statistics and live allocation tracking.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Write};
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use allocator::AllocResult;
+use spinlock::SpinNoIrq;
+
+use crate::write_meminfo_line;
+
+/// Number of buckets in [`AllocStats::histogram`].
+pub const NUM_SIZE_BUCKETS: usize = 14;
+const MIN_BUCKET_SHIFT: usize = 4; // 16 B
+
+/// Number of slots in the live allocation table.
+const LIVE_TABLE_BITS: u32 = 12;
+const LIVE_TABLE_SIZE: usize = 1 << LIVE_TABLE_BITS;
+
+/// The interface to get the current task ID, which is used to tag live
+/// allocations.
+///
+/// It must be implemented when the `stats` feature is enabled.
+#[crate_interface::def_interface]
+pub trait AllocStatsIf {
+    /// Returns the ID of the current task, or [`None`] if unavailable.
+    fn current_task_id() -> Option<u64>;
+}
+
+/// A snapshot of the statistics of the global allocator.
+#[derive(Debug, Clone, Copy)]
+pub struct AllocStats {
+    /// Number of successful byte allocations.
+    pub alloc_count: u64,
+    /// Number of byte deallocations.
+    pub dealloc_count: u64,
+    /// Number of failed byte allocations.
+    pub failed_count: u64,
+    /// Bytes requested by the allocations that are not deallocated yet.
+    pub used_bytes: usize,
+    /// The maximum of [`used_bytes`](Self::used_bytes) ever reached.
+    pub peak_used_bytes: usize,
+    /// Number of successful page allocations, including those to expand the
+    /// heap of the byte allocator.
+    pub page_alloc_count: u64,
+    /// Number of page deallocations.
+    pub page_dealloc_count: u64,
+    /// Number of allocated pages.
+    pub used_pages: usize,
+    /// The maximum of [`used_pages`](Self::used_pages) ever reached.
+    pub peak_used_pages: usize,
+    /// Number of byte allocations in each size bucket. See
+    /// [`bucket_size`](Self::bucket_size) for the bucket bounds.
+    pub histogram: [u64; NUM_SIZE_BUCKETS],
+    /// Number of live allocations that are not tracked because the table is
+    /// full.
+    pub untracked_count: u64,
+}
+
+impl AllocStats {
+    /// Returns the maximum size (inclusive) of allocations counted in bucket
+    /// `idx`, or [`None`] for the last bucket, which has no upper bound.
+    ///
+    /// Bucket `idx` counts the sizes in `(bucket_size(idx - 1), bucket_size(idx)]`.
+    pub const fn bucket_size(idx: usize) -> Option<usize> {
+        if idx + 1 < NUM_SIZE_BUCKETS {
+            Some(1 << (idx + MIN_BUCKET_SHIFT))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for AllocStats {
+    /// Formats the statistics in the format of `/proc/meminfo`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines = [
+            ("AllocCount", self.alloc_count),
+            ("DeallocCount", self.dealloc_count),
+            ("AllocFailed", self.failed_count),
+            ("PageAllocCount", self.page_alloc_count),
+            ("PageDeallocCount", self.page_dealloc_count),
+            ("Untracked", self.untracked_count),
+        ];
+        for (name, value) in lines {
+            write_meminfo_line(f, name, value, "")?;
+        }
+        let sizes = [
+            ("AllocUsed", self.used_bytes),
+            ("AllocPeak", self.peak_used_bytes),
+            ("PageUsed", self.used_pages * crate::PAGE_SIZE),
+            ("PagePeak", self.peak_used_pages * crate::PAGE_SIZE),
+        ];
+        for (name, bytes) in sizes {
+            write_meminfo_line(f, name, bytes / 1024, " kB")?;
+        }
+        let mut name = String::new();
+        for (idx, count) in self.histogram.iter().enumerate() {
+            name.clear();
+            match Self::bucket_size(idx) {
+                Some(size) if size >= 1024 => write!(name, "Size{}K", size / 1024)?,
+                Some(size) => write!(name, "Size{}", size)?,
+                None => name.push_str("SizeLarge"),
+            }
+            write_meminfo_line(f, &name, count, "")?;
+        }
+        Ok(())
+    }
+}
+
+/// A live allocation recorded when tracking is enabled.
+#[derive(Debug, Clone, Copy)]
+pub struct LiveAlloc {
+    /// Start address of the allocation.
+    pub addr: usize,
+    /// Requested size in bytes.
+    pub size: usize,
+    /// ID of the task that made the allocation.
+    pub task_id: Option<u64>,
+}
+
+impl LiveAlloc {
+    const EMPTY: Self = Self {
+        addr: 0,
+        size: 0,
+        task_id: None,
+    };
+}
+
+/// An open addressing hash table of live allocations, indexed by address.
+struct LiveTable {
+    slots: [LiveAlloc; LIVE_TABLE_SIZE],
+    len: usize,
+}
+
+impl LiveTable {
+    const fn new() -> Self {
+        Self {
+            slots: [LiveAlloc::EMPTY; LIVE_TABLE_SIZE],
+            len: 0,
+        }
+    }
+
+    fn hash(addr: usize) -> usize {
+        // Fibonacci hashing, takes the high bits of the product.
+        let h = ((addr >> MIN_BUCKET_SHIFT) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        (h >> (u64::BITS - LIVE_TABLE_BITS)) as usize
+    }
+
+    fn insert(&mut self, alloc: LiveAlloc) -> bool {
+        // Keep one slot empty, at which probes for untracked addresses stop.
+        if self.len == LIVE_TABLE_SIZE - 1 {
+            return false;
+        }
+        let mut idx = Self::hash(alloc.addr);
+        while self.slots[idx].addr != 0 {
+            idx = (idx + 1) % LIVE_TABLE_SIZE;
+        }
+        self.slots[idx] = alloc;
+        self.len += 1;
+        true
+    }
+
+    fn remove(&mut self, addr: usize) {
+        let mut idx = Self::hash(addr);
+        loop {
+            match self.slots[idx].addr {
+                0 => return, // not tracked
+                a if a == addr => break,
+                _ => idx = (idx + 1) % LIVE_TABLE_SIZE,
+            }
+        }
+        // Backward shift deletion: move later entries of the probe sequence
+        // into the hole, so that lookups never stop early.
+        let mut hole = idx;
+        let mut next = idx;
+        loop {
+            next = (next + 1) % LIVE_TABLE_SIZE;
+            let entry = self.slots[next];
+            if entry.addr == 0 {
+                break;
+            }
+            let home = Self::hash(entry.addr);
+            let dist_next = (next + LIVE_TABLE_SIZE - home) % LIVE_TABLE_SIZE;
+            let dist_hole = (hole + LIVE_TABLE_SIZE - home) % LIVE_TABLE_SIZE;
+            if dist_hole < dist_next {
+                self.slots[hole] = entry;
+                hole = next;
+            }
+        }
+        self.slots[hole] = LiveAlloc::EMPTY;
+        self.len -= 1;
+    }
+
+    fn clear(&mut self) {
+        self.slots.fill(LiveAlloc::EMPTY);
+        self.len = 0;
+    }
+}
+
+struct Counters {
+    alloc_count: AtomicU64,
+    dealloc_count: AtomicU64,
+    failed_count: AtomicU64,
+    used_bytes: AtomicUsize,
+    peak_used_bytes: AtomicUsize,
+    page_alloc_count: AtomicU64,
+    page_dealloc_count: AtomicU64,
+    used_pages: AtomicUsize,
+    peak_used_pages: AtomicUsize,
+    histogram: [AtomicU64; NUM_SIZE_BUCKETS],
+    untracked_count: AtomicU64,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+static COUNTERS: Counters = Counters {
+    alloc_count: AtomicU64::new(0),
+    dealloc_count: AtomicU64::new(0),
+    failed_count: AtomicU64::new(0),
+    used_bytes: AtomicUsize::new(0),
+    peak_used_bytes: AtomicUsize::new(0),
+    page_alloc_count: AtomicU64::new(0),
+    page_dealloc_count: AtomicU64::new(0),
+    used_pages: AtomicUsize::new(0),
+    peak_used_pages: AtomicUsize::new(0),
+    histogram: [ZERO; NUM_SIZE_BUCKETS],
+    untracked_count: AtomicU64::new(0),
+};
+
+static TRACKING: AtomicBool = AtomicBool::new(false);
+static LIVE_TABLE: SpinNoIrq<LiveTable> = SpinNoIrq::new(LiveTable::new());
+
+fn bucket_of(size: usize) -> usize {
+    let shift = size.max(1).next_power_of_two().trailing_zeros() as usize;
+    shift
+        .saturating_sub(MIN_BUCKET_SHIFT)
+        .min(NUM_SIZE_BUCKETS - 1)
+}
+
+pub(crate) fn record_alloc(res: &AllocResult<NonNull<u8>>, size: usize) {
+    let c = &COUNTERS;
+    let Ok(ptr) = res else {
+        c.failed_count.fetch_add(1, Ordering::Relaxed);
+        return;
+    };
+    c.alloc_count.fetch_add(1, Ordering::Relaxed);
+    c.histogram[bucket_of(size)].fetch_add(1, Ordering::Relaxed);
+    let used = c.used_bytes.fetch_add(size, Ordering::Relaxed) + size;
+    c.peak_used_bytes.fetch_max(used, Ordering::Relaxed);
+
+    if TRACKING.load(Ordering::Relaxed) {
+        let alloc = LiveAlloc {
+            addr: ptr.as_ptr() as usize,
+            size,
+            task_id: crate_interface::call_interface!(AllocStatsIf::current_task_id),
+        };
+        if !LIVE_TABLE.lock().insert(alloc) {
+            c.untracked_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+pub(crate) fn record_dealloc(ptr: NonNull<u8>, size: usize) {
+    let c = &COUNTERS;
+    c.dealloc_count.fetch_add(1, Ordering::Relaxed);
+    c.used_bytes.fetch_sub(size, Ordering::Relaxed);
+    if TRACKING.load(Ordering::Relaxed) {
+        LIVE_TABLE.lock().remove(ptr.as_ptr() as usize);
+    }
+}
+
+pub(crate) fn record_alloc_pages(res: &AllocResult<usize>, num_pages: usize) {
+    let c = &COUNTERS;
+    if res.is_ok() {
+        c.page_alloc_count.fetch_add(1, Ordering::Relaxed);
+        let used = c.used_pages.fetch_add(num_pages, Ordering::Relaxed) + num_pages;
+        c.peak_used_pages.fetch_max(used, Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn record_dealloc_pages(num_pages: usize) {
+    let c = &COUNTERS;
+    c.page_dealloc_count.fetch_add(1, Ordering::Relaxed);
+    c.used_pages.fetch_sub(num_pages, Ordering::Relaxed);
+}
+
+/// Returns a snapshot of the statistics of the global allocator.
+///
+/// The counters are updated independently, so they may be slightly
+/// inconsistent with each other if there are concurrent allocations.
+pub fn alloc_stats() -> AllocStats {
+    let c = &COUNTERS;
+    AllocStats {
+        alloc_count: c.alloc_count.load(Ordering::Relaxed),
+        dealloc_count: c.dealloc_count.load(Ordering::Relaxed),
+        failed_count: c.failed_count.load(Ordering::Relaxed),
+        used_bytes: c.used_bytes.load(Ordering::Relaxed),
+        peak_used_bytes: c.peak_used_bytes.load(Ordering::Relaxed),
+        page_alloc_count: c.page_alloc_count.load(Ordering::Relaxed),
+        page_dealloc_count: c.page_dealloc_count.load(Ordering::Relaxed),
+        used_pages: c.used_pages.load(Ordering::Relaxed),
+        peak_used_pages: c.peak_used_pages.load(Ordering::Relaxed),
+        histogram: core::array::from_fn(|i| c.histogram[i].load(Ordering::Relaxed)),
+        untracked_count: c.untracked_count.load(Ordering::Relaxed),
+    }
+}
+
+/// Enables or disables tracking of live allocations.
+///
+/// Only the allocations made after tracking is enabled are tracked, and the
+/// records are cleared when it's disabled. At most 4095 allocations can be
+/// tracked at the same time.
+pub fn set_live_tracking(enable: bool) {
+    let mut table = LIVE_TABLE.lock();
+    if !enable {
+        table.clear();
+    }
+    TRACKING.store(enable, Ordering::Relaxed);
+}
+
+/// Returns the tracked live allocations, which are possible memory leaks if
+/// they are not expected to be alive.
+///
+/// The result is empty if tracking is not enabled.
+pub fn live_allocs() -> Vec<LiveAlloc> {
+    // Allocate the buffer without holding the lock, as the allocation itself
+    // is tracked.
+    let len = LIVE_TABLE.lock().len;
+    let mut allocs = Vec::with_capacity(len + 16);
+    let table = LIVE_TABLE.lock();
+    let live = table.slots.iter().filter(|a| a.addr != 0);
+    for alloc in live.take(allocs.capacity()) {
+        allocs.push(*alloc);
+    }
+    drop(table);
+    allocs.sort_unstable_by_key(|a| a.addr);
+    allocs
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::boxed::Box;
+
+    fn live(addr: usize) -> LiveAlloc {
+        LiveAlloc {
+            addr,
+            size: 16,
+            task_id: None,
+        }
+    }
+
+    #[test]
+    fn test_full_live_table() {
+        let mut table = Box::new(LiveTable::new());
+        let addrs = (1..=LIVE_TABLE_SIZE).map(|i| i << MIN_BUCKET_SHIFT);
+        let tracked = addrs.filter(|&addr| table.insert(live(addr))).count();
+        assert_eq!(tracked, LIVE_TABLE_SIZE - 1);
+        assert_eq!(table.len, LIVE_TABLE_SIZE - 1);
+
+        // The last one was refused, and removing it must not hang.
+        table.remove(LIVE_TABLE_SIZE << MIN_BUCKET_SHIFT);
+        table.remove(0x1234_5670);
+        assert_eq!(table.len, LIVE_TABLE_SIZE - 1);
+
+        table.remove(1 << MIN_BUCKET_SHIFT);
+        assert_eq!(table.len, LIVE_TABLE_SIZE - 2);
+        assert!(table.insert(live(LIVE_TABLE_SIZE << MIN_BUCKET_SHIFT)));
+        for i in 2..=LIVE_TABLE_SIZE {
+            table.remove(i << MIN_BUCKET_SHIFT);
+        }
+        assert_eq!(table.len, 0);
+        assert!(table.slots.iter().all(|a| a.addr == 0));
+    }
+}
@@ -26,5 +26,24 @@ create_test_img() {
 	sudo umount mnt
 }
 
+create_ext4_img() {
+	local name=$1
+	local blkcount=$2
+	local src=$(mktemp -d)
+	for i in $(seq 1 1000); do
+	  echo "Rust is cool!" >>"$src/long.txt"
+	done
+	echo "Rust is cool!" >>"$src/short.txt"
+	mkdir -p "$src/very/long/path"
+	echo "Rust is cool!" >>"$src/very/long/path/test.txt"
+	mkdir -p "$src/very-long-dir-name"
+	echo "Rust is cool!" >>"$src/very-long-dir-name/very-long-file-name.txt"
+
+	rm -f "$name"
+	mkfs.ext4 -b 1024 -I 256 -O ^has_journal -L "Test!" -E root_owner=0:0 -d "$src" "$name" $blkcount
+	rm -rf "$src"
+}
+
 create_test_img "$CUR_DIR/fat16.img" 2500 16
 create_test_img "$CUR_DIR/fat32.img" 34000 32
+create_ext4_img "$CUR_DIR/ext4.img" 4096
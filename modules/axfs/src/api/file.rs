@@ -1,5 +1,6 @@
 use axio::{prelude::*, Result, SeekFrom};
 use core::fmt;
+use core::time::Duration;
 
 use crate::fops;
 
@@ -16,12 +17,20 @@ pub struct File {
 }
 
 /// Metadata information about a file.
-pub struct Metadata(fops::FileAttr);
+pub struct Metadata(pub(super) fops::FileAttr);
 
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone, Debug)]
 pub struct OpenOptions(fops::OpenOptions);
 
+/// Representation of the various timestamps on a file, which are durations
+/// since the Unix epoch.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FileTimes {
+    pub(super) accessed: Option<Duration>,
+    pub(super) modified: Option<Duration>,
+}
+
 impl OpenOptions {
     /// Creates a blank new set of options ready for configuration.
     pub const fn new() -> Self {
@@ -90,6 +99,11 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.0.is_symlink()
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
@@ -110,6 +124,21 @@ impl Metadata {
     pub const fn blocks(&self) -> u64 {
         self.0.blocks()
     }
+
+    /// Returns the last access time of this metadata.
+    pub const fn accessed(&self) -> Duration {
+        self.0.atime()
+    }
+
+    /// Returns the last modification time of this metadata.
+    pub const fn modified(&self) -> Duration {
+        self.0.mtime()
+    }
+
+    /// Returns the raw attributes of the node.
+    pub const fn raw_metadata(&self) -> &fops::FileAttr {
+        &self.0
+    }
 }
 
 impl fmt::Debug for Metadata {
@@ -123,6 +152,28 @@ impl fmt::Debug for Metadata {
     }
 }
 
+impl FileTimes {
+    /// Creates a new `FileTimes` with no times set.
+    pub const fn new() -> Self {
+        Self {
+            accessed: None,
+            modified: None,
+        }
+    }
+
+    /// Sets the last access time of a file.
+    pub const fn set_accessed(mut self, t: Duration) -> Self {
+        self.accessed = Some(t);
+        self
+    }
+
+    /// Sets the last modified time of a file.
+    pub const fn set_modified(mut self, t: Duration) -> Self {
+        self.modified = Some(t);
+        self
+    }
+}
+
 impl File {
     /// Attempts to open a file in read-only mode.
     pub fn open(path: &str) -> Result<Self> {
@@ -162,6 +213,21 @@ impl File {
     pub fn metadata(&self) -> Result<Metadata> {
         self.inner.get_attr().map(Metadata)
     }
+
+    /// Changes the permissions on the underlying file.
+    pub fn set_permissions(&self, perm: Permissions) -> Result<()> {
+        self.inner.set_perm(perm)
+    }
+
+    /// Changes the timestamps of the underlying file.
+    pub fn set_times(&self, times: FileTimes) -> Result<()> {
+        self.inner.set_times(times.accessed, times.modified)
+    }
+
+    /// Changes the modification time of the underlying file.
+    pub fn set_modified(&self, time: Duration) -> Result<()> {
+        self.set_times(FileTimes::new().set_modified(time))
+    }
 }
 
 impl Read for File {
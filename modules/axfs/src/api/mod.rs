@@ -4,7 +4,8 @@ mod dir;
 mod file;
 
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
-pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
+pub use self::file::{File, FileTimes, FileType, Metadata, OpenOptions, Permissions};
+pub use crate::root::MountFlags;
 
 use alloc::{string::String, vec::Vec};
 use axio::{self as io, prelude::*};
@@ -59,6 +60,48 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Query the metadata about a file without following symlinks.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    let node = crate::root::lookup_no_follow(path)?;
+    Ok(Metadata(node.get_attr()?))
+}
+
+/// Changes the permissions found on a file or a directory.
+pub fn set_permissions(path: &str, perm: Permissions) -> io::Result<()> {
+    crate::root::lookup_writable(path, true)?.set_perm(perm)
+}
+
+/// Changes the timestamps of a file or a directory.
+pub fn set_times(path: &str, times: FileTimes) -> io::Result<()> {
+    let node = crate::root::lookup_writable(path, true)?;
+    node.set_times(times.accessed, times.modified)
+}
+
+/// Changes the timestamps of a file, a directory or a symbolic link without
+/// following symlinks.
+pub fn set_symlink_times(path: &str, times: FileTimes) -> io::Result<()> {
+    let node = crate::root::lookup_writable(path, false)?;
+    node.set_times(times.accessed, times.modified)
+}
+
+/// Reads a symbolic link, returning the path that the link points to.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(path)
+}
+
+/// Creates a new symbolic link at `link`, which points to the `original`
+/// path.
+pub fn symlink(original: &str, link: &str) -> io::Result<()> {
+    crate::root::create_symlink(original, link)
+}
+
+/// Creates a new hard link at `link` to the `original` file.
+///
+/// This only works then the new path is in the same mount.
+pub fn hard_link(original: &str, link: &str) -> io::Result<()> {
+    crate::root::create_link(original, link)
+}
+
 /// Creates a new, empty directory at the provided path.
 pub fn create_dir(path: &str) -> io::Result<()> {
     DirBuilder::new().create(path)
@@ -72,18 +115,42 @@ pub fn create_dir_all(path: &str) -> io::Result<()> {
 
 /// Removes an empty directory.
 pub fn remove_dir(path: &str) -> io::Result<()> {
-    crate::root::remove_dir(None, path)
+    crate::root::remove_dir(path)
 }
 
 /// Removes a file from the filesystem.
 pub fn remove_file(path: &str) -> io::Result<()> {
-    crate::root::remove_file(None, path)
+    crate::root::remove_file(path)
 }
 
 /// Rename a file or directory to a new name.
 /// Delete the original file if `old` already exists.
 ///
-/// This only works then the new path is in the same mounted fs.
+/// This only works then the new path is in the same mount.
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     crate::root::rename(old, new)
 }
+
+/// Mounts the filesystem of type `fstype` on `source` at the directory
+/// `target`.
+///
+/// `source` is the block device for filesystems on disks, which are named
+/// `/dev/vda`, `/dev/vdb`, etc. in the probing order, and `/dev/vda` holds the
+/// root filesystem. Only the type of the root filesystem is supported on
+/// disks, and `source` is ignored by `ramfs` (or `tmpfs`), `devfs`, `proc`
+/// and `sysfs`.
+///
+/// With [`MountFlags::BIND`], the directory `source` is mounted at `target`
+/// and `fstype` is ignored. With [`MountFlags::REMOUNT`], only the flags of
+/// the mount at `target` are changed.
+pub fn mount(source: &str, target: &str, fstype: &str, flags: MountFlags) -> io::Result<()> {
+    crate::root::mount(source, target, fstype, flags)
+}
+
+/// Unmounts the filesystem mounted at `target`.
+///
+/// It fails with [`ResourceBusy`](io::Error::ResourceBusy) if there are other
+/// mounts under `target`, or the current directory is in it.
+pub fn umount(target: &str) -> io::Result<()> {
+    crate::root::umount(target)
+}
@@ -0,0 +1,184 @@
+//! A write-back cache of disk blocks.
+
+use alloc::{boxed::Box, collections::BTreeMap, collections::BTreeSet, vec::Vec};
+use axdriver::prelude::*;
+use axsync::Mutex;
+
+use crate::dev::BLOCK_SIZE;
+
+/// Maximum number of cached blocks (1 MB).
+const MAX_CACHED_BLOCKS: usize = 2048;
+
+struct CachedBlock {
+    block_id: u64,
+    /// Whether the block is used since the clock hand passed it.
+    referenced: bool,
+    data: Box<[u8; BLOCK_SIZE]>,
+}
+
+struct BlockCacheInner {
+    dev: AxBlockDevice,
+    blocks: Vec<CachedBlock>,
+    /// Maps block IDs to indices in `blocks`.
+    index: BTreeMap<u64, usize>,
+    /// IDs of the modified blocks that are not written back yet.
+    dirty: BTreeSet<u64>,
+    /// The index in `blocks` to look for the next victim.
+    hand: usize,
+}
+
+/// A cache of disk blocks shared by all users of a block device.
+///
+/// When the cache is full, a block that is not used recently is evicted by
+/// the clock algorithm. Writes only modify the cached blocks, which are
+/// written back to the device when evicted or by [`flush`](Self::flush).
+pub struct BlockCache {
+    inner: Mutex<BlockCacheInner>,
+}
+
+impl BlockCacheInner {
+    /// Returns the index of the cached block, reads it from the device if
+    /// it's not cached and `fill` is true.
+    fn get(&mut self, block_id: u64, fill: bool) -> DevResult<usize> {
+        if let Some(&idx) = self.index.get(&block_id) {
+            self.blocks[idx].referenced = true;
+            return Ok(idx);
+        }
+
+        let idx = if self.blocks.len() < MAX_CACHED_BLOCKS {
+            self.blocks.push(CachedBlock {
+                block_id,
+                referenced: false,
+                data: Box::new([0; BLOCK_SIZE]),
+            });
+            self.blocks.len() - 1
+        } else {
+            let idx = self.victim();
+            let victim_id = self.blocks[idx].block_id;
+            self.write_back(idx)?;
+            self.index.remove(&victim_id);
+            idx
+        };
+        let block = &mut self.blocks[idx];
+        if fill {
+            if let Err(e) = self.dev.read_block(block_id, block.data.as_mut_slice()) {
+                // Leave the slot unused, it will be evicted soon.
+                block.block_id = u64::MAX;
+                block.referenced = false;
+                return Err(e);
+            }
+        }
+        block.block_id = block_id;
+        block.referenced = true;
+        self.index.insert(block_id, idx);
+        Ok(idx)
+    }
+
+    /// Returns the index of the block to evict. The blocks that are
+    /// referenced get a second chance as the clock hand passes them.
+    fn victim(&mut self) -> usize {
+        loop {
+            if self.hand >= self.blocks.len() {
+                self.hand = 0;
+            }
+            let idx = self.hand;
+            self.hand += 1;
+            let block = &mut self.blocks[idx];
+            if !block.referenced {
+                return idx;
+            }
+            block.referenced = false;
+        }
+    }
+
+    /// Writes the block in slot `idx` back to the device if it's dirty.
+    fn write_back(&mut self, idx: usize) -> DevResult {
+        let block = &self.blocks[idx];
+        if self.dirty.remove(&block.block_id) {
+            if let Err(e) = self.dev.write_block(block.block_id, block.data.as_slice()) {
+                self.dirty.insert(block.block_id);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BlockCache {
+    /// Creates an empty cache for the block device.
+    pub fn new(dev: AxBlockDevice) -> Self {
+        assert_eq!(BLOCK_SIZE, dev.block_size());
+        Self {
+            inner: Mutex::new(BlockCacheInner {
+                dev,
+                blocks: Vec::new(),
+                index: BTreeMap::new(),
+                dirty: BTreeSet::new(),
+                hand: 0,
+            }),
+        }
+    }
+
+    /// Returns the number of blocks of the device.
+    pub fn num_blocks(&self) -> u64 {
+        self.inner.lock().dev.num_blocks()
+    }
+
+    /// Reads `buf.len()` bytes from the block at the given offset.
+    pub fn read(&self, block_id: u64, offset: usize, buf: &mut [u8]) -> DevResult {
+        let mut inner = self.inner.lock();
+        let idx = inner.get(block_id, true)?;
+        buf.copy_from_slice(&inner.blocks[idx].data[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    /// Writes `buf` to the block at the given offset.
+    ///
+    /// The block is not read from the device if it's entirely overwritten.
+    pub fn write(&self, block_id: u64, offset: usize, buf: &[u8]) -> DevResult {
+        let mut inner = self.inner.lock();
+        let idx = inner.get(block_id, buf.len() < BLOCK_SIZE)?;
+        inner.blocks[idx].data[offset..offset + buf.len()].copy_from_slice(buf);
+        inner.dirty.insert(block_id);
+        Ok(())
+    }
+
+    /// Writes all modified blocks back to the device, in the order of block
+    /// IDs.
+    pub fn flush(&self) -> DevResult {
+        let mut inner = self.inner.lock();
+        while let Some(&block_id) = inner.dirty.first() {
+            let idx = inner.index[&block_id];
+            inner.write_back(idx)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the unmodified blocks to free memory, returns the number of
+    /// bytes freed.
+    ///
+    /// It does not allocate memory, and does nothing if the cache is in use,
+    /// so that it can be called when the memory is exhausted.
+    pub fn shrink(&self) -> usize {
+        let Some(mut inner) = self.inner.try_lock() else {
+            return 0;
+        };
+        let inner = &mut *inner;
+        let old_len = inner.blocks.len();
+        for idx in (0..old_len).rev() {
+            let block_id = inner.blocks[idx].block_id;
+            if inner.dirty.contains(&block_id) {
+                continue;
+            }
+            inner.index.remove(&block_id);
+            inner.blocks.swap_remove(idx);
+            // The last block is moved to `idx`.
+            if let Some(moved) = inner.blocks.get(idx) {
+                if let Some(i) = inner.index.get_mut(&moved.block_id) {
+                    *i = idx;
+                }
+            }
+        }
+        (old_len - inner.blocks.len()) * BLOCK_SIZE
+    }
+}
@@ -1,31 +1,42 @@
+use alloc::{format, string::String, sync::Arc, vec::Vec};
 use axdriver::prelude::*;
+use axerrno::{ax_err, AxResult};
+use lazy_init::LazyInit;
 
-const BLOCK_SIZE: usize = 512;
+use crate::block_cache::BlockCache;
+
+pub(crate) const BLOCK_SIZE: usize = 512;
+
+/// The caches of all block devices, in the probing order.
+static DISKS: LazyInit<Vec<Arc<BlockCache>>> = LazyInit::new();
 
 /// A disk device with a cursor.
+///
+/// All accesses go through the [`BlockCache`] of the device, so
+/// [`flush`](Self::flush) must be called to write the modifications back.
 pub struct Disk {
     block_id: u64,
     offset: usize,
-    dev: AxBlockDevice,
+    cache: Arc<BlockCache>,
 }
 
 impl Disk {
-    /// Create a new disk.
-    pub fn new(dev: AxBlockDevice) -> Self {
-        assert_eq!(BLOCK_SIZE, dev.block_size());
+    /// Create a disk with the cache of a block device.
+    fn new(cache: Arc<BlockCache>) -> Self {
         Self {
             block_id: 0,
             offset: 0,
-            dev,
+            cache,
         }
     }
 
     /// Get the size of the disk.
     pub fn size(&self) -> u64 {
-        self.dev.num_blocks() * BLOCK_SIZE as u64
+        self.cache.num_blocks() * BLOCK_SIZE as u64
     }
 
     /// Get the position of the cursor.
+    #[cfg_attr(feature = "ext4", allow(dead_code))] // only used by fatfs
     pub fn position(&self) -> u64 {
         self.block_id * BLOCK_SIZE as u64 + self.offset as u64
     }
@@ -38,55 +49,84 @@ impl Disk {
 
     /// Read within one block, returns the number of bytes read.
     pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
-        let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev
-                .read_block(self.block_id, &mut buf[0..BLOCK_SIZE])?;
-            self.block_id += 1;
-            BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            buf[..count].copy_from_slice(&data[start..start + count]);
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
-        };
-        Ok(read_size)
+        let count = buf.len().min(BLOCK_SIZE - self.offset);
+        self.cache
+            .read(self.block_id, self.offset, &mut buf[..count])?;
+        self.advance(count);
+        Ok(count)
     }
 
     /// Write within one block, returns the number of bytes written.
     pub fn write_one(&mut self, buf: &[u8]) -> DevResult<usize> {
-        let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
+        let count = buf.len().min(BLOCK_SIZE - self.offset);
+        self.cache
+            .write(self.block_id, self.offset, &buf[..count])?;
+        self.advance(count);
+        Ok(count)
+    }
+
+    /// Write all modified blocks back to the device.
+    pub fn flush(&mut self) -> DevResult {
+        self.cache.flush()
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.offset += count;
+        if self.offset >= BLOCK_SIZE {
             self.block_id += 1;
-            BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            data[start..start + count].copy_from_slice(&buf[..count]);
-            self.dev.write_block(self.block_id, &data)?;
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
-        };
-        Ok(write_size)
+            self.offset -= BLOCK_SIZE;
+        }
+    }
+}
+
+/// Returns the name of the `idx`-th block device, i.e., `/dev/vda`,
+/// `/dev/vdb`, etc.
+pub(crate) fn disk_name(idx: usize) -> String {
+    format!("/dev/vd{}", (b'a' + idx as u8) as char)
+}
+
+/// Registers the block devices, the first one holds the root filesystem.
+pub(crate) fn init_disks(devs: Vec<AxBlockDevice>) {
+    for (i, dev) in devs.iter().enumerate() {
+        info!(
+            "  use block device {}: {:?}",
+            disk_name(i),
+            dev.device_name()
+        );
+    }
+    DISKS.init_by(
+        devs.into_iter()
+            .map(|dev| Arc::new(BlockCache::new(dev)))
+            .collect(),
+    );
+}
+
+fn disk_index(name: &str) -> Option<usize> {
+    (0..DISKS.len()).find(|&i| disk_name(i) == name)
+}
+
+/// Returns whether `name` is the name of a block device, see [`disk_name`].
+pub(crate) fn is_disk(name: &str) -> bool {
+    disk_index(name).is_some()
+}
+
+/// Opens the block device with the given name, see [`disk_name`].
+pub(crate) fn open_disk(name: &str) -> AxResult<Disk> {
+    match disk_index(name) {
+        Some(i) => Ok(Disk::new(DISKS[i].clone())),
+        None => ax_err!(NotFound, "no such block device"),
     }
 }
+
+/// Writes all modified blocks of all block devices back.
+pub(crate) fn flush_disks() -> DevResult {
+    DISKS.try_get().map_or(Ok(()), |disks| {
+        disks.iter().try_for_each(|cache| cache.flush())
+    })
+}
+
+/// Drops unmodified blocks of all block devices, returns the number of bytes
+/// freed.
+pub(crate) fn shrink_disks() -> usize {
+    DISKS.iter().map(|cache| cache.shrink()).sum()
+}
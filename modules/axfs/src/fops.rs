@@ -1,10 +1,15 @@
 //! Low-level filesystem operations.
 
+use alloc::{format, string::String};
 use axerrno::{ax_err, ax_err_type, AxResult};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
+use axsync::Mutex;
 use capability::{Cap, WithCap};
 use core::fmt;
+use core::time::Duration;
+
+use crate::page_cache::{self, PageCache};
 
 #[cfg(feature = "myfs")]
 pub use crate::dev::Disk;
@@ -21,16 +26,26 @@ pub type FileAttr = axfs_vfs::VfsNodeAttr;
 pub type FilePerm = axfs_vfs::VfsNodePerm;
 
 /// An opened file object, with open permissions and a cursor.
+///
+/// Reads of files on the block device are cached in a few pages per opened
+/// file.
 pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
     offset: u64,
+    /// Whether writes to the node need to invalidate the page caches.
+    cacheable: bool,
+    page_cache: Option<Mutex<PageCache>>,
+    /// Whether the file is on a read-only mount.
+    read_only_mount: bool,
 }
 
 /// An opened directory object, with open permissions and a cursor for
 /// [`read_dir`](Directory::read_dir).
 pub struct Directory {
     node: WithCap<VfsNodeRef>,
+    /// The absolute path of the directory, ends with `/`.
+    path: String,
     entry_idx: usize,
 }
 
@@ -112,24 +127,24 @@ impl OpenOptions {
 }
 
 impl File {
-    fn _open_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn _open_at(path: &str, opts: &OpenOptions) -> AxResult<Self> {
         debug!("open file: {} {:?}", path, opts);
         if !opts.is_valid() {
             return ax_err!(InvalidInput);
         }
 
-        let node_option = crate::root::lookup(dir, path);
-        let node = if opts.create || opts.create_new {
+        let node_option = crate::root::lookup_on_mount(path, true);
+        let (node, read_only_mount) = if opts.create || opts.create_new {
             match node_option {
-                Ok(node) => {
+                Ok(found) => {
                     // already exists
                     if opts.create_new {
                         return ax_err!(AlreadyExists);
                     }
-                    node
+                    found
                 }
                 // not exists, create new
-                Err(VfsError::NotFound) => crate::root::create_file(dir, path)?,
+                Err(VfsError::NotFound) => (crate::root::create_file(path)?, false),
                 Err(e) => return Err(e),
             }
         } else {
@@ -143,31 +158,43 @@ impl File {
         {
             return ax_err!(IsADirectory);
         }
+        if read_only_mount && (opts.write || opts.append || opts.truncate) {
+            return ax_err!(ReadOnlyFilesystem);
+        }
         let access_cap = opts.into();
         if !perm_to_cap(attr.perm()).contains(access_cap) {
             return ax_err!(PermissionDenied);
         }
 
         node.open()?;
+        let cacheable = page_cache::is_cacheable(&node);
         if opts.truncate {
             node.truncate(0)?;
+            if cacheable {
+                page_cache::invalidate_all();
+            }
         }
+        let page_cache = (cacheable && opts.read).then(|| Mutex::new(PageCache::new()));
         Ok(Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
             offset: 0,
+            cacheable,
+            page_cache,
+            read_only_mount,
         })
     }
 
     /// Opens a file at the path relative to the current directory. Returns a
     /// [`File`] object.
     pub fn open(path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_at(None, path, opts)
+        Self::_open_at(path, opts)
     }
 
     /// Truncates the file to the specified size.
     pub fn truncate(&self, size: u64) -> AxResult {
         self.node.access(Cap::WRITE)?.truncate(size)?;
+        self.invalidate_caches();
         Ok(())
     }
 
@@ -176,8 +203,7 @@ impl File {
     ///
     /// After the read, the cursor will be advanced by the number of bytes read.
     pub fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        let node = self.node.access(Cap::READ)?;
-        let read_len = node.read_at(self.offset, buf)?;
+        let read_len = self.read_at(self.offset, buf)?;
         self.offset += read_len as u64;
         Ok(read_len)
     }
@@ -187,7 +213,12 @@ impl File {
     /// It does not update the file cursor.
     pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::READ)?;
-        let read_len = node.read_at(offset, buf)?;
+        let read_len = match &self.page_cache {
+            Some(cache) if PageCache::should_cache(buf.len()) => {
+                cache.lock().read_at(node, offset, buf)?
+            }
+            _ => node.read_at(offset, buf)?,
+        };
         Ok(read_len)
     }
 
@@ -202,6 +233,7 @@ impl File {
             self.offset = self.get_attr()?.size();
         };
         let write_len = node.write_at(self.offset, buf)?;
+        self.invalidate_caches();
         self.offset += write_len as u64;
         Ok(write_len)
     }
@@ -213,6 +245,7 @@ impl File {
     pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
+        self.invalidate_caches();
         Ok(write_len)
     }
 
@@ -240,10 +273,89 @@ impl File {
     pub fn get_attr(&self) -> AxResult<FileAttr> {
         self.node.access(Cap::empty())?.get_attr()
     }
+
+    /// Sets the permission of the file.
+    pub fn set_perm(&self, perm: FilePerm) -> AxResult {
+        self.check_writable_mount()?;
+        self.node.access(Cap::empty())?.set_perm(perm)
+    }
+
+    /// Sets the access and modification times of the file, a time of `None`
+    /// is left unchanged.
+    pub fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> AxResult {
+        self.check_writable_mount()?;
+        self.node.access(Cap::empty())?.set_times(atime, mtime)
+    }
+
+    /// Returns a [`FileHandle`] to the node of the file, with the same
+    /// permissions as the file.
+    pub fn handle(&self) -> AxResult<FileHandle> {
+        let node = unsafe { self.node.access_unchecked() }.clone();
+        node.open()?;
+        Ok(FileHandle {
+            node: WithCap::new(node, self.node.cap()),
+            cacheable: self.cacheable,
+        })
+    }
+
+    fn check_writable_mount(&self) -> AxResult {
+        if self.read_only_mount {
+            ax_err!(ReadOnlyFilesystem)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn invalidate_caches(&self) {
+        if self.cacheable {
+            page_cache::invalidate_all();
+        }
+    }
+}
+
+/// A handle to the node of an opened file, which reads and writes at any
+/// position without the file and its cursor, e.g., for memory mappings.
+///
+/// It's created by [`File::handle`], and keeps the node open until dropped.
+pub struct FileHandle {
+    node: WithCap<VfsNodeRef>,
+    cacheable: bool,
+}
+
+impl FileHandle {
+    /// Whether the file is opened for reading.
+    pub fn is_readable(&self) -> bool {
+        self.node.can_access(Cap::READ)
+    }
+
+    /// Whether the file is opened for writing.
+    pub fn is_writable(&self) -> bool {
+        self.node.can_access(Cap::WRITE)
+    }
+
+    /// Reads the file at the given position. Returns the number of bytes read.
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
+        self.node.access(Cap::READ)?.read_at(offset, buf)
+    }
+
+    /// Writes the file at the given position. Returns the number of bytes
+    /// written.
+    pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
+        let write_len = self.node.access(Cap::WRITE)?.write_at(offset, buf)?;
+        if self.cacheable {
+            page_cache::invalidate_all();
+        }
+        Ok(write_len)
+    }
+
+    /// Gets the file attributes.
+    pub fn get_attr(&self) -> AxResult<FileAttr> {
+        self.node.access(Cap::empty())?.get_attr()
+    }
 }
 
 impl Directory {
-    fn _open_dir_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn _open_dir_at(path: &str, opts: &OpenOptions) -> AxResult<Self> {
         debug!("open dir: {}", path);
         if !opts.read {
             return ax_err!(InvalidInput);
@@ -252,11 +364,15 @@ impl Directory {
             return ax_err!(InvalidInput);
         }
 
-        let node = crate::root::lookup(dir, path)?;
+        let mut path = crate::root::resolve_path(path, true)?;
+        let node = crate::root::lookup(&path)?;
         let attr = node.get_attr()?;
         if !attr.is_dir() {
             return ax_err!(NotADirectory);
         }
+        if !path.ends_with('/') {
+            path.push('/');
+        }
         let access_cap = opts.into();
         if !perm_to_cap(attr.perm()).contains(access_cap) {
             return ax_err!(PermissionDenied);
@@ -265,54 +381,57 @@ impl Directory {
         node.open()?;
         Ok(Self {
             node: WithCap::new(node, access_cap),
+            path,
             entry_idx: 0,
         })
     }
 
-    fn access_at(&self, path: &str) -> AxResult<Option<&VfsNodeRef>> {
-        if path.starts_with('/') {
-            Ok(None)
+    /// Returns the path relative to this directory as an absolute path.
+    fn access_at(&self, path: &str) -> AxResult<String> {
+        if path.is_empty() || path.starts_with('/') {
+            Ok(path.into())
         } else {
-            Ok(Some(self.node.access(Cap::EXECUTE)?))
+            self.node.access(Cap::EXECUTE)?;
+            Ok(format!("{}{}", self.path, path))
         }
     }
 
     /// Opens a directory at the path relative to the current directory.
     /// Returns a [`Directory`] object.
     pub fn open_dir(path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_dir_at(None, path, opts)
+        Self::_open_dir_at(path, opts)
     }
 
     /// Opens a directory at the path relative to this directory. Returns a
     /// [`Directory`] object.
     pub fn open_dir_at(&self, path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_dir_at(self.access_at(path)?, path, opts)
+        Self::_open_dir_at(&self.access_at(path)?, opts)
     }
 
     /// Opens a file at the path relative to this directory. Returns a [`File`]
     /// object.
     pub fn open_file_at(&self, path: &str, opts: &OpenOptions) -> AxResult<File> {
-        File::_open_at(self.access_at(path)?, path, opts)
+        File::_open_at(&self.access_at(path)?, opts)
     }
 
     /// Creates an empty file at the path relative to this directory.
     pub fn create_file(&self, path: &str) -> AxResult<VfsNodeRef> {
-        crate::root::create_file(self.access_at(path)?, path)
+        crate::root::create_file(&self.access_at(path)?)
     }
 
     /// Creates an empty directory at the path relative to this directory.
     pub fn create_dir(&self, path: &str) -> AxResult {
-        crate::root::create_dir(self.access_at(path)?, path)
+        crate::root::create_dir(&self.access_at(path)?)
     }
 
     /// Removes a file at the path relative to this directory.
     pub fn remove_file(&self, path: &str) -> AxResult {
-        crate::root::remove_file(self.access_at(path)?, path)
+        crate::root::remove_file(&self.access_at(path)?)
     }
 
     /// Removes a directory at the path relative to this directory.
     pub fn remove_dir(&self, path: &str) -> AxResult {
-        crate::root::remove_dir(self.access_at(path)?, path)
+        crate::root::remove_dir(&self.access_at(path)?)
     }
 
     /// Reads directory entries starts from the current position into the
@@ -341,6 +460,19 @@ impl Directory {
 impl Drop for File {
     fn drop(&mut self) {
         unsafe { self.node.access_unchecked().release().ok() };
+        if self.cacheable && self.node.can_access(Cap::WRITE) {
+            // Write the modified blocks back on close.
+            crate::sync().ok();
+        }
+    }
+}
+
+impl Drop for FileHandle {
+    fn drop(&mut self) {
+        unsafe { self.node.access_unchecked().release().ok() };
+        if self.cacheable && self.is_writable() {
+            crate::sync().ok();
+        }
     }
 }
 
@@ -0,0 +1,41 @@
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::dev::Disk;
+
+pub use axfs_ext4::{Ext4FileSystem, FileNode};
+
+impl axfs_ext4::BlockDevice for Disk {
+    fn read_at(&mut self, pos: u64, mut buf: &mut [u8]) -> VfsResult {
+        if pos + buf.len() as u64 > self.size() {
+            return Err(VfsError::UnexpectedEof);
+        }
+        self.set_position(pos);
+        while !buf.is_empty() {
+            match self.read_one(buf) {
+                Ok(0) => return Err(VfsError::UnexpectedEof),
+                Ok(n) => buf = &mut buf[n..],
+                Err(_) => return Err(VfsError::Io),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_at(&mut self, pos: u64, mut buf: &[u8]) -> VfsResult {
+        if pos + buf.len() as u64 > self.size() {
+            return Err(VfsError::UnexpectedEof);
+        }
+        self.set_position(pos);
+        while !buf.is_empty() {
+            match self.write_one(buf) {
+                Ok(0) => return Err(VfsError::WriteZero),
+                Ok(n) => buf = &buf[n..],
+                Err(_) => return Err(VfsError::Io),
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> VfsResult {
+        Disk::flush(self).map_err(|_| VfsError::Io)
+    }
+}
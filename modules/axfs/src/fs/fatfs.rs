@@ -1,22 +1,50 @@
 use alloc::sync::Arc;
 use core::cell::UnsafeCell;
+use core::time::Duration;
 
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use fatfs::{Date, DateTime, Time, TimeProvider};
+use fatfs::{Dir, File, LossyOemCpConverter, Read, Seek, SeekFrom, Write};
 
 use crate::dev::Disk;
 
 const BLOCK_SIZE: usize = 512;
 
+/// Seconds from the Unix epoch to 1980-01-01, the earliest FAT timestamp.
+const FAT_MIN_SECS: u64 = 315_532_800;
+/// Seconds from the Unix epoch to 2107-12-31 23:59:59, the latest FAT
+/// timestamp.
+const FAT_MAX_SECS: u64 = 4_354_819_199;
+
 pub struct FatFileSystem {
-    inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
+    inner: fatfs::FileSystem<Disk, FatTimeProvider, LossyOemCpConverter>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+pub struct FileWrapper<'a> {
+    file: Mutex<File<'a, Disk, FatTimeProvider, LossyOemCpConverter>>,
+    times: Mutex<Times>,
+}
+
+pub struct DirWrapper<'a> {
+    dir: Dir<'a, Disk, FatTimeProvider, LossyOemCpConverter>,
+    times: Times,
+}
+
+/// Provides the time of [`axfs_vfs::current_time`] to timestamp the
+/// directory entries.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FatTimeProvider;
+
+/// The access and modification times in a directory entry. FAT has no
+/// change time, the modification time is used instead.
+#[derive(Clone, Copy, Default)]
+struct Times {
+    accessed: Duration,
+    modified: Duration,
+}
 
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
@@ -30,35 +58,80 @@ impl FatFileSystem {
     pub fn new(mut disk: Disk) -> Self {
         let opts = fatfs::FormatVolumeOptions::new();
         fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
-            inner,
-            root_dir: UnsafeCell::new(None),
-        }
+        Self::open(disk).expect("failed to initialize FAT filesystem")
     }
 
     #[cfg(not(feature = "use-ramdisk"))]
     pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+        Self::open(disk).expect("failed to initialize FAT filesystem")
+    }
+
+    /// Opens the FAT filesystem on the disk without formatting it.
+    pub fn open(disk: Disk) -> VfsResult<Self> {
+        let opts = fatfs::FsOptions::new().time_provider(FatTimeProvider);
+        let inner = fatfs::FileSystem::new(disk, opts).map_err(as_vfs_err)?;
+        Ok(Self {
             inner,
             root_dir: UnsafeCell::new(None),
-        }
+        })
     }
 
     pub fn init(&'static self) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        let root_dir = Self::new_dir(self.inner.root_dir(), Times::default());
+        unsafe { *self.root_dir.get() = Some(root_dir) }
+    }
+
+    /// Initializes the filesystem to be mounted. It is never freed, even after
+    /// unmounted, as its nodes borrow it.
+    pub fn into_mountable(self) -> Arc<Self> {
+        let fs = Arc::new(self);
+        let leaked: &'static Self = unsafe { &*Arc::into_raw(fs.clone()) };
+        leaked.init();
+        fs
     }
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+    fn new_file(
+        file: File<'_, Disk, FatTimeProvider, LossyOemCpConverter>,
+        times: Times,
+    ) -> Arc<FileWrapper> {
+        Arc::new(FileWrapper {
+            file: Mutex::new(file),
+            times: Mutex::new(times),
+        })
     }
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+    fn new_dir(
+        dir: Dir<'_, Disk, FatTimeProvider, LossyOemCpConverter>,
+        times: Times,
+    ) -> Arc<DirWrapper> {
+        Arc::new(DirWrapper { dir, times })
+    }
+}
+
+impl Times {
+    /// Reads the times in the directory entry of `path` in `dir`, they are
+    /// zero if there is no entry (e.g., `..` of the root directory).
+    fn of_entry(dir: &Dir<'_, Disk, FatTimeProvider, LossyOemCpConverter>, path: &str) -> Self {
+        let (parent, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => match dir.open_dir(parent) {
+                Ok(parent) => (parent, name),
+                Err(_) => return Self::default(),
+            },
+            None => (dir.clone(), path),
+        };
+        parent
+            .iter()
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_name().eq_ignore_ascii_case(name))
+            .map_or_else(Self::default, |entry| Self {
+                accessed: from_fat_date(entry.accessed()),
+                modified: from_fat_date_time(entry.modified()),
+            })
+    }
+
+    fn set_attr_times(&self, attr: &mut VfsNodeAttr) {
+        attr.set_times(self.accessed, self.modified, self.modified);
     }
 }
 
@@ -66,29 +139,62 @@ impl VfsNodeOps for FileWrapper<'static> {
     axfs_vfs::impl_vfs_non_dir_default! {}
 
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        let size = self.0.lock().seek(SeekFrom::End(0)).map_err(as_vfs_err)?;
+        let size = self
+            .file
+            .lock()
+            .seek(SeekFrom::End(0))
+            .map_err(as_vfs_err)?;
         let blocks = (size + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64;
         // FAT fs doesn't support permissions, we just set everything to 755
         let perm = VfsNodePerm::from_bits_truncate(0o755);
-        Ok(VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks))
+        let mut attr = VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks);
+        self.times.lock().set_attr_times(&mut attr);
+        Ok(attr)
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        let mut file = self.file.lock();
+        let mut times = self.times.lock();
+        if let Some(atime) = atime {
+            let date = to_fat_date_time(atime).date;
+            file.set_accessed(date);
+            times.accessed = from_fat_date(date);
+        }
+        if let Some(mtime) = mtime {
+            let date_time = to_fat_date_time(mtime);
+            file.set_modified(date_time);
+            times.modified = from_fat_date_time(date_time);
+        }
+        Ok(())
     }
 
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
         file.read(buf).map_err(as_vfs_err)
     }
 
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.write(buf).map_err(as_vfs_err)
+        let len = file.write(buf).map_err(as_vfs_err)?;
+        // The entry is timestamped by `FatTimeProvider` on writes.
+        self.times.lock().modified = from_fat_date_time(FatTimeProvider.get_current_date_time());
+        Ok(len)
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.truncate().map_err(as_vfs_err)
+        file.truncate().map_err(as_vfs_err)?;
+        let now = FatTimeProvider.get_current_date_time();
+        file.set_modified(now);
+        self.times.lock().modified = from_fat_date_time(now);
+        Ok(())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.file.lock().flush().map_err(as_vfs_err)
     }
 }
 
@@ -97,18 +203,20 @@ impl VfsNodeOps for DirWrapper<'static> {
 
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
         // FAT fs doesn't support permissions, we just set everything to 755
-        Ok(VfsNodeAttr::new(
+        let mut attr = VfsNodeAttr::new(
             VfsNodePerm::from_bits_truncate(0o755),
             VfsNodeType::Dir,
             BLOCK_SIZE as u64,
             1,
-        ))
+        );
+        self.times.set_attr_times(&mut attr);
+        Ok(attr)
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
-        self.0
-            .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+        self.dir.open_dir("..").map_or(None, |dir| {
+            Some(FatFileSystem::new_dir(dir, Times::default()))
+        })
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -122,10 +230,12 @@ impl VfsNodeOps for DirWrapper<'static> {
         }
 
         // TODO: use `fatfs::Dir::find_entry`, but it's not public.
-        if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
-        } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+        if let Ok(file) = self.dir.open_file(path) {
+            let times = Times::of_entry(&self.dir, path);
+            Ok(FatFileSystem::new_file(file, times))
+        } else if let Ok(dir) = self.dir.open_dir(path) {
+            let times = Times::of_entry(&self.dir, path);
+            Ok(FatFileSystem::new_dir(dir, times))
         } else {
             Err(VfsError::NotFound)
         }
@@ -143,11 +253,11 @@ impl VfsNodeOps for DirWrapper<'static> {
 
         match ty {
             VfsNodeType::File => {
-                self.0.create_file(path).map_err(as_vfs_err)?;
+                self.dir.create_file(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             VfsNodeType::Dir => {
-                self.0.create_dir(path).map_err(as_vfs_err)?;
+                self.dir.create_dir(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             _ => Err(VfsError::Unsupported),
@@ -161,11 +271,11 @@ impl VfsNodeOps for DirWrapper<'static> {
         if let Some(rest) = path.strip_prefix("./") {
             return self.remove(rest);
         }
-        self.0.remove(path).map_err(as_vfs_err)
+        self.dir.remove(path).map_err(as_vfs_err)
     }
 
     fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
-        let mut iter = self.0.iter().skip(start_idx);
+        let mut iter = self.dir.iter().skip(start_idx);
         for (i, out_entry) in dirents.iter_mut().enumerate() {
             let x = iter.next();
             match x {
@@ -192,8 +302,8 @@ impl VfsNodeOps for DirWrapper<'static> {
             src_path, dst_path
         );
 
-        self.0
-            .rename(src_path, &self.0, dst_path)
+        self.dir
+            .rename(src_path, &self.dir, dst_path)
             .map_err(as_vfs_err)
     }
 }
@@ -205,6 +315,16 @@ impl VfsOps for FatFileSystem {
     }
 }
 
+impl TimeProvider for FatTimeProvider {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        to_fat_date_time(axfs_vfs::current_time())
+    }
+}
+
 impl fatfs::IoBase for Disk {
     type Error = ();
 }
@@ -243,7 +363,7 @@ impl Write for Disk {
         Ok(write_len)
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        Disk::flush(self).map_err(|_| ())
     }
 }
 
@@ -281,3 +401,63 @@ const fn as_vfs_err(err: fatfs::Error<()>) -> VfsError {
         _ => VfsError::Io,
     }
 }
+
+/// Converts the time since the Unix epoch to a FAT timestamp, which is
+/// clamped to the years 1980 to 2107.
+fn to_fat_date_time(time: Duration) -> DateTime {
+    let (secs, millis) = match time.as_secs() {
+        secs @ FAT_MIN_SECS..=FAT_MAX_SECS => (secs, time.subsec_millis()),
+        secs => (secs.clamp(FAT_MIN_SECS, FAT_MAX_SECS), 0),
+    };
+    let (year, month, day) = civil_from_days(secs / 86400);
+    let secs_of_day = secs % 86400;
+    let date = Date::new(year as u16, month as u16, day as u16);
+    let time = Time::new(
+        (secs_of_day / 3600) as u16,
+        (secs_of_day / 60 % 60) as u16,
+        (secs_of_day % 60) as u16,
+        millis as u16,
+    );
+    DateTime::new(date, time)
+}
+
+fn from_fat_date(date: Date) -> Duration {
+    let days = days_from_civil(date.year as i64, date.month as i64, date.day as i64);
+    Duration::from_secs(days.max(0) as u64 * 86400)
+}
+
+fn from_fat_date_time(date_time: DateTime) -> Duration {
+    let time = date_time.time;
+    let secs_of_day = time.hour as u64 * 3600 + time.min as u64 * 60 + time.sec as u64;
+    from_fat_date(date_time.date)
+        + Duration::from_secs(secs_of_day)
+        + Duration::from_millis(time.millis as u64)
+}
+
+/// Returns the number of days since 1970-01-01 of a date in the proleptic
+/// Gregorian calendar.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Returns the date of the number of days since 1970-01-01, the inverse of
+/// [`days_from_civil`].
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let doe = days % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
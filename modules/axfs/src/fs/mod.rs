@@ -1,6 +1,14 @@
+use alloc::sync::Arc;
+use axerrno::{ax_err, AxResult};
+use axfs_vfs::VfsOps;
+
+use crate::dev::Disk;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "myfs")] {
         pub mod myfs;
+    } else if #[cfg(feature = "ext4")] {
+        pub mod ext4;
     } else if #[cfg(feature = "fatfs")] {
         pub mod fatfs;
     }
@@ -11,3 +19,41 @@ pub use axfs_devfs as devfs;
 
 #[cfg(feature = "ramfs")]
 pub use axfs_ramfs as ramfs;
+
+#[cfg(feature = "procfs")]
+pub mod procfs;
+
+/// Creates the root filesystem on the first block device.
+pub(crate) fn new_root_fs(disk: Disk) -> Arc<dyn VfsOps> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "myfs")] { // override the default filesystem
+            myfs::new_myfs(disk)
+        } else if #[cfg(feature = "ext4")] {
+            Arc::new(ext4::Ext4FileSystem::new(disk).expect("failed to initialize ext4 filesystem"))
+        } else if #[cfg(feature = "fatfs")] {
+            fatfs::FatFileSystem::new(disk).into_mountable()
+        }
+    }
+}
+
+/// Opens the filesystem of type `fstype` on a block device to be mounted.
+/// Only the type of the root filesystem is supported.
+pub(crate) fn open_disk_fs(fstype: &str, disk: Disk) -> AxResult<Arc<dyn VfsOps>> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "myfs")] {
+            if fstype == "myfs" {
+                return Ok(myfs::new_myfs(disk));
+            }
+        } else if #[cfg(feature = "ext4")] {
+            if matches!(fstype, "ext2" | "ext3" | "ext4") {
+                return Ok(Arc::new(ext4::Ext4FileSystem::new(disk)?));
+            }
+        } else if #[cfg(feature = "fatfs")] {
+            if matches!(fstype, "vfat" | "fat") {
+                return Ok(fatfs::FatFileSystem::open(disk)?.into_mountable());
+            }
+        }
+    }
+    let _ = disk;
+    ax_err!(InvalidInput, "unknown filesystem type")
+}
@@ -0,0 +1,282 @@
+//! Dynamic files in procfs, i.e., `/proc/meminfo` and `/proc/<tid>/status`.
+//!
+//! The static part of procfs is a [`RamFileSystem`], and the dynamic files
+//! are rendered when they are read. The task directories are generated on the
+//! fly from the task registry of `axtask`.
+
+#[cfg(feature = "multitask")]
+use alloc::format;
+use alloc::{boxed::Box, string::String, sync::Arc};
+use axfs_ramfs::RamFileSystem;
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+
+/// The procfs, which adds `meminfo` and a directory for each task to the root
+/// directory of a [`RamFileSystem`].
+pub struct ProcFileSystem {
+    base: RamFileSystem,
+    root: Arc<ProcRootDir>,
+}
+
+impl ProcFileSystem {
+    /// Creates a procfs with the static files in `base`.
+    pub fn new(base: RamFileSystem) -> Self {
+        let root = Arc::new(ProcRootDir {
+            base: base.root_dir(),
+        });
+        Self { base, root }
+    }
+}
+
+impl VfsOps for ProcFileSystem {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.base.mount(path, mount_point)
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+struct ProcRootDir {
+    base: VfsNodeRef,
+}
+
+impl ProcRootDir {
+    /// Returns the number of entries in the static root directory.
+    fn base_entries(&self) -> VfsResult<usize> {
+        let mut buf = [const { VfsDirEntry::default() }; 16];
+        let mut count = 0;
+        loop {
+            let n = self.base.read_dir(count, &mut buf)?;
+            count += n;
+            if n < buf.len() {
+                return Ok(count);
+            }
+        }
+    }
+}
+
+impl VfsNodeOps for ProcRootDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.base.get_attr()
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.base.parent()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node: VfsNodeRef = match name {
+            "meminfo" => Arc::new(GenFile::new(render_meminfo)),
+            #[cfg(feature = "multitask")]
+            _ if name.parse().is_ok_and(|id| axtask::find_task(id).is_some()) => {
+                Arc::new(TaskDir {
+                    id: name.parse().unwrap(),
+                    root: self,
+                })
+            }
+            _ => return self.base.clone().lookup(path),
+        };
+        match rest {
+            Some(rest) => node.lookup(rest),
+            None => Ok(node),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let nr_base = self.base_entries()?;
+        let mut n = 0;
+        if start_idx < nr_base {
+            n = self.base.read_dir(start_idx, dirents)?;
+        }
+        // The dynamic entries follow the static ones.
+        let skip = (start_idx + n).saturating_sub(nr_base);
+        if skip == 0 && n < dirents.len() {
+            dirents[n] = VfsDirEntry::new("meminfo", VfsNodeType::File);
+            n += 1;
+        }
+        #[cfg(feature = "multitask")]
+        {
+            let tasks = axtask::all_tasks();
+            let mut tasks = tasks.iter().skip(skip.saturating_sub(1));
+            for ent in dirents[n..].iter_mut() {
+                match tasks.next() {
+                    Some(task) => {
+                        let name = format!("{}", task.id().as_u64());
+                        *ent = VfsDirEntry::new(&name, VfsNodeType::Dir);
+                        n += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+        Ok(n)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        self.base.create(path, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        self.base.remove(path)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        self.base.rename(src_path, dst_path)
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// The directory `/proc/<tid>`.
+#[cfg(feature = "multitask")]
+struct TaskDir {
+    id: u64,
+    root: Arc<ProcRootDir>,
+}
+
+#[cfg(feature = "multitask")]
+impl VfsNodeOps for TaskDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o555),
+            VfsNodeType::Dir,
+            0,
+            0,
+        ))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        Some(self.root.clone())
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node: VfsNodeRef = match name {
+            "" | "." => self.clone(),
+            ".." => self.root.clone(),
+            "status" => {
+                let id = self.id;
+                Arc::new(GenFile::new(move || render_task_status(id)))
+            }
+            _ => return Err(VfsError::NotFound),
+        };
+        match rest {
+            Some(rest) => node.lookup(rest),
+            None => Ok(node),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let entries = [
+            (".", VfsNodeType::Dir),
+            ("..", VfsNodeType::Dir),
+            ("status", VfsNodeType::File),
+        ];
+        let mut n = 0;
+        for (ent, (name, ty)) in dirents.iter_mut().zip(entries.iter().skip(start_idx)) {
+            *ent = VfsDirEntry::new(name, *ty);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// Renders `/proc/meminfo`.
+fn render_meminfo() -> VfsResult<String> {
+    let mut content = String::new();
+    axalloc::global_allocator()
+        .write_meminfo(&mut content)
+        .map_err(|_| VfsError::Io)?;
+    Ok(content)
+}
+
+/// Renders `/proc/<tid>/status`.
+#[cfg(feature = "multitask")]
+fn render_task_status(id: u64) -> VfsResult<String> {
+    let info = axtask::find_task(id).ok_or(VfsError::NotFound)?.info();
+    Ok(format!(
+        "Name:\t{}\n\
+             State:\t{}\n\
+             Tid:\t{}\n\
+             Cpu:\t{}\n\
+             Cpus_allowed:\t{:x}\n\
+             Priority:\t{}\n\
+             StackSize:\t{} kB\n\
+             StackUsed:\t{} kB\n\
+             RunTime:\t{} us\n\
+             WaitTime:\t{} us\n\
+             voluntary_ctxt_switches:\t{}\n\
+             nonvoluntary_ctxt_switches:\t{}\n",
+        info.name,
+        info.state.as_str(),
+        info.id,
+        info.stats.last_cpu,
+        info.cpumask.bits(),
+        info.priority,
+        info.stack_size / 1024,
+        info.stack_used.div_ceil(1024),
+        info.stats.run_time.as_micros(),
+        info.stats.wait_time.as_micros(),
+        info.stats.nvcsw,
+        info.stats.nivcsw,
+    ))
+}
+
+/// A read-only file whose content is rendered when it's read.
+struct GenFile {
+    render: Box<dyn Fn() -> VfsResult<String> + Send + Sync>,
+}
+
+impl GenFile {
+    fn new(render: impl Fn() -> VfsResult<String> + Send + Sync + 'static) -> Self {
+        Self {
+            render: Box::new(render),
+        }
+    }
+
+    fn render(&self) -> VfsResult<String> {
+        (self.render)()
+    }
+}
+
+impl VfsNodeOps for GenFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o444),
+            VfsNodeType::File,
+            self.render()?.len() as u64,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = self.render()?;
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(start + buf.len());
+        let src = &content.as_bytes()[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
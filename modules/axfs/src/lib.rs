@@ -6,6 +6,9 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!    is **enabled** by default.
+//! - `ext4`: Use [ext4] (also ext2/ext3) as the main filesystem and mount it
+//!    on `/`. This feature is **disabled** by default, but it will override
+//!    `fatfs` if both are enabled.
 //! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
 //!    **enabled** by default.
 //! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
@@ -15,8 +18,26 @@
 //!    to create and initialize other filesystems. This feature is **disabled** by
 //!    by default, but it will override other filesystem selection features if
 //!    both are enabled.
+//! - `multitask`: Add a `/proc/<tid>/status` file for each task to procfs.
+//!
+//! # Caching
+//!
+//! All accesses to the block device go through a write-back cache of disk
+//! blocks shared by the filesystems on it. Modified blocks are written back
+//! when they are evicted, when a file opened for writing is closed, or by
+//! [`sync`], which is also called when the application exits.
+//! Unmodified blocks are dropped when the memory is exhausted. Reads of
+//! opened files on the block device are also cached per file in a few pages.
+//!
+//! # Mounts
+//!
+//! The first block device (`/dev/vda`) holds the root filesystem. Other
+//! filesystems, including the ones on other block devices (`/dev/vdb`, ...),
+//! can be mounted on any directory at runtime with [`api::mount`], possibly
+//! nested or read-only, and unmounted with [`api::umount`].
 //!
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
+//! [ext4]: https://en.wikipedia.org/wiki/Ext4
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -26,21 +47,40 @@
 extern crate log;
 extern crate alloc;
 
+mod block_cache;
 mod dev;
 mod fs;
 mod mounts;
+mod page_cache;
 mod root;
 
 pub mod api;
 pub mod fops;
 
+use alloc::vec::Vec;
 use axdriver::{prelude::*, AxDeviceContainer};
 
 /// Initializes filesystems by block devices.
+///
+/// The first block device holds the root filesystem, and the others can be
+/// mounted later by [`api::mount`].
 pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
     info!("Initialize filesystems...");
 
-    let dev = blk_devs.take_one().expect("No block device found!");
-    info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    axfs_vfs::set_clock(axhal::time::current_time);
+    let mut devs = Vec::new();
+    while let Some(dev) = blk_devs.take_one() {
+        devs.push(dev);
+    }
+    assert!(!devs.is_empty(), "No block device found!");
+    self::dev::init_disks(devs);
+    if axalloc::register_reclaimer(|_| self::dev::shrink_disks()).is_err() {
+        warn!("failed to register the reclaimer of the block cache");
+    }
+    self::root::init_rootfs();
+}
+
+/// Writes all modified blocks in the cache back to the block devices.
+pub fn sync() -> axerrno::AxResult {
+    self::dev::flush_disks().map_err(|_| axerrno::AxError::Io)
 }
@@ -1,8 +1,25 @@
 use alloc::sync::Arc;
+use axerrno::AxResult;
 use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
 
 use crate::fs;
 
+/// Creates a filesystem of `fstype` to be mounted. `source` is the block
+/// device for the filesystems on disks, and is ignored by the others.
+pub(crate) fn new_fs(source: &str, fstype: &str) -> AxResult<Arc<dyn VfsOps>> {
+    match fstype {
+        #[cfg(feature = "devfs")]
+        "devfs" => Ok(devfs()),
+        #[cfg(feature = "ramfs")]
+        "ramfs" | "tmpfs" => Ok(ramfs()),
+        #[cfg(feature = "procfs")]
+        "proc" => procfs(),
+        #[cfg(feature = "sysfs")]
+        "sysfs" => Ok(sysfs()?),
+        _ => fs::open_disk_fs(fstype, crate::dev::open_disk(source)?),
+    }
+}
+
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
@@ -22,7 +39,7 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
 }
 
 #[cfg(feature = "procfs")]
-pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
+pub(crate) fn procfs() -> VfsResult<Arc<dyn VfsOps>> {
     let procfs = fs::ramfs::RamFileSystem::new();
     let proc_root = procfs.root_dir();
 
@@ -44,6 +61,9 @@ pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     proc_root.create("self", VfsNodeType::Dir)?;
     proc_root.create("self/stat", VfsNodeType::File)?;
 
+    // Create /proc/meminfo and /proc/<tid>/status on the fly
+    let procfs = fs::procfs::ProcFileSystem::new(procfs);
+
     Ok(Arc::new(procfs))
 }
 
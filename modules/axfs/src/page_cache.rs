@@ -0,0 +1,141 @@
+//! A small cache of file pages for each opened file.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axfs_vfs::{VfsNodeRef, VfsResult};
+
+const PAGE_SIZE: usize = 4096;
+/// Maximum number of cached pages of each file (32 KB).
+const MAX_CACHED_PAGES: usize = 8;
+
+/// Bumped by every write to the files whose reads are cached, so that the
+/// caches of all opened files can tell whether their pages are stale.
+static WRITE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Returns whether the reads of the node are worth caching, i.e., it's a
+/// file of the filesystem on the block device.
+pub(crate) fn is_cacheable(node: &VfsNodeRef) -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(all(feature = "ext4", not(feature = "myfs")))] {
+            node.as_any().is::<crate::fs::ext4::FileNode>()
+        } else if #[cfg(all(feature = "fatfs", not(feature = "myfs")))] {
+            node.as_any().is::<crate::fs::fatfs::FileWrapper<'static>>()
+        } else {
+            let _ = node;
+            false
+        }
+    }
+}
+
+/// Invalidates the pages cached by all opened files. It must be called
+/// after writing or truncating a cacheable file.
+pub(crate) fn invalidate_all() {
+    WRITE_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+struct CachedPage {
+    index: u64,
+    /// Number of valid bytes, less than [`PAGE_SIZE`] only for the last page
+    /// of the file.
+    len: usize,
+    last_used: u64,
+    data: Box<[u8; PAGE_SIZE]>,
+}
+
+/// The cached pages of an opened file, the least recently used one is
+/// evicted when full.
+pub(crate) struct PageCache {
+    pages: Vec<CachedPage>,
+    generation: u64,
+    tick: u64,
+}
+
+impl PageCache {
+    pub const fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            generation: 0,
+            tick: 0,
+        }
+    }
+
+    /// Whether a read of `len` bytes should go through the cache. Larger
+    /// reads are sent to the filesystem directly.
+    pub const fn should_cache(len: usize) -> bool {
+        len < PAGE_SIZE * MAX_CACHED_PAGES
+    }
+
+    /// Reads the file at the given offset through the cache. Returns the
+    /// number of bytes read.
+    pub fn read_at(&mut self, node: &VfsNodeRef, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let generation = WRITE_GENERATION.load(Ordering::Acquire);
+        if generation != self.generation {
+            self.pages.clear();
+            self.generation = generation;
+        }
+
+        let mut read_len = 0;
+        while read_len < buf.len() {
+            let pos = offset + read_len as u64;
+            let page_offset = (pos % PAGE_SIZE as u64) as usize;
+            let page = self.get(node, pos / PAGE_SIZE as u64)?;
+            if page_offset >= page.len {
+                break;
+            }
+            let count = (page.len - page_offset).min(buf.len() - read_len);
+            buf[read_len..read_len + count]
+                .copy_from_slice(&page.data[page_offset..page_offset + count]);
+            read_len += count;
+            if page.len < PAGE_SIZE {
+                break; // end of file
+            }
+        }
+        Ok(read_len)
+    }
+
+    /// Returns the page with the given index, loads it from the file if it's
+    /// not cached.
+    fn get(&mut self, node: &VfsNodeRef, index: u64) -> VfsResult<&CachedPage> {
+        self.tick += 1;
+        if let Some(idx) = self.pages.iter().position(|p| p.index == index) {
+            self.pages[idx].last_used = self.tick;
+            return Ok(&self.pages[idx]);
+        }
+
+        let idx = if self.pages.len() < MAX_CACHED_PAGES {
+            self.pages.push(CachedPage {
+                index,
+                len: 0,
+                last_used: 0,
+                data: Box::new([0; PAGE_SIZE]),
+            });
+            self.pages.len() - 1
+        } else {
+            let (idx, _) = self
+                .pages
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, p)| p.last_used)
+                .unwrap();
+            idx
+        };
+
+        let page = &mut self.pages[idx];
+        // Do not reuse the stale content if the load fails.
+        page.index = u64::MAX;
+        page.last_used = 0;
+        let mut len = 0;
+        while len < PAGE_SIZE {
+            let n = node.read_at(index * PAGE_SIZE as u64 + len as u64, &mut page.data[len..])?;
+            if n == 0 {
+                break;
+            }
+            len += n;
+        }
+        page.index = index;
+        page.len = len;
+        page.last_used = self.tick;
+        Ok(page)
+    }
+}
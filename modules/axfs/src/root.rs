@@ -1,211 +1,339 @@
-//! Root directory of the filesystem
+//! Root directory of the filesystem and the mount table.
 //!
-//! TODO: it doesn't work very well if the mount points have containment relationships.
+//! Paths are first resolved to absolute paths without symbolic links, and then
+//! looked up in the filesystem mounted at the longest mount point that
+//! contains them, so mounts can be nested in other mounts.
 
-use alloc::{string::String, sync::Arc, vec::Vec};
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec, vec::Vec};
 use axerrno::{ax_err, AxError, AxResult};
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use lazy_init::LazyInit;
 
-use crate::{api::FileType, fs, mounts};
+use crate::{dev, fs, mounts};
+
+/// Maximum number of symbolic links followed in a path lookup.
+const MAX_SYMLINKS: usize = 40;
 
 static CURRENT_DIR_PATH: Mutex<String> = Mutex::new(String::new());
-static CURRENT_DIR: LazyInit<Mutex<VfsNodeRef>> = LazyInit::new();
 
-struct MountPoint {
-    path: &'static str,
-    fs: Arc<dyn VfsOps>,
+/// The mount table, indexed by the mount points without the leading and
+/// trailing `/`, so the root filesystem is mounted at `""`.
+static MOUNTS: Mutex<BTreeMap<String, Arc<Mount>>> = Mutex::new(BTreeMap::new());
+
+bitflags::bitflags! {
+    /// Flags of [`mount`](crate::api::mount), with the same values as the
+    /// `MS_*` flags of Linux.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MountFlags: u32 {
+        /// Mount read-only.
+        const RDONLY = 1;
+        /// Change the flags of an existing mount.
+        const REMOUNT = 32;
+        /// Mount a directory at another place.
+        const BIND = 4096;
+    }
 }
 
-struct RootDirectory {
-    main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+/// A filesystem, or a directory of it for bind mounts, mounted on a path.
+struct Mount {
+    /// The block device, or the directory for bind mounts.
+    source: String,
+    fs: Arc<dyn VfsOps>,
+    /// The directory that the mount point refers to.
+    root: VfsNodeRef,
+    flags: MountFlags,
 }
 
-static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
-
-impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+impl Mount {
+    fn is_read_only(&self) -> bool {
+        self.flags.contains(MountFlags::RDONLY)
     }
-}
 
-impl Drop for MountPoint {
-    fn drop(&mut self) {
-        self.fs.umount().ok();
+    fn check_writable(&self) -> AxResult {
+        if self.is_read_only() {
+            ax_err!(ReadOnlyFilesystem)
+        } else {
+            Ok(())
+        }
     }
 }
 
-impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
-        Self {
-            main_fs,
-            mounts: Vec::new(),
-        }
-    }
+/// Returns the key of the absolute path in [`MOUNTS`].
+fn mount_key(path: &str) -> &str {
+    path.trim_matches('/')
+}
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
-        if path == "/" {
-            return ax_err!(InvalidInput, "cannot mount root filesystem");
-        }
-        if !path.starts_with('/') {
-            return ax_err!(InvalidInput, "mount path must start with '/'");
-        }
-        if self.mounts.iter().any(|mp| mp.path == path) {
-            return ax_err!(InvalidInput, "mount point already exists");
+/// Finds the mount that the absolute path `path` is in, and returns it with
+/// the rest of the path relative to the mount point.
+fn find_mount(path: &str) -> (Arc<Mount>, &str) {
+    let path = path.trim_start_matches('/');
+    let mounts = MOUNTS.lock();
+    // check the ancestors from the nearest, component by component
+    let mut len = path.trim_end_matches('/').len();
+    loop {
+        if let Some(mount) = mounts.get(&path[..len]) {
+            return (mount.clone(), &path[len..]);
         }
-        // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
-        fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
-        Ok(())
-    }
-
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+        assert!(len > 0, "the root filesystem is not mounted");
+        len = path[..len].rfind('/').unwrap_or(0);
     }
+}
 
-    pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+/// Adds a mount at the absolute path `path`, which must be a directory. The
+/// filesystem is notified if it's newly created rather than bind mounted.
+fn add_mount(path: &str, mount: Mount, new_fs: bool) -> AxResult {
+    let mount_point = lookup_resolved(path)?;
+    if !mount_point.get_attr()?.is_dir() {
+        return ax_err!(NotADirectory);
     }
-
-    fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
-    where
-        F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
+    let key = mount_key(path);
+    let mount = Arc::new(mount);
     {
-        debug!("lookup at root: {}", path);
-        let path = path.trim_matches('/');
-        if let Some(rest) = path.strip_prefix("./") {
-            return self.lookup_mounted_fs(rest, f);
+        let mut mounts = MOUNTS.lock();
+        if mounts.contains_key(key) {
+            return ax_err!(ResourceBusy, "mount point already exists");
         }
-
-        let mut idx = 0;
-        let mut max_len = 0;
-
-        // Find the filesystem that has the longest mounted path match
-        // TODO: more efficient, e.g. trie
-        for (i, mp) in self.mounts.iter().enumerate() {
-            // skip the first '/'
-            if path.starts_with(&mp.path[1..]) && mp.path.len() - 1 > max_len {
-                max_len = mp.path.len() - 1;
-                idx = i;
-            }
-        }
-
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
-        } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
+        mounts.insert(key.into(), mount.clone());
+    }
+    if new_fs {
+        if let Err(e) = mount.fs.mount(path, mount_point) {
+            MOUNTS.lock().remove(key);
+            return Err(e);
         }
     }
+    Ok(())
 }
 
-impl VfsNodeOps for RootDirectory {
-    axfs_vfs::impl_vfs_dir_default! {}
-
-    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        self.main_fs.root_dir().get_attr()
-    }
-
-    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
-        self.lookup_mounted_fs(path, |fs, rest_path| fs.root_dir().lookup(rest_path))
-    }
-
-    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
-        self.lookup_mounted_fs(path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                Ok(()) // already exists
-            } else {
-                fs.root_dir().create(rest_path, ty)
-            }
-        })
-    }
-
-    fn remove(&self, path: &str) -> VfsResult {
-        self.lookup_mounted_fs(path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot remove mount points
-            } else {
-                fs.root_dir().remove(rest_path)
-            }
-        })
-    }
-
-    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
-        self.lookup_mounted_fs(src_path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot rename mount points
-            } else {
-                fs.root_dir().rename(rest_path, dst_path)
-            }
-        })
-    }
+/// Mounts a filesystem not on block devices at `path` during initialization,
+/// the mount point is created in `main_fs` if it does not exist.
+#[cfg(any(
+    feature = "devfs",
+    feature = "ramfs",
+    feature = "procfs",
+    feature = "sysfs"
+))]
+fn mount_at_init(main_fs: &Arc<dyn VfsOps>, path: &str, fstype: &str, fs: Arc<dyn VfsOps>) {
+    let mount = Mount {
+        source: fstype.into(),
+        root: fs.root_dir(),
+        fs,
+        flags: MountFlags::empty(),
+    };
+    main_fs
+        .root_dir()
+        .create(path, VfsNodeType::Dir)
+        .and_then(|_| add_mount(path, mount, true))
+        .unwrap_or_else(|e| panic!("failed to mount {} at {}: {:?}", fstype, path, e));
 }
 
-pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "myfs")] { // override the default filesystem
-            let main_fs = fs::myfs::new_myfs(disk);
-        } else if #[cfg(feature = "fatfs")] {
-            static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
-            FAT_FS.init_by(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
-            FAT_FS.init();
-            let main_fs = FAT_FS.clone();
-        }
-    }
-
-    let mut root_dir = RootDirectory::new(main_fs);
+pub(crate) fn init_rootfs() {
+    let source = dev::disk_name(0);
+    let disk = dev::open_disk(&source).expect("failed to open the root block device");
+    let main_fs = fs::new_root_fs(disk);
+    let root = Mount {
+        source,
+        root: main_fs.root_dir(),
+        fs: main_fs.clone(),
+        flags: MountFlags::empty(),
+    };
+    MOUNTS.lock().insert(String::new(), Arc::new(root));
+    *CURRENT_DIR_PATH.lock() = "/".into();
 
     #[cfg(feature = "devfs")]
-    root_dir
-        .mount("/dev", mounts::devfs())
-        .expect("failed to mount devfs at /dev");
+    mount_at_init(&main_fs, "/dev", "devfs", mounts::devfs());
 
     #[cfg(feature = "ramfs")]
-    root_dir
-        .mount("/tmp", mounts::ramfs())
-        .expect("failed to mount ramfs at /tmp");
+    mount_at_init(&main_fs, "/tmp", "ramfs", mounts::ramfs());
 
     // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
-    root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
-        .expect("fail to mount procfs at /proc");
+    mount_at_init(&main_fs, "/proc", "proc", mounts::procfs().unwrap()); // should not fail
 
     // Mount another ramfs as sysfs
     #[cfg(feature = "sysfs")]
-    root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
-        .expect("fail to mount sysfs at /sys");
-
-    ROOT_DIR.init_by(Arc::new(root_dir));
-    CURRENT_DIR.init_by(Mutex::new(ROOT_DIR.clone()));
-    *CURRENT_DIR_PATH.lock() = "/".into();
+    mount_at_init(&main_fs, "/sys", "sysfs", mounts::sysfs().unwrap()); // should not fail
 }
 
-fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
-    if path.starts_with('/') {
-        ROOT_DIR.clone()
+/// Mounts the filesystem `fstype` on `source` at `target`, see
+/// [`api::mount`](crate::api::mount).
+pub(crate) fn mount(source: &str, target: &str, fstype: &str, flags: MountFlags) -> AxResult {
+    let target = resolve_path(target, true)?;
+    if flags.contains(MountFlags::REMOUNT) {
+        let mut mounts = MOUNTS.lock();
+        let Some(mount) = mounts.get_mut(mount_key(&target)) else {
+            return ax_err!(InvalidInput, "not a mount point");
+        };
+        *mount = Arc::new(Mount {
+            source: mount.source.clone(),
+            fs: mount.fs.clone(),
+            root: mount.root.clone(),
+            flags: flags & MountFlags::RDONLY,
+        });
+        return Ok(());
+    }
+
+    if flags.contains(MountFlags::BIND) {
+        let source = resolve_path(source, true)?;
+        let root = lookup_resolved(&source)?;
+        if !root.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        let fs = find_mount(&source).0.fs.clone();
+        let mount = Mount {
+            source,
+            fs,
+            root,
+            flags: flags & MountFlags::RDONLY,
+        };
+        add_mount(&target, mount, false)
     } else {
-        dir.cloned().unwrap_or_else(|| CURRENT_DIR.lock().clone())
+        if dev::is_disk(source) && MOUNTS.lock().values().any(|m| m.source == source) {
+            return ax_err!(ResourceBusy, "block device is already mounted");
+        }
+        let fs = mounts::new_fs(source, fstype)?;
+        let mount = Mount {
+            source: source.into(),
+            root: fs.root_dir(),
+            fs,
+            flags: flags & MountFlags::RDONLY,
+        };
+        add_mount(&target, mount, true)
     }
 }
 
-pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
+/// Unmounts the filesystem mounted at `target`, see
+/// [`api::umount`](crate::api::umount).
+pub(crate) fn umount(target: &str) -> AxResult {
+    let target = resolve_path(target, true)?;
+    let key = mount_key(&target);
+    if key.is_empty() {
+        return ax_err!(ResourceBusy, "cannot unmount the root filesystem");
+    }
+    let cwd = CURRENT_DIR_PATH.lock().clone();
+    let mut mounts = MOUNTS.lock();
+    if !mounts.contains_key(key) {
+        return ax_err!(InvalidInput, "not a mount point");
+    }
+    let prefix = format!("{}/", key);
+    if mounts.keys().any(|k| k.starts_with(&prefix)) || cwd[1..].starts_with(&prefix) {
+        return ax_err!(ResourceBusy);
+    }
+    let mount = mounts.remove(key).unwrap();
+    // the filesystem may be still mounted elsewhere by bind mounts
+    let in_use = mounts.values().any(|m| Arc::ptr_eq(&m.fs, &mount.fs));
+    drop(mounts);
+    if !in_use {
+        mount.fs.umount()?;
+        crate::sync()?;
+    }
+    Ok(())
+}
+
+/// Prepends the current directory to a relative path.
+fn join_current_dir(path: &str) -> String {
     if path.starts_with('/') {
-        Ok(axfs_vfs::path::canonicalize(path))
+        path.into()
     } else {
-        let path = CURRENT_DIR_PATH.lock().clone() + path;
-        Ok(axfs_vfs::path::canonicalize(&path))
+        CURRENT_DIR_PATH.lock().clone() + path
     }
 }
 
-pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
+    Ok(axfs_vfs::path::canonicalize(&join_current_dir(path)))
+}
+
+/// Pushes the components of `path` to a stack, the first one on the top.
+fn push_components(stack: &mut Vec<String>, path: &str) {
+    let comps = path.rsplit('/').filter(|c| !c.is_empty() && *c != ".");
+    stack.extend(comps.map(String::from));
+}
+
+/// Reads the target of the symbolic link `node`.
+fn read_link_of(node: &VfsNodeRef) -> AxResult<String> {
+    let mut buf = vec![0; node.get_attr()?.size() as usize];
+    let len = node.readlink(&mut buf)?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
+/// Resolves `path` relative to the current directory to an absolute path
+/// without `.`, `..` and symbolic links.
+///
+/// The last component is followed only if `follow` is true or `path` ends
+/// with `/`, and it need not exist.
+pub(crate) fn resolve_path(path: &str, follow: bool) -> AxResult<String> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
+    let follow = follow || path.ends_with('/');
+    let mut resolved = String::new();
+    // The directories of `resolved`, from the root, so that each component
+    // is looked up in its parent only once.
+    let mut dirs = vec![find_mount("/").0.root.clone()];
+    let mut stack = Vec::new();
+    push_components(&mut stack, &join_current_dir(path));
+
+    let mut links = 0;
+    while let Some(comp) = stack.pop() {
+        if comp == ".." {
+            // No symbolic links in `resolved`, so `..` can be removed directly.
+            resolved.truncate(resolved.rfind('/').unwrap_or(0));
+            if dirs.len() > 1 {
+                dirs.pop();
+            }
+            continue;
+        }
+        let parent_len = resolved.len();
+        resolved.push('/');
+        resolved.push_str(&comp);
+        if stack.is_empty() && !follow {
+            continue;
+        }
+
+        let node = match lookup_child(dirs.last().unwrap(), &resolved, &comp) {
+            Ok(node) => node,
+            Err(AxError::NotFound) if stack.is_empty() => break,
+            Err(e) => return Err(e),
+        };
+        if node.get_attr()?.is_symlink() {
+            links += 1;
+            if links > MAX_SYMLINKS {
+                return ax_err!(FilesystemLoop);
+            }
+            let target = read_link_of(&node)?;
+            if target.starts_with('/') {
+                resolved.clear();
+                dirs.truncate(1);
+            } else {
+                resolved.truncate(parent_len);
+            }
+            push_components(&mut stack, &target);
+        } else {
+            dirs.push(node);
+        }
+    }
+    if path.ends_with('/') || resolved.is_empty() {
+        resolved.push('/');
+    }
+    Ok(resolved)
+}
+
+/// Looks up `name` in the directory `dir`, where `path` is the absolute path
+/// of the result. Returns the root of the mount if `path` is a mount point.
+fn lookup_child(dir: &VfsNodeRef, path: &str, name: &str) -> AxResult<VfsNodeRef> {
+    if let Some(mount) = MOUNTS.lock().get(mount_key(path)) {
+        return Ok(mount.root.clone());
+    }
+    if !dir.get_attr()?.is_dir() {
+        return ax_err!(NotADirectory);
+    }
+    dir.clone().lookup(name)
+}
+
+/// Looks up a path returned by [`resolve_path`].
+fn lookup_resolved(path: &str) -> AxResult<VfsNodeRef> {
+    let (mount, rest) = find_mount(path);
+    let node = mount.root.clone().lookup(rest)?;
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
     } else {
@@ -213,38 +341,81 @@ pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRe
     }
 }
 
-pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
-    if path.is_empty() {
-        return ax_err!(NotFound);
-    } else if path.ends_with('/') {
+pub(crate) fn lookup(path: &str) -> AxResult<VfsNodeRef> {
+    lookup_resolved(&resolve_path(path, true)?)
+}
+
+/// Looks up a path without following the symbolic link at the end.
+pub(crate) fn lookup_no_follow(path: &str) -> AxResult<VfsNodeRef> {
+    lookup_resolved(&resolve_path(path, false)?)
+}
+
+/// Looks up a path, and returns the node with whether it's on a read-only
+/// mount.
+pub(crate) fn lookup_on_mount(path: &str, follow: bool) -> AxResult<(VfsNodeRef, bool)> {
+    let path = resolve_path(path, follow)?;
+    let node = lookup_resolved(&path)?;
+    Ok((node, find_mount(&path).0.is_read_only()))
+}
+
+/// Looks up a path to modify the attributes of the node, which fails on
+/// read-only mounts.
+pub(crate) fn lookup_writable(path: &str, follow: bool) -> AxResult<VfsNodeRef> {
+    let (node, read_only) = lookup_on_mount(path, follow)?;
+    if read_only {
+        ax_err!(ReadOnlyFilesystem)
+    } else {
+        Ok(node)
+    }
+}
+
+/// Returns the absolute path of a node to be created at `path`, along with
+/// the mount it is in and the path relative to the mount point.
+fn resolve_new(path: &str) -> AxResult<(Arc<Mount>, String)> {
+    let path = resolve_path(path, false)?;
+    let (mount, rest) = find_mount(&path);
+    mount.check_writable()?;
+    let rest = rest.trim_end_matches('/').into();
+    Ok((mount, rest))
+}
+
+pub(crate) fn create_file(path: &str) -> AxResult<VfsNodeRef> {
+    if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
-    let parent = parent_node_of(dir, path);
-    parent.create(path, VfsNodeType::File)?;
-    parent.lookup(path)
+    let path = resolve_path(path, true)?;
+    let (mount, rest) = find_mount(&path);
+    mount.check_writable()?;
+    mount.root.create(rest, VfsNodeType::File)?;
+    mount.root.clone().lookup(rest)
 }
 
-pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    match lookup(dir, path) {
+pub(crate) fn create_dir(path: &str) -> AxResult {
+    match lookup_no_follow(path) {
         Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
+        Err(AxError::NotFound) => {
+            let (mount, rest) = resolve_new(path)?;
+            mount.root.create(&rest, VfsNodeType::Dir)
+        }
         Err(e) => Err(e),
     }
 }
 
-pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
+pub(crate) fn remove_file(path: &str) -> AxResult {
+    let path = resolve_path(path, false)?;
+    let attr = lookup_resolved(&path)?.get_attr()?;
+    let (mount, rest) = find_mount(&path);
     if attr.is_dir() {
         ax_err!(IsADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        mount.check_writable()?;
+        mount.root.remove(rest)
     }
 }
 
-pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
+pub(crate) fn remove_dir(path: &str) -> AxResult {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
@@ -258,18 +429,68 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     {
         return ax_err!(InvalidInput);
     }
-    if ROOT_DIR.contains(&absolute_path(path)?) {
-        return ax_err!(PermissionDenied);
-    }
 
-    let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
+    let path = resolve_path(path, false)?;
+    let attr = lookup_resolved(&path)?.get_attr()?;
+    let (mount, rest) = find_mount(&path);
     if !attr.is_dir() {
         ax_err!(NotADirectory)
-    } else if !attr.perm().owner_writable() {
-        ax_err!(PermissionDenied)
+    } else if rest.trim_matches('/').is_empty() || !attr.perm().owner_writable() {
+        ax_err!(PermissionDenied) // including mount points
     } else {
-        parent_node_of(dir, path).remove(path)
+        mount.check_writable()?;
+        mount.root.remove(rest.trim_end_matches('/'))
+    }
+}
+
+/// Creates a symbolic link at `path`, which points to `target`.
+pub(crate) fn create_symlink(target: &str, path: &str) -> AxResult {
+    if target.is_empty() {
+        return ax_err!(NotFound);
+    } else if path.ends_with('/') {
+        return ax_err!(NotADirectory);
+    }
+    match lookup_no_follow(path) {
+        Ok(_) => ax_err!(AlreadyExists),
+        Err(AxError::NotFound) => {
+            let (mount, rest) = resolve_new(path)?;
+            mount.root.symlink(&rest, target)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the target of the symbolic link at `path`.
+pub(crate) fn read_link(path: &str) -> AxResult<String> {
+    let node = lookup_no_follow(path)?;
+    if !node.get_attr()?.is_symlink() {
+        return ax_err!(InvalidInput);
+    }
+    read_link_of(&node)
+}
+
+/// Creates a hard link at `new` to the node at `old`, which must be in the
+/// same mount.
+pub(crate) fn create_link(old: &str, new: &str) -> AxResult {
+    if new.ends_with('/') {
+        return ax_err!(NotADirectory);
+    }
+    let old = resolve_path(old, false)?;
+    let new = resolve_path(new, false)?;
+    let node = lookup_resolved(&old)?;
+    if node.get_attr()?.is_dir() {
+        return ax_err!(PermissionDenied);
+    }
+    let (old_mount, _) = find_mount(&old);
+    let (new_mount, new_rest) = find_mount(&new);
+    if !Arc::ptr_eq(&old_mount, &new_mount) {
+        return ax_err!(CrossesDevices);
+    }
+    new_mount.check_writable()?;
+    match new_mount.root.clone().lookup(new_rest) {
+        Ok(_) => ax_err!(AlreadyExists),
+        Err(AxError::NotFound) => new_mount.root.link(new_rest, &node),
+        Err(e) => Err(e),
     }
 }
 
@@ -278,33 +499,80 @@ pub(crate) fn current_dir() -> AxResult<String> {
 }
 
 pub(crate) fn set_current_dir(path: &str) -> AxResult {
-    let mut abs_path = absolute_path(path)?;
+    // The path of the current directory is kept free of symbolic links.
+    let mut abs_path = resolve_path(path, true)?;
     if !abs_path.ends_with('/') {
         abs_path += "/";
     }
-    if abs_path == "/" {
-        *CURRENT_DIR.lock() = ROOT_DIR.clone();
-        *CURRENT_DIR_PATH.lock() = "/".into();
-        return Ok(());
-    }
 
-    let node = lookup(None, &abs_path)?;
-    let attr = node.get_attr()?;
+    let attr = lookup_resolved(&abs_path)?.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_executable() {
         ax_err!(PermissionDenied)
     } else {
-        *CURRENT_DIR.lock() = node;
         *CURRENT_DIR_PATH.lock() = abs_path;
         Ok(())
     }
 }
 
 pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
-        warn!("dst file already exist, now remove it");
-        remove_file(None, new)?;
+    let old = resolve_path(old, false)?;
+    let new = resolve_path(new, false)?;
+    let (old_mount, old_rest) = find_mount(&old);
+    let (new_mount, new_rest) = find_mount(&new);
+    if old_rest.trim_matches('/').is_empty() {
+        return ax_err!(PermissionDenied); // cannot rename mount points
+    } else if !Arc::ptr_eq(&old_mount, &new_mount) {
+        return ax_err!(CrossesDevices);
+    }
+    old_mount.check_writable()?;
+    let src = lookup_resolved(&old)?;
+    if old == new {
+        return Ok(());
+    }
+    let src_is_dir = src.get_attr()?.is_dir();
+    let old_dir = old.trim_end_matches('/');
+    if src_is_dir
+        && new
+            .strip_prefix(old_dir)
+            .is_some_and(|r| r.starts_with('/'))
+    {
+        return ax_err!(InvalidInput); // cannot move a directory into itself
+    }
+    // Only remove `new` when the rename can be done.
+    match lookup_resolved(&new) {
+        Ok(dst) => {
+            if same_node(&src, &dst) {
+                return Ok(()); // hard links to the same node
+            }
+            match (src_is_dir, dst.get_attr()?.is_dir()) {
+                (true, false) => return ax_err!(NotADirectory),
+                (false, true) => return ax_err!(IsADirectory),
+                _ => {}
+            }
+            warn!("dst file already exist, now remove it");
+            remove_file(&new)?;
+        }
+        Err(AxError::NotFound) => {}
+        Err(e) => return Err(e),
+    }
+    old_mount.root.rename(old_rest, new_rest)
+}
+
+/// Whether `a` and `b` are the same node, e.g. hard links to one file.
+fn same_node(a: &VfsNodeRef, b: &VfsNodeRef) -> bool {
+    if Arc::ptr_eq(a, b) {
+        return true;
+    }
+    // Nodes of ext4 are created on each lookup, compare the inode numbers.
+    #[cfg(all(feature = "ext4", not(feature = "myfs")))]
+    {
+        use fs::ext4::FileNode;
+        let (a, b) = (a.as_any(), b.as_any());
+        if let (Some(a), Some(b)) = (a.downcast_ref::<FileNode>(), b.downcast_ref::<FileNode>()) {
+            return a.ino() == b.ino();
+        }
     }
-    parent_node_of(None, old).rename(old, new)
+    false
 }
@@ -164,6 +164,95 @@ fn test_remove_file_dir() -> Result<()> {
     Ok(())
 }
 
+fn test_cached_read_write() -> Result<()> {
+    let fname = "/cache-test.bin";
+    println!("test cached read and write {:?}:", fname);
+    let data = (0..20000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    fs::write(fname, &data)?;
+
+    // small reads at odd offsets across pages
+    let mut reader = File::open(fname)?;
+    let mut buf = [0; 1000];
+    for offset in [0, 4000, 4095, 8191, 19500] {
+        reader.seek(io::SeekFrom::Start(offset))?;
+        let n = reader.read(&mut buf)?;
+        let expected = &data[offset as usize..(offset as usize + 1000).min(data.len())];
+        assert_eq!(&buf[..n], expected);
+    }
+
+    // writes through another handle are visible to the cached reader
+    let mut writer = File::options().write(true).open(fname)?;
+    writer.seek(io::SeekFrom::Start(4090))?;
+    writer.write_all(&[0xaa; 100])?;
+    reader.seek(io::SeekFrom::Start(4000))?;
+    reader.read_exact(&mut buf)?;
+    assert_eq!(&buf[..90], &data[4000..4090]);
+    assert_eq!(&buf[90..190], &[0xaa; 100]);
+    assert_eq!(&buf[190..], &data[4190..5000]);
+
+    // and so is truncation
+    writer.set_len(4096)?;
+    reader.seek(io::SeekFrom::Start(4000))?;
+    assert_eq!(reader.read(&mut buf)?, 96);
+    drop(writer);
+    drop(reader);
+
+    fs::remove_file(fname)?;
+
+    // files larger than the block cache are written back on eviction
+    let data = (0..0x18_0000).map(|i| (i % 253) as u8).collect::<Vec<_>>();
+    fs::write(fname, &data)?;
+    assert_eq!(fs::read(fname)?, data);
+    fs::remove_file(fname)?;
+
+    println!("test_cached_read_write() OK!");
+    Ok(())
+}
+
+fn test_file_handle() -> Result<()> {
+    use axfs::fops::{File as RawFile, OpenOptions as RawOpenOptions};
+
+    let fname = "/handle-test.bin";
+    println!("test file handles {:?}:", fname);
+    let data = (0..10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    fs::write(fname, &data)?;
+
+    let mut opts = RawOpenOptions::new();
+    opts.read(true);
+    let mut file = RawFile::open(fname, &opts)?;
+    let handle = file.handle()?;
+    assert!(handle.is_readable() && !handle.is_writable());
+    assert_err!(handle.write_at(0, &[0]), PermissionDenied);
+
+    // reads through the handle do not move the cursor of the file
+    let mut buf = [0; 100];
+    assert_eq!(handle.read_at(5000, &mut buf)?, 100);
+    assert_eq!(&buf[..], &data[5000..5100]);
+    assert_eq!(file.read(&mut buf)?, 100);
+    assert_eq!(&buf[..], &data[..100]);
+    drop(file);
+
+    // handles outlive the files, and writes are visible to cached readers
+    opts.write(true);
+    let writer = RawFile::open(fname, &opts)?.handle()?;
+    assert!(writer.is_writable());
+    let mut reader = File::open(fname)?;
+    reader.read_exact(&mut buf)?;
+    assert_eq!(writer.write_at(10, &[0xaa; 10])?, 10);
+    reader.seek(io::SeekFrom::Start(0))?;
+    reader.read_exact(&mut buf)?;
+    assert_eq!(&buf[10..20], &[0xaa; 10]);
+    assert_eq!(handle.read_at(9990, &mut buf)?, 10);
+    assert_eq!(writer.get_attr()?.size(), 10000);
+    drop(reader);
+    drop(handle);
+    drop(writer);
+
+    fs::remove_file(fname)?;
+    println!("test_file_handle() OK!");
+    Ok(())
+}
+
 fn test_devfs_ramfs() -> Result<()> {
     const N: usize = 32;
     let mut buf = [1; N];
@@ -231,7 +320,8 @@ fn test_devfs_ramfs() -> Result<()> {
     // parent of '/dev'
     assert_eq!(fs::create_dir("///dev//..//233//"), Ok(()));
     assert_eq!(fs::write(".///dev//..//233//.///test.txt", "test"), Ok(()));
-    assert_err!(fs::remove_file("./dev//../..//233//.///test.txt"), NotFound);
+    assert!(fs::metadata("./dev//../..//233//.///test.txt")?.is_file()); // `/..` is `/`
+    assert_err!(fs::remove_file("./dev//../..//234//.///test.txt"), NotFound);
     assert_eq!(fs::remove_file("./dev//..//233//../233/./test.txt"), Ok(()));
     assert_eq!(fs::remove_dir("dev//foo/../foo/../.././/233"), Ok(()));
     assert_err!(fs::remove_dir("very/../dev//"), PermissionDenied);
@@ -252,11 +342,203 @@ fn test_devfs_ramfs() -> Result<()> {
     Ok(())
 }
 
+#[cfg(any(feature = "myfs", feature = "ext4"))]
+fn test_links() -> Result<()> {
+    // symbolic links to a file and a directory
+    let contents = fs::read_to_string("very/long/path/test.txt")?;
+    fs::symlink("very/long/path/test.txt", "/link-to-file")?;
+    fs::symlink("/very/long", "very-long-dir-name/link-to-dir")?;
+    assert_eq!(fs::read_link("link-to-file")?, "very/long/path/test.txt");
+    assert!(fs::symlink_metadata("/link-to-file")?.is_symlink());
+    assert!(fs::metadata("/link-to-file")?.is_file());
+    assert_eq!(fs::read_to_string("link-to-file")?, contents);
+    let fname = "very-long-dir-name//link-to-dir/./path/test.txt";
+    assert_eq!(fs::read_to_string(fname)?, contents);
+    assert!(fs::metadata("very-long-dir-name/link-to-dir/../long")?.is_dir());
+    assert_err!(
+        fs::metadata("very-long-dir-name/link-to-dir/../path"),
+        NotFound
+    );
+    assert_err!(fs::read_link("very"), InvalidInput);
+    assert_err!(fs::symlink("very", "link-to-file"), AlreadyExists);
+    assert_err!(fs::metadata("link-to-file/"), NotADirectory);
+    assert_err!(
+        fs::remove_dir("very-long-dir-name/link-to-dir"),
+        NotADirectory
+    );
+
+    // a dangling link, writes to it create the target
+    fs::symlink("../very/new-file.txt", "very/dangling")?;
+    assert_err!(fs::metadata("very/dangling"), NotFound);
+    fs::write("very/dangling", "dangling")?;
+    assert_eq!(fs::read_to_string("/very/new-file.txt")?, "dangling");
+
+    // loops
+    fs::symlink("loop2", "loop1")?;
+    fs::symlink("./loop1", "loop2")?;
+    assert_err!(fs::metadata("loop1"), FilesystemLoop);
+    assert_err!(fs::create_dir("loop1/dir"), FilesystemLoop);
+    assert!(fs::symlink_metadata("loop1")?.is_symlink());
+
+    // the current directory is kept without links
+    fs::set_current_dir("very-long-dir-name/link-to-dir")?;
+    assert_eq!(fs::current_dir()?, "/very/long/");
+    assert!(fs::metadata("path/test.txt")?.is_file());
+    fs::set_current_dir("/")?;
+
+    // hard links
+    fs::hard_link("short.txt", "very/hard-link.txt")?;
+    fs::write("very/hard-link.txt", "changed")?;
+    assert_eq!(fs::read_to_string("short.txt")?, "changed");
+    assert_err!(fs::hard_link("very", "very2"), PermissionDenied);
+    assert_err!(fs::hard_link("short.txt", "/tmp/link"), CrossesDevices);
+    assert_err!(fs::hard_link("short.txt", "link-to-file"), AlreadyExists);
+    fs::remove_file("short.txt")?;
+    assert_eq!(fs::read_to_string("very/hard-link.txt")?, "changed");
+    fs::hard_link("very/hard-link.txt", "short.txt")?;
+    fs::remove_file("very/hard-link.txt")?;
+
+    // removing links does not remove the targets
+    fs::remove_file("very-long-dir-name/link-to-dir")?;
+    assert!(fs::metadata("very/long")?.is_dir());
+    for fname in ["link-to-file", "very/dangling", "loop1", "loop2"] {
+        fs::remove_file(fname)?;
+    }
+    assert_eq!(fs::read_to_string("very/long/path/test.txt")?, contents);
+    fs::remove_file("very/new-file.txt")?;
+
+    println!("test_links() OK!");
+    Ok(())
+}
+
+#[cfg(any(feature = "myfs", feature = "ext4"))]
+fn test_attrs() -> Result<()> {
+    use core::time::Duration;
+    use fs::FileTimes;
+
+    let fname = "very/attrs.txt";
+    fs::write(fname, "attrs")?;
+    let atime = Duration::new(1_000_000_000, 0);
+    let mtime = Duration::new(1_500_000_000, 0);
+    fs::set_times(fname, FileTimes::new().set_accessed(atime))?;
+    let metadata = fs::metadata(fname)?;
+    assert_eq!(metadata.accessed(), atime);
+
+    let file = File::options().append(true).open(fname)?;
+    file.set_modified(mtime)?;
+    let metadata = file.metadata()?;
+    assert_eq!(metadata.accessed(), atime);
+    assert_eq!(metadata.modified(), mtime);
+
+    file.set_permissions(fs::Permissions::from_bits_truncate(0o640))?;
+    assert_eq!(fs::metadata(fname)?.permissions().bits(), 0o640);
+    fs::set_permissions(fname, fs::Permissions::from_bits_truncate(0o600))?;
+    assert_eq!(file.metadata()?.permissions().bits(), 0o600);
+
+    assert_eq!(fs::metadata(fname)?.raw_metadata().nlink(), 1);
+    fs::hard_link(fname, "very/attrs-link.txt")?;
+    assert_eq!(fs::metadata(fname)?.raw_metadata().nlink(), 2);
+    fs::rename(fname, "very/attrs-link.txt")?; // the same file, does nothing
+    assert_eq!(fs::metadata(fname)?.raw_metadata().nlink(), 2);
+    fs::remove_file("very/attrs-link.txt")?;
+    assert_eq!(file.metadata()?.raw_metadata().nlink(), 1);
+    drop(file);
+    fs::remove_file(fname)?;
+
+    println!("test_attrs() OK!");
+    Ok(())
+}
+
+fn test_mount() -> Result<()> {
+    use fs::MountFlags;
+
+    // mount a ramfs, and another one inside it
+    fs::create_dir("/mnt")?;
+    fs::mount("", "/mnt", "ramfs", MountFlags::empty())?;
+    assert_err!(
+        fs::mount("", "/mnt/", "ramfs", MountFlags::empty()),
+        ResourceBusy
+    );
+    fs::write("/mnt/a.txt", "mnt")?;
+    fs::create_dir("mnt/sub")?;
+    fs::mount("", "mnt/sub", "ramfs", MountFlags::empty())?;
+    assert_eq!(fs::read_dir("/mnt/sub")?.count(), 0);
+    fs::set_current_dir("/mnt")?;
+    fs::write("sub/b.txt", "sub")?;
+    assert_eq!(fs::read_to_string("./sub/../sub/b.txt")?, "sub");
+    fs::set_current_dir("/")?;
+    assert_err!(
+        fs::hard_link("/mnt/a.txt", "/mnt/sub/a.txt"),
+        CrossesDevices
+    );
+    // failed renames keep the destination
+    fs::write("/mnt/sub/a.txt", "sub")?;
+    assert_err!(fs::rename("/mnt/a.txt", "/mnt/sub/a.txt"), CrossesDevices);
+    assert_err!(fs::rename("/mnt/sub/none.txt", "/mnt/sub/a.txt"), NotFound);
+    assert_eq!(fs::read_to_string("/mnt/sub/a.txt")?, "sub");
+    fs::create_dir("/mnt/sub/d")?;
+    fs::write("/mnt/sub/d/f", "d/f")?;
+    assert_err!(fs::rename("/mnt/sub/d", "/mnt/sub/a.txt"), NotADirectory);
+    assert_err!(fs::rename("/mnt/sub/a.txt", "/mnt/sub/d"), IsADirectory);
+    assert_err!(fs::rename("/mnt/sub/d", "/mnt/sub/d/f"), InvalidInput);
+    assert_eq!(fs::read_to_string("/mnt/sub/d/f")?, "d/f");
+    fs::hard_link("/mnt/sub/a.txt", "/mnt/sub/c.txt")?;
+    fs::rename("/mnt/sub/a.txt", "/mnt/sub/c.txt")?;
+    assert_eq!(fs::read_to_string("/mnt/sub/a.txt")?, "sub");
+    assert_eq!(fs::read_to_string("/mnt/sub/c.txt")?, "sub");
+    fs::remove_file("/mnt/sub/c.txt")?;
+    fs::remove_file("/mnt/sub/d/f")?;
+    fs::remove_dir("/mnt/sub/d")?;
+    fs::remove_file("/mnt/sub/a.txt")?;
+    assert_err!(fs::remove_dir("/mnt/sub"), PermissionDenied);
+
+    // a read-only bind mount, without the nested mount
+    fs::create_dir("/bind")?;
+    fs::mount("/mnt", "/bind", "", MountFlags::BIND | MountFlags::RDONLY)?;
+    assert_eq!(fs::read_to_string("/bind/a.txt")?, "mnt");
+    assert_eq!(fs::read_dir("/bind/sub")?.count(), 0);
+    assert_err!(fs::write("/bind/a.txt", "bind"), ReadOnlyFilesystem);
+    assert_err!(fs::write("/bind/new.txt", "bind"), ReadOnlyFilesystem);
+    assert_err!(fs::create_dir("/bind/dir"), ReadOnlyFilesystem);
+    assert_err!(fs::remove_file("/bind/a.txt"), ReadOnlyFilesystem);
+    let perm = fs::Permissions::from_bits_truncate(0o600);
+    assert_err!(fs::set_permissions("/bind/a.txt", perm), ReadOnlyFilesystem);
+    assert!(File::open("/bind/a.txt").is_ok());
+
+    // remount it writable
+    let flags = MountFlags::REMOUNT | MountFlags::BIND;
+    fs::mount("", "/bind", "", flags)?;
+    fs::write("/bind/a.txt", "bind")?;
+    assert_eq!(fs::read_to_string("/mnt/a.txt")?, "bind");
+
+    // unmount in order
+    assert_err!(fs::umount("/mnt"), ResourceBusy);
+    fs::umount("/mnt/sub")?;
+    assert_err!(fs::umount("/mnt/sub"), InvalidInput);
+    fs::umount("/bind")?;
+    assert_eq!(fs::read_to_string("/mnt/a.txt")?, "bind");
+    fs::umount("/mnt")?;
+    assert_err!(fs::umount("/"), ResourceBusy);
+    assert_eq!(fs::read_dir("/mnt")?.count(), 0);
+    fs::remove_dir("/mnt")?;
+    fs::remove_dir("/bind")?;
+
+    println!("test_mount() OK!");
+    Ok(())
+}
+
 pub fn test_all() {
     test_read_write_file().expect("test_read_write_file() failed");
     test_read_dir().expect("test_read_dir() failed");
     test_file_permission().expect("test_file_permission() failed");
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
+    test_cached_read_write().expect("test_cached_read_write() failed");
+    test_file_handle().expect("test_file_handle() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
+    test_mount().expect("test_mount() failed");
+    #[cfg(any(feature = "myfs", feature = "ext4"))]
+    test_links().expect("test_links() failed");
+    #[cfg(any(feature = "myfs", feature = "ext4"))]
+    test_attrs().expect("test_attrs() failed");
 }
@@ -1,4 +1,4 @@
-#![cfg(not(feature = "myfs"))]
+#![cfg(not(any(feature = "myfs", feature = "ext4")))]
 
 mod test_common;
 
@@ -4,6 +4,12 @@ use aarch64_cpu::registers::{ESR_EL1, FAR_EL1};
 use tock_registers::interfaces::Readable;
 
 use super::TrapFrame;
+use crate::trap::PageFaultFlags;
+
+/// Write not Read bit in the ISS of data aborts.
+const ISS_DABT_WNR: u64 = 1 << 6;
+/// Mask of the fault status code type in the ISS of data/instruction aborts.
+const ISS_FSC_TYPE_MASK: u64 = 0b1111_00;
 
 global_asm!(include_str!("trap.S"));
 
@@ -35,51 +41,77 @@ fn invalid_exception(tf: &TrapFrame, kind: TrapKind, source: TrapSource) {
     );
 }
 
+fn handle_page_fault(tf: &TrapFrame, iss: u64, is_user: bool, is_instr: bool) {
+    let vaddr = FAR_EL1.get() as usize;
+    let access_flags = if is_instr {
+        PageFaultFlags::EXECUTE
+    } else if iss & ISS_DABT_WNR != 0 {
+        PageFaultFlags::WRITE
+    } else {
+        PageFaultFlags::READ
+    };
+
+    if !is_user && crate::trap::is_stack_guard(vaddr) {
+        crate::trap::handle_stack_overflow_extern(vaddr);
+    }
+    // Only translation, access flag and permission faults can be resolved.
+    let is_page_fault = matches!(iss & ISS_FSC_TYPE_MASK, 0b0001_00 | 0b0010_00 | 0b0011_00);
+    if is_page_fault && crate::trap::handle_page_fault_extern(vaddr.into(), access_flags, is_user) {
+        return;
+    }
+    if is_user {
+        warn!(
+            "EL0 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}",
+            tf.elr, vaddr, iss
+        );
+    } else {
+        panic!(
+            "EL1 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}:\n{:#x?}",
+            tf.elr, vaddr, iss, tf,
+        );
+    }
+}
+
 #[no_mangle]
 fn handle_sync_exception(tf: &mut TrapFrame) {
     let esr = ESR_EL1.extract();
+    let iss = esr.read(ESR_EL1::ISS);
     match esr.read_as_enum(ESR_EL1::EC) {
         Some(ESR_EL1::EC::Value::Brk64) => {
-            let iss = esr.read(ESR_EL1::ISS);
             debug!("BRK #{:#x} @ {:#x} ", iss, tf.elr);
             tf.elr += 4;
         }
+        Some(ESR_EL1::EC::Value::DataAbortLowerEL) => handle_page_fault(tf, iss, true, false),
+        Some(ESR_EL1::EC::Value::InstrAbortLowerEL) => handle_page_fault(tf, iss, true, true),
+        Some(ESR_EL1::EC::Value::DataAbortCurrentEL) => handle_page_fault(tf, iss, false, false),
+        Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => handle_page_fault(tf, iss, false, true),
         Some(ESR_EL1::EC::Value::SVC64) => {
-            warn!("No supervisor call is supported currently!");
-        }
-        Some(ESR_EL1::EC::Value::DataAbortLowerEL)
-        | Some(ESR_EL1::EC::Value::InstrAbortLowerEL) => {
-            let iss = esr.read(ESR_EL1::ISS);
-            warn!(
-                "EL0 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}",
-                tf.elr,
-                FAR_EL1.get(),
-                iss
-            );
-        }
-        Some(ESR_EL1::EC::Value::DataAbortCurrentEL)
-        | Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => {
-            let iss = esr.read(ESR_EL1::ISS);
-            panic!(
-                "EL1 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}:\n{:#x?}",
-                tf.elr,
-                FAR_EL1.get(),
-                iss,
-                tf,
-            );
+            if !crate::trap::handle_sync_exception_extern(esr.get() as _, tf) {
+                warn!("No supervisor call is supported currently!");
+            }
         }
         _ => {
-            panic!(
-                "Unhandled synchronous exception @ {:#x}: ESR={:#x} (EC {:#08b}, ISS {:#x})",
-                tf.elr,
-                esr.get(),
-                esr.read(ESR_EL1::EC),
-                esr.read(ESR_EL1::ISS),
-            );
+            if !crate::trap::handle_sync_exception_extern(esr.get() as _, tf) {
+                panic!(
+                    "Unhandled synchronous exception @ {:#x}: ESR={:#x} (EC {:#08b}, ISS {:#x})",
+                    tf.elr,
+                    esr.get(),
+                    esr.read(ESR_EL1::EC),
+                    iss,
+                );
+            }
         }
     }
 }
 
+/// Called on the overflow stack, when the trap frame can't be pushed onto the
+/// overflowed kernel stack.
+#[no_mangle]
+fn handle_stack_overflow_exception(tf: &TrapFrame) -> ! {
+    error!("Kernel stack overflow @ {:#x}:\n{:#x?}", tf.elr, tf);
+    crate::trap::handle_stack_overflow_extern(FAR_EL1.get() as usize)
+}
+
 #[no_mangle]
 fn handle_irq_exception(_tf: &TrapFrame) {
     crate::trap::handle_irq_extern(0)
@@ -1,6 +1,8 @@
 use riscv::register::scause::{self, Exception as E, Trap};
+use riscv::register::stval;
 
 use super::TrapFrame;
+use crate::trap::PageFaultFlags;
 
 include_asm_marcos!();
 
@@ -14,19 +16,53 @@ fn handle_breakpoint(sepc: &mut usize) {
     *sepc += 2
 }
 
+fn handle_page_fault(tf: &TrapFrame, access_flags: PageFaultFlags, is_user: bool) {
+    let vaddr = stval::read();
+    if !is_user && crate::trap::is_stack_guard(vaddr) {
+        crate::trap::handle_stack_overflow_extern(vaddr);
+    }
+    if !crate::trap::handle_page_fault_extern(vaddr.into(), access_flags, is_user) {
+        panic!(
+            "Unhandled {} Page Fault @ {:#x}, fault_vaddr={:#x} ({:?}):\n{:#x?}",
+            if is_user { "User" } else { "Supervisor" },
+            tf.sepc,
+            vaddr,
+            access_flags,
+            tf,
+        );
+    }
+}
+
 #[no_mangle]
-fn riscv_trap_handler(tf: &mut TrapFrame, _from_user: bool) {
+fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     let scause = scause::read();
     match scause.cause() {
+        Trap::Exception(E::LoadPageFault) => handle_page_fault(tf, PageFaultFlags::READ, from_user),
+        Trap::Exception(E::StorePageFault) => {
+            handle_page_fault(tf, PageFaultFlags::WRITE, from_user)
+        }
+        Trap::Exception(E::InstructionPageFault) => {
+            handle_page_fault(tf, PageFaultFlags::EXECUTE, from_user)
+        }
         Trap::Exception(E::Breakpoint) => handle_breakpoint(&mut tf.sepc),
         Trap::Interrupt(_) => crate::trap::handle_irq_extern(scause.bits()),
         _ => {
-            panic!(
-                "Unhandled trap {:?} @ {:#x}:\n{:#x?}",
-                scause.cause(),
-                tf.sepc,
-                tf
-            );
+            if !crate::trap::handle_sync_exception_extern(scause.bits(), tf) {
+                panic!(
+                    "Unhandled trap {:?} @ {:#x}:\n{:#x?}",
+                    scause.cause(),
+                    tf.sepc,
+                    tf
+                );
+            }
         }
     }
 }
+
+/// Called on the overflow stack, when the trap frame can't be pushed onto the
+/// overflowed kernel stack.
+#[no_mangle]
+fn riscv_stack_overflow_handler(tf: &TrapFrame) -> ! {
+    error!("Kernel stack overflow @ {:#x}:\n{:#x?}", tf.sepc, tf);
+    crate::trap::handle_stack_overflow_extern(stval::read())
+}
@@ -13,6 +13,10 @@ pub struct IdtStruct {
 }
 
 impl IdtStruct {
+    /// Index of the interrupt stack table (IST) entry used by the double
+    /// fault handler.
+    pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+
     /// Constructs a new IDT struct that filled with entries from
     /// `trap_handler_table`.
     #[allow(clippy::new_without_default)]
@@ -32,7 +36,12 @@ impl IdtStruct {
             )
         };
         for i in 0..NUM_INT {
-            entries[i].set_handler_fn(unsafe { core::mem::transmute(ENTRIES[i]) });
+            let opts = entries[i].set_handler_fn(unsafe { core::mem::transmute(ENTRIES[i]) });
+            if i == x86::irq::DOUBLE_FAULT_VECTOR as usize {
+                // Kernel stack overflows end up with double faults, which
+                // must be handled on a known good stack.
+                unsafe { opts.set_stack_index(Self::DOUBLE_FAULT_IST_INDEX) };
+            }
         }
         idt
     }
@@ -1,42 +1,70 @@
 use x86::{controlregs::cr2, irq::*};
+use x86_64::structures::idt::PageFaultErrorCode;
 
 use super::context::TrapFrame;
+use crate::trap::PageFaultFlags;
 
 core::arch::global_asm!(include_str!("trap.S"));
 
 const IRQ_VECTOR_START: u8 = 0x20;
 const IRQ_VECTOR_END: u8 = 0xff;
 
+fn handle_page_fault(tf: &TrapFrame) {
+    let vaddr = unsafe { cr2() };
+    let err = PageFaultErrorCode::from_bits_truncate(tf.error_code);
+    let mut access_flags = PageFaultFlags::empty();
+    if err.contains(PageFaultErrorCode::CAUSED_BY_WRITE) {
+        access_flags |= PageFaultFlags::WRITE;
+    } else if err.contains(PageFaultErrorCode::INSTRUCTION_FETCH) {
+        access_flags |= PageFaultFlags::EXECUTE;
+    } else {
+        access_flags |= PageFaultFlags::READ;
+    }
+
+    if !tf.is_user() && crate::trap::is_stack_guard(vaddr) {
+        crate::trap::handle_stack_overflow_extern(vaddr);
+    }
+    if crate::trap::handle_page_fault_extern(vaddr.into(), access_flags, tf.is_user()) {
+        return;
+    }
+    if tf.is_user() {
+        warn!(
+            "User #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}",
+            tf.rip, vaddr, tf.error_code,
+        );
+    } else {
+        panic!(
+            "Kernel #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}:\n{:#x?}",
+            tf.rip, vaddr, tf.error_code, tf,
+        );
+    }
+}
+
 #[no_mangle]
-fn x86_trap_handler(tf: &TrapFrame) {
+fn x86_trap_handler(tf: &mut TrapFrame) {
     match tf.vector as u8 {
-        PAGE_FAULT_VECTOR => {
-            if tf.is_user() {
-                warn!(
-                    "User #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}",
-                    tf.rip,
-                    unsafe { cr2() },
-                    tf.error_code,
-                );
-            } else {
-                panic!(
-                    "Kernel #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}:\n{:#x?}",
-                    tf.rip,
-                    unsafe { cr2() },
-                    tf.error_code,
-                    tf,
-                );
-            }
-        }
+        PAGE_FAULT_VECTOR => handle_page_fault(tf),
         BREAKPOINT_VECTOR => debug!("#BP @ {:#x} ", tf.rip),
-        GENERAL_PROTECTION_FAULT_VECTOR => {
-            panic!(
-                "#GP @ {:#x}, error_code={:#x}:\n{:#x?}",
-                tf.rip, tf.error_code, tf
-            );
+        DOUBLE_FAULT_VECTOR => {
+            // A page fault in the guard page can't push its trap frame onto
+            // the overflowed stack, and becomes a double fault.
+            let vaddr = unsafe { cr2() };
+            if crate::trap::is_stack_guard(vaddr) {
+                crate::trap::handle_stack_overflow_extern(vaddr);
+            }
+            panic!("#DF @ {:#x}:\n{:#x?}", tf.rip, tf);
         }
         IRQ_VECTOR_START..=IRQ_VECTOR_END => crate::trap::handle_irq_extern(tf.vector as _),
         _ => {
+            if crate::trap::handle_sync_exception_extern(tf.vector as _, tf) {
+                return;
+            }
+            if tf.vector as u8 == GENERAL_PROTECTION_FAULT_VECTOR {
+                panic!(
+                    "#GP @ {:#x}, error_code={:#x}:\n{:#x?}",
+                    tf.rip, tf.error_code, tf
+                );
+            }
             panic!(
                 "Unhandled exception {} (error_code = {:#x}) @ {:#x}:\n{:#x?}",
                 tf.vector, tf.error_code, tf.rip, tf
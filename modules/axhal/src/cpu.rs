@@ -9,6 +9,12 @@ static IS_BSP: bool = false;
 #[percpu::def_percpu]
 static CURRENT_TASK_PTR: usize = 0;
 
+/// Bottom of the current task stack, right above its guard page. It's read by
+/// the trap entries to detect kernel stack overflows.
+#[no_mangle]
+#[percpu::def_percpu]
+static STACK_LIMIT: usize = 0;
+
 /// Returns the ID of the current CPU.
 #[inline]
 pub fn this_cpu_id() -> usize {
@@ -73,6 +79,26 @@ pub unsafe fn set_current_task_ptr<T>(ptr: *const T) {
     }
 }
 
+/// Returns the bottom of the current task stack, right above its guard page.
+///
+/// Returns 0 if the current stack has no guard page.
+#[inline]
+pub fn current_stack_limit() -> usize {
+    STACK_LIMIT.read_current()
+}
+
+/// Sets the bottom of the current task stack, right above its guard page, or
+/// 0 if the stack has no guard page.
+///
+/// # Safety
+///
+/// It must be called with IRQs disabled, right before switching to the new
+/// stack. The page below `limit` must be unmapped.
+#[inline]
+pub unsafe fn set_current_stack_limit(limit: usize) {
+    STACK_LIMIT.write_current_raw(limit)
+}
+
 #[allow(dead_code)]
 pub(crate) fn init_primary(cpu_id: usize) {
     percpu::init(axconfig::SMP);
@@ -81,6 +107,7 @@ pub(crate) fn init_primary(cpu_id: usize) {
         CPU_ID.write_current_raw(cpu_id);
         IS_BSP.write_current_raw(true);
     }
+    crate::trap::init_percpu(cpu_id);
 }
 
 #[allow(dead_code)]
@@ -90,4 +117,5 @@ pub(crate) fn init_secondary(cpu_id: usize) {
         CPU_ID.write_current_raw(cpu_id);
         IS_BSP.write_current_raw(false);
     }
+    crate::trap::init_percpu(cpu_id);
 }
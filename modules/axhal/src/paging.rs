@@ -1,7 +1,9 @@
 //! Page table manipulation.
 
 use axalloc::global_allocator;
+use lazy_init::LazyInit;
 use page_table::PagingIf;
+use spinlock::SpinNoIrq;
 
 use crate::mem::{phys_to_virt, virt_to_phys, MemRegionFlags, PhysAddr, VirtAddr, PAGE_SIZE_4K};
 
@@ -64,3 +66,19 @@ cfg_if::cfg_if! {
         pub type PageTable = page_table::aarch64::A64PageTable<PagingIfImpl>;
     }
 }
+
+static KERNEL_PAGE_TABLE: LazyInit<SpinNoIrq<PageTable>> = LazyInit::new();
+
+/// Sets the page table of the kernel address space, which is shared by all
+/// CPUs.
+///
+/// It can only be called once.
+pub fn init_kernel_page_table(page_table: PageTable) {
+    KERNEL_PAGE_TABLE.init_by(SpinNoIrq::new(page_table));
+}
+
+/// Returns the page table of the kernel address space, or [`None`] if it's
+/// not initialized.
+pub fn kernel_page_table() -> Option<&'static SpinNoIrq<PageTable>> {
+    KERNEL_PAGE_TABLE.try_get()
+}
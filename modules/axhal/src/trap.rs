@@ -1,6 +1,23 @@
 //! Trap handling.
 
 use crate_interface::{call_interface, def_interface};
+use memory_addr::VirtAddr;
+
+use crate::arch::TrapFrame;
+
+pub use page_table_entry::MappingFlags as PageFaultFlags;
+
+/// Size of the per-CPU stack to handle kernel stack overflows.
+const OVERFLOW_STACK_SIZE: usize = 0x4000;
+
+/// Stacks to handle kernel stack overflows, one for each CPU.
+static mut OVERFLOW_STACKS: [[u8; OVERFLOW_STACK_SIZE]; axconfig::SMP] =
+    [[0; OVERFLOW_STACK_SIZE]; axconfig::SMP];
+
+/// Top of the overflow stack of the current CPU, used by the trap entries.
+#[no_mangle]
+#[percpu::def_percpu]
+static OVERFLOW_STACK_TOP: usize = 0;
 
 /// Trap handler interface.
 ///
@@ -13,7 +30,27 @@ use crate_interface::{call_interface, def_interface};
 pub trait TrapHandler {
     /// Handles interrupt requests for the given IRQ number.
     fn handle_irq(irq_num: usize);
-    // more e.g.: handle_page_fault();
+    /// Handles a page fault at `vaddr`, where `access_flags` tells whether it
+    /// was a read, write or instruction fetch, and `is_user` tells whether it
+    /// occurred in user mode.
+    ///
+    /// Returns `true` if the fault is resolved (e.g., the page is mapped now)
+    /// and the faulting instruction can be retried.
+    fn handle_page_fault(vaddr: VirtAddr, access_flags: PageFaultFlags, is_user: bool) -> bool;
+    /// Handles a synchronous exception that is not handled by axhal, e.g.,
+    /// a system call or an illegal instruction.
+    ///
+    /// `code` is the architecture-specific exception cause, i.e., the vector
+    /// number on x86_64, `scause` on RISC-V, and `ESR_EL1` on AArch64. The
+    /// trap frame may be modified, e.g., to skip the faulting instruction.
+    ///
+    /// Returns `true` if the exception is handled.
+    fn handle_sync_exception(code: usize, tf: &mut TrapFrame) -> bool;
+    /// Handles a kernel stack overflow of the current task, which is detected
+    /// by a fault at `fault_vaddr` in the guard page below the task stack.
+    ///
+    /// It runs on a separate per-CPU stack, and should not return.
+    fn handle_stack_overflow(fault_vaddr: usize);
 }
 
 /// Call the external IRQ handler.
@@ -21,3 +58,47 @@ pub trait TrapHandler {
 pub(crate) fn handle_irq_extern(irq_num: usize) {
     call_interface!(TrapHandler::handle_irq, irq_num);
 }
+
+/// Call the external page fault handler.
+#[allow(dead_code)]
+pub(crate) fn handle_page_fault_extern(
+    vaddr: VirtAddr,
+    access_flags: PageFaultFlags,
+    is_user: bool,
+) -> bool {
+    call_interface!(TrapHandler::handle_page_fault, vaddr, access_flags, is_user)
+}
+
+/// Call the external synchronous exception handler.
+#[allow(dead_code)]
+pub(crate) fn handle_sync_exception_extern(code: usize, tf: &mut TrapFrame) -> bool {
+    call_interface!(TrapHandler::handle_sync_exception, code, tf)
+}
+
+/// Call the external kernel stack overflow handler.
+#[allow(dead_code)]
+pub(crate) fn handle_stack_overflow_extern(fault_vaddr: usize) -> ! {
+    call_interface!(TrapHandler::handle_stack_overflow, fault_vaddr);
+    panic!("Kernel stack overflow, fault_vaddr={:#x}", fault_vaddr);
+}
+
+/// Returns whether `vaddr` is in the guard page below the current task stack.
+#[allow(dead_code)]
+pub(crate) fn is_stack_guard(vaddr: usize) -> bool {
+    let limit = crate::cpu::current_stack_limit();
+    limit != 0 && (limit - crate::mem::PAGE_SIZE_4K..limit).contains(&vaddr)
+}
+
+/// Returns the top of the overflow stack of the current CPU.
+#[allow(dead_code)]
+pub(crate) fn overflow_stack_top() -> usize {
+    unsafe { OVERFLOW_STACK_TOP.read_current_raw() }
+}
+
+/// Sets up the overflow stack of the current CPU.
+#[allow(dead_code)]
+pub(crate) fn init_percpu(cpu_id: usize) {
+    let top =
+        unsafe { core::ptr::addr_of!(OVERFLOW_STACKS[cpu_id]) as usize } + OVERFLOW_STACK_SIZE;
+    unsafe { OVERFLOW_STACK_TOP.write_current_raw(top) };
+}
@@ -0,0 +1,556 @@
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::fmt;
+
+use axalloc::GlobalPage;
+use axerrno::{ax_err, AxResult};
+use axhal::mem::{virt_to_phys, PAGE_SIZE_4K};
+use axhal::paging::{MappingFlags, PageSize};
+use memory_addr::{PhysAddr, VirtAddr};
+
+use crate::aspace::PageTableRef;
+use crate::{flush_tlb, paging_err_to_ax_err};
+
+/// A file that can back a [`MemoryArea`].
+pub trait FileBackend: Send + Sync {
+    /// Reads data from the file at `offset` into `buf`, returns the number of
+    /// bytes read. Returns `0` if `offset` is beyond the end of the file.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize>;
+    /// Writes data in `buf` to the file at `offset`, returns the number of
+    /// bytes written.
+    fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize>;
+    /// Returns the size of the file in bytes.
+    fn size(&self) -> AxResult<u64>;
+    /// Whether the file can be written, which is required by shared mappings
+    /// with write access.
+    fn is_writable(&self) -> bool;
+}
+
+/// Where the physical frames of a [`MemoryArea`] come from.
+#[derive(Clone)]
+pub enum Backend {
+    /// Linear mapping to a contiguous physical memory region, whose start
+    /// address is `vaddr - pa_va_offset`. All pages are mapped at once.
+    Linear {
+        /// `vaddr - paddr` of the mapping.
+        pa_va_offset: usize,
+    },
+    /// Zero-filled anonymous memory allocated from the global allocator.
+    ///
+    /// If `populate` is `false`, each page is allocated on its first access.
+    /// With huge pages, the area must be aligned to `page_size`, and the
+    /// pages are mapped by huge page table entries to reduce TLB misses.
+    Alloc {
+        /// Whether to allocate and map all pages at once.
+        populate: bool,
+        /// Size of the pages to allocate and map.
+        page_size: PageSize,
+    },
+    /// Pages are allocated and filled with the content of `file` (starting
+    /// from `offset`) on their first access.
+    File {
+        /// The file to read pages from.
+        file: Arc<dyn FileBackend>,
+        /// Offset in the file of the first page of the area.
+        offset: u64,
+        /// Whether modifications are written back to the file when the pages
+        /// are synchronized, discarded or unmapped.
+        shared: bool,
+    },
+}
+
+impl fmt::Debug for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Linear { pa_va_offset } => f
+                .debug_struct("Linear")
+                .field("pa_va_offset", &format_args!("{:#x}", pa_va_offset))
+                .finish(),
+            Self::Alloc {
+                populate,
+                page_size,
+            } => f
+                .debug_struct("Alloc")
+                .field("populate", populate)
+                .field("page_size", page_size)
+                .finish(),
+            Self::File { offset, shared, .. } => f
+                .debug_struct("File")
+                .field("offset", offset)
+                .field("shared", shared)
+                .finish(),
+        }
+    }
+}
+
+impl Backend {
+    /// Whether an area with this backend can be merged with the next area
+    /// with the `next` backend, when the first area is `size` bytes.
+    fn can_merge(&self, size: usize, next: &Self) -> bool {
+        match (self, next) {
+            (Self::Linear { pa_va_offset: a }, Self::Linear { pa_va_offset: b }) => a == b,
+            (
+                Self::Alloc {
+                    populate: p1,
+                    page_size: s1,
+                },
+                Self::Alloc {
+                    populate: p2,
+                    page_size: s2,
+                },
+            ) => p1 == p2 && s1 == s2,
+            (
+                Self::File {
+                    file: f1,
+                    offset: o1,
+                    shared: s1,
+                },
+                Self::File {
+                    file: f2,
+                    offset: o2,
+                    shared: s2,
+                },
+            ) => same_file(f1, f2) && o1 + size as u64 == *o2 && s1 == s2,
+            _ => false,
+        }
+    }
+
+    /// Checks that the backend can be mapped with `flags`.
+    pub(crate) fn check_flags(&self, flags: MappingFlags) -> AxResult {
+        match self {
+            Self::File {
+                file, shared: true, ..
+            } if flags.contains(MappingFlags::WRITE) && !file.is_writable() => {
+                ax_err!(PermissionDenied, "shared mapping of a read-only file")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether `a` and `b` point to the same file (comparing the data pointers
+/// only, as the vtables may differ).
+fn same_file(a: &Arc<dyn FileBackend>, b: &Arc<dyn FileBackend>) -> bool {
+    core::ptr::addr_eq(Arc::as_ptr(a), Arc::as_ptr(b))
+}
+
+/// Pages of shared file mappings to be written back to the files.
+///
+/// Writing files may sleep, so the operations of
+/// [`AddrSpace`](crate::AddrSpace) only collect the pages, and the caller
+/// writes them back by [`WriteBack::run`] after releasing the lock of the
+/// address space. For the same reason, it also holds the files of the
+/// removed areas, which are closed when it's dropped.
+#[derive(Default)]
+#[must_use = "pages are not written back until `run` is called"]
+pub struct WriteBack {
+    pages: Vec<(Arc<dyn FileBackend>, u64, Arc<GlobalPage>)>,
+    files: Vec<Arc<dyn FileBackend>>,
+}
+
+impl WriteBack {
+    /// Whether there is no page to write back.
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Adds the pages of `other` to `self`.
+    pub fn append(&mut self, mut other: Self) {
+        self.pages.append(&mut other.pages);
+        self.files.append(&mut other.files);
+    }
+
+    /// Writes the pages back to the files. Only the part within the current
+    /// file size is written.
+    ///
+    /// All pages are written even if some of them fail, and the first error
+    /// is returned.
+    pub fn run(self) -> AxResult {
+        let mut res = Ok(());
+        for (file, pos, frame) in self.pages {
+            let file_size = match file.size() {
+                Ok(size) => size,
+                Err(e) => {
+                    res = res.and(Err(e));
+                    continue;
+                }
+            };
+            if pos < file_size {
+                let len = PAGE_SIZE_4K.min((file_size - pos) as usize);
+                res = res.and(write_all_at(file.as_ref(), pos, &frame.as_slice()[..len]));
+            }
+        }
+        res
+    }
+}
+
+/// A page of a file mapping to be read from the file, see
+/// [`MemoryArea::handle_page_fault`].
+pub(crate) struct FilePage {
+    file: Arc<dyn FileBackend>,
+    pos: u64,
+}
+
+impl FilePage {
+    /// Allocates a frame and fills it with the file content. It may sleep.
+    pub(crate) fn read(&self) -> AxResult<GlobalPage> {
+        let mut frame = GlobalPage::alloc_zero()?;
+        read_all_at(self.file.as_ref(), self.pos, frame.as_slice_mut())?;
+        Ok(frame)
+    }
+}
+
+/// The result of handling a page fault in a [`MemoryArea`].
+pub(crate) enum PageFault {
+    /// The page is mapped with the required access now.
+    Resolved,
+    /// It's a real access violation, or the page can not be mapped.
+    Invalid,
+    /// The page must be read from the file first, without holding the lock
+    /// of the address space. Then the fault is handled again with the frame.
+    Fill(FilePage),
+}
+
+/// A contiguous virtual memory region with the same mapping flags and backend.
+pub struct MemoryArea {
+    start: VirtAddr,
+    size: usize,
+    flags: MappingFlags,
+    backend: Backend,
+    /// Frames allocated for the `Alloc` and `File` backends, indexed by the
+    /// virtual address they are mapped to. They are shared with [`WriteBack`]
+    /// until written back.
+    frames: BTreeMap<VirtAddr, Arc<GlobalPage>>,
+}
+
+impl MemoryArea {
+    pub(crate) fn new(start: VirtAddr, size: usize, flags: MappingFlags, backend: Backend) -> Self {
+        Self {
+            start,
+            size,
+            flags,
+            backend,
+            frames: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the start virtual address of the area.
+    pub const fn start(&self) -> VirtAddr {
+        self.start
+    }
+
+    /// Returns the end virtual address (exclusive) of the area.
+    pub fn end(&self) -> VirtAddr {
+        self.start + self.size
+    }
+
+    /// Returns the size (in bytes) of the area.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the mapping flags of the area.
+    pub const fn flags(&self) -> MappingFlags {
+        self.flags
+    }
+
+    /// Returns the backend of the area.
+    pub const fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Returns the size of the pages that the area is mapped with.
+    pub const fn page_size(&self) -> PageSize {
+        match self.backend {
+            Backend::Alloc { page_size, .. } => page_size,
+            _ => PageSize::Size4K,
+        }
+    }
+
+    /// Returns the number of bytes of physical memory allocated for the area.
+    ///
+    /// It's always `0` for the linear backend.
+    pub fn committed_size(&self) -> usize {
+        self.frames.values().map(|frame| frame.size()).sum()
+    }
+
+    /// Whether `vaddr` is in the area.
+    pub fn contains(&self, vaddr: VirtAddr) -> bool {
+        self.start <= vaddr && vaddr < self.end()
+    }
+
+    fn pages(&self) -> impl Iterator<Item = VirtAddr> {
+        let start = self.start.as_usize();
+        (start..start + self.size)
+            .step_by(self.page_size().into())
+            .map(VirtAddr::from)
+    }
+
+    /// Creates the initial mappings of the area.
+    pub(crate) fn map(&mut self, pt: &mut PageTableRef) -> AxResult {
+        match self.backend {
+            Backend::Linear { pa_va_offset } => {
+                let paddr = PhysAddr::from(self.start.as_usize() - pa_va_offset);
+                // Not use huge pages, so that the area can be split later.
+                pt.with(|pt| pt.map_region(self.start, paddr, self.size, self.flags, false))
+                    .map_err(paging_err_to_ax_err)
+            }
+            Backend::Alloc { populate: true, .. } => {
+                for vaddr in self.pages() {
+                    if let Err(e) = self
+                        .alloc_frame()
+                        .and_then(|f| self.map_frame(pt, vaddr, f))
+                    {
+                        // No page to write back for anonymous areas.
+                        let _ = self.unmap(pt)?;
+                        return Err(e);
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes all mappings of the area, and frees the allocated frames.
+    ///
+    /// Pages of shared file mappings are returned to be written back, along
+    /// with the file so that it's not closed with the lock held.
+    pub(crate) fn unmap(&mut self, pt: &mut PageTableRef) -> AxResult<WriteBack> {
+        if let Backend::Linear { .. } = self.backend {
+            pt.with(|pt| pt.unmap_region(self.start, self.size))
+                .map_err(paging_err_to_ax_err)?;
+            flush_tlb(None);
+            return Ok(WriteBack::default());
+        }
+        let mut write_back = self.discard(pt, self.start, self.end())?;
+        if let Backend::File { file, .. } = &self.backend {
+            write_back.files.push(file.clone());
+        }
+        Ok(write_back)
+    }
+
+    /// Changes the mapping flags of the area, and updates the present
+    /// mappings.
+    pub(crate) fn protect(&mut self, pt: &mut PageTableRef, flags: MappingFlags) -> AxResult {
+        let update = |pt: &mut PageTableRef, vaddr: VirtAddr| -> AxResult {
+            pt.with(|pt| pt.update(vaddr, None, Some(flags)))
+                .map_err(paging_err_to_ax_err)?;
+            flush_tlb(Some(vaddr));
+            Ok(())
+        };
+        match self.backend {
+            Backend::Linear { .. } => self.pages().try_for_each(|vaddr| update(pt, vaddr))?,
+            _ => self
+                .frames
+                .keys()
+                .try_for_each(|&vaddr| update(pt, vaddr))?,
+        }
+        self.flags = flags;
+        Ok(())
+    }
+
+    /// Returns the allocated pages in `[start, end)` to be written back to
+    /// the file, if the area is a shared file mapping.
+    pub(crate) fn write_back(&self, start: VirtAddr, end: VirtAddr) -> WriteBack {
+        let Backend::File {
+            file,
+            offset,
+            shared: true,
+        } = &self.backend
+        else {
+            return WriteBack::default();
+        };
+        WriteBack {
+            pages: self
+                .frames
+                .range(start..end)
+                .map(|(&vaddr, frame)| {
+                    let pos = offset + (vaddr.as_usize() - self.start.as_usize()) as u64;
+                    (file.clone(), pos, frame.clone())
+                })
+                .collect(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Frees the allocated pages in `[start, end)`, so that they will be
+    /// allocated and filled again on the next access.
+    ///
+    /// Pages of shared file mappings are returned to be written back. It has
+    /// no effect on the linear backend.
+    pub(crate) fn discard(
+        &mut self,
+        pt: &mut PageTableRef,
+        start: VirtAddr,
+        end: VirtAddr,
+    ) -> AxResult<WriteBack> {
+        if let Backend::Linear { .. } = self.backend {
+            return Ok(WriteBack::default());
+        }
+        let write_back = self.write_back(start, end);
+        while let Some((&vaddr, _)) = self.frames.range(start..end).next() {
+            pt.with(|pt| pt.unmap(vaddr))
+                .map_err(paging_err_to_ax_err)?;
+            flush_tlb(Some(vaddr));
+            // Free the frame after it is no longer accessible.
+            self.frames.remove(&vaddr);
+        }
+        Ok(write_back)
+    }
+
+    /// Splits the area at `pos`, and returns the part after `pos`.
+    ///
+    /// `pos` must be aligned to [`page_size`](Self::page_size) and within
+    /// `(start, end)`.
+    pub(crate) fn split(&mut self, pos: VirtAddr) -> Self {
+        debug_assert!(self.start < pos && pos < self.end() && pos.is_aligned(self.page_size()));
+        let left_size = pos.as_usize() - self.start.as_usize();
+        let backend = match &self.backend {
+            Backend::File {
+                file,
+                offset,
+                shared,
+            } => Backend::File {
+                file: file.clone(),
+                offset: offset + left_size as u64,
+                shared: *shared,
+            },
+            backend => backend.clone(),
+        };
+        let right = Self {
+            start: pos,
+            size: self.size - left_size,
+            flags: self.flags,
+            backend,
+            frames: self.frames.split_off(&pos),
+        };
+        self.size = left_size;
+        right
+    }
+
+    /// Merges `next` into the area if it starts at the end of the area, and
+    /// has the same flags and a compatible backend. Otherwise, returns `next`
+    /// back.
+    pub(crate) fn merge(&mut self, mut next: Self) -> Result<(), Self> {
+        if next.start != self.end()
+            || next.flags.bits() != self.flags.bits()
+            || !self.backend.can_merge(self.size, &next.backend)
+        {
+            return Err(next);
+        }
+        self.size += next.size;
+        self.frames.append(&mut next.frames);
+        Ok(())
+    }
+
+    /// Handles a page fault at `vaddr` in the area.
+    ///
+    /// Pages of file mappings are not read here, as it may sleep. Instead,
+    /// [`PageFault::Fill`] is returned, and the fault should be handled again
+    /// with the page and the frame read by [`FilePage::read`] as `filled`.
+    pub(crate) fn handle_page_fault(
+        &mut self,
+        pt: &mut PageTableRef,
+        vaddr: VirtAddr,
+        access_flags: MappingFlags,
+        filled: Option<(FilePage, GlobalPage)>,
+    ) -> PageFault {
+        if !self.flags.contains(access_flags) {
+            return PageFault::Invalid;
+        }
+        let vaddr = vaddr.align_down(self.page_size());
+        if let Backend::Linear { .. } = self.backend {
+            // Always mapped, it's a real access violation.
+            return PageFault::Invalid;
+        }
+        if self.frames.contains_key(&vaddr) {
+            // Mapped by another CPU, or a stale TLB entry.
+            flush_tlb(Some(vaddr));
+            return PageFault::Resolved;
+        }
+        let frame = match &self.backend {
+            Backend::File { file, offset, .. } => {
+                let pos = offset + (vaddr.as_usize() - self.start.as_usize()) as u64;
+                match filled {
+                    // The area may be remapped while the page is read.
+                    Some((page, frame)) if same_file(&page.file, file) && page.pos == pos => {
+                        Ok(frame)
+                    }
+                    _ => {
+                        let file = file.clone();
+                        return PageFault::Fill(FilePage { file, pos });
+                    }
+                }
+            }
+            _ => self.alloc_frame(),
+        };
+        match frame.and_then(|frame| self.map_frame(pt, vaddr, frame)) {
+            Ok(()) => PageFault::Resolved,
+            Err(e) => {
+                warn!("failed to populate page {:#x}: {:?}", vaddr, e);
+                PageFault::Invalid
+            }
+        }
+    }
+
+    /// Allocates a zero-filled frame of the page size.
+    fn alloc_frame(&self) -> AxResult<GlobalPage> {
+        match self.page_size() {
+            PageSize::Size4K => Ok(GlobalPage::alloc_zero()?),
+            page_size => Ok(GlobalPage::alloc_huge_zero(page_size.into())?),
+        }
+    }
+
+    /// Maps the page at `vaddr` to `frame`.
+    fn map_frame(&mut self, pt: &mut PageTableRef, vaddr: VirtAddr, frame: GlobalPage) -> AxResult {
+        let page_size = self.page_size();
+        let paddr = frame.start_paddr(virt_to_phys);
+        if !paddr.is_aligned(page_size) {
+            // The offset of the linear mapping is not aligned to the page size.
+            return ax_err!(Unsupported, "huge page is not aligned in physical memory");
+        }
+        pt.with(|pt| pt.map(vaddr, paddr, page_size, self.flags))
+            .map_err(paging_err_to_ax_err)?;
+        self.frames.insert(vaddr, Arc::new(frame));
+        Ok(())
+    }
+}
+
+impl fmt::Debug for MemoryArea {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryArea")
+            .field("va_range", &(self.start..self.end()))
+            .field("flags", &self.flags)
+            .field("backend", &self.backend)
+            .field("committed", &self.committed_size())
+            .finish()
+    }
+}
+
+/// Fills `buf` with the file content at `pos`. The part beyond the end of the
+/// file is left unchanged.
+fn read_all_at(file: &dyn FileBackend, mut pos: u64, mut buf: &mut [u8]) -> AxResult {
+    while !buf.is_empty() {
+        let n = file.read_at(pos, buf)?;
+        if n == 0 {
+            break;
+        }
+        pos += n as u64;
+        let tmp = buf;
+        buf = &mut tmp[n..];
+    }
+    Ok(())
+}
+
+fn write_all_at(file: &dyn FileBackend, mut pos: u64, mut buf: &[u8]) -> AxResult {
+    while !buf.is_empty() {
+        match file.write_at(pos, buf)? {
+            0 => return ax_err!(WriteZero),
+            n => {
+                pos += n as u64;
+                buf = &buf[n..];
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,807 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::fmt;
+
+use axalloc::GlobalPage;
+use axerrno::{ax_err, AxResult};
+use axhal::paging::{MappingFlags, PageTable};
+use memory_addr::{is_aligned, is_aligned_4k, PhysAddr, VirtAddr};
+use spinlock::SpinNoIrq;
+
+use crate::area::{Backend, FilePage, MemoryArea, PageFault, WriteBack};
+use crate::paging_err_to_ax_err;
+
+/// The page table used by an address space.
+pub(crate) enum PageTableRef {
+    /// Owned by the address space.
+    Owned(PageTable),
+    /// Shared with others, e.g., the kernel page table.
+    Shared(&'static SpinNoIrq<PageTable>),
+}
+
+impl PageTableRef {
+    /// Calls `f` with the page table, which is locked during the call if it's
+    /// shared.
+    pub(crate) fn with<R>(&mut self, f: impl FnOnce(&mut PageTable) -> R) -> R {
+        match self {
+            Self::Owned(pt) => f(pt),
+            Self::Shared(pt) => f(&mut pt.lock()),
+        }
+    }
+}
+
+/// A virtual address space, which consists of non-overlapping memory areas.
+///
+/// Only the mappings in `[base, end)` are managed by the address space.
+/// Adjacent areas with the same flags and compatible backends are merged.
+///
+/// The operations never perform file I/O, so that the address space can be
+/// protected by a spinlock and accessed in page fault handlers. The pages of
+/// shared file mappings to be written back are returned as [`WriteBack`],
+/// which should be run after releasing the lock.
+pub struct AddrSpace {
+    base: VirtAddr,
+    end: VirtAddr,
+    areas: BTreeMap<VirtAddr, MemoryArea>,
+    pt: PageTableRef,
+}
+
+impl AddrSpace {
+    /// Creates a new empty address space in `[base, base + size)`, with its
+    /// own page table.
+    pub fn new_empty(base: VirtAddr, size: usize) -> AxResult<Self> {
+        Ok(Self {
+            base,
+            end: base + size,
+            areas: BTreeMap::new(),
+            pt: PageTableRef::Owned(PageTable::try_new().map_err(paging_err_to_ax_err)?),
+        })
+    }
+
+    /// Creates a new empty address space in `[base, base + size)`, which
+    /// shares the page table `pt` with others.
+    pub(crate) fn new_shared(
+        base: VirtAddr,
+        size: usize,
+        pt: &'static SpinNoIrq<PageTable>,
+    ) -> Self {
+        Self {
+            base,
+            end: base + size,
+            areas: BTreeMap::new(),
+            pt: PageTableRef::Shared(pt),
+        }
+    }
+
+    /// Returns the base address of the address space.
+    pub const fn base(&self) -> VirtAddr {
+        self.base
+    }
+
+    /// Returns the end address (exclusive) of the address space.
+    pub const fn end(&self) -> VirtAddr {
+        self.end
+    }
+
+    /// Returns the size (in bytes) of the address space.
+    pub const fn size(&self) -> usize {
+        self.end.as_usize() - self.base.as_usize()
+    }
+
+    /// Returns the physical address of the root page table.
+    pub fn page_table_root(&self) -> PhysAddr {
+        match &self.pt {
+            PageTableRef::Owned(pt) => pt.root_paddr(),
+            PageTableRef::Shared(pt) => pt.lock().root_paddr(),
+        }
+    }
+
+    /// Returns an iterator over all memory areas, in ascending order of the
+    /// start address.
+    pub fn areas(&self) -> impl Iterator<Item = &MemoryArea> {
+        self.areas.values()
+    }
+
+    /// Returns the memory area that contains `vaddr`.
+    pub fn find_area(&self, vaddr: VirtAddr) -> Option<&MemoryArea> {
+        self.areas
+            .range(..=vaddr)
+            .next_back()
+            .map(|(_, area)| area)
+            .filter(|area| area.contains(vaddr))
+    }
+
+    /// Finds a free region of `size` bytes that does not overlap with any
+    /// memory area, and returns its start address, which is aligned to
+    /// `align` (a power of two, e.g., a page size).
+    ///
+    /// The search starts from `hint` and the lowest suitable address is
+    /// returned. Returns [`None`] if no such region.
+    pub fn find_free_area(&self, hint: VirtAddr, size: usize, align: usize) -> Option<VirtAddr> {
+        let mut start = hint.max(self.base).align_up(align);
+        for area in self.areas.values() {
+            if area.end() <= start {
+                continue;
+            }
+            if start.as_usize().checked_add(size)? <= area.start().as_usize() {
+                return Some(start);
+            }
+            start = area.end().align_up(align);
+        }
+        if start.as_usize().checked_add(size)? <= self.end.as_usize() {
+            Some(start)
+        } else {
+            None
+        }
+    }
+
+    /// Adds a new memory area `[start, start + size)` with the given mapping
+    /// `flags` and `backend`.
+    ///
+    /// Returns [`AlreadyExists`](axerrno::AxError::AlreadyExists) if the area
+    /// overlaps with an existing one, or
+    /// [`PermissionDenied`](axerrno::AxError::PermissionDenied) if it's a
+    /// shared mapping of a read-only file with write access. Except for the
+    /// linear backend and the populated allocation backend, no physical memory
+    /// is committed until the pages are accessed. Areas with huge pages must
+    /// be aligned to the huge page size.
+    pub fn map(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        backend: Backend,
+    ) -> AxResult {
+        self.validate_map(start, size, flags, &backend)?;
+        if self.overlaps(start, start + size) {
+            return ax_err!(AlreadyExists, "the area overlaps with an existing one");
+        }
+        self.map_unchecked(start, size, flags, backend)
+    }
+
+    /// Adds a new memory area like [`AddrSpace::map`], but replaces the
+    /// existing mappings in `[start, start + size)`.
+    ///
+    /// The arguments are validated before the existing mappings are removed,
+    /// so that they are kept on invalid arguments. Pages of the removed shared
+    /// file mappings are returned to be written back.
+    pub fn map_fixed(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        backend: Backend,
+    ) -> AxResult<WriteBack> {
+        self.validate_map(start, size, flags, &backend)?;
+        let write_back = self.unmap(start, size)?;
+        self.map_unchecked(start, size, flags, backend)?;
+        Ok(write_back)
+    }
+
+    fn validate_map(
+        &self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        backend: &Backend,
+    ) -> AxResult {
+        self.validate_range(start, size)?;
+        self.check_boundary(start)?;
+        self.check_boundary(start + size)?;
+        match *backend {
+            Backend::Linear { pa_va_offset } if !is_aligned_4k(pa_va_offset) => {
+                return ax_err!(InvalidInput, "pa_va_offset is not page-aligned");
+            }
+            Backend::Alloc { page_size, .. }
+                if !start.is_aligned(page_size) || !is_aligned(size, page_size.into()) =>
+            {
+                return ax_err!(InvalidInput, "the area is not aligned to the page size");
+            }
+            _ => {}
+        }
+        backend.check_flags(flags)
+    }
+
+    fn map_unchecked(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        backend: Backend,
+    ) -> AxResult {
+        debug!(
+            "map [{:#x}, {:#x}) {:?} {:?}",
+            start,
+            start + size,
+            flags,
+            backend
+        );
+        let mut area = MemoryArea::new(start, size, flags, backend);
+        area.map(&mut self.pt)?;
+        self.areas.insert(start, area);
+        self.merge_around(start, start + size);
+        Ok(())
+    }
+
+    /// Removes the mappings in `[start, start + size)`, and frees the
+    /// physical memory allocated for them.
+    ///
+    /// Memory areas that partially overlap with the region are shrunk or
+    /// split. It's not an error if the region is not mapped. Pages of shared
+    /// file mappings are returned to be written back.
+    pub fn unmap(&mut self, start: VirtAddr, size: usize) -> AxResult<WriteBack> {
+        self.validate_range(start, size)?;
+        let end = start + size;
+        debug!("unmap [{:#x}, {:#x})", start, end);
+
+        self.check_boundary(start)?;
+        self.check_boundary(end)?;
+        self.split_at(start);
+        self.split_at(end);
+        let mut write_back = WriteBack::default();
+        while let Some((&vaddr, _)) = self.areas.range(start..end).next() {
+            let mut area = self.areas.remove(&vaddr).unwrap();
+            write_back.append(area.unmap(&mut self.pt)?);
+        }
+        Ok(write_back)
+    }
+
+    /// Changes the mapping flags of `[start, start + size)` to `flags`.
+    ///
+    /// Memory areas that partially overlap with the region are split. The
+    /// unmapped parts of the region are skipped. Returns
+    /// [`PermissionDenied`](axerrno::AxError::PermissionDenied) without
+    /// changing anything if write access is added to a shared mapping of a
+    /// read-only file.
+    pub fn protect(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) -> AxResult {
+        self.validate_range(start, size)?;
+        let end = start + size;
+        debug!("protect [{:#x}, {:#x}) {:?}", start, end, flags);
+
+        self.check_boundary(start)?;
+        self.check_boundary(end)?;
+        for area in self.areas_in(start, end) {
+            area.backend().check_flags(flags)?;
+        }
+        self.split_at(start);
+        self.split_at(end);
+        for area in self.areas.range_mut(start..end).map(|(_, area)| area) {
+            area.protect(&mut self.pt, flags)?;
+        }
+        self.merge_around(start, end);
+        Ok(())
+    }
+
+    /// Whether every page in `[start, start + size)` belongs to a memory area.
+    pub fn is_range_mapped(&self, start: VirtAddr, size: usize) -> bool {
+        let end = start + size;
+        let mut next = start;
+        for area in self.areas_in(start, end) {
+            if area.start() > next {
+                return false;
+            }
+            next = area.end();
+        }
+        next >= end
+    }
+
+    /// Returns the pages in `[start, start + size)` of shared file mappings
+    /// to be written back to the files.
+    ///
+    /// Only the pages that have been accessed are returned. The unmapped parts
+    /// of the region are skipped.
+    pub fn sync(&self, start: VirtAddr, size: usize) -> AxResult<WriteBack> {
+        self.validate_range(start, size)?;
+        let end = start + size;
+        let mut write_back = WriteBack::default();
+        for area in self.areas_in(start, end) {
+            write_back.append(area.write_back(start.max(area.start()), end.min(area.end())));
+        }
+        Ok(write_back)
+    }
+
+    /// Frees the physical memory allocated for `[start, start + size)`, so
+    /// that the pages are allocated and filled again on the next access.
+    ///
+    /// Pages of shared file mappings are returned to be written back, while
+    /// those of anonymous and private file mappings are lost. It has no
+    /// effect on the linear mappings and unmapped parts of the region.
+    pub fn discard(&mut self, start: VirtAddr, size: usize) -> AxResult<WriteBack> {
+        self.validate_range(start, size)?;
+        let end = start + size;
+        self.check_boundary(start)?;
+        self.check_boundary(end)?;
+        let mut write_back = WriteBack::default();
+        for area in self.areas.values_mut() {
+            if area.start() < end && start < area.end() {
+                let (start, end) = (start.max(area.start()), end.min(area.end()));
+                write_back.append(area.discard(&mut self.pt, start, end)?);
+            }
+        }
+        Ok(write_back)
+    }
+
+    /// Handles a page fault at `vaddr` with the given access flags in the
+    /// address space protected by `aspace`.
+    ///
+    /// Returns `true` if the fault is resolved, i.e., the page is mapped with
+    /// the required access now. The lock is released while reading pages of
+    /// file mappings, which may sleep.
+    pub fn handle_page_fault(
+        aspace: &SpinNoIrq<Self>,
+        vaddr: VirtAddr,
+        access_flags: MappingFlags,
+    ) -> bool {
+        let mut filled = None;
+        loop {
+            let page = match aspace.lock().fault(vaddr, access_flags, filled.take()) {
+                PageFault::Resolved => return true,
+                PageFault::Invalid => return false,
+                PageFault::Fill(page) => page,
+            };
+            match page.read() {
+                Ok(frame) => filled = Some((page, frame)),
+                Err(e) => {
+                    warn!("failed to read page {:#x}: {:?}", vaddr, e);
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// Removes all memory areas, and returns the pages of shared file
+    /// mappings to be written back.
+    pub fn clear(&mut self) -> WriteBack {
+        let mut write_back = WriteBack::default();
+        while let Some((_, mut area)) = self.areas.pop_first() {
+            match area.unmap(&mut self.pt) {
+                Ok(wb) => write_back.append(wb),
+                Err(e) => warn!("failed to unmap {:?}: {:?}", area, e),
+            }
+        }
+        write_back
+    }
+
+    fn fault(
+        &mut self,
+        vaddr: VirtAddr,
+        access_flags: MappingFlags,
+        filled: Option<(FilePage, GlobalPage)>,
+    ) -> PageFault {
+        let area = self
+            .areas
+            .range_mut(..=vaddr)
+            .next_back()
+            .map(|(_, area)| area)
+            .filter(|area| area.contains(vaddr));
+        match area {
+            Some(area) => area.handle_page_fault(&mut self.pt, vaddr, access_flags, filled),
+            None => PageFault::Invalid,
+        }
+    }
+
+    fn validate_range(&self, start: VirtAddr, size: usize) -> AxResult {
+        if !start.is_aligned_4k() || !is_aligned_4k(size) || size == 0 {
+            return ax_err!(InvalidInput, "address or size is not page-aligned");
+        }
+        match start.as_usize().checked_add(size) {
+            Some(end) if start >= self.base && end <= self.end.as_usize() => Ok(()),
+            _ => ax_err!(InvalidInput, "out of the address space"),
+        }
+    }
+
+    /// Checks that `pos` can be a boundary of the memory area that contains
+    /// it, i.e., it does not fall in the middle of a huge page.
+    fn check_boundary(&self, pos: VirtAddr) -> AxResult {
+        match self.find_area(pos) {
+            Some(area) if !pos.is_aligned(area.page_size()) => {
+                ax_err!(InvalidInput, "address is not aligned to the huge page size")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the memory areas that overlap with `[start, end)`.
+    fn areas_in(&self, start: VirtAddr, end: VirtAddr) -> impl Iterator<Item = &MemoryArea> {
+        self.areas
+            .values()
+            .skip_while(move |area| area.end() <= start)
+            .take_while(move |area| area.start() < end)
+    }
+
+    fn overlaps(&self, start: VirtAddr, end: VirtAddr) -> bool {
+        match self.areas.range(..end).next_back() {
+            Some((_, area)) => area.end() > start,
+            None => false,
+        }
+    }
+
+    /// Splits the memory area that contains `pos`, so that `pos` is the
+    /// boundary of two areas.
+    fn split_at(&mut self, pos: VirtAddr) {
+        let area = self
+            .areas
+            .range_mut(..pos)
+            .next_back()
+            .map(|(_, area)| area)
+            .filter(|area| area.end() > pos);
+        if let Some(area) = area {
+            let right = area.split(pos);
+            self.areas.insert(pos, right);
+        }
+    }
+
+    /// Merges the adjacent memory areas that overlap with or touch
+    /// `[start, end]`, if they have the same flags and compatible backends.
+    fn merge_around(&mut self, start: VirtAddr, end: VirtAddr) {
+        let starts: Vec<_> = self
+            .areas
+            .range(..=end)
+            .rev()
+            .take_while(|(_, area)| area.end() >= start)
+            .map(|(&vaddr, _)| vaddr)
+            .collect();
+        // Merge each area into the previous one, from the highest.
+        for vaddr in starts {
+            let Some((&prev_start, _)) = self.areas.range(..vaddr).next_back() else {
+                continue;
+            };
+            let area = self.areas.remove(&vaddr).unwrap();
+            let prev = self.areas.get_mut(&prev_start).unwrap();
+            if let Err(area) = prev.merge(area) {
+                self.areas.insert(vaddr, area);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AddrSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AddrSpace")
+            .field("va_range", &(self.base..self.end))
+            .field("areas", &self.areas)
+            .finish()
+    }
+}
+
+impl Drop for AddrSpace {
+    fn drop(&mut self) {
+        if let Err(e) = self.clear().run() {
+            warn!("failed to write back pages: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::{sync::Arc, vec, vec::Vec};
+    use core::alloc::Layout;
+
+    use axerrno::{AxError, AxResult};
+    use axhal::mem::{phys_to_virt, PAGE_SIZE_4K as PAGE};
+    use axhal::paging::{MappingFlags, PageSize};
+    use memory_addr::VirtAddr;
+    use spinlock::SpinNoIrq;
+
+    use super::AddrSpace;
+    use crate::area::{Backend, FileBackend};
+
+    const BASE: usize = 0x1000_0000;
+
+    /// A file in memory, which is writable if the second field is `true`.
+    struct MemFile(SpinNoIrq<Vec<u8>>, bool);
+
+    impl FileBackend for MemFile {
+        fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
+            let data = self.0.lock();
+            let start = data.len().min(offset as usize);
+            let n = buf.len().min(data.len() - start);
+            buf[..n].copy_from_slice(&data[start..start + n]);
+            Ok(n)
+        }
+
+        fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
+            let mut data = self.0.lock();
+            let end = offset as usize + buf.len();
+            if end > data.len() {
+                data.resize(end, 0);
+            }
+            data[offset as usize..end].copy_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn size(&self) -> AxResult<u64> {
+            Ok(self.0.lock().len() as u64)
+        }
+
+        fn is_writable(&self) -> bool {
+            self.1
+        }
+    }
+
+    fn rw() -> MappingFlags {
+        MappingFlags::READ | MappingFlags::WRITE
+    }
+
+    fn lazy() -> Backend {
+        Backend::Alloc {
+            populate: false,
+            page_size: PageSize::Size4K,
+        }
+    }
+
+    fn va(offset: usize) -> VirtAddr {
+        VirtAddr::from(BASE + offset)
+    }
+
+    fn new_aspace() -> SpinNoIrq<AddrSpace> {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            const SIZE: usize = 32 << 20;
+            let layout = Layout::from_size_align(SIZE, 2 << 20).unwrap();
+            let start = unsafe { alloc::alloc::alloc(layout) } as usize;
+            axalloc::global_init(start, SIZE);
+        });
+        SpinNoIrq::new(AddrSpace::new_empty(va(0), 0x1000_0000).unwrap())
+    }
+
+    /// Returns `(start offset, size, flags)` of all areas.
+    fn areas(aspace: &AddrSpace) -> Vec<(usize, usize, usize)> {
+        aspace
+            .areas()
+            .map(|area| {
+                (
+                    area.start().as_usize() - BASE,
+                    area.size(),
+                    area.flags().bits(),
+                )
+            })
+            .collect()
+    }
+
+    fn committed(aspace: &AddrSpace) -> usize {
+        aspace.areas().map(|area| area.committed_size()).sum()
+    }
+
+    /// Returns the mapped frame and flags of the page at `offset`.
+    fn query(aspace: &SpinNoIrq<AddrSpace>, offset: usize) -> Option<(*mut u8, MappingFlags)> {
+        let (paddr, flags, _) = aspace.lock().pt.with(|pt| pt.query(va(offset))).ok()?;
+        Some((phys_to_virt(paddr).as_mut_ptr(), flags))
+    }
+
+    fn fault(aspace: &SpinNoIrq<AddrSpace>, offset: usize, flags: MappingFlags) -> bool {
+        AddrSpace::handle_page_fault(aspace, va(offset), flags)
+    }
+
+    #[test]
+    fn test_unmap_split() {
+        let aspace = new_aspace();
+        aspace.lock().map(va(0), 4 * PAGE, rw(), lazy()).unwrap();
+        assert_eq!(committed(&aspace.lock()), 0);
+        assert!(fault(&aspace, 0, MappingFlags::READ));
+        assert!(fault(&aspace, 3 * PAGE + 8, MappingFlags::WRITE));
+        assert!(!fault(&aspace, 4 * PAGE, MappingFlags::READ));
+        assert_eq!(committed(&aspace.lock()), 2 * PAGE);
+
+        let wb = aspace.lock().unmap(va(PAGE), PAGE).unwrap();
+        assert!(wb.is_empty());
+        let bits = rw().bits();
+        assert_eq!(
+            areas(&aspace.lock()),
+            vec![(0, PAGE, bits), (2 * PAGE, 2 * PAGE, bits)]
+        );
+        assert!(!fault(&aspace, PAGE, MappingFlags::READ));
+        assert!(query(&aspace, 0).is_some());
+        assert!(query(&aspace, 3 * PAGE).is_some());
+
+        // Unmapping holes is not an error.
+        let _ = aspace.lock().unmap(va(0), 8 * PAGE).unwrap();
+        assert!(areas(&aspace.lock()).is_empty());
+        assert!(query(&aspace, 0).is_none());
+        assert!(query(&aspace, 3 * PAGE).is_none());
+        assert_eq!(
+            aspace.lock().unmap(va(1), PAGE).err(),
+            Some(AxError::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_protect_split_merge() {
+        let aspace = new_aspace();
+        aspace.lock().map(va(0), 4 * PAGE, rw(), lazy()).unwrap();
+        assert!(fault(&aspace, PAGE, MappingFlags::WRITE));
+
+        let ro = MappingFlags::READ;
+        aspace.lock().protect(va(PAGE), 2 * PAGE, ro).unwrap();
+        let (rw_bits, ro_bits) = (rw().bits(), ro.bits());
+        assert_eq!(
+            areas(&aspace.lock()),
+            vec![
+                (0, PAGE, rw_bits),
+                (PAGE, 2 * PAGE, ro_bits),
+                (3 * PAGE, PAGE, rw_bits)
+            ]
+        );
+        assert_eq!(query(&aspace, PAGE).unwrap().1.bits(), ro_bits);
+        assert!(!fault(&aspace, 2 * PAGE, MappingFlags::WRITE));
+        assert!(fault(&aspace, 2 * PAGE, MappingFlags::READ));
+        assert_eq!(query(&aspace, 2 * PAGE).unwrap().1.bits(), ro_bits);
+
+        // Restoring the flags merges the areas with their frames.
+        aspace.lock().protect(va(PAGE), 2 * PAGE, rw()).unwrap();
+        assert_eq!(areas(&aspace.lock()), vec![(0, 4 * PAGE, rw_bits)]);
+        assert_eq!(committed(&aspace.lock()), 2 * PAGE);
+        assert_eq!(query(&aspace, 2 * PAGE).unwrap().1.bits(), rw_bits);
+    }
+
+    #[test]
+    fn test_map_merge() {
+        let aspace = new_aspace();
+        let mut aspace = aspace.lock();
+        aspace.map(va(PAGE), PAGE, rw(), lazy()).unwrap();
+        aspace.map(va(0), PAGE, rw(), lazy()).unwrap();
+        aspace.map(va(2 * PAGE), PAGE, rw(), lazy()).unwrap();
+        let bits = rw().bits();
+        assert_eq!(areas(&aspace), vec![(0, 3 * PAGE, bits)]);
+        assert_eq!(
+            aspace.map(va(2 * PAGE), 2 * PAGE, rw(), lazy()).err(),
+            Some(AxError::AlreadyExists)
+        );
+
+        // Different flags or backends are not merged.
+        aspace
+            .map(va(3 * PAGE), PAGE, MappingFlags::READ, lazy())
+            .unwrap();
+        let populated = Backend::Alloc {
+            populate: true,
+            page_size: PageSize::Size4K,
+        };
+        aspace.map(va(4 * PAGE), PAGE, rw(), populated).unwrap();
+        assert_eq!(areas(&aspace).len(), 3);
+        assert_eq!(committed(&aspace), PAGE);
+        assert_eq!(aspace.find_free_area(va(0), PAGE, PAGE), Some(va(5 * PAGE)));
+    }
+
+    #[test]
+    fn test_huge_page_boundary() {
+        let aspace = new_aspace();
+        const HUGE: usize = PageSize::Size2M as usize;
+        let huge = Backend::Alloc {
+            populate: false,
+            page_size: PageSize::Size2M,
+        };
+        assert_eq!(
+            aspace.lock().map(va(PAGE), HUGE, rw(), huge.clone()).err(),
+            Some(AxError::InvalidInput)
+        );
+        aspace.lock().map(va(0), 2 * HUGE, rw(), huge).unwrap();
+        assert_eq!(
+            aspace.lock().unmap(va(PAGE), PAGE).err(),
+            Some(AxError::InvalidInput)
+        );
+        assert_eq!(
+            aspace.lock().protect(va(0), PAGE, MappingFlags::READ).err(),
+            Some(AxError::InvalidInput)
+        );
+
+        assert!(fault(&aspace, HUGE + PAGE, MappingFlags::WRITE));
+        assert_eq!(committed(&aspace.lock()), HUGE);
+        aspace
+            .lock()
+            .protect(va(HUGE), HUGE, MappingFlags::READ)
+            .unwrap();
+        assert_eq!(areas(&aspace.lock()).len(), 2);
+        let _ = aspace.lock().unmap(va(HUGE), HUGE).unwrap();
+        assert_eq!(committed(&aspace.lock()), 0);
+    }
+
+    #[test]
+    fn test_file_fill_and_write_back() {
+        let aspace = new_aspace();
+        let len = 2 * PAGE + PAGE / 2;
+        let content: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let file = Arc::new(MemFile(SpinNoIrq::new(content.clone()), true));
+        let backend = Backend::File {
+            file: file.clone(),
+            offset: 0,
+            shared: true,
+        };
+        aspace.lock().map(va(0), 4 * PAGE, rw(), backend).unwrap();
+
+        assert!(fault(&aspace, PAGE + 8, MappingFlags::READ));
+        assert!(fault(&aspace, 2 * PAGE, MappingFlags::WRITE));
+        let page1 = query(&aspace, PAGE).unwrap().0;
+        let page2 = query(&aspace, 2 * PAGE).unwrap().0;
+        unsafe {
+            assert_eq!(*page1, content[PAGE]);
+            // The part beyond the end of the file is zero-filled.
+            assert_eq!(*page2.add(PAGE / 2), 0);
+            *page1 = 0xff;
+            *page2 = 0xee;
+        }
+
+        let wb = aspace.lock().sync(va(0), 4 * PAGE).unwrap();
+        assert!(!wb.is_empty());
+        wb.run().unwrap();
+        let data = file.0.lock().clone();
+        assert_eq!(data.len(), len);
+        assert_eq!((data[PAGE], data[2 * PAGE]), (0xff, 0xee));
+
+        // Splitting keeps the file offset.
+        aspace
+            .lock()
+            .protect(va(2 * PAGE), PAGE, MappingFlags::READ)
+            .unwrap();
+        let wb = aspace.lock().discard(va(2 * PAGE), PAGE).unwrap();
+        wb.run().unwrap();
+        assert!(fault(&aspace, 2 * PAGE, MappingFlags::READ));
+        let page2 = query(&aspace, 2 * PAGE).unwrap().0;
+        assert_eq!(unsafe { *page2.add(1) }, content[2 * PAGE + 1]);
+
+        unsafe { *page1.add(1) = 0xdd };
+        let wb = aspace.lock().unmap(va(0), 4 * PAGE).unwrap();
+        assert!(areas(&aspace.lock()).is_empty());
+        wb.run().unwrap();
+        assert_eq!(file.0.lock()[PAGE + 1], 0xdd);
+    }
+
+    #[test]
+    fn test_map_fixed_and_permission() {
+        let aspace = new_aspace();
+        let file = Arc::new(MemFile(SpinNoIrq::new(vec![1; 2 * PAGE]), false));
+        let file_backend = |shared| Backend::File {
+            file: file.clone(),
+            offset: 0,
+            shared,
+        };
+        let mut aspace = aspace.lock();
+        assert_eq!(
+            aspace.map(va(0), 2 * PAGE, rw(), file_backend(true)).err(),
+            Some(AxError::PermissionDenied)
+        );
+        aspace
+            .map(va(0), 2 * PAGE, MappingFlags::READ, file_backend(true))
+            .unwrap();
+        aspace
+            .map(va(2 * PAGE), 2 * PAGE, rw(), file_backend(false))
+            .unwrap();
+        assert_eq!(
+            aspace.protect(va(0), 4 * PAGE, rw()).err(),
+            Some(AxError::PermissionDenied)
+        );
+        let ro_bits = MappingFlags::READ.bits();
+        assert_eq!(areas(&aspace)[0], (0, 2 * PAGE, ro_bits));
+        assert_eq!(areas(&aspace)[1], (2 * PAGE, 2 * PAGE, rw().bits()));
+
+        // Invalid arguments keep the existing mappings.
+        let huge = Backend::Alloc {
+            populate: false,
+            page_size: PageSize::Size2M,
+        };
+        assert_eq!(
+            aspace.map_fixed(va(0), PAGE, rw(), huge).err(),
+            Some(AxError::InvalidInput)
+        );
+        assert_eq!(
+            aspace
+                .map_fixed(va(0), PAGE, rw(), file_backend(true))
+                .err(),
+            Some(AxError::PermissionDenied)
+        );
+        assert_eq!(areas(&aspace).len(), 2);
+
+        let wb = aspace
+            .map_fixed(va(PAGE), 2 * PAGE, MappingFlags::READ, lazy())
+            .unwrap();
+        assert!(wb.is_empty());
+        assert_eq!(
+            areas(&aspace),
+            vec![
+                (0, PAGE, ro_bits),
+                (PAGE, 2 * PAGE, ro_bits),
+                (3 * PAGE, PAGE, rw().bits())
+            ]
+        );
+    }
+}
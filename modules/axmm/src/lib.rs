@@ -0,0 +1,83 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) virtual memory management module.
+//!
+//! An address space ([`AddrSpace`]) is a set of non-overlapping memory areas
+//! ([`MemoryArea`]), each of which has its own mapping flags and [`Backend`].
+//! The pages of anonymous and file-backed areas are allocated on the first
+//! access by handling page faults, so that a large area can be reserved
+//! without committing physical memory.
+//!
+//! File I/O (reading pages on page faults, and writing back shared file
+//! mappings) is performed without holding the lock of the address space, see
+//! [`AddrSpace`] and [`WriteBack`].
+//!
+//! The kernel address space ([`kernel_aspace`]) manages the region
+//! `[KERNEL_ASPACE_BASE, KERNEL_ASPACE_BASE + KERNEL_ASPACE_SIZE)` of the
+//! kernel page table, which is defined in [axconfig].
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+mod area;
+mod aspace;
+
+use axconfig::{KERNEL_ASPACE_BASE, KERNEL_ASPACE_SIZE};
+use axerrno::AxError;
+use axhal::paging::{MappingFlags, PagingError};
+use lazy_init::LazyInit;
+use memory_addr::VirtAddr;
+use spinlock::SpinNoIrq;
+
+pub use self::area::{Backend, FileBackend, MemoryArea, WriteBack};
+pub use self::aspace::AddrSpace;
+
+#[cfg(not(test))]
+use axhal::arch::flush_tlb;
+
+/// Page tables are never activated in unit tests.
+#[cfg(test)]
+fn flush_tlb(_vaddr: Option<VirtAddr>) {}
+
+static KERNEL_ASPACE: LazyInit<SpinNoIrq<AddrSpace>> = LazyInit::new();
+
+const fn paging_err_to_ax_err(e: PagingError) -> AxError {
+    match e {
+        PagingError::NoMemory => AxError::NoMemory,
+        PagingError::NotAligned => AxError::InvalidInput,
+        PagingError::NotMapped => AxError::NotFound,
+        PagingError::AlreadyMapped => AxError::AlreadyExists,
+        PagingError::MappedToHugePage => AxError::BadState,
+    }
+}
+
+/// Initializes the kernel address space.
+///
+/// It must be called after the kernel page table is initialized by
+/// [`axhal::paging::init_kernel_page_table`].
+pub fn init_kernel_aspace() {
+    let pt = axhal::paging::kernel_page_table().expect("kernel page table is not initialized");
+    let aspace = AddrSpace::new_shared(KERNEL_ASPACE_BASE.into(), KERNEL_ASPACE_SIZE, pt);
+    KERNEL_ASPACE.init_by(SpinNoIrq::new(aspace));
+}
+
+/// Returns the kernel address space.
+///
+/// It's protected by a [`SpinNoIrq`] lock, so that page faults can be handled
+/// in any context. [`WriteBack`]s returned by the operations should be run
+/// after releasing the lock.
+pub fn kernel_aspace() -> &'static SpinNoIrq<AddrSpace> {
+    &KERNEL_ASPACE
+}
+
+/// Handles a page fault at `vaddr` in the kernel address space.
+///
+/// Returns `true` if the fault is resolved, i.e., the page is mapped with the
+/// required access now. It may sleep when the page is read from a file.
+pub fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags) -> bool {
+    match KERNEL_ASPACE.try_get() {
+        Some(aspace) => AddrSpace::handle_page_fault(aspace, vaddr, access_flags),
+        None => false,
+    }
+}
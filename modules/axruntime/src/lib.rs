@@ -6,7 +6,8 @@
 //! # Cargo Features
 //!
 //! - `alloc`: Enable global memory allocator.
-//! - `paging`: Enable page table manipulation support.
+//! - `paging`: Enable page table manipulation and virtual memory management
+//!   (the [axmm] module).
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
 //! - `smp`: Enable SMP (symmetric multiprocessing) support.
@@ -84,6 +85,17 @@ impl axlog::LogIf for LogIfImpl {
     }
 }
 
+#[cfg(feature = "alloc-stats")]
+struct AllocStatsIfImpl;
+
+#[cfg(feature = "alloc-stats")]
+#[crate_interface::impl_interface]
+impl axalloc::AllocStatsIf for AllocStatsIfImpl {
+    fn current_task_id() -> Option<u64> {
+        <LogIfImpl as axlog::LogIf>::current_task_id()
+    }
+}
+
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 static INITED_CPUS: AtomicUsize = AtomicUsize::new(0);
@@ -144,13 +156,18 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     {
         info!("Initialize kernel page table...");
         remap_kernel_memory().expect("remap kernel memoy failed");
+        axmm::init_kernel_aspace();
     }
 
     info!("Initialize platform devices...");
     axhal::platform_init();
 
     #[cfg(feature = "multitask")]
-    axtask::init_scheduler();
+    {
+        axtask::init_scheduler();
+        #[cfg(feature = "alloc")]
+        axalloc::set_oom_killer(oom_kill_current);
+    }
 
     #[cfg(any(feature = "fs", feature = "net", feature = "display"))]
     {
@@ -191,6 +208,10 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
     unsafe { main() };
 
+    #[cfg(feature = "fs")]
+    if let Err(e) = axfs::sync() {
+        warn!("failed to sync filesystems: {:?}", e);
+    }
 
 
     let mut ps2 = axhal::ps2_key::ps2_key::Ps2::new();
@@ -216,6 +237,17 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     }
 }
 
+/// Kills the current task when it runs out of memory, as if by `SIGKILL`.
+///
+/// It's called in the allocator, so the task exits later at a safe point.
+#[cfg(all(feature = "alloc", feature = "multitask"))]
+fn oom_kill_current() {
+    if let Some(curr) = axtask::current_may_uninit() {
+        warn!("task {} is killed for out of memory", curr.id().as_u64());
+        axtask::request_exit(128 + 9);
+    }
+}
+
 #[cfg(feature = "alloc")]
 fn init_allocator() {
     use axhal::mem::{memory_regions, phys_to_virt, MemRegionFlags};
@@ -248,10 +280,7 @@ fn init_allocator() {
 #[cfg(feature = "paging")]
 fn remap_kernel_memory() -> Result<(), axhal::paging::PagingError> {
     use axhal::mem::{memory_regions, phys_to_virt};
-    use axhal::paging::PageTable;
-    use lazy_init::LazyInit;
-
-    static KERNEL_PAGE_TABLE: LazyInit<PageTable> = LazyInit::new();
+    use axhal::paging::{kernel_page_table, PageTable};
 
     if axhal::cpu::this_cpu_is_bsp() {
         let mut kernel_page_table = PageTable::try_new()?;
@@ -264,10 +293,11 @@ fn remap_kernel_memory() -> Result<(), axhal::paging::PagingError> {
                 true,
             )?;
         }
-        KERNEL_PAGE_TABLE.init_by(kernel_page_table);
+        axhal::paging::init_kernel_page_table(kernel_page_table);
     }
 
-    unsafe { axhal::arch::write_page_table_root(KERNEL_PAGE_TABLE.root_paddr()) };
+    let root_paddr = kernel_page_table().unwrap().lock().root_paddr();
+    unsafe { axhal::arch::write_page_table_root(root_paddr) };
     Ok(())
 }
 
@@ -1,3 +1,7 @@
+use axhal::arch::TrapFrame;
+use axhal::mem::VirtAddr;
+use axhal::trap::PageFaultFlags;
+
 struct TrapHandlerImpl;
 
 #[crate_interface::impl_interface]
@@ -10,4 +14,29 @@ impl axhal::trap::TrapHandler for TrapHandlerImpl {
             drop(guard); // rescheduling may occur when preemption is re-enabled.
         }
     }
+
+    fn handle_page_fault(_vaddr: VirtAddr, _access_flags: PageFaultFlags, _is_user: bool) -> bool {
+        // There are only kernel address spaces for now.
+        #[cfg(feature = "paging")]
+        if !_is_user {
+            return axmm::handle_page_fault(_vaddr, _access_flags);
+        }
+        false
+    }
+
+    fn handle_sync_exception(_code: usize, _tf: &mut TrapFrame) -> bool {
+        false
+    }
+
+    fn handle_stack_overflow(fault_vaddr: usize) {
+        #[cfg(feature = "multitask")]
+        if let Some(curr) = axtask::current_may_uninit() {
+            panic!(
+                "stack overflow in {}, fault_vaddr={:#x}",
+                curr.id_name(),
+                fault_vaddr
+            );
+        }
+        panic!("kernel stack overflow, fault_vaddr={:#x}", fault_vaddr);
+    }
 }
@@ -0,0 +1,128 @@
+//! A barrier built on the wait queue.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axtask::WaitQueue;
+
+/// A barrier enables multiple tasks to synchronize the beginning of some
+/// computation, similar to
+/// [`std::sync::Barrier`](https://doc.rust-lang.org/std/sync/struct.Barrier.html).
+///
+/// The barrier can be reused after all tasks have rendezvoused.
+pub struct Barrier {
+    wq: WaitQueue,
+    num_tasks: usize,
+    count: AtomicUsize,
+    generation: AtomicUsize,
+}
+
+/// A `BarrierWaitResult` is returned by [`Barrier::wait()`] when all tasks
+/// in the [`Barrier`] have rendezvoused.
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` if this task is the "leader task" for the call to
+    /// [`Barrier::wait()`].
+    ///
+    /// Only one task will have `true` returned from their result, all other
+    /// tasks will have `false` returned.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Creates a new barrier that can block a given number of tasks.
+    ///
+    /// A barrier will block `n`-1 tasks which call [`wait()`](Self::wait)
+    /// and then wake up all tasks at once when the `n`th task calls
+    /// [`wait()`](Self::wait).
+    pub const fn new(n: usize) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            num_tasks: n,
+            count: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Blocks the current task until all tasks have rendezvoused here.
+    ///
+    /// Barriers are re-usable after all tasks have rendezvoused once, and can
+    /// be used continuously.
+    ///
+    /// A single (arbitrary) task will receive a [`BarrierWaitResult`] that
+    /// returns `true` from [`BarrierWaitResult::is_leader()`] when returning
+    /// from this function, and all other tasks will receive a result that
+    /// will return `false` from [`BarrierWaitResult::is_leader()`].
+    pub fn wait(&self) -> BarrierWaitResult {
+        let generation = self.generation.load(Ordering::Acquire);
+        if self.count.fetch_add(1, Ordering::AcqRel) + 1 >= self.num_tasks {
+            // All tasks of this generation have arrived, so no one else can
+            // touch `count` until the generation changes.
+            self.count.store(0, Ordering::Relaxed);
+            self.generation.fetch_add(1, Ordering::Release);
+            self.wq.notify_all(true);
+            BarrierWaitResult(true)
+        } else {
+            self.wq
+                .wait_until(|| self.generation.load(Ordering::Acquire) != generation);
+            BarrierWaitResult(false)
+        }
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Barrier").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for BarrierWaitResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BarrierWaitResult")
+            .field("is_leader", &self.is_leader())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Barrier;
+    use axtask as thread;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn rendezvous() {
+        let _lock = crate::tests::init_serial();
+
+        const NUM_TASKS: usize = 10;
+        const NUM_ROUNDS: usize = 10;
+        static BARRIER: Barrier = Barrier::new(NUM_TASKS);
+        static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+        static LEADERS: AtomicUsize = AtomicUsize::new(0);
+        static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| {
+                for round in 0..NUM_ROUNDS {
+                    ARRIVED.fetch_add(1, Ordering::Relaxed);
+                    if BARRIER.wait().is_leader() {
+                        LEADERS.fetch_add(1, Ordering::Relaxed);
+                    }
+                    // every task of this round has arrived
+                    assert!(ARRIVED.load(Ordering::Relaxed) >= (round + 1) * NUM_TASKS);
+                    BARRIER.wait();
+                }
+                FINISHED.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        while FINISHED.load(Ordering::Relaxed) < NUM_TASKS {
+            thread::yield_now();
+        }
+        assert_eq!(LEADERS.load(Ordering::Relaxed), NUM_ROUNDS);
+        println!("Barrier test OK");
+    }
+}
@@ -0,0 +1,200 @@
+//! A condition variable built on the wait queue.
+
+use core::fmt;
+use core::ops::DerefMut;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use axtask::WaitQueue;
+
+use crate::{MutexGuard, PiMutexGuard};
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// A guard of a mutex that a [`Condvar`] can wait with, i.e., [`MutexGuard`]
+/// or [`PiMutexGuard`].
+pub trait CondvarGuard: DerefMut + Sized + sealed::Sealed {
+    /// Unlocks the mutex, calls `f`, and then locks the mutex again.
+    #[doc(hidden)]
+    fn unlocked<R>(self, f: impl FnOnce() -> R) -> (Self, R);
+}
+
+impl<T: ?Sized> sealed::Sealed for MutexGuard<'_, T> {}
+
+impl<T: ?Sized> CondvarGuard for MutexGuard<'_, T> {
+    fn unlocked<R>(self, f: impl FnOnce() -> R) -> (Self, R) {
+        let mutex = self.lock;
+        drop(self);
+        let ret = f();
+        (mutex.lock(), ret)
+    }
+}
+
+impl<T: ?Sized> sealed::Sealed for PiMutexGuard<'_, T> {}
+
+impl<T: ?Sized> CondvarGuard for PiMutexGuard<'_, T> {
+    fn unlocked<R>(self, f: impl FnOnce() -> R) -> (Self, R) {
+        let mutex = self.lock;
+        drop(self);
+        let ret = f();
+        (mutex.lock(), ret)
+    }
+}
+
+/// A type indicating whether a timed wait on a condition variable returned
+/// due to a time out or not.
+///
+/// It is returned by the [`Condvar::wait_timeout`] method.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait was known to have timed out.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/// A Condition Variable, similar to
+/// [`std::sync::Condvar`](https://doc.rust-lang.org/std/sync/struct.Condvar.html).
+///
+/// Condition variables represent the ability to block a task such that it
+/// consumes no CPU time while waiting for an event to occur. It's used along
+/// with a [`Mutex`](crate::Mutex) or a [`PiMutex`](crate::PiMutex).
+///
+/// Every notification bumps a sequence number. A waiting task is not woken up
+/// until the sequence number changes, so notifications sent after the mutex is
+/// released but before the task is put into the wait queue are not lost.
+pub struct Condvar {
+    wq: WaitQueue,
+    seq: AtomicU32,
+}
+
+impl Condvar {
+    /// Creates a new condition variable which is ready to be waited on and
+    /// notified.
+    pub const fn new() -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            seq: AtomicU32::new(0),
+        }
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification.
+    ///
+    /// This function will atomically unlock the mutex specified (represented
+    /// by `guard`) and block the current task. When this function call
+    /// returns, the lock specified will have been re-acquired.
+    ///
+    /// Note that this function is susceptible to spurious wakeups.
+    pub fn wait<G: CondvarGuard>(&self, guard: G) -> G {
+        let seq = self.seq.load(Ordering::Acquire);
+        let (guard, ()) = guard.unlocked(|| {
+            self.wq
+                .wait_until(|| self.seq.load(Ordering::Acquire) != seq)
+        });
+        guard
+    }
+
+    /// Blocks the current task until the provided `condition` becomes false.
+    ///
+    /// `condition` is checked immediately; if not met (returns `true`), this
+    /// will [`wait`](Self::wait) for the next notification then check again.
+    /// This repeats until `condition` returns `false`, in which case this
+    /// function returns.
+    pub fn wait_while<G, F>(&self, mut guard: G, mut condition: F) -> G
+    where
+        G: CondvarGuard,
+        F: FnMut(&mut G::Target) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// the specified duration.
+    ///
+    /// The returned [`WaitTimeoutResult`] indicates whether the timeout is
+    /// known to have elapsed.
+    ///
+    /// If the `irq` feature is not enabled, the timeout is ignored and it
+    /// waits until a notification is received.
+    pub fn wait_timeout<G: CondvarGuard>(&self, guard: G, dur: Duration) -> (G, WaitTimeoutResult) {
+        let seq = self.seq.load(Ordering::Acquire);
+        let (guard, timed_out) = guard.unlocked(|| {
+            let condition = || self.seq.load(Ordering::Acquire) != seq;
+            #[cfg(feature = "irq")]
+            let timed_out = self.wq.wait_timeout_until(dur, condition);
+            #[cfg(not(feature = "irq"))]
+            let timed_out = {
+                let _ = dur;
+                self.wq.wait_until(condition);
+                false
+            };
+            timed_out
+        });
+        (guard, WaitTimeoutResult(timed_out))
+    }
+
+    /// Wakes up one blocked task on this condvar.
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+
+    /// Wakes up all blocked tasks on this condvar.
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_all(true);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Condvar, Mutex};
+    use axtask as thread;
+
+    #[test]
+    fn notify_all() {
+        let _lock = crate::tests::init_serial();
+
+        const NUM_TASKS: u32 = 10;
+        static M: Mutex<(u32, bool)> = Mutex::new((0, false));
+        static CV: Condvar = Condvar::new();
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| {
+                let mut val = M.lock();
+                val.0 += 1;
+                CV.notify_all();
+                val = CV.wait_while(val, |v| !v.1);
+                val.0 -= 1;
+                CV.notify_all();
+            });
+        }
+
+        let mut val = CV.wait_while(M.lock(), |v| v.0 != NUM_TASKS);
+        val.1 = true;
+        CV.notify_all();
+        let val = CV.wait_while(val, |v| v.0 != 0);
+        assert!(val.1);
+        println!("Condvar test OK");
+    }
+}
@@ -3,26 +3,84 @@
 //! Currently supported primitives:
 //!
 //! - [`Mutex`]: A mutual exclusion primitive.
+//! - [`PiMutex`]: A mutual exclusion primitive with priority inheritance.
+//! - [`Condvar`]: A condition variable, used along with [`Mutex`] or
+//!   [`PiMutex`].
+//! - [`RwLock`]: A readers-writer lock.
+//! - [`Semaphore`]: A counting semaphore.
+//! - [`Barrier`]: A barrier to synchronize multiple tasks.
+//! - [`Once`] and [`OnceLock`]: One-time initialization primitives.
 //! - mod [`spin`](spinlock): spin-locks.
 //!
 //! # Cargo Features
 //!
 //! - `multitask`: For use in the multi-threaded environments. If the feature is
-//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`]. This
-//!   feature is enabled by default.
+//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`], and other
+//!   blocking primitives are not available. This feature is enabled by default.
+//! - `irq`: Interrupts are enabled. If this feature is enabled, timeouts of
+//!   [`Condvar::wait_timeout`] and [`Semaphore::acquire_timeout`] take effect.
 
 #![cfg_attr(not(test), no_std)]
 #![feature(doc_cfg)]
 
 pub use spinlock as spin;
 
+#[cfg(feature = "multitask")]
+extern crate alloc;
+
+#[cfg(feature = "multitask")]
+mod barrier;
+#[cfg(feature = "multitask")]
+mod condvar;
 #[cfg(feature = "multitask")]
 mod mutex;
+#[cfg(feature = "multitask")]
+mod once;
+#[cfg(feature = "multitask")]
+mod pi_mutex;
+#[cfg(feature = "multitask")]
+mod rwlock;
+#[cfg(feature = "multitask")]
+mod semaphore;
 
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::barrier::{Barrier, BarrierWaitResult};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::condvar::{Condvar, CondvarGuard, WaitTimeoutResult};
 #[cfg(feature = "multitask")]
 #[doc(cfg(feature = "multitask"))]
 pub use self::mutex::{Mutex, MutexGuard};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::once::{Once, OnceLock};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::pi_mutex::{PiMutex, PiMutexGuard};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::semaphore::{Semaphore, SemaphoreGuard};
 
 #[cfg(not(feature = "multitask"))]
 #[doc(cfg(not(feature = "multitask")))]
 pub use spinlock::{SpinNoIrq as Mutex, SpinNoIrqGuard as MutexGuard};
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, MutexGuard, Once};
+
+    static INIT: Once = Once::new();
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// Initializes the scheduler once, and makes tests run one by one as they
+    /// share the same task manager.
+    pub fn init_serial() -> MutexGuard<'static, ()> {
+        let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        INIT.call_once(axtask::init_scheduler);
+        guard
+    }
+}
@@ -23,7 +23,7 @@ pub struct Mutex<T: ?Sized> {
 ///
 /// When the guard falls out of scope it will release the lock.
 pub struct MutexGuard<'a, T: ?Sized + 'a> {
-    lock: &'a Mutex<T>,
+    pub(crate) lock: &'a Mutex<T>,
     data: *mut T,
 }
 
@@ -118,6 +118,25 @@ impl<T: ?Sized> Mutex<T> {
         }
     }
 
+    /// Creates a guard for the [`Mutex`] which is already locked by the
+    /// current task, without locking it again.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held by the current task, and no other guard of it
+    /// exists. This is useful along with [`force_unlock`](Self::force_unlock)
+    /// when exposing the lock to FFI.
+    pub unsafe fn make_guard_unchecked(&self) -> MutexGuard<T> {
+        debug_assert_eq!(
+            self.owner_id.load(Ordering::Relaxed),
+            current().id().as_u64()
+        );
+        MutexGuard {
+            lock: self,
+            data: unsafe { &mut *self.data.get() },
+        }
+    }
+
     /// Force unlock the [`Mutex`].
     ///
     /// # Safety
@@ -201,9 +220,6 @@ impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
 mod tests {
     use crate::Mutex;
     use axtask as thread;
-    use std::sync::Once;
-
-    static INIT: Once = Once::new();
 
     fn may_interrupt() {
         // simulate interrupts
@@ -214,7 +230,7 @@ mod tests {
 
     #[test]
     fn lots_and_lots() {
-        INIT.call_once(thread::init_scheduler);
+        let _lock = crate::tests::init_serial();
 
         const NUM_TASKS: u32 = 10;
         const NUM_ITERS: u32 = 10_000;
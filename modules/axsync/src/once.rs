@@ -0,0 +1,219 @@
+//! One-time initialization primitives built on the wait queue.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use axtask::WaitQueue;
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+/// A synchronization primitive which can be used to run a one-time global
+/// initialization, similar to
+/// [`std::sync::Once`](https://doc.rust-lang.org/std/sync/struct.Once.html).
+///
+/// Tasks calling [`call_once`](Self::call_once) while another task is running
+/// the initialization will block until it completes.
+pub struct Once {
+    wq: WaitQueue,
+    state: AtomicU8,
+}
+
+impl Once {
+    /// Creates a new `Once` value.
+    pub const fn new() -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            state: AtomicU8::new(INCOMPLETE),
+        }
+    }
+
+    /// Returns `true` if some [`call_once`](Self::call_once) call has
+    /// completed successfully.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// Performs an initialization routine once and only once. The given
+    /// closure will be executed if this is the first time `call_once` has been
+    /// called, and otherwise the routine will *not* be invoked.
+    ///
+    /// This method will block the current task if another initialization
+    /// routine is currently running.
+    pub fn call_once<F: FnOnce()>(&self, f: F) {
+        if self.is_completed() {
+            return;
+        }
+        match self
+            .state
+            .compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                f();
+                self.state.store(COMPLETE, Ordering::Release);
+                self.wq.notify_all(true);
+            }
+            Err(_) => self.wq.wait_until(|| self.is_completed()),
+        }
+    }
+}
+
+impl Default for Once {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Once {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Once").finish_non_exhaustive()
+    }
+}
+
+/// A synchronization primitive which can be written to only once, similar to
+/// [`std::sync::OnceLock`](https://doc.rust-lang.org/std/sync/struct.OnceLock.html).
+pub struct OnceLock<T> {
+    once: Once,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// Same unsafe impls as `std::sync::OnceLock`
+unsafe impl<T: Sync + Send> Sync for OnceLock<T> {}
+unsafe impl<T: Send> Send for OnceLock<T> {}
+
+impl<T> OnceLock<T> {
+    /// Creates a new empty cell.
+    pub const fn new() -> Self {
+        Self {
+            once: Once::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Gets the reference to the underlying value.
+    ///
+    /// Returns `None` if the cell is empty, or being initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.once.is_completed() {
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    /// Gets the mutable reference to the underlying value.
+    ///
+    /// Returns `None` if the cell is empty.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.once.is_completed() {
+            Some(unsafe { self.value.get_mut().assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Sets the contents of this cell to `value`.
+    ///
+    /// May block if another task is currently attempting to initialize the
+    /// cell. Returns `Err(value)` if the cell was already initialized.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// Gets the contents of the cell, initializing it with `f` if the cell was
+    /// empty.
+    ///
+    /// Many tasks may call `get_or_init` concurrently with different
+    /// initializing functions, but it is guaranteed that only one function
+    /// will be executed.
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        self.once.call_once(|| unsafe {
+            (*self.value.get()).write(f());
+        });
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+
+    /// Consumes the cell, returning the wrapped value.
+    ///
+    /// Returns `None` if the cell was empty.
+    pub fn into_inner(mut self) -> Option<T> {
+        if self.once.is_completed() {
+            // Mark it as incomplete so that the value will not be dropped twice.
+            self.once = Once::new();
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(v) => f.debug_tuple("OnceLock").field(v).finish(),
+            None => f.write_str("OnceLock(<uninit>)"),
+        }
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if self.once.is_completed() {
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OnceLock;
+    use axtask as thread;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn init_once() {
+        let _lock = crate::tests::init_serial();
+
+        const NUM_TASKS: usize = 10;
+        static CELL: OnceLock<usize> = OnceLock::new();
+        static INIT_TIMES: AtomicUsize = AtomicUsize::new(0);
+        static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+        for i in 0..NUM_TASKS {
+            thread::spawn(move || {
+                let val = CELL.get_or_init(|| {
+                    INIT_TIMES.fetch_add(1, Ordering::Relaxed);
+                    thread::yield_now();
+                    i
+                });
+                assert_eq!(CELL.get(), Some(val));
+                FINISHED.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        while FINISHED.load(Ordering::Relaxed) < NUM_TASKS {
+            thread::yield_now();
+        }
+        assert_eq!(INIT_TIMES.load(Ordering::Relaxed), 1);
+        assert!(CELL.set(NUM_TASKS).is_err());
+        println!("OnceLock test OK");
+    }
+}
@@ -0,0 +1,450 @@
+//! A sleeping mutex with priority inheritance.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axtask::{current, AxTaskRef, WaitQueue};
+use spinlock::SpinNoIrq;
+
+/// The bit in the owner word indicating that some tasks are blocked on the
+/// mutex, so that unlocking it must go through the slow path.
+const WAITERS: u64 = 1 << 63;
+
+/// Bookkeeping of the contended [`PiMutex`]es, used to propagate priorities
+/// along chains of blocked tasks.
+///
+/// Mutexes are identified by the addresses of their owner words, which stay
+/// valid as long as any task is blocked on them.
+struct PiState {
+    /// Tasks blocked on each mutex.
+    waiters: BTreeMap<usize, Vec<AxTaskRef>>,
+    /// The mutex each blocked task is waiting for, indexed by the task ID.
+    blocked_on: BTreeMap<u64, usize>,
+}
+
+/// Only touched on contention, so a single lock for all mutexes is enough.
+static PI_STATE: SpinNoIrq<PiState> = SpinNoIrq::new(PiState {
+    waiters: BTreeMap::new(),
+    blocked_on: BTreeMap::new(),
+});
+
+fn owner_of(key: usize) -> u64 {
+    // SAFETY: the mutex is borrowed by its waiters.
+    unsafe { &*(key as *const AtomicU64) }.load(Ordering::Relaxed) & !WAITERS
+}
+
+impl PiState {
+    /// Boosts the owner of the mutex `key` to `prio`, and so on for the owner
+    /// of the mutex it's blocked on, until a task already has the priority.
+    fn propagate(&self, mut key: usize, prio: isize) {
+        loop {
+            let owner_id = owner_of(key);
+            let Some(owner) = axtask::find_task(owner_id) else {
+                break;
+            };
+            if owner.priority() <= prio {
+                break;
+            }
+            axtask::inherit_priority(&owner, prio);
+            if owner.priority() > prio {
+                // Not supported by the scheduler.
+                break;
+            }
+            match self.blocked_on.get(&owner_id) {
+                Some(&next) => key = next,
+                None => break,
+            }
+        }
+    }
+
+    /// Resets the priority of the current task to the highest one of its own
+    /// and the waiters of the mutexes it still holds.
+    fn restore_current(&self, curr: &AxTaskRef) {
+        let curr_id = curr.id().as_u64();
+        let inherited = self
+            .waiters
+            .iter()
+            .filter(|(&key, _)| owner_of(key) == curr_id)
+            .flat_map(|(_, waiters)| waiters.iter().map(|t| t.priority()))
+            .min();
+        axtask::restore_priority();
+        if let Some(prio) = inherited {
+            axtask::inherit_priority(curr, prio);
+        }
+    }
+}
+
+/// A mutual exclusion primitive with priority inheritance, to avoid priority
+/// inversion.
+///
+/// It works like [`Mutex`](crate::Mutex), except that:
+///
+/// - The owner of the mutex runs with the priority of the highest-priority
+///   task blocked on it (see [`axtask::inherit_priority`]), if it's higher
+///   than its own. If the owner is blocked on another [`PiMutex`] in turn, the
+///   priority is passed on to the owner of that mutex, and so on.
+/// - When unlocked, the mutex is handed over to the highest-priority waiter
+///   directly, and the previous owner drops the priority inherited through
+///   it.
+pub struct PiMutex<T: ?Sized> {
+    wq: WaitQueue,
+    /// The ID of the owner task, with [`WAITERS`] set if it's contended.
+    owner: AtomicU64,
+    data: UnsafeCell<T>,
+}
+
+/// A guard that provides mutable data access of [`PiMutex`].
+///
+/// When the guard falls out of scope it will release the lock.
+pub struct PiMutexGuard<'a, T: ?Sized + 'a> {
+    pub(crate) lock: &'a PiMutex<T>,
+    data: *mut T,
+}
+
+// Same unsafe impls as `std::sync::Mutex`
+unsafe impl<T: ?Sized + Send> Sync for PiMutex<T> {}
+unsafe impl<T: ?Sized + Send> Send for PiMutex<T> {}
+
+impl<T> PiMutex<T> {
+    /// Creates a new [`PiMutex`] wrapping the supplied data.
+    #[inline(always)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            owner: AtomicU64::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`PiMutex`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        let PiMutex { data, .. } = self;
+        data.into_inner()
+    }
+}
+
+impl<T: ?Sized> PiMutex<T> {
+    /// Returns `true` if the lock is currently held.
+    ///
+    /// The result should be considered 'out of date' the instant it is
+    /// called. Do not use it for synchronization purposes.
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.owner.load(Ordering::Relaxed) != 0
+    }
+
+    fn key(&self) -> usize {
+        &self.owner as *const AtomicU64 as usize
+    }
+
+    fn make_guard(&self) -> PiMutexGuard<T> {
+        PiMutexGuard {
+            lock: self,
+            data: unsafe { &mut *self.data.get() },
+        }
+    }
+
+    /// Locks the [`PiMutex`] and returns a guard that permits access to the
+    /// inner data.
+    ///
+    /// If the mutex is held by another task, the current task blocks, and the
+    /// owner inherits its priority until the mutex is handed over.
+    pub fn lock(&self) -> PiMutexGuard<T> {
+        let curr = current();
+        let curr_id = curr.id().as_u64();
+        if self
+            .owner
+            .compare_exchange(0, curr_id, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            return self.make_guard();
+        }
+
+        {
+            let mut pi = PI_STATE.lock();
+            // Mark the mutex as contended, or take it if it was just unlocked.
+            let mut owner = self.owner.load(Ordering::Relaxed);
+            loop {
+                let new = if owner == 0 { curr_id } else { owner | WAITERS };
+                assert_ne!(
+                    owner & !WAITERS,
+                    curr_id,
+                    "{} tried to acquire mutex it already owns.",
+                    curr.id_name()
+                );
+                match self.owner.compare_exchange_weak(
+                    owner,
+                    new,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) if owner == 0 => return self.make_guard(),
+                    Ok(_) => break,
+                    Err(o) => owner = o,
+                }
+            }
+            let key = self.key();
+            pi.waiters
+                .entry(key)
+                .or_default()
+                .push(curr.as_task_ref().clone());
+            pi.blocked_on.insert(curr_id, key);
+            pi.propagate(key, curr.priority());
+        }
+        // Wait until the mutex is handed over by `force_unlock`.
+        self.wq
+            .wait_until(|| self.owner.load(Ordering::Acquire) & !WAITERS == curr_id);
+        self.make_guard()
+    }
+
+    /// Try to lock this [`PiMutex`], returning a lock guard if successful.
+    #[inline(always)]
+    pub fn try_lock(&self) -> Option<PiMutexGuard<T>> {
+        let curr_id = current().id().as_u64();
+        self.owner
+            .compare_exchange(0, curr_id, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| self.make_guard())
+    }
+
+    /// Creates a guard for the [`PiMutex`] which is already locked by the
+    /// current task, without locking it again.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held by the current task, and no other guard of it
+    /// exists. This is useful along with [`force_unlock`](Self::force_unlock)
+    /// when exposing the lock to FFI.
+    pub unsafe fn make_guard_unchecked(&self) -> PiMutexGuard<T> {
+        debug_assert_eq!(
+            self.owner.load(Ordering::Relaxed) & !WAITERS,
+            current().id().as_u64()
+        );
+        self.make_guard()
+    }
+
+    /// Force unlock the [`PiMutex`].
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if the lock is not held by the current
+    /// thread. However, this can be useful in some instances for exposing
+    /// the lock to FFI that doesn’t know how to deal with RAII.
+    pub unsafe fn force_unlock(&self) {
+        let curr = current();
+        let curr_id = curr.id().as_u64();
+        if self
+            .owner
+            .compare_exchange(curr_id, 0, Ordering::Release, Ordering::Relaxed)
+            .is_ok()
+        {
+            return;
+        }
+
+        let next = {
+            let mut pi = PI_STATE.lock();
+            assert_eq!(
+                self.owner.load(Ordering::Relaxed) & !WAITERS,
+                curr_id,
+                "{} tried to release mutex it doesn't own",
+                curr.id_name()
+            );
+            let key = self.key();
+            let waiters = pi.waiters.get_mut(&key).unwrap();
+            // The first one of the highest priority.
+            let (index, _) = waiters
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, t)| t.priority())
+                .unwrap();
+            let next = waiters.remove(index);
+            let rest = waiters.iter().map(|t| t.priority()).min();
+            if waiters.is_empty() {
+                pi.waiters.remove(&key);
+            }
+            pi.blocked_on.remove(&next.id().as_u64());
+
+            let next_id = next.id().as_u64();
+            match rest {
+                Some(prio) => {
+                    self.owner.store(next_id | WAITERS, Ordering::Release);
+                    axtask::inherit_priority(&next, prio);
+                }
+                None => self.owner.store(next_id, Ordering::Release),
+            }
+            pi.restore_current(curr.as_task_ref());
+            next
+        };
+        self.wq.notify_task(true, &next);
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the [`PiMutex`] mutably, no actual locking
+    /// needs to take place.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+impl<T: Default> Default for PiMutex<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for PiMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => write!(f, "PiMutex {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "PiMutex {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for PiMutexGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that only we are referencing data
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for PiMutexGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        // We know statically that only we are referencing data
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for PiMutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized> Drop for PiMutexGuard<'a, T> {
+    /// The dropping of the [`PiMutexGuard`] will release the lock it was
+    /// created from.
+    fn drop(&mut self) {
+        unsafe { self.lock.force_unlock() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PiMutex, Semaphore};
+    use axtask as thread;
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[test]
+    fn lots_and_lots() {
+        let _lock = crate::tests::init_serial();
+
+        const NUM_TASKS: usize = 10;
+        const NUM_ITERS: usize = 1000;
+        static M: PiMutex<usize> = PiMutex::new(0);
+        static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| {
+                let prio = thread::current().priority();
+                for _ in 0..NUM_ITERS {
+                    let mut val = M.lock();
+                    *val += 1;
+                    thread::yield_now();
+                    drop(val);
+                    // no inherited priority is left after unlocking.
+                    assert_eq!(thread::current().priority(), prio);
+                    thread::yield_now();
+                }
+                FINISHED.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        while FINISHED.load(Ordering::Relaxed) < NUM_TASKS {
+            thread::yield_now();
+        }
+        assert!(!M.is_locked());
+        assert_eq!(*M.lock(), NUM_ITERS * NUM_TASKS);
+        println!("PiMutex test OK");
+    }
+
+    #[test]
+    fn priority_inversion() {
+        let _lock = crate::tests::init_serial();
+
+        // `L` (low) holds `M1`, `M` (medium) holds `M2` and blocks on `M1`,
+        // then `H` (high) blocks on `M2`.
+        const LOW: isize = 50;
+        const MEDIUM: isize = 30;
+        const HIGH: isize = 10;
+        static M1: PiMutex<()> = PiMutex::new(());
+        static M2: PiMutex<()> = PiMutex::new(());
+        static GO: Semaphore = Semaphore::new(0);
+        static STARTED: AtomicUsize = AtomicUsize::new(0);
+        static H_DONE: AtomicBool = AtomicBool::new(false);
+
+        let low = thread::spawn(|| {
+            assert!(thread::set_priority(LOW));
+            let guard = M1.lock();
+            STARTED.fetch_add(1, Ordering::Release);
+            GO.acquire();
+            // Boosted through the chain while holding `M1`.
+            assert_eq!(thread::current().priority(), HIGH);
+            drop(guard);
+            assert_eq!(thread::current().priority(), LOW);
+        });
+        while STARTED.load(Ordering::Acquire) < 1 {
+            thread::yield_now();
+        }
+
+        let medium = thread::spawn(|| {
+            assert!(thread::set_priority(MEDIUM));
+            let guard2 = M2.lock();
+            STARTED.fetch_add(1, Ordering::Release);
+            let guard1 = M1.lock();
+            drop(guard1);
+            // Still inherits from `H` blocked on `M2`.
+            assert_eq!(thread::current().priority(), HIGH);
+            assert!(!H_DONE.load(Ordering::Acquire));
+            drop(guard2);
+            assert_eq!(thread::current().priority(), MEDIUM);
+        });
+        while STARTED.load(Ordering::Acquire) < 2 {
+            thread::yield_now();
+        }
+        assert_eq!(low.priority(), MEDIUM);
+
+        let high = thread::spawn(|| {
+            assert!(thread::set_priority(HIGH));
+            STARTED.fetch_add(1, Ordering::Release);
+            let _guard = M2.lock();
+            H_DONE.store(true, Ordering::Release);
+        });
+        while STARTED.load(Ordering::Acquire) < 3 {
+            thread::yield_now();
+        }
+        // All of them are blocked now.
+        assert_eq!(medium.priority(), HIGH);
+        assert_eq!(low.priority(), HIGH);
+
+        GO.release();
+        assert_eq!(low.join(), Some(0));
+        assert_eq!(medium.join(), Some(0));
+        assert_eq!(high.join(), Some(0));
+        assert!(H_DONE.load(Ordering::Acquire));
+        assert!(!M1.is_locked() && !M2.is_locked());
+        println!("PiMutex priority inversion test OK");
+    }
+}
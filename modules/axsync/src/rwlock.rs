@@ -0,0 +1,350 @@
+//! A naïve sleeping readers-writer lock.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axtask::WaitQueue;
+
+const WRITER: usize = 1 << (usize::BITS - 1);
+const WRITER_WAITING: usize = 1 << (usize::BITS - 2);
+const READERS_MASK: usize = !(WRITER | WRITER_WAITING);
+
+/// A reader-writer lock, similar to
+/// [`std::sync::RwLock`](https://doc.rust-lang.org/std/sync/struct.RwLock.html).
+///
+/// This type of lock allows a number of readers or at most one writer at any
+/// point in time. When the lock is not available, the current task will block
+/// and be put into the wait queue. When the last reader or the writer releases
+/// the lock, all tasks waiting on the queue will be woken up.
+///
+/// Writers are preferred: once a writer is blocked, new readers can not
+/// acquire the lock until a writer gets it, so that writers are not starved
+/// by a continuous stream of readers.
+pub struct RwLock<T: ?Sized> {
+    wq: WaitQueue,
+    // the highest bit indicates whether a writer holds the lock, the second
+    // highest bit indicates whether a writer is blocked, and the rest is the
+    // number of readers.
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+/// A guard that provides immutable data access.
+///
+/// When the guard falls out of scope it will decrement the read count,
+/// potentially releasing the lock.
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    data: *const T,
+}
+
+/// A guard that provides mutable data access.
+///
+/// When the guard falls out of scope it will release the lock.
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    data: *mut T,
+}
+
+// Same unsafe impls as `std::sync::RwLock`
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Creates a new [`RwLock`] wrapping the supplied data.
+    #[inline(always)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`RwLock`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        let RwLock { data, .. } = self;
+        data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Returns the number of readers that currently hold the lock.
+    ///
+    /// This function provides no synchronization guarantees and so its result
+    /// should be considered 'out of date' the instant it is called.
+    #[inline(always)]
+    pub fn reader_count(&self) -> usize {
+        self.state.load(Ordering::Relaxed) & READERS_MASK
+    }
+
+    /// Returns the number of writers that currently hold the lock, which is
+    /// either 0 or 1.
+    ///
+    /// This function provides no synchronization guarantees and so its result
+    /// should be considered 'out of date' the instant it is called.
+    #[inline(always)]
+    pub fn writer_count(&self) -> usize {
+        (self.state.load(Ordering::Relaxed) & WRITER != 0) as usize
+    }
+
+    /// Locks this [`RwLock`] with shared read access, blocking the current
+    /// task until it can be acquired.
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        loop {
+            if let Some(guard) = self.try_read() {
+                return guard;
+            }
+            // Wait until no writer holds or waits for the lock before retrying
+            self.wq
+                .wait_until(|| self.state.load(Ordering::Relaxed) & (WRITER | WRITER_WAITING) == 0);
+        }
+    }
+
+    /// Attempts to acquire this [`RwLock`] with shared read access.
+    ///
+    /// It fails if a writer holds the lock or is blocked waiting for it.
+    #[inline(always)]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        while state & (WRITER | WRITER_WAITING) == 0 {
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(RwLockReadGuard {
+                        lock: self,
+                        data: self.data.get(),
+                    })
+                }
+                Err(s) => state = s,
+            }
+        }
+        None
+    }
+
+    /// Locks this [`RwLock`] with exclusive write access, blocking the current
+    /// task until it can be acquired.
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        loop {
+            if let Some(guard) = self.try_write() {
+                return guard;
+            }
+            // Stop new readers, and wait until the lock looks unlocked before
+            // retrying. The flag is set again if another task wins the race.
+            self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+            self.wq
+                .wait_until(|| self.state.load(Ordering::Relaxed) & !WRITER_WAITING == 0);
+        }
+    }
+
+    /// Attempts to lock this [`RwLock`] with exclusive write access.
+    #[inline(always)]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        // The lock is free if only the `WRITER_WAITING` flag is set, which is
+        // cleared on success.
+        while state & !WRITER_WAITING == 0 {
+            match self.state.compare_exchange_weak(
+                state,
+                WRITER,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(RwLockWriteGuard {
+                        lock: self,
+                        data: self.data.get(),
+                    })
+                }
+                Err(s) => state = s,
+            }
+        }
+        None
+    }
+
+    /// Force decrement the reader count.
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if there are no outstanding
+    /// [`RwLockReadGuard`]s forgotten by the current task. However, this can
+    /// be useful in some instances for exposing the lock to FFI that doesn’t
+    /// know how to deal with RAII.
+    pub unsafe fn force_read_decrement(&self) {
+        let state = self.state.fetch_sub(1, Ordering::Release);
+        assert!(
+            state & READERS_MASK != 0 && state & WRITER == 0,
+            "tried to release a reader lock which is not held"
+        );
+        if state & READERS_MASK == 1 {
+            // the last reader, wake up waiting writers.
+            self.wq.notify_all(true);
+        }
+    }
+
+    /// Force unlock exclusive write access.
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if there is no outstanding
+    /// [`RwLockWriteGuard`] forgotten by the current task. However, this can
+    /// be useful in some instances for exposing the lock to FFI that doesn’t
+    /// know how to deal with RAII.
+    pub unsafe fn force_write_unlock(&self) {
+        let state = self.state.swap(0, Ordering::Release);
+        assert_eq!(
+            state & !WRITER_WAITING,
+            WRITER,
+            "tried to release a writer lock which is not held"
+        );
+        self.wq.notify_all(true);
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the [`RwLock`] mutably, no actual locking needs
+    /// to take place -- the mutable borrow statically guarantees no locks
+    /// exist.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_read() {
+            Some(guard) => write!(f, "RwLock {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "RwLock {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for RwLockWriteGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe { self.lock.force_read_decrement() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe { self.lock.force_write_unlock() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RwLock;
+    use axtask as thread;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn readers_and_writers() {
+        let _lock = crate::tests::init_serial();
+
+        const NUM_TASKS: usize = 10;
+        const NUM_ITERS: usize = 1000;
+        static LOCK: RwLock<(usize, usize)> = RwLock::new((0, 0));
+        static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| {
+                for _ in 0..NUM_ITERS {
+                    let mut val = LOCK.write();
+                    val.0 += 1;
+                    thread::yield_now();
+                    val.1 += 1;
+                    drop(val);
+                    let val = LOCK.read();
+                    thread::yield_now();
+                    assert_eq!(val.0, val.1);
+                }
+                FINISHED.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        while FINISHED.load(Ordering::Relaxed) < NUM_TASKS {
+            thread::yield_now();
+        }
+        assert_eq!(*LOCK.read(), (NUM_TASKS * NUM_ITERS, NUM_TASKS * NUM_ITERS));
+        println!("RwLock test OK");
+    }
+
+    #[test]
+    fn writer_preferred() {
+        let _lock = crate::tests::init_serial();
+
+        static LOCK: RwLock<usize> = RwLock::new(0);
+        static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+        let reader = LOCK.read();
+        let writer = thread::spawn(|| {
+            *LOCK.write() += 1;
+            WRITTEN.store(1, Ordering::Relaxed);
+        });
+        while LOCK.writer_count() == 0 && LOCK.try_read().is_some() {
+            thread::yield_now();
+        }
+        // The writer is blocked, so new readers must wait for it.
+        assert!(LOCK.try_read().is_none());
+        assert_eq!(LOCK.reader_count(), 1);
+        assert_eq!(WRITTEN.load(Ordering::Relaxed), 0);
+
+        drop(reader);
+        assert_eq!(*LOCK.read(), 1);
+        writer.join();
+        assert_eq!(WRITTEN.load(Ordering::Relaxed), 1);
+        println!("RwLock writer preference test OK");
+    }
+}
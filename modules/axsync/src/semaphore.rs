@@ -0,0 +1,160 @@
+//! A counting semaphore built on the wait queue.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axtask::WaitQueue;
+
+/// A counting, blocking semaphore.
+///
+/// Semaphores are a form of atomic counter where access is only granted if
+/// the counter is a positive value. Each acquisition will block the current
+/// task until the counter is positive, and then decrement it. Each release
+/// will increment the counter and wake up one blocked task.
+pub struct Semaphore {
+    wq: WaitQueue,
+    count: AtomicUsize,
+}
+
+/// An RAII guard which will release a resource acquired from a semaphore when
+/// dropped.
+pub struct SemaphoreGuard<'a> {
+    sem: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the initial count specified.
+    pub const fn new(count: usize) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            count: AtomicUsize::new(count),
+        }
+    }
+
+    /// Returns the current count of the semaphore.
+    ///
+    /// This function provides no synchronization guarantees and so its result
+    /// should be considered 'out of date' the instant it is called.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Acquires a resource of this semaphore, blocking the current task until
+    /// it can do so.
+    ///
+    /// This method will block until the internal count of the semaphore is
+    /// positive, and then decrement it.
+    pub fn acquire(&self) {
+        if !self.try_acquire() {
+            self.wq.wait_until(|| self.try_acquire());
+        }
+    }
+
+    /// Tries to acquire a resource of this semaphore without blocking.
+    ///
+    /// Returns `true` if the internal count was positive and is decremented.
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.load(Ordering::Relaxed);
+        while count > 0 {
+            match self.count.compare_exchange_weak(
+                count,
+                count - 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(c) => count = c,
+            }
+        }
+        false
+    }
+
+    /// Acquires a resource of this semaphore, blocking the current task until
+    /// it can do so or the given duration has elapsed.
+    ///
+    /// Returns `true` if a resource is acquired.
+    ///
+    /// If the `irq` feature is not enabled, the timeout is ignored and it
+    /// blocks until a resource is acquired.
+    pub fn acquire_timeout(&self, dur: Duration) -> bool {
+        if self.try_acquire() {
+            return true;
+        }
+        #[cfg(feature = "irq")]
+        return !self.wq.wait_timeout_until(dur, || self.try_acquire());
+        #[cfg(not(feature = "irq"))]
+        {
+            let _ = dur;
+            self.wq.wait_until(|| self.try_acquire());
+            true
+        }
+    }
+
+    /// Releases a resource of this semaphore.
+    ///
+    /// This will increment the internal count of the semaphore, and wake up
+    /// one task waiting for it.
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+
+    /// Acquires a resource of this semaphore, returning an RAII guard to
+    /// release the semaphore when dropped.
+    pub fn access(&self) -> SemaphoreGuard {
+        self.acquire();
+        SemaphoreGuard { sem: self }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+impl<'a> Drop for SemaphoreGuard<'a> {
+    fn drop(&mut self) {
+        self.sem.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Semaphore;
+    use axtask as thread;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn limit_concurrency() {
+        let _lock = crate::tests::init_serial();
+
+        const NUM_TASKS: usize = 10;
+        const MAX_CONCURRENCY: usize = 3;
+        static SEM: Semaphore = Semaphore::new(MAX_CONCURRENCY);
+        static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+        static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| {
+                for _ in 0..100 {
+                    let _guard = SEM.access();
+                    let active = ACTIVE.fetch_add(1, Ordering::Relaxed) + 1;
+                    assert!(active <= MAX_CONCURRENCY);
+                    thread::yield_now();
+                    ACTIVE.fetch_sub(1, Ordering::Relaxed);
+                }
+                FINISHED.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        while FINISHED.load(Ordering::Relaxed) < NUM_TASKS {
+            thread::yield_now();
+        }
+        assert_eq!(SEM.count(), MAX_CONCURRENCY);
+        println!("Semaphore test OK");
+    }
+}
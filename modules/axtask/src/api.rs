@@ -2,12 +2,24 @@
 
 use alloc::{string::String, sync::Arc};
 
-pub(crate) use crate::run_queue::{AxRunQueue, RUN_QUEUE};
+pub(crate) use crate::run_queue::current_run_queue;
 
 #[doc(cfg(feature = "multitask"))]
-pub use crate::task::{CurrentTask, TaskId, TaskInner};
+pub use crate::cpumask::CpuMask;
+#[doc(cfg(feature = "multitask"))]
+pub use crate::registry::{all_tasks, find_task};
+#[doc(cfg(feature = "multitask"))]
+pub use crate::stats::TaskStats;
+#[doc(cfg(feature = "multitask"))]
+pub use crate::task::{CurrentTask, TaskId, TaskInfo, TaskInner, TaskState};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::wait_queue::WaitQueue;
+#[doc(cfg(feature = "sched_edf"))]
+#[cfg(feature = "sched_edf")]
+pub use scheduler::EDFParams;
+#[doc(cfg(feature = "sched_rt"))]
+#[cfg(feature = "sched_rt")]
+pub use scheduler::RTPolicy;
 
 /// The reference type of a task.
 pub type AxTaskRef = Arc<AxTask>;
@@ -17,13 +29,26 @@ cfg_if::cfg_if! {
         const MAX_TIME_SLICE: usize = 5;
         pub(crate) type AxTask = scheduler::RRTask<TaskInner, MAX_TIME_SLICE>;
         pub(crate) type Scheduler = scheduler::RRScheduler<TaskInner, MAX_TIME_SLICE>;
+        pub(crate) const DEFAULT_PRIORITY: isize = 0;
     } else if #[cfg(feature = "sched_cfs")] {
         pub(crate) type AxTask = scheduler::CFSTask<TaskInner>;
         pub(crate) type Scheduler = scheduler::CFScheduler<TaskInner>;
+        pub(crate) const DEFAULT_PRIORITY: isize = 0;
+    } else if #[cfg(feature = "sched_rt")] {
+        const MAX_TIME_SLICE: usize = 5;
+        pub(crate) type AxTask = scheduler::RTTask<TaskInner, MAX_TIME_SLICE>;
+        pub(crate) type Scheduler = scheduler::RTScheduler<TaskInner, MAX_TIME_SLICE>;
+        // New tasks run at the lowest real-time priority.
+        pub(crate) const DEFAULT_PRIORITY: isize = scheduler::RT_PRIO_LEVELS as isize - 1;
+    } else if #[cfg(feature = "sched_edf")] {
+        pub(crate) type AxTask = scheduler::EDFTask<TaskInner>;
+        pub(crate) type Scheduler = scheduler::EDFScheduler<TaskInner>;
+        pub(crate) const DEFAULT_PRIORITY: isize = 0;
     } else {
         // If no scheduler features are set, use FIFO as the default.
         pub(crate) type AxTask = scheduler::FifoTask<TaskInner>;
         pub(crate) type Scheduler = scheduler::FifoScheduler<TaskInner>;
+        pub(crate) const DEFAULT_PRIORITY: isize = 0;
     }
 }
 
@@ -84,18 +109,43 @@ pub fn init_scheduler_secondary() {
 #[doc(cfg(feature = "irq"))]
 pub fn on_timer_tick() {
     crate::timers::check_events();
-    RUN_QUEUE.lock().scheduler_timer_tick();
+    current_run_queue().scheduler_timer_tick();
 }
 
 /// Spawns a new task with the given parameters.
 ///
+/// The task is placed on the least loaded CPU, and stays there unless it is
+/// stolen by another idle CPU.
+///
 /// Returns the task reference.
 pub fn spawn_raw<F>(f: F, name: String, stack_size: usize) -> AxTaskRef
 where
     F: FnOnce() + Send + 'static,
 {
+    spawn_raw_with_affinity(f, name, stack_size, CpuMask::full())
+}
+
+/// Spawns a new task with the given parameters, which is only allowed to run
+/// on the CPUs in `cpumask`.
+///
+/// Returns the task reference.
+///
+/// # Panics
+///
+/// Panics if `cpumask` is empty.
+pub fn spawn_raw_with_affinity<F>(
+    f: F,
+    name: String,
+    stack_size: usize,
+    cpumask: CpuMask,
+) -> AxTaskRef
+where
+    F: FnOnce() + Send + 'static,
+{
+    assert!(!cpumask.is_empty(), "empty CPU affinity");
     let task = TaskInner::new(f, name, stack_size);
-    RUN_QUEUE.lock().add_task(task.clone());
+    task.set_cpumask(cpumask);
+    crate::run_queue::spawn_task(task.clone());
     task
 }
 
@@ -112,23 +162,89 @@ where
     spawn_raw(f, "".into(), axconfig::TASK_STACK_SIZE)
 }
 
+/// Gets the scheduling statistics of all tasks (except the idle task) that
+/// ran on the given CPU, or [`None`] if the CPU is not online.
+///
+/// The [`last_cpu`](TaskStats::last_cpu) field is always `cpu_id`.
+pub fn cpu_stats(cpu_id: usize) -> Option<TaskStats> {
+    crate::run_queue::cpu_stats(cpu_id)
+}
+
 /// Set the priority for current task.
 ///
 /// The range of the priority is dependent on the underlying scheduler. For
 /// example, in the [CFS] scheduler, the priority is the nice value, ranging from
-/// -20 to 19.
+/// -20 to 19. A lower value always means a higher priority.
 ///
 /// Returns `true` if the priority is set successfully.
 ///
 /// [CFS]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
 pub fn set_priority(prio: isize) -> bool {
-    RUN_QUEUE.lock().set_current_priority(prio)
+    current_run_queue().set_current_priority(prio)
+}
+
+/// Sets the scheduling policy and the priority of the current task.
+///
+/// It's only available with the [real-time scheduler][1], where the priority
+/// ranges from 0 (the highest) to 99 (the lowest), and `policy` decides how
+/// tasks of the same priority share the CPU.
+///
+/// Returns `true` if the policy and priority are set successfully.
+///
+/// [1]: scheduler::RTScheduler
+#[cfg(feature = "sched_rt")]
+pub fn set_current_policy(policy: RTPolicy, prio: isize) -> bool {
+    current_run_queue().set_current_policy(policy, prio)
+}
+
+/// Sets the deadline parameters of the current task, or makes it a
+/// best-effort task if `params` is [`None`].
+///
+/// It's only available with the [EDF scheduler][1]. The parameters are in
+/// timer ticks. A deadline task is admitted with the bandwidth of the current
+/// CPU, and stays on it until it becomes a best-effort task again.
+///
+/// Returns `false` if `params` is invalid, or the task can not be admitted
+/// since the CPU does not have enough bandwidth left.
+///
+/// [1]: scheduler::EDFScheduler
+#[cfg(feature = "sched_edf")]
+pub fn set_current_deadline(params: Option<EDFParams>) -> bool {
+    current_run_queue().set_current_deadline(params)
+}
+
+/// Boosts the priority of the given task to `prio`, if `prio` is higher than
+/// the task's current priority (i.e., has a lower value).
+///
+/// It's used to implement priority inheritance, where the owner of a lock
+/// runs with the priority of its highest-priority waiter. The boost lasts
+/// until the task calls [`restore_priority`].
+pub fn inherit_priority(task: &AxTaskRef, prio: isize) {
+    crate::run_queue::inherit_priority(task, prio)
+}
+
+/// Restores the priority of the current task to the one set by
+/// [`set_priority`], dropping any priority inherited by [`inherit_priority`].
+pub fn restore_priority() {
+    current_run_queue().restore_current_priority()
+}
+
+/// Set the CPU affinity for current task.
+///
+/// If the current CPU is not in `cpumask`, the current task will be migrated
+/// to one of the allowed CPUs immediately.
+///
+/// Returns `true` if the affinity is set successfully, or `false` if `cpumask`
+/// is empty, or the current task is a deadline task (see
+/// [`set_current_deadline`]).
+pub fn set_current_affinity(cpumask: CpuMask) -> bool {
+    current_run_queue().set_current_affinity(cpumask)
 }
 
 /// Current task gives up the CPU time voluntarily, and switches to another
 /// ready task.
 pub fn yield_now() {
-    RUN_QUEUE.lock().yield_current();
+    current_run_queue().yield_current();
 }
 
 /// Current task is going to sleep for the given duration.
@@ -143,14 +259,27 @@ pub fn sleep(dur: core::time::Duration) {
 /// If the feature `irq` is not enabled, it uses busy-wait instead.
 pub fn sleep_until(deadline: axhal::time::TimeValue) {
     #[cfg(feature = "irq")]
-    RUN_QUEUE.lock().sleep_until(deadline);
+    current_run_queue().sleep_until(deadline);
     #[cfg(not(feature = "irq"))]
     axhal::time::busy_wait_until(deadline);
 }
 
 /// Exits the current task.
 pub fn exit(exit_code: i32) -> ! {
-    RUN_QUEUE.lock().exit_current(exit_code)
+    current_run_queue().exit_current(exit_code)
+}
+
+/// Makes the current task exit at its next safe point, i.e., when it yields
+/// or is preempted.
+///
+/// Unlike [`exit`], it can be called when the task may hold locks or be in
+/// the middle of updating shared data, e.g., in the memory allocator.
+pub fn request_exit(exit_code: i32) {
+    let curr = current();
+    curr.request_exit(exit_code);
+    // Be preempted as soon as preemption is enabled again.
+    #[cfg(feature = "preempt")]
+    curr.set_preempt_pending(true);
 }
 
 /// The idle task routine.
@@ -0,0 +1,87 @@
+use core::fmt;
+
+use axconfig::SMP;
+
+const _: () = assert!(SMP <= usize::BITS as usize, "too many CPUs for `CpuMask`");
+
+/// A set of CPUs, used as the CPU affinity of tasks.
+///
+/// It's represented as a bitmap, where the bit `i` indicates whether the CPU
+/// `i` is in the set. CPUs with IDs not less than [`axconfig::SMP`] are never
+/// in the set.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CpuMask(usize);
+
+impl CpuMask {
+    const VALID_BITS: usize = usize::MAX >> (usize::BITS as usize - SMP);
+
+    /// Creates an empty CPU set.
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Creates a CPU set contains all CPUs.
+    pub const fn full() -> Self {
+        Self(Self::VALID_BITS)
+    }
+
+    /// Creates a CPU set only contains the given CPU.
+    pub const fn one_shot(cpu_id: usize) -> Self {
+        if cpu_id < SMP {
+            Self(1 << cpu_id)
+        } else {
+            Self(0)
+        }
+    }
+
+    /// Creates a CPU set from the raw bitmap. Bits for non-existent CPUs are
+    /// ignored.
+    pub const fn from_bits(bits: usize) -> Self {
+        Self(bits & Self::VALID_BITS)
+    }
+
+    /// Returns the raw bitmap of the CPU set.
+    pub const fn bits(&self) -> usize {
+        self.0
+    }
+
+    /// Whether the given CPU is in the set.
+    pub const fn get(&self, cpu_id: usize) -> bool {
+        cpu_id < SMP && self.0 & (1 << cpu_id) != 0
+    }
+
+    /// Adds the given CPU to the set if `value` is `true`, otherwise removes
+    /// it from the set.
+    pub fn set(&mut self, cpu_id: usize, value: bool) {
+        if cpu_id < SMP {
+            if value {
+                self.0 |= 1 << cpu_id;
+            } else {
+                self.0 &= !(1 << cpu_id);
+            }
+        }
+    }
+
+    /// Whether the set contains no CPUs.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over the IDs of CPUs in the set.
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let bits = self.0;
+        (0..SMP).filter(move |&i| bits & (1 << i) != 0)
+    }
+}
+
+impl Default for CpuMask {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+impl fmt::Debug for CpuMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
@@ -4,6 +4,10 @@
 //! creation, scheduling, sleeping, termination, etc. The scheduler algorithm
 //! is configurable by cargo features.
 //!
+//! Each CPU has its own run queue. New tasks are placed on the least loaded
+//! CPU, and an idle CPU steals ready tasks from the busiest one. Tasks can be
+//! restricted to a set of CPUs by their CPU affinity (see [`CpuMask`]).
+//!
 //! # Cargo Features
 //!
 //! - `multitask`: Enable multi-task support. If it's enabled, complex task
@@ -13,6 +17,9 @@
 //!    APIs can be used, such as [`sleep`], [`sleep_until`], and
 //!    [`WaitQueue::wait_timeout`].
 //! - `preempt`: Enable preemptive scheduling.
+//! - `paging`: Put an unmapped guard page below each task stack to catch
+//!   stack overflows. Otherwise, a canary at the bottom of the stack is
+//!   checked on context switches.
 //! - `sched_fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
 //!   and it can be overriden by other scheduler features.
@@ -20,10 +27,18 @@
 //!   the `multitask` and `preempt` features if it is enabled.
 //! - `sched_cfs`: Use the [Completely Fair Scheduler][3]. It also enables the
 //!   the `multitask` and `preempt` features if it is enabled.
+//! - `sched_rt`: Use the [real-time fixed-priority scheduler][4], with 100
+//!   priority levels and FIFO or round-robin policies within a level. It also
+//!   enables the `multitask` and `preempt` features if it is enabled.
+//! - `sched_edf`: Use the [Earliest Deadline First scheduler][5] for periodic
+//!   real-time tasks. It also enables the `multitask` and `preempt` features
+//!   if it is enabled.
 //!
 //! [1]: scheduler::FifoScheduler
 //! [2]: scheduler::RRScheduler
 //! [3]: scheduler::CFScheduler
+//! [4]: scheduler::RTScheduler
+//! [5]: scheduler::EDFScheduler
 
 #![cfg_attr(not(test), no_std)]
 #![feature(doc_cfg)]
@@ -35,7 +50,10 @@ cfg_if::cfg_if! {
         extern crate log;
         extern crate alloc;
 
+        mod cpumask;
+        mod registry;
         mod run_queue;
+        mod stats;
         mod task;
         mod api;
         mod wait_queue;
@@ -0,0 +1,37 @@
+//! The global registry of all tasks.
+
+use alloc::{collections::BTreeMap, sync::Weak, vec::Vec};
+
+use spinlock::SpinNoIrq;
+
+use crate::{AxTask, AxTaskRef, TaskId};
+
+/// All tasks that are not dropped yet, indexed by the task ID.
+///
+/// It holds weak references, so that a task is removed when it's dropped,
+/// rather than when it exits.
+static TASKS: SpinNoIrq<BTreeMap<u64, Weak<AxTask>>> = SpinNoIrq::new(BTreeMap::new());
+
+pub(crate) fn register(task: &AxTaskRef) {
+    TASKS
+        .lock()
+        .insert(task.id().as_u64(), AxTaskRef::downgrade(task));
+}
+
+pub(crate) fn unregister(id: TaskId) {
+    TASKS.lock().remove(&id.as_u64());
+}
+
+/// Returns all tasks that are not dropped yet, including the exited ones,
+/// in the order of task IDs.
+///
+/// It's a snapshot of the registry, tasks created after the call are not
+/// included.
+pub fn all_tasks() -> Vec<AxTaskRef> {
+    TASKS.lock().values().filter_map(Weak::upgrade).collect()
+}
+
+/// Finds the task with the given ID, if it's not dropped yet.
+pub fn find_task(id: u64) -> Option<AxTaskRef> {
+    TASKS.lock().get(&id).and_then(Weak::upgrade)
+}
@@ -1,72 +1,293 @@
+use alloc::boxed::Box;
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axconfig::SMP;
+use kernel_guard::NoPreemptIrqSave;
 use lazy_init::LazyInit;
 use scheduler::BaseScheduler;
-use spinlock::SpinNoIrq;
+use spinlock::{SpinNoIrq, SpinRaw};
 
+use crate::stats::{StatsCounter, TaskStats};
 use crate::task::{CurrentTask, TaskState};
-use crate::{AxTaskRef, Scheduler, TaskInner, WaitQueue};
-
-// TODO: per-CPU
-pub(crate) static RUN_QUEUE: LazyInit<SpinNoIrq<AxRunQueue>> = LazyInit::new();
+use crate::{AxTaskRef, CpuMask, Scheduler, TaskInner, WaitQueue};
 
-// TODO: per-CPU
-static EXITED_TASKS: SpinNoIrq<VecDeque<AxTaskRef>> = SpinNoIrq::new(VecDeque::new());
+#[percpu::def_percpu]
+static RUN_QUEUE: LazyInit<&'static AxRunQueue> = LazyInit::new();
 
-static WAIT_FOR_EXIT: WaitQueue = WaitQueue::new();
+/// Run queues of all CPUs, indexed by the CPU ID. Used to access the run
+/// queues of remote CPUs (e.g., for task placement and work stealing).
+static RUN_QUEUES: [LazyInit<&'static AxRunQueue>; SMP] = [RUN_QUEUE_INIT; SMP];
 
-#[percpu::def_percpu]
-static IDLE_TASK: LazyInit<AxTaskRef> = LazyInit::new();
+#[allow(clippy::declare_interior_mutable_const)] // used only as the array initializer
+const RUN_QUEUE_INIT: LazyInit<&'static AxRunQueue> = LazyInit::new();
 
+/// The run queue of a CPU.
+///
+/// Each CPU has its own scheduler, idle task and garbage collection (gc) task.
+/// Tasks stay on the run queue they are placed in (and are woken up there), and
+/// only migrate when they are stolen by an idle CPU, or when their CPU affinity
+/// no longer contains the current CPU.
 pub(crate) struct AxRunQueue {
-    scheduler: Scheduler,
+    cpu_id: usize,
+    /// The scheduler is locked with both IRQs and preemption disabled. When a
+    /// context switch happens, the lock is held across the switch and released
+    /// by the next task (see [`finish_switch`]).
+    scheduler: SpinRaw<Scheduler>,
+    /// Number of tasks in `scheduler`, used for load balancing.
+    nr_ready: AtomicUsize,
+    idle_task: AxTaskRef,
+    /// The previous task which is not allowed to run on this CPU anymore. It
+    /// will be moved to another run queue after it's switched out.
+    migrating_task: SpinRaw<Option<AxTaskRef>>,
+    /// The previous task that is being switched out. Its `on_cpu` flag is
+    /// cleared after the context switch (see [`finish_switch`]).
+    prev_task: SpinRaw<Option<AxTaskRef>>,
+    exited_tasks: SpinNoIrq<VecDeque<AxTaskRef>>,
+    wait_for_exit: WaitQueue,
+    /// Statistics of all non-idle tasks that ran on this CPU.
+    stats: StatsCounter,
+    /// When the current non-idle task was switched in, or `u64::MAX` if the
+    /// CPU is idle.
+    busy_since: AtomicU64,
+}
+
+/// A reference to the run queue of the current CPU.
+///
+/// IRQs and preemption are disabled while holding it, so the current task
+/// can not migrate to other CPUs.
+pub(crate) struct CurrentRunQueueRef {
+    inner: &'static AxRunQueue,
+    _guard: NoPreemptIrqSave,
+}
+
+impl Deref for CurrentRunQueueRef {
+    type Target = AxRunQueue;
+    fn deref(&self) -> &Self::Target {
+        self.inner
+    }
+}
+
+/// Gets the run queue of the current CPU.
+pub(crate) fn current_run_queue() -> CurrentRunQueueRef {
+    let _guard = NoPreemptIrqSave::new();
+    // Safety: IRQs and preemption are disabled.
+    let inner = unsafe { *RUN_QUEUE.current_ref_raw().get_unchecked() };
+    CurrentRunQueueRef { inner, _guard }
+}
+
+/// Selects the run queue for the given task, which is the least loaded one
+/// in the task's CPU affinity, preferring the current CPU.
+fn select_run_queue(task: &AxTaskRef) -> &'static AxRunQueue {
+    let cpumask = task.cpumask();
+    let curr_rq = current_run_queue();
+    let mut selected = cpumask.get(curr_rq.cpu_id).then_some(curr_rq.inner);
+    for rq in RUN_QUEUES.iter().filter_map(|rq| rq.try_get()) {
+        if !cpumask.get(rq.cpu_id) {
+            continue;
+        }
+        match selected {
+            Some(s) if rq.nr_ready() >= s.nr_ready() => {}
+            _ => selected = Some(*rq),
+        }
+    }
+    selected.expect("no CPU is available for the task")
+}
+
+/// Spawns a new task into a run queue selected by [`select_run_queue`].
+pub(crate) fn spawn_task(task: AxTaskRef) {
+    let _guard = NoPreemptIrqSave::new();
+    select_run_queue(&task).add_task(task);
+}
+
+/// Wakes up a blocked task on the run queue it belongs to.
+///
+/// IRQs and preemption must be disabled before calling this function.
+pub(crate) fn unblock_task(task: AxTaskRef, resched: bool) {
+    // The task may have been put into a wait queue (or the timer list) but
+    // not switched out yet on another CPU. Wait for its context to be saved,
+    // otherwise it could be picked by a CPU while still running on another.
+    while task.is_blocked() && task.on_cpu() {
+        core::hint::spin_loop();
+    }
+    loop {
+        let rq = *RUN_QUEUES[task.cpu_id()];
+        if rq.unblock_task(&task, resched) {
+            break;
+        }
+        // The task was stolen by another CPU, try again.
+    }
+}
+
+/// Gets the scheduling statistics of the given CPU.
+pub(crate) fn cpu_stats(cpu_id: usize) -> Option<TaskStats> {
+    let rq = RUN_QUEUES.get(cpu_id)?.try_get()?;
+    let mut stats = rq.stats.snapshot(None);
+    let busy_since = rq.busy_since.load(Ordering::Relaxed);
+    if busy_since != u64::MAX {
+        // Include the current task, which has not been switched out yet.
+        let now = axhal::time::current_time_nanos();
+        stats.run_time += Duration::from_nanos(now.saturating_sub(busy_since));
+    }
+    stats.last_cpu = cpu_id;
+    Some(stats)
+}
+
+/// Boosts the priority of the given task on the run queue it belongs to.
+pub(crate) fn inherit_priority(task: &AxTaskRef, prio: isize) {
+    let _guard = NoPreemptIrqSave::new();
+    loop {
+        let rq = *RUN_QUEUES[task.cpu_id()];
+        if rq.inherit_priority(task, prio) {
+            break;
+        }
+        // The task was migrated to another CPU, try again.
+    }
 }
 
 impl AxRunQueue {
-    pub fn new() -> SpinNoIrq<Self> {
-        let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE);
-        let mut scheduler = Scheduler::new();
-        scheduler.add_task(gc_task);
-        SpinNoIrq::new(Self { scheduler })
+    fn new(cpu_id: usize, idle_task: AxTaskRef) -> &'static Self {
+        let rq = Box::leak(Box::new(Self {
+            cpu_id,
+            scheduler: SpinRaw::new(Scheduler::new()),
+            nr_ready: AtomicUsize::new(0),
+            idle_task,
+            migrating_task: SpinRaw::new(None),
+            prev_task: SpinRaw::new(None),
+            exited_tasks: SpinNoIrq::new(VecDeque::new()),
+            wait_for_exit: WaitQueue::new(),
+            stats: StatsCounter::new(),
+            busy_since: AtomicU64::new(u64::MAX),
+        }));
+        rq.idle_task.set_cpu_id(cpu_id);
+
+        let gc_task = TaskInner::new(|| gc_entry(rq), "gc".into(), axconfig::TASK_STACK_SIZE);
+        // The gc task must not run on other CPUs, otherwise it may release an
+        // exited task which is still being switched out on this CPU.
+        gc_task.set_cpumask(CpuMask::one_shot(cpu_id));
+        let _guard = NoPreemptIrqSave::new();
+        rq.add_task(gc_task);
+        rq
+    }
+
+    /// Returns the number of ready tasks in the run queue.
+    pub fn nr_ready(&self) -> usize {
+        self.nr_ready.load(Ordering::Relaxed)
     }
 
-    pub fn add_task(&mut self, task: AxTaskRef) {
-        debug!("task spawn: {}", task.id_name());
+    pub fn add_task(&self, task: AxTaskRef) {
+        debug!("task spawn: {} on CPU {}", task.id_name(), self.cpu_id);
         assert!(task.is_ready());
-        self.scheduler.add_task(task);
+        task.set_cpu_id(self.cpu_id);
+        task.stats_counter()
+            .mark_ready(axhal::time::current_time_nanos());
+        let mut scheduler = self.scheduler.lock();
+        scheduler.add_task(task);
+        self.update_nr_ready(&scheduler);
     }
 
     #[cfg(feature = "irq")]
-    pub fn scheduler_timer_tick(&mut self) {
+    pub fn scheduler_timer_tick(&self) {
         let curr = crate::current();
-        if !curr.is_idle() && self.scheduler.task_tick(curr.as_task_ref()) {
+        let mut scheduler = self.scheduler.lock();
+        if !curr.is_idle() && scheduler.task_tick(curr.as_task_ref()) {
             #[cfg(feature = "preempt")]
             curr.set_preempt_pending(true);
         }
+        #[cfg(feature = "sched_edf")]
+        if curr.is_idle() {
+            scheduler.idle_tick();
+        }
+        // Throttled tasks may be released by the tick.
+        self.update_nr_ready(&scheduler);
     }
 
-    pub fn yield_current(&mut self) {
+    pub fn yield_current(&self) {
         let curr = crate::current();
         trace!("task yield: {}", curr.id_name());
         assert!(curr.is_running());
+        if let Some(exit_code) = curr.exit_requested() {
+            self.exit_current(exit_code);
+        }
         self.resched(false);
     }
 
-    pub fn set_current_priority(&mut self, prio: isize) -> bool {
-        self.scheduler
-            .set_priority(crate::current().as_task_ref(), prio)
+    pub fn set_current_priority(&self, prio: isize) -> bool {
+        let curr = crate::current();
+        if self.scheduler.lock().set_priority(curr.as_task_ref(), prio) {
+            curr.set_base_priority(prio);
+            curr.set_priority(prio);
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(feature = "sched_rt")]
+    pub fn set_current_policy(&self, policy: crate::RTPolicy, prio: isize) -> bool {
+        if self.set_current_priority(prio) {
+            let curr = crate::current();
+            debug!("task set policy: {}, {:?}", curr.id_name(), policy);
+            curr.as_task_ref().set_policy(policy);
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(feature = "sched_edf")]
+    pub fn set_current_deadline(&self, params: Option<scheduler::EDFParams>) -> bool {
+        let curr = crate::current();
+        debug!("task set deadline: {}, {:?}", curr.id_name(), params);
+        // The task is pinned to this CPU while it has the parameters, see
+        // `set_current_affinity`.
+        self.scheduler.lock().set_params(curr.as_task_ref(), params)
+    }
+
+    pub fn restore_current_priority(&self) {
+        let curr = crate::current();
+        let base = curr.base_priority();
+        if curr.priority() != base && self.scheduler.lock().set_priority(curr.as_task_ref(), base) {
+            debug!("task restore priority: {}, prio={}", curr.id_name(), base);
+            curr.set_priority(base);
+        }
+    }
+
+    pub fn set_current_affinity(&self, cpumask: CpuMask) -> bool {
+        if cpumask.is_empty() {
+            return false;
+        }
+        let curr = crate::current();
+        // Deadline tasks are admitted with the bandwidth of this CPU.
+        #[cfg(feature = "sched_edf")]
+        if curr.as_task_ref().params().is_some() {
+            return false;
+        }
+        debug!(
+            "task set affinity: {}, cpumask={:?}",
+            curr.id_name(),
+            cpumask
+        );
+        curr.set_cpumask(cpumask);
+        if !cpumask.get(self.cpu_id) {
+            // migrate to another CPU immediately.
+            self.resched(false);
+        }
+        true
     }
 
     #[cfg(feature = "preempt")]
-    pub fn preempt_resched(&mut self) {
+    pub fn preempt_resched(&self) {
         let curr = crate::current();
         assert!(curr.is_running());
 
-        // When we get the mutable reference of the run queue, we must
-        // have held the `SpinNoIrq` lock with both IRQs and preemption
-        // disabled. So we need to set `current_disable_count` to 1 in
-        // `can_preempt()` to obtain the preemption permission before
-        //  locking the run queue.
+        // When we get the reference of the run queue, we must have both IRQs
+        // and preemption disabled. So we need to set `current_disable_count`
+        // to 1 in `can_preempt()` to obtain the preemption permission before
+        // getting the run queue.
         let can_preempt = curr.can_preempt(1);
 
         debug!(
@@ -75,31 +296,39 @@ impl AxRunQueue {
             can_preempt
         );
         if can_preempt {
+            if let Some(exit_code) = curr.exit_requested() {
+                self.exit_current(exit_code);
+            }
             self.resched(true);
         } else {
             curr.set_preempt_pending(true);
         }
     }
 
-    pub fn exit_current(&mut self, exit_code: i32) -> ! {
+    pub fn exit_current(&self, exit_code: i32) -> ! {
         let curr = crate::current();
         debug!("task exit: {}, exit_code={}", curr.id_name(), exit_code);
         assert!(curr.is_running());
         assert!(!curr.is_idle());
         if curr.is_init() {
-            EXITED_TASKS.lock().clear();
+            for rq in RUN_QUEUES.iter().filter_map(|rq| rq.try_get()) {
+                rq.exited_tasks.lock().clear();
+            }
             axhal::misc::terminate();
         } else {
             curr.set_state(TaskState::Exited);
-            curr.notify_exit(exit_code, self);
-            EXITED_TASKS.lock().push_back(curr.clone());
-            WAIT_FOR_EXIT.notify_one_locked(false, self);
+            curr.notify_exit(exit_code);
+            // Give the bandwidth of the deadline task back.
+            #[cfg(feature = "sched_edf")]
+            self.scheduler.lock().set_params(curr.as_task_ref(), None);
+            self.exited_tasks.lock().push_back(curr.clone());
+            self.wait_for_exit.notify_one(false);
             self.resched(false);
         }
         unreachable!("task exited!");
     }
 
-    pub fn block_current<F>(&mut self, wait_queue_push: F)
+    pub fn block_current<F>(&self, wait_queue_push: F)
     where
         F: FnOnce(AxTaskRef),
     {
@@ -117,20 +346,46 @@ impl AxRunQueue {
         self.resched(false);
     }
 
-    pub fn unblock_task(&mut self, task: AxTaskRef, resched: bool) {
-        debug!("task unblock: {}", task.id_name());
+    /// Wakes up the given task if it belongs to this run queue. Returns
+    /// `false` if it belongs to another run queue.
+    fn unblock_task(&self, task: &AxTaskRef, resched: bool) -> bool {
+        let mut scheduler = self.scheduler.lock();
+        // The CPU ID of a task can only be changed with the scheduler of its
+        // run queue locked, so it's stable here.
+        if task.cpu_id() != self.cpu_id {
+            return false;
+        }
+        debug!("task unblock: {} on CPU {}", task.id_name(), self.cpu_id);
         if task.is_blocked() {
             task.set_state(TaskState::Ready);
-            self.scheduler.add_task(task); // TODO: priority
-            if resched {
+            task.stats_counter()
+                .mark_ready(axhal::time::current_time_nanos());
+            scheduler.add_task(task.clone()); // TODO: priority
+            self.update_nr_ready(&scheduler);
+            if resched && self.cpu_id == axhal::cpu::this_cpu_id() {
                 #[cfg(feature = "preempt")]
                 crate::current().set_preempt_pending(true);
             }
         }
+        true
+    }
+
+    /// Boosts the priority of the given task to `prio` if it belongs to this
+    /// run queue. Returns `false` if it belongs to another run queue.
+    fn inherit_priority(&self, task: &AxTaskRef, prio: isize) -> bool {
+        let mut scheduler = self.scheduler.lock();
+        if task.cpu_id() != self.cpu_id {
+            return false;
+        }
+        if prio < task.priority() && scheduler.set_priority(task, prio) {
+            debug!("task inherit priority: {}, prio={}", task.id_name(), prio);
+            task.set_priority(prio);
+        }
+        true
     }
 
     #[cfg(feature = "irq")]
-    pub fn sleep_until(&mut self, deadline: axhal::time::TimeValue) {
+    pub fn sleep_until(&self, deadline: axhal::time::TimeValue) {
         let curr = crate::current();
         debug!("task sleep: {}, deadline={:?}", curr.id_name(), deadline);
         assert!(curr.is_running());
@@ -138,8 +393,9 @@ impl AxRunQueue {
 
         let now = axhal::time::current_time();
         if now < deadline {
-            crate::timers::set_alarm_wakeup(deadline, curr.clone());
+            // Block first, so that the alarm can not fire on a running task.
             curr.set_state(TaskState::Blocked);
+            crate::timers::set_alarm_wakeup(deadline, curr.clone());
             self.resched(false);
         }
     }
@@ -148,22 +404,80 @@ impl AxRunQueue {
 impl AxRunQueue {
     /// Common reschedule subroutine. If `preempt`, keep current task's time
     /// slice, otherwise reset it.
-    fn resched(&mut self, preempt: bool) {
+    fn resched(&self, preempt: bool) {
         let prev = crate::current();
+        let mut scheduler = self.scheduler.lock();
         if prev.is_running() {
             prev.set_state(TaskState::Ready);
-            if !prev.is_idle() {
-                self.scheduler.put_prev_task(prev.clone(), preempt);
+            if prev.is_idle() {
+                // the idle task is never in the scheduler.
+            } else if prev.cpumask().get(self.cpu_id) {
+                scheduler.put_prev_task(prev.clone(), preempt);
+            } else {
+                *self.migrating_task.lock() = Some(prev.clone());
             }
         }
-        let next = self.scheduler.pick_next_task().unwrap_or_else(|| unsafe {
-            // Safety: IRQs must be disabled at this time.
-            IDLE_TASK.current_ref_raw().get_unchecked().clone()
-        });
+        let next = scheduler.pick_next_task();
+        self.update_nr_ready(&scheduler);
+        let next = next
+            .or_else(|| self.steal_task())
+            .unwrap_or_else(|| self.idle_task.clone());
+        // The lock will be released by the next task after the context switch,
+        // which may be running on another CPU than the one it was switched out.
+        core::mem::forget(scheduler);
         self.switch_to(prev, next);
+        unsafe { finish_switch() };
+    }
+
+    /// Publishes the number of ready tasks of the locked scheduler, which is
+    /// read by other CPUs without locking it.
+    fn update_nr_ready(&self, scheduler: &Scheduler) {
+        self.nr_ready.store(scheduler.nr_ready(), Ordering::Relaxed);
+    }
+
+    /// Steals a ready task from the busiest run queue of other CPUs.
+    ///
+    /// The scheduler of this run queue must be locked.
+    fn steal_task(&self) -> Option<AxTaskRef> {
+        let victim = RUN_QUEUES
+            .iter()
+            .filter_map(|rq| rq.try_get())
+            .filter(|rq| rq.cpu_id != self.cpu_id && rq.nr_ready() > 0)
+            .max_by_key(|rq| rq.nr_ready())?;
+        // Use `try_lock` to avoid deadlocks when two CPUs steal from each other.
+        let mut scheduler = victim.scheduler.try_lock()?;
+        // Tasks still being switched out on the victim CPU can not be run here.
+        let task = scheduler.steal_task(|t| t.cpumask().get(self.cpu_id) && !t.on_cpu())?;
+        victim.update_nr_ready(&scheduler);
+        debug!(
+            "task steal: {} from CPU {} to CPU {}",
+            task.id_name(),
+            victim.cpu_id,
+            self.cpu_id
+        );
+        task.set_cpu_id(self.cpu_id);
+        Some(task)
+    }
+
+    /// Updates the statistics of both tasks and this CPU on a context switch.
+    fn account_switch(&self, prev_task: &CurrentTask, next_task: &AxTaskRef) {
+        let now = axhal::time::current_time_nanos();
+        // A task that is still ready was preempted or yielded.
+        let voluntary = !prev_task.is_ready();
+        let ran = prev_task.stats_counter().switch_out(now, voluntary);
+        let waited = next_task.stats_counter().switch_in(now, self.cpu_id);
+        if !prev_task.is_idle() {
+            self.stats.add_run(ran, voluntary);
+        }
+        if next_task.is_idle() {
+            self.busy_since.store(u64::MAX, Ordering::Relaxed);
+        } else {
+            self.stats.add_wait(waited);
+            self.busy_since.store(now, Ordering::Relaxed);
+        }
     }
 
-    fn switch_to(&mut self, prev_task: CurrentTask, next_task: AxTaskRef) {
+    fn switch_to(&self, prev_task: CurrentTask, next_task: AxTaskRef) {
         trace!(
             "context switch: {} -> {}",
             prev_task.id_name(),
@@ -175,29 +489,54 @@ impl AxRunQueue {
         if prev_task.ptr_eq(&next_task) {
             return;
         }
+        self.account_switch(&prev_task, &next_task);
+        #[cfg(not(feature = "paging"))]
+        prev_task.check_stack_canary();
 
         unsafe {
             let prev_ctx_ptr = prev_task.ctx_mut_ptr();
             let next_ctx_ptr = next_task.ctx_mut_ptr();
 
-            // The strong reference count of `prev_task` will be decremented by 1,
-            // but won't be dropped until `gc_entry()` is called.
             assert!(Arc::strong_count(prev_task.as_task_ref()) > 1);
             assert!(Arc::strong_count(&next_task) >= 1);
 
-            CurrentTask::set_current(prev_task, next_task);
+            // Keep the reference of `prev_task` until its context is saved.
+            let prev_task = CurrentTask::set_current(prev_task, next_task);
+            *self.prev_task.lock() = Some(prev_task);
             (*prev_ctx_ptr).switch_to(&*next_ctx_ptr);
         }
     }
 }
 
-fn gc_entry() {
+/// Marks the previous task as no longer on the CPU, and releases the
+/// scheduler lock of the current CPU, which was held by the previous task
+/// across the context switch. Then moves the previous task to another run
+/// queue if it needs to migrate.
+///
+/// # Safety
+///
+/// It must be called right after the context switch, with IRQs disabled.
+pub(crate) unsafe fn finish_switch() {
+    let rq = *RUN_QUEUE.current_ref_raw().get_unchecked();
+    let prev_task = rq.prev_task.lock().take();
+    if let Some(prev_task) = &prev_task {
+        prev_task.set_on_cpu(false);
+    }
+    rq.scheduler.force_unlock();
+    let migrating_task = rq.migrating_task.lock().take();
+    if let Some(task) = migrating_task {
+        debug!("task migrate: {} from CPU {}", task.id_name(), rq.cpu_id);
+        select_run_queue(&task).add_task(task);
+    }
+}
+
+fn gc_entry(rq: &'static AxRunQueue) {
     loop {
         // Drop all exited tasks and recycle resources.
-        let n = EXITED_TASKS.lock().len();
+        let n = rq.exited_tasks.lock().len();
         for _ in 0..n {
             // Do not do the slow drops in the critical section.
-            let task = EXITED_TASKS.lock().pop_front();
+            let task = rq.exited_tasks.lock().pop_front();
             if let Some(task) = task {
                 if Arc::strong_count(&task) == 1 {
                     // If I'm the last holder of the task, drop it immediately.
@@ -205,29 +544,43 @@ fn gc_entry() {
                 } else {
                     // Otherwise (e.g, `switch_to` is not compeleted, held by the
                     // joiner, etc), push it back and wait for them to drop first.
-                    EXITED_TASKS.lock().push_back(task);
+                    rq.exited_tasks.lock().push_back(task);
                 }
             }
         }
-        WAIT_FOR_EXIT.wait();
+        rq.wait_for_exit.wait();
     }
 }
 
+fn init_run_queue(cpu_id: usize, idle_task: AxTaskRef) {
+    let rq = AxRunQueue::new(cpu_id, idle_task);
+    RUN_QUEUES[cpu_id].init_by(rq);
+    RUN_QUEUE.with_current(|r| r.init_by(rq));
+}
+
 pub(crate) fn init() {
+    let cpu_id = axhal::cpu::this_cpu_id();
+
     const IDLE_TASK_STACK_SIZE: usize = 4096;
     let idle_task = TaskInner::new(|| crate::run_idle(), "idle".into(), IDLE_TASK_STACK_SIZE);
-    IDLE_TASK.with_current(|i| i.init_by(idle_task.clone()));
 
     let main_task = TaskInner::new_init("main".into());
     main_task.set_state(TaskState::Running);
+    main_task.set_cpu_id(cpu_id);
 
-    RUN_QUEUE.init_by(AxRunQueue::new());
+    init_run_queue(cpu_id, idle_task);
+    RUN_QUEUES[cpu_id]
+        .busy_since
+        .store(axhal::time::current_time_nanos(), Ordering::Relaxed);
     unsafe { CurrentTask::init_current(main_task) }
 }
 
 pub(crate) fn init_secondary() {
+    let cpu_id = axhal::cpu::this_cpu_id();
+
     let idle_task = TaskInner::new_init("idle".into());
     idle_task.set_state(TaskState::Running);
-    IDLE_TASK.with_current(|i| i.init_by(idle_task.clone()));
+
+    init_run_queue(cpu_id, idle_task.clone());
     unsafe { CurrentTask::init_current(idle_task) }
 }
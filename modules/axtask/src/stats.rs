@@ -0,0 +1,96 @@
+//! Scheduling statistics of tasks and CPUs.
+
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+/// Scheduling statistics of a task, or of all tasks that ran on a CPU.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskStats {
+    /// Total time spent running.
+    pub run_time: Duration,
+    /// Total time spent in the ready queue, waiting for a CPU.
+    pub wait_time: Duration,
+    /// Number of voluntary context switches, where the task blocked or
+    /// exited.
+    pub nvcsw: u64,
+    /// Number of involuntary context switches, where the task was preempted
+    /// or yielded while it's still runnable.
+    pub nivcsw: u64,
+    /// The CPU that the task ran on most recently.
+    pub last_cpu: usize,
+}
+
+/// Counters behind [`TaskStats`], updated on context switches.
+pub(crate) struct StatsCounter {
+    run_ns: AtomicU64,
+    wait_ns: AtomicU64,
+    nvcsw: AtomicU64,
+    nivcsw: AtomicU64,
+    last_cpu: AtomicUsize,
+    /// When the task was last switched in, switched out or woken up.
+    stamp_ns: AtomicU64,
+}
+
+impl StatsCounter {
+    pub const fn new() -> Self {
+        Self {
+            run_ns: AtomicU64::new(0),
+            wait_ns: AtomicU64::new(0),
+            nvcsw: AtomicU64::new(0),
+            nivcsw: AtomicU64::new(0),
+            last_cpu: AtomicUsize::new(0),
+            stamp_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Records that the task becomes ready at `now`.
+    pub fn mark_ready(&self, now: u64) {
+        self.stamp_ns.store(now, Ordering::Relaxed);
+    }
+
+    /// Records that the task is switched in on `cpu_id` at `now`. Returns the
+    /// time it waited in the ready queue.
+    pub fn switch_in(&self, now: u64, cpu_id: usize) -> u64 {
+        let waited = now.saturating_sub(self.stamp_ns.swap(now, Ordering::Relaxed));
+        self.last_cpu.store(cpu_id, Ordering::Relaxed);
+        self.add_wait(waited);
+        waited
+    }
+
+    /// Records that the task is switched out at `now`. Returns the time it
+    /// ran since it was switched in.
+    pub fn switch_out(&self, now: u64, voluntary: bool) -> u64 {
+        let ran = now.saturating_sub(self.stamp_ns.swap(now, Ordering::Relaxed));
+        self.add_run(ran, voluntary);
+        ran
+    }
+
+    pub fn add_run(&self, ran: u64, voluntary: bool) {
+        self.run_ns.fetch_add(ran, Ordering::Relaxed);
+        if voluntary {
+            self.nvcsw.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.nivcsw.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn add_wait(&self, waited: u64) {
+        self.wait_ns.fetch_add(waited, Ordering::Relaxed);
+    }
+
+    /// Returns a snapshot of the counters. If `running_at` is given, the time
+    /// since the task was switched in is counted as run time.
+    pub fn snapshot(&self, running_at: Option<u64>) -> TaskStats {
+        let mut run_ns = self.run_ns.load(Ordering::Relaxed);
+        if let Some(now) = running_at {
+            run_ns += now.saturating_sub(self.stamp_ns.load(Ordering::Relaxed));
+        }
+        TaskStats {
+            run_time: Duration::from_nanos(run_ns),
+            wait_time: Duration::from_nanos(self.wait_ns.load(Ordering::Relaxed)),
+            nvcsw: self.nvcsw.load(Ordering::Relaxed),
+            nivcsw: self.nivcsw.load(Ordering::Relaxed),
+            last_cpu: self.last_cpu.load(Ordering::Relaxed),
+        }
+    }
+}
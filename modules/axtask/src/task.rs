@@ -1,18 +1,18 @@
 use alloc::{boxed::Box, string::String, sync::Arc};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
+use core::sync::atomic::{
+    AtomicBool, AtomicI32, AtomicIsize, AtomicU64, AtomicU8, AtomicUsize, Ordering,
+};
 use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
 
-#[cfg(feature = "preempt")]
-use core::sync::atomic::AtomicUsize;
-
 #[cfg(feature = "tls")]
 use axhal::tls::TlsArea;
 
 use axhal::arch::TaskContext;
 use memory_addr::{align_up_4k, VirtAddr};
 
-use crate::{AxRunQueue, AxTask, AxTaskRef, WaitQueue};
+use crate::stats::{StatsCounter, TaskStats};
+use crate::{AxTask, AxTaskRef, CpuMask, WaitQueue};
 
 /// A unique identifier for a thread.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -21,13 +21,53 @@ pub struct TaskId(u64);
 /// The possible states of a task.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub(crate) enum TaskState {
+pub enum TaskState {
+    /// The task is running on a CPU.
     Running = 1,
+    /// The task is in a run queue, waiting for a CPU.
     Ready = 2,
+    /// The task is waiting for an event, e.g., in a wait queue or sleeping.
     Blocked = 3,
+    /// The task has exited, but is not dropped yet.
     Exited = 4,
 }
 
+impl TaskState {
+    /// Returns the name of the state in lower case.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Ready => "ready",
+            Self::Blocked => "blocked",
+            Self::Exited => "exited",
+        }
+    }
+}
+
+/// A snapshot of the information of a task, see [`TaskInner::info`].
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    /// The task ID.
+    pub id: u64,
+    /// The task name.
+    pub name: String,
+    /// The task state.
+    pub state: TaskState,
+    /// The effective priority of the task.
+    pub priority: isize,
+    /// The CPU whose run queue the task belongs to.
+    pub cpu_id: usize,
+    /// The set of CPUs the task is allowed to run on.
+    pub cpumask: CpuMask,
+    /// Size of the kernel stack in bytes, or 0 if the task runs on the boot
+    /// stack of a CPU.
+    pub stack_size: usize,
+    /// Maximum number of bytes of the kernel stack that have ever been used.
+    pub stack_used: usize,
+    /// Scheduling statistics.
+    pub stats: TaskStats,
+}
+
 /// The inner task structure.
 pub struct TaskInner {
     id: TaskId,
@@ -37,17 +77,36 @@ pub struct TaskInner {
 
     entry: Option<*mut dyn FnOnce()>,
     state: AtomicU8,
+    /// Whether the task is the current task of a CPU, including the time it's
+    /// being switched out, until its context is saved.
+    on_cpu: AtomicBool,
+
+    /// The CPU whose run queue the task belongs to.
+    cpu_id: AtomicUsize,
+    /// The set of CPUs the task is allowed to run on.
+    cpumask: AtomicUsize,
+
+    /// The priority set by [`set_priority`](crate::set_priority).
+    base_priority: AtomicIsize,
+    /// The priority used by the scheduler, which may be higher than
+    /// `base_priority` due to priority inheritance.
+    priority: AtomicIsize,
 
     in_wait_queue: AtomicBool,
     #[cfg(feature = "irq")]
     in_timer_list: AtomicBool,
 
+    stats: StatsCounter,
+
     #[cfg(feature = "preempt")]
     need_resched: AtomicBool,
     #[cfg(feature = "preempt")]
     preempt_disable_count: AtomicUsize,
 
     exit_code: AtomicI32,
+    /// Set by [`request_exit`](crate::request_exit), `exit_code` holds the
+    /// requested one.
+    exit_requested: AtomicBool,
     wait_for_exit: WaitQueue,
 
     kstack: Option<TaskStack>,
@@ -96,6 +155,64 @@ impl TaskInner {
         self.name.as_str()
     }
 
+    /// Gets the set of CPUs the task is allowed to run on.
+    pub fn cpumask(&self) -> CpuMask {
+        CpuMask::from_bits(self.cpumask.load(Ordering::Acquire))
+    }
+
+    /// Gets the effective priority of the task.
+    ///
+    /// A lower value means a higher priority. It's the same as the one set by
+    /// [`set_priority`](crate::set_priority), unless the task is boosted by
+    /// [`inherit_priority`](crate::inherit_priority).
+    pub fn priority(&self) -> isize {
+        self.priority.load(Ordering::Acquire)
+    }
+
+    /// Gets the scheduling statistics of the task.
+    ///
+    /// If the task is running, the time since it was switched in is included
+    /// in the run time.
+    pub fn stats(&self) -> TaskStats {
+        let running_at = self.is_running().then(axhal::time::current_time_nanos);
+        self.stats.snapshot(running_at)
+    }
+
+    /// Gets a snapshot of the information of the task.
+    pub fn info(&self) -> TaskInfo {
+        let (stack_size, stack_used) = self
+            .kstack
+            .as_ref()
+            .map_or((0, 0), |s| (s.size(), s.used()));
+        TaskInfo {
+            id: self.id.as_u64(),
+            name: self.name.clone(),
+            state: self.state(),
+            priority: self.priority(),
+            cpu_id: self.cpu_id(),
+            cpumask: self.cpumask(),
+            stack_size,
+            stack_used,
+            stats: self.stats(),
+        }
+    }
+
+    /// Returns the bottom of the kernel stack if it's protected by a guard
+    /// page, otherwise returns 0.
+    #[cfg(feature = "paging")]
+    pub(crate) fn stack_limit(&self) -> usize {
+        self.kstack.as_ref().map_or(0, |s| s.limit())
+    }
+
+    /// Panics if the kernel stack has overflowed, i.e., the canary at its
+    /// bottom is overwritten.
+    #[cfg(not(feature = "paging"))]
+    pub(crate) fn check_stack_canary(&self) {
+        if self.kstack.as_ref().is_some_and(|s| !s.canary_intact()) {
+            panic!("stack overflow in {}", self.id_name());
+        }
+    }
+
     /// Get a combined string of the task ID and name.
     pub fn id_name(&self) -> alloc::string::String {
         alloc::format!("Task({}, {:?})", self.id.as_u64(), self.name)
@@ -121,14 +238,21 @@ impl TaskInner {
             is_init: false,
             entry: None,
             state: AtomicU8::new(TaskState::Ready as u8),
+            on_cpu: AtomicBool::new(false),
+            cpu_id: AtomicUsize::new(0),
+            cpumask: AtomicUsize::new(CpuMask::full().bits()),
+            base_priority: AtomicIsize::new(crate::api::DEFAULT_PRIORITY),
+            priority: AtomicIsize::new(crate::api::DEFAULT_PRIORITY),
             in_wait_queue: AtomicBool::new(false),
             #[cfg(feature = "irq")]
             in_timer_list: AtomicBool::new(false),
+            stats: StatsCounter::new(),
             #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
             preempt_disable_count: AtomicUsize::new(0),
             exit_code: AtomicI32::new(0),
+            exit_requested: AtomicBool::new(false),
             wait_for_exit: WaitQueue::new(),
             kstack: None,
             ctx: UnsafeCell::new(TaskContext::new()),
@@ -157,7 +281,9 @@ impl TaskInner {
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        let task = Arc::new(AxTask::new(t));
+        crate::registry::register(&task);
+        task
     }
 
     /// Creates an "init task" using the current CPU states, to use as the
@@ -174,11 +300,14 @@ impl TaskInner {
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        let task = Arc::new(AxTask::new(t));
+        crate::registry::register(&task);
+        task
     }
 
+    /// Gets the state of the task.
     #[inline]
-    pub(crate) fn state(&self) -> TaskState {
+    pub fn state(&self) -> TaskState {
         self.state.load(Ordering::Acquire).into()
     }
 
@@ -202,6 +331,16 @@ impl TaskInner {
         matches!(self.state(), TaskState::Blocked)
     }
 
+    #[inline]
+    pub(crate) fn on_cpu(&self) -> bool {
+        self.on_cpu.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_on_cpu(&self, on_cpu: bool) {
+        self.on_cpu.store(on_cpu, Ordering::Release)
+    }
+
     #[inline]
     pub(crate) const fn is_init(&self) -> bool {
         self.is_init
@@ -212,6 +351,41 @@ impl TaskInner {
         self.is_idle
     }
 
+    #[inline]
+    pub(crate) fn stats_counter(&self) -> &StatsCounter {
+        &self.stats
+    }
+
+    #[inline]
+    pub(crate) fn cpu_id(&self) -> usize {
+        self.cpu_id.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_cpu_id(&self, cpu_id: usize) {
+        self.cpu_id.store(cpu_id, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn set_cpumask(&self, cpumask: CpuMask) {
+        self.cpumask.store(cpumask.bits(), Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn base_priority(&self) -> isize {
+        self.base_priority.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_base_priority(&self, prio: isize) {
+        self.base_priority.store(prio, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn set_priority(&self, prio: isize) {
+        self.priority.store(prio, Ordering::Release)
+    }
+
     #[inline]
     pub(crate) fn in_wait_queue(&self) -> bool {
         self.in_wait_queue.load(Ordering::Acquire)
@@ -243,6 +417,13 @@ impl TaskInner {
     #[inline]
     #[cfg(feature = "preempt")]
     pub(crate) fn can_preempt(&self, current_disable_count: usize) -> bool {
+        // The guards of `kernel_guard` are no-ops in user-mode (e.g., unit
+        // tests), so they are not counted.
+        let current_disable_count = if cfg!(target_os = "none") {
+            current_disable_count
+        } else {
+            0
+        };
         self.preempt_disable_count.load(Ordering::Acquire) == current_disable_count
     }
 
@@ -265,16 +446,31 @@ impl TaskInner {
     fn current_check_preempt_pending() {
         let curr = crate::current();
         if curr.need_resched.load(Ordering::Acquire) && curr.can_preempt(0) {
-            let mut rq = crate::RUN_QUEUE.lock();
+            let rq = crate::current_run_queue();
             if curr.need_resched.load(Ordering::Acquire) {
                 rq.preempt_resched();
             }
         }
     }
 
-    pub(crate) fn notify_exit(&self, exit_code: i32, rq: &mut AxRunQueue) {
+    pub(crate) fn request_exit(&self, exit_code: i32) {
+        self.exit_code.store(exit_code, Ordering::Release);
+        self.exit_requested.store(true, Ordering::Release);
+    }
+
+    /// Returns the requested exit code if the task should exit at its next
+    /// safe point.
+    pub(crate) fn exit_requested(&self) -> Option<i32> {
+        if self.exit_requested.load(Ordering::Acquire) {
+            Some(self.exit_code.load(Ordering::Acquire))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn notify_exit(&self, exit_code: i32) {
         self.exit_code.store(exit_code, Ordering::Release);
-        self.wait_for_exit.notify_all_locked(false, rq);
+        self.wait_for_exit.notify_all(false);
     }
 
     #[inline]
@@ -296,34 +492,161 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        crate::registry::unregister(self.id);
     }
 }
 
+/// Size of the unmapped guard page below each task stack.
+#[cfg(feature = "paging")]
+const GUARD_SIZE: usize = memory_addr::PAGE_SIZE_4K;
+
+/// Magic number at the bottom of each task stack. It's checked on context
+/// switches to detect stack overflows.
+#[cfg(not(feature = "paging"))]
+const STACK_CANARY: usize = 0x5a5a_c3c3_5a5a_c3c3_u64 as usize;
+
 struct TaskStack {
     ptr: NonNull<u8>,
     layout: Layout,
+    /// Flags of the guard page before it's unmapped, or [`None`] if the guard
+    /// page is still mapped.
+    #[cfg(feature = "paging")]
+    guard_flags: Option<axhal::paging::MappingFlags>,
 }
 
 impl TaskStack {
+    /// Allocates a zero-filled stack, so that [`TaskStack::used`] can find
+    /// the deepest position ever written.
+    ///
+    /// The stack is allocated with a guard page below it, which is unmapped
+    /// from the kernel page table (if it's set up) to catch overflows.
+    #[cfg(feature = "paging")]
     pub fn alloc(size: usize) -> Self {
-        let layout = Layout::from_size_align(size, 16).unwrap();
+        use axhal::paging::{kernel_page_table, PageSize};
+
+        let layout = Layout::from_size_align(size, memory_addr::PAGE_SIZE_4K).unwrap();
+        let num_pages = (size + GUARD_SIZE) / memory_addr::PAGE_SIZE_4K;
+        let base = axalloc::global_allocator()
+            .alloc_pages(num_pages, memory_addr::PAGE_SIZE_4K)
+            .expect("failed to allocate task stack");
+        let ptr = NonNull::new((base + GUARD_SIZE) as *mut u8).unwrap();
+        unsafe { core::ptr::write_bytes(ptr.as_ptr(), 0, size) };
+
+        // Split the huge page containing the guard page first, so that only
+        // the guard page is unmapped. Other CPUs may still hold stale TLB
+        // entries of it, they are flushed before running the task (see
+        // `flush_stack_guard`).
+        let guard = VirtAddr::from(base);
+        let guard_flags = kernel_page_table().and_then(|pt| {
+            let mut pt = pt.lock();
+            pt.split_huge(guard).ok()?;
+            match pt.query(guard) {
+                Ok((_, flags, PageSize::Size4K)) if pt.unmap(guard).is_ok() => Some(flags),
+                _ => None,
+            }
+        });
+        if guard_flags.is_some() {
+            axhal::arch::flush_tlb(Some(guard));
+        }
         Self {
-            ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
+            ptr,
             layout,
+            guard_flags,
         }
     }
 
+    /// Allocates a zero-filled stack, so that [`TaskStack::used`] can find
+    /// the deepest position ever written.
+    ///
+    /// A canary is placed at the bottom of the stack to detect overflows.
+    #[cfg(not(feature = "paging"))]
+    pub fn alloc(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, 16).unwrap();
+        let ptr = NonNull::new(unsafe { alloc::alloc::alloc_zeroed(layout) }).unwrap();
+        unsafe { (ptr.as_ptr() as *mut usize).write(STACK_CANARY) };
+        Self { ptr, layout }
+    }
+
+    pub const fn size(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Returns the maximum stack usage in bytes, by scanning for the first
+    /// non-zero word from the bottom.
+    pub fn used(&self) -> usize {
+        let words = unsafe {
+            core::slice::from_raw_parts(
+                self.ptr.as_ptr() as *const usize,
+                self.size() / core::mem::size_of::<usize>(),
+            )
+        };
+        // Skip the canary at the bottom.
+        #[cfg(not(feature = "paging"))]
+        let words = &words[1..];
+        let unused = words.iter().take_while(|&&w| w == 0).count();
+        self.size() - unused * core::mem::size_of::<usize>()
+    }
+
+    /// Returns the bottom of the stack if it's protected by a guard page,
+    /// otherwise returns 0.
+    #[cfg(feature = "paging")]
+    pub fn limit(&self) -> usize {
+        if self.guard_flags.is_some() {
+            self.ptr.as_ptr() as usize
+        } else {
+            0
+        }
+    }
+
+    /// Returns whether the canary at the bottom of the stack is intact, i.e.,
+    /// the stack has not overflowed.
+    #[cfg(not(feature = "paging"))]
+    pub fn canary_intact(&self) -> bool {
+        unsafe { (self.ptr.as_ptr() as *const usize).read() == STACK_CANARY }
+    }
+
     pub const fn top(&self) -> VirtAddr {
         unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
     }
 }
 
 impl Drop for TaskStack {
+    #[cfg(feature = "paging")]
+    fn drop(&mut self) {
+        let base = self.ptr.as_ptr() as usize - GUARD_SIZE;
+        if let Some(flags) = self.guard_flags {
+            // Restore the linear mapping of the guard page.
+            let paddr = axhal::mem::virt_to_phys(base.into());
+            axhal::paging::kernel_page_table()
+                .unwrap()
+                .lock()
+                .map(base.into(), paddr, axhal::paging::PageSize::Size4K, flags)
+                .expect("failed to map the guard page of task stack");
+        }
+        let num_pages = (self.size() + GUARD_SIZE) / memory_addr::PAGE_SIZE_4K;
+        axalloc::global_allocator().dealloc_pages(base, num_pages);
+    }
+
+    #[cfg(not(feature = "paging"))]
     fn drop(&mut self) {
         unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
     }
 }
 
+/// Flushes the TLB entry of the guard page below the stack limit on the
+/// current CPU.
+///
+/// The guard page is unmapped on the CPU that allocated the stack, other CPUs
+/// may still hold stale TLB entries of it (or of the huge page it was split
+/// from). Flushing it on every CPU before running the task acts as a lazy TLB
+/// shootdown, so that overflows are always caught.
+#[cfg(feature = "paging")]
+fn flush_stack_guard(limit: usize) {
+    if limit != 0 {
+        axhal::arch::flush_tlb(Some(VirtAddr::from(limit - GUARD_SIZE)));
+    }
+}
+
 use core::mem::ManuallyDrop;
 
 /// A wrapper of [`AxTaskRef`] as the current task.
@@ -359,15 +682,29 @@ impl CurrentTask {
     pub(crate) unsafe fn init_current(init_task: AxTaskRef) {
         #[cfg(feature = "tls")]
         axhal::arch::write_thread_pointer(init_task.tls.tls_ptr() as usize);
+        #[cfg(feature = "paging")]
+        {
+            axhal::cpu::set_current_stack_limit(init_task.stack_limit());
+            flush_stack_guard(init_task.stack_limit());
+        }
+        init_task.set_on_cpu(true);
         let ptr = Arc::into_raw(init_task);
         axhal::cpu::set_current_task_ptr(ptr);
     }
 
-    pub(crate) unsafe fn set_current(prev: Self, next: AxTaskRef) {
-        let Self(arc) = prev;
-        ManuallyDrop::into_inner(arc); // `call Arc::drop()` to decrease prev task reference count.
+    /// Sets `next` as the current task, and returns the reference of `prev`
+    /// held by the CPU.
+    pub(crate) unsafe fn set_current(prev: Self, next: AxTaskRef) -> AxTaskRef {
+        #[cfg(feature = "paging")]
+        {
+            axhal::cpu::set_current_stack_limit(next.stack_limit());
+            flush_stack_guard(next.stack_limit());
+        }
+        next.set_on_cpu(true);
         let ptr = Arc::into_raw(next);
         axhal::cpu::set_current_task_ptr(ptr);
+        let Self(arc) = prev;
+        ManuallyDrop::into_inner(arc)
     }
 }
 
@@ -380,7 +717,7 @@ impl Deref for CurrentTask {
 
 extern "C" fn task_entry() -> ! {
     // release the lock that was implicitly held across the reschedule
-    unsafe { crate::RUN_QUEUE.force_unlock() };
+    unsafe { crate::run_queue::finish_switch() };
     #[cfg(feature = "irq")]
     axhal::arch::enable_irqs();
     let task = crate::current();
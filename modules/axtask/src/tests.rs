@@ -128,3 +128,23 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_request_exit() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static REACHED: AtomicUsize = AtomicUsize::new(0);
+    let task = axtask::spawn_raw(
+        || {
+            axtask::request_exit(128 + 9);
+            REACHED.fetch_add(1, Ordering::Relaxed);
+            axtask::yield_now();
+            REACHED.fetch_add(1, Ordering::Relaxed);
+        },
+        "request_exit".into(),
+        0x1000,
+    );
+    assert_eq!(task.join(), Some(128 + 9));
+    assert_eq!(REACHED.load(Ordering::Relaxed), 1);
+}
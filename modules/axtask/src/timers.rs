@@ -4,7 +4,8 @@ use lazy_init::LazyInit;
 use spinlock::SpinNoIrq;
 use timer_list::{TimeValue, TimerEvent, TimerList};
 
-use crate::{AxTaskRef, RUN_QUEUE};
+use crate::run_queue::unblock_task;
+use crate::AxTaskRef;
 
 // TODO: per-CPU
 static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<TaskWakeupEvent>>> = LazyInit::new();
@@ -13,9 +14,9 @@ struct TaskWakeupEvent(AxTaskRef);
 
 impl TimerEvent for TaskWakeupEvent {
     fn callback(self, _now: TimeValue) {
-        let mut rq = RUN_QUEUE.lock();
+        let _guard = kernel_guard::NoPreemptIrqSave::new();
         self.0.set_in_timer_list(false);
-        rq.unblock_task(self.0, true);
+        unblock_task(self.0, true);
     }
 }
 
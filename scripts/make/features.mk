@@ -15,11 +15,15 @@ ifeq ($(APP_TYPE),c)
   ax_feat_prefix := axfeat/
   lib_feat_prefix := axlibc/
   lib_features := fp_simd alloc multitask fs net fd pipe select epoll
+  # Opt-in library features, which are only enabled when listed in `FEATURES`
+  # or `features.txt` of the app, and never implied by other features.
+  lib_opt_features := mmap
 else
   # TODO: it's better to use `axfeat/` as `ax_feat_prefix`, but all apps need to have `axfeat` as a dependency
   ax_feat_prefix := axstd/
   lib_feat_prefix := axstd/
   lib_features :=
+  lib_opt_features :=
 endif
 
 override FEATURES := $(shell echo $(FEATURES) | tr ',' ' ')
@@ -52,8 +56,8 @@ ifeq ($(shell test $(SMP) -gt 1; echo $$?),0)
   lib_feat += smp
 endif
 
-ax_feat += $(filter-out $(lib_features),$(FEATURES))
-lib_feat += $(filter $(lib_features),$(FEATURES))
+ax_feat += $(filter-out $(lib_features) $(lib_opt_features),$(FEATURES))
+lib_feat += $(filter $(lib_features) $(lib_opt_features),$(FEATURES))
 
 AX_FEAT := $(strip $(addprefix $(ax_feat_prefix),$(ax_feat)))
 LIB_FEAT := $(strip $(addprefix $(lib_feat_prefix),$(lib_feat)))
@@ -2,6 +2,8 @@
 #include <stdio.h>
 #include <sys/mman.h>
 
+#ifndef AX_CONFIG_MMAP
+
 // TODO:
 void *mmap(void *addr, size_t len, int prot, int flags, int fildes, off_t off)
 {
@@ -16,16 +18,15 @@ int munmap(void *addr, size_t length)
     return 0;
 }
 
-// TODO:
-void *mremap(void *old_address, size_t old_size, size_t new_size, int flags,
-             ... /* void *new_address */)
+// TODO
+int mprotect(void *addr, size_t len, int prot)
 {
     unimplemented();
-    return NULL;
+    return 0;
 }
 
 // TODO
-int mprotect(void *addr, size_t len, int prot)
+int msync(void *addr, size_t length, int flags)
 {
     unimplemented();
     return 0;
@@ -37,3 +38,13 @@ int madvise(void *addr, size_t len, int advice)
     unimplemented();
     return 0;
 }
+
+#endif // AX_CONFIG_MMAP
+
+// TODO:
+void *mremap(void *old_address, size_t old_size, size_t new_size, int flags,
+             ... /* void *new_address */)
+{
+    unimplemented();
+    return NULL;
+}
@@ -21,7 +21,14 @@ void srandom(unsigned int s)
 
 void *calloc(size_t m, size_t n)
 {
+    if (n && m > SIZE_MAX / n) {
+        errno = ENOMEM;
+        return 0;
+    }
+
     void *mem = malloc(m * n);
+    if (!mem)
+        return 0;
 
     return memset(mem, 0, n * m);
 }
@@ -34,6 +41,8 @@ void *realloc(void *memblock, size_t size)
     size_t o_size = *(size_t *)(memblock - 8);
 
     void *mem = malloc(size);
+    if (!mem)
+        return 0;
 
     for (int i = 0; i < (o_size < size ? o_size : size); i++)
         ((char *)mem)[i] = ((char *)memblock)[i];
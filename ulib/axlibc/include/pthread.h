@@ -2,6 +2,7 @@
 #define _PTHREAD_H
 
 #include <features.h>
+#include <sched.h>
 #include <time.h>
 
 #define PTHREAD_CANCEL_ENABLE  0
@@ -15,12 +16,29 @@ typedef struct {
     unsigned __attr;
 } pthread_condattr_t;
 
+#include <ax_pthread_barrier.h>
+#include <ax_pthread_cond.h>
 #include <ax_pthread_mutex.h>
+#include <ax_pthread_rwlock.h>
 
 typedef struct {
     unsigned __attr;
 } pthread_mutexattr_t;
 
+typedef struct {
+    unsigned __attr;
+} pthread_rwlockattr_t;
+
+typedef struct {
+    unsigned __attr;
+} pthread_barrierattr_t;
+
+#define PTHREAD_BARRIER_SERIAL_THREAD (-1)
+
+#define PTHREAD_PRIO_NONE    0
+#define PTHREAD_PRIO_INHERIT 1
+#define PTHREAD_PRIO_PROTECT 2
+
 typedef struct {
     union {
         int __i[sizeof(long) == 8 ? 14 : 9];
@@ -32,15 +50,6 @@ typedef struct {
 #define _a_guardsize __u.__s[1]
 #define _a_stackaddr __u.__s[2]
 
-typedef struct {
-    union {
-        int __i[12];
-        volatile int __vi[12];
-        void *__p[12 * sizeof(int) / sizeof(void *)];
-    } __u;
-} pthread_cond_t;
-#define _c_clock  __u.__i[4]
-#define _c_shared __u.__p[0]
 
 typedef void *pthread_t;
 
@@ -66,13 +75,36 @@ int pthread_mutex_lock(pthread_mutex_t *);
 int pthread_mutex_unlock(pthread_mutex_t *);
 int pthread_mutex_trylock(pthread_mutex_t *);
 
+int pthread_mutexattr_init(pthread_mutexattr_t *);
+int pthread_mutexattr_destroy(pthread_mutexattr_t *);
+int pthread_mutexattr_setprotocol(pthread_mutexattr_t *, int);
+int pthread_mutexattr_getprotocol(const pthread_mutexattr_t *__restrict, int *__restrict);
+
 int pthread_setname_np(pthread_t, const char *);
+int pthread_setaffinity_np(pthread_t, size_t, const cpu_set_t *);
 
 int pthread_cond_init(pthread_cond_t *__restrict__ __cond,
                       const pthread_condattr_t *__restrict__ __cond_attr);
 int pthread_cond_signal(pthread_cond_t *__cond);
 int pthread_cond_wait(pthread_cond_t *__restrict__ __cond, pthread_mutex_t *__restrict__ __mutex);
 int pthread_cond_broadcast(pthread_cond_t *);
+int pthread_cond_timedwait(pthread_cond_t *__restrict__ __cond, pthread_mutex_t *__restrict__ __mutex,
+                           const struct timespec *__restrict__ __abstime);
+int pthread_cond_destroy(pthread_cond_t *__cond);
+
+int pthread_rwlock_init(pthread_rwlock_t *__restrict__ __rwlock,
+                        const pthread_rwlockattr_t *__restrict__ __attr);
+int pthread_rwlock_destroy(pthread_rwlock_t *__rwlock);
+int pthread_rwlock_rdlock(pthread_rwlock_t *__rwlock);
+int pthread_rwlock_tryrdlock(pthread_rwlock_t *__rwlock);
+int pthread_rwlock_wrlock(pthread_rwlock_t *__rwlock);
+int pthread_rwlock_trywrlock(pthread_rwlock_t *__rwlock);
+int pthread_rwlock_unlock(pthread_rwlock_t *__rwlock);
+
+int pthread_barrier_init(pthread_barrier_t *__restrict__ __barrier,
+                         const pthread_barrierattr_t *__restrict__ __attr, unsigned __count);
+int pthread_barrier_destroy(pthread_barrier_t *__barrier);
+int pthread_barrier_wait(pthread_barrier_t *__barrier);
 
 int pthread_attr_init(pthread_attr_t *__attr);
 int pthread_attr_getstacksize(const pthread_attr_t *__restrict__ __attr,
@@ -2,6 +2,15 @@
 #define _SCHED_H
 
 #include <stddef.h>
+#include <sys/types.h>
+
+#define SCHED_OTHER 0
+#define SCHED_FIFO  1
+#define SCHED_RR    2
+
+struct sched_param {
+    int sched_priority;
+};
 
 typedef struct cpu_set_t {
     unsigned long __bits[128 / sizeof(long)];
@@ -19,5 +28,8 @@ typedef struct cpu_set_t {
 #define CPU_ZERO(set)   CPU_ZERO_S(sizeof(cpu_set_t), set)
 
 int sched_setaffinity(pid_t, size_t, const cpu_set_t *);
+int sched_setscheduler(pid_t, int, const struct sched_param *);
+int sched_get_priority_max(int);
+int sched_get_priority_min(int);
 
 #endif // _SCHED_H
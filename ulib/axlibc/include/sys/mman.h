@@ -30,13 +30,30 @@
 #else
 #define MAP_ANONYMOUS 0x20 /* Don't use a file.  */
 #endif
-#define MAP_ANON MAP_ANONYMOUS
+#define MAP_ANON      MAP_ANONYMOUS
+#define MAP_NORESERVE 0x4000 /* Don't check for reservations.  */
+#define MAP_POPULATE  0x8000 /* Populate (prefault) pagetables.  */
+#define MAP_HUGETLB   0x40000 /* Create huge page mapping.  */
 /* When MAP_HUGETLB is set bits [26:31] encode the log2 of the huge page size.  */
 #define MAP_HUGE_SHIFT 26
 #define MAP_HUGE_MASK  0x3f
+#define MAP_HUGE_2MB   (21 << MAP_HUGE_SHIFT)
+#define MAP_HUGE_1GB   (30 << MAP_HUGE_SHIFT)
 
 #define MAP_FAILED ((void *)-1)
 
+/* Flags to `msync'.  */
+#define MS_ASYNC      1 /* Sync memory asynchronously.  */
+#define MS_SYNC       4 /* Synchronous memory sync.  */
+#define MS_INVALIDATE 2 /* Invalidate the caches.  */
+
+/* Advice to `madvise'.  */
+#define MADV_NORMAL     0 /* No further special treatment.  */
+#define MADV_RANDOM     1 /* Expect random page references.  */
+#define MADV_SEQUENTIAL 2 /* Expect sequential page references.  */
+#define MADV_WILLNEED   3 /* Will need these pages.  */
+#define MADV_DONTNEED   4 /* Don't need these pages.  */
+
 /* Flags for mremap.  */
 #define MREMAP_MAYMOVE   1
 #define MREMAP_FIXED     2
@@ -47,6 +64,7 @@ int munmap(void *addr, size_t length);
 void *mremap(void *old_address, size_t old_size, size_t new_size, int flags,
              ... /* void *new_address */);
 int mprotect(void *addr, size_t len, int prot);
+int msync(void *addr, size_t length, int flags);
 int madvise(void *addr, size_t length, int advice);
 
 #endif
@@ -0,0 +1,23 @@
+#ifndef _SYS_MOUNT_H
+#define _SYS_MOUNT_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MS_RDONLY  1
+#define MS_REMOUNT 32
+#define MS_BIND    4096
+
+#define MNT_FORCE  1
+#define MNT_DETACH 2
+
+int mount(const char *, const char *, const char *, unsigned long, const void *);
+int umount(const char *);
+int umount2(const char *, int);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // _SYS_MOUNT_H
@@ -1,7 +1,9 @@
-use core::ffi::{c_char, c_int};
+use core::ffi::{c_char, c_int, c_ulong, c_void};
 
 use arceos_posix_api::{
-    sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat,
+    sys_chmod, sys_fchmod, sys_fstat, sys_futimens, sys_getcwd, sys_link, sys_lseek, sys_lstat,
+    sys_mount, sys_open, sys_readlink, sys_rename, sys_stat, sys_symlink, sys_umount2,
+    sys_utimensat,
 };
 
 use crate::{ctypes, utils::e};
@@ -51,6 +53,43 @@ pub unsafe extern "C" fn lstat(path: *const c_char, buf: *mut ctypes::stat) -> c
     e(sys_lstat(path, buf) as _)
 }
 
+/// Change the permission bits of the file `path` to `mode`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn chmod(path: *const c_char, mode: ctypes::mode_t) -> c_int {
+    e(sys_chmod(path, mode))
+}
+
+/// Change the permission bits of the file `fd` to `mode`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn fchmod(fd: c_int, mode: ctypes::mode_t) -> c_int {
+    e(sys_fchmod(fd, mode))
+}
+
+/// Change the access and modification times of the file `path`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn utimensat(
+    dirfd: c_int,
+    path: *const c_char,
+    times: *const ctypes::timespec,
+    flags: c_int,
+) -> c_int {
+    e(sys_utimensat(dirfd, path, times, flags))
+}
+
+/// Change the access and modification times of the file `fd`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn futimens(fd: c_int, times: *const ctypes::timespec) -> c_int {
+    e(sys_futimens(fd, times))
+}
+
 /// Get the path of the current directory.
 #[no_mangle]
 pub unsafe extern "C" fn getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
@@ -65,3 +104,61 @@ pub unsafe extern "C" fn getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
 pub unsafe extern "C" fn rename(old: *const c_char, new: *const c_char) -> c_int {
     e(sys_rename(old, new))
 }
+
+/// Create a symbolic link `linkpath`, which points to `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    e(sys_symlink(target, linkpath))
+}
+
+/// Read the target of the symbolic link `path` into `buf`.
+///
+/// Return the number of bytes placed in `buf`.
+#[no_mangle]
+pub unsafe extern "C" fn readlink(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsiz: usize,
+) -> ctypes::ssize_t {
+    e(sys_readlink(path, buf, bufsiz) as _) as _
+}
+
+/// Create a hard link `new` to the file `old`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn link(old: *const c_char, new: *const c_char) -> c_int {
+    e(sys_link(old, new))
+}
+
+/// Mount the filesystem `fstype` on `source` at the directory `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn mount(
+    source: *const c_char,
+    target: *const c_char,
+    fstype: *const c_char,
+    flags: c_ulong,
+    data: *const c_void,
+) -> c_int {
+    e(sys_mount(source, target, fstype, flags, data))
+}
+
+/// Unmount the filesystem mounted at `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn umount(target: *const c_char) -> c_int {
+    e(sys_umount2(target, 0))
+}
+
+/// Unmount the filesystem mounted at `target` with `flags`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[no_mangle]
+pub unsafe extern "C" fn umount2(target: *const c_char, flags: c_int) -> c_int {
+    e(sys_umount2(target, flags))
+}
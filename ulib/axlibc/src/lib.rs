@@ -10,8 +10,11 @@
 //! - Memory
 //!     - `alloc`: Enable dynamic memory allocation.
 //!     - `tls`: Enable thread-local storage.
+//!     - `mmap`: Enable memory mapping functions ([mmap], [munmap], etc.).
 //! - Task management
 //!     - `multitask`: Enable multi-threading support.
+//!     - `sched_rt`: Use the real-time scheduler, required by `SCHED_FIFO` and
+//!       `SCHED_RR`.
 //! - Upperlayer stacks
 //!     - `fs`: Enable file system support.
 //!     - `net`: Enable networking support.
@@ -24,6 +27,8 @@
 //! [ArceOS]: https://github.com/rcore-os/arceos
 //! [select]: https://man7.org/linux/man-pages/man2/select.2.html
 //! [epoll]: https://man7.org/linux/man-pages/man7/epoll.7.html
+//! [mmap]: https://man7.org/linux/man-pages/man2/mmap.2.html
+//! [munmap]: https://man7.org/linux/man-pages/man2/munmap.2.html
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
 #![feature(doc_cfg)]
@@ -57,12 +62,16 @@ mod fs;
 mod io_mpx;
 #[cfg(feature = "alloc")]
 mod malloc;
+#[cfg(feature = "mmap")]
+mod mman;
 #[cfg(feature = "net")]
 mod net;
 #[cfg(feature = "pipe")]
 mod pipe;
 #[cfg(feature = "multitask")]
 mod pthread;
+#[cfg(feature = "multitask")]
+mod semaphore;
 #[cfg(feature = "alloc")]
 mod strftime;
 #[cfg(feature = "fp_simd")]
@@ -85,22 +94,31 @@ pub use self::io::{read, writev};
 pub use self::errno::strerror;
 pub use self::mktime::mktime;
 pub use self::rand::{rand, random, srand};
-pub use self::resource::{getrlimit, setrlimit};
+pub use self::resource::{getrlimit, getrusage, setrlimit};
 pub use self::setjmp::{longjmp, setjmp};
 pub use self::sys::sysconf;
 pub use self::time::{clock_gettime, nanosleep};
-pub use self::unistd::{abort, exit, getpid};
+pub use self::unistd::{
+    abort, exit, getpid, sched_get_priority_max, sched_get_priority_min, sched_setaffinity,
+    sched_setscheduler,
+};
 
 #[cfg(feature = "alloc")]
 pub use self::malloc::{free, malloc};
 #[cfg(feature = "alloc")]
 pub use self::strftime::strftime;
 
+#[cfg(feature = "mmap")]
+pub use self::mman::{madvise, mmap, mprotect, msync, munmap};
+
 #[cfg(feature = "fd")]
 pub use self::fd_ops::{ax_fcntl, close, dup, dup2, dup3};
 
 #[cfg(feature = "fs")]
-pub use self::fs::{ax_open, fstat, getcwd, lseek, lstat, rename, stat};
+pub use self::fs::{
+    ax_open, chmod, fchmod, fstat, futimens, getcwd, link, lseek, lstat, mount, readlink, rename,
+    stat, symlink, umount, umount2, utimensat,
+};
 
 #[cfg(feature = "net")]
 pub use self::net::{
@@ -109,9 +127,32 @@ pub use self::net::{
 };
 
 #[cfg(feature = "multitask")]
-pub use self::pthread::{pthread_create, pthread_exit, pthread_join, pthread_self};
+pub use self::pthread::{pthread_barrier_destroy, pthread_barrier_init, pthread_barrier_wait};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_cond_broadcast, pthread_cond_destroy, pthread_cond_init, pthread_cond_signal,
+    pthread_cond_timedwait, pthread_cond_wait,
+};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_create, pthread_exit, pthread_join, pthread_self, pthread_setaffinity_np,
+};
 #[cfg(feature = "multitask")]
 pub use self::pthread::{pthread_mutex_init, pthread_mutex_lock, pthread_mutex_unlock};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_mutexattr_destroy, pthread_mutexattr_getprotocol, pthread_mutexattr_init,
+    pthread_mutexattr_setprotocol,
+};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_rwlock_destroy, pthread_rwlock_init, pthread_rwlock_rdlock, pthread_rwlock_tryrdlock,
+    pthread_rwlock_trywrlock, pthread_rwlock_unlock, pthread_rwlock_wrlock,
+};
+#[cfg(feature = "multitask")]
+pub use self::semaphore::{
+    sem_destroy, sem_getvalue, sem_init, sem_post, sem_timedwait, sem_trywait, sem_wait,
+};
 
 #[cfg(feature = "pipe")]
 pub use self::pipe::pipe;
@@ -0,0 +1,52 @@
+use core::ffi::{c_int, c_void};
+
+use arceos_posix_api::{sys_madvise, sys_mmap, sys_mprotect, sys_msync, sys_munmap};
+
+use crate::{ctypes, utils::e};
+
+/// Map files or anonymous memory into the address space.
+///
+/// Return `MAP_FAILED` and set `errno` on error.
+#[no_mangle]
+pub unsafe extern "C" fn mmap(
+    addr: *mut c_void,
+    len: ctypes::size_t,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    off: ctypes::off_t,
+) -> *mut c_void {
+    let ret = sys_mmap(addr, len as _, prot, flags, fd, off);
+    // Error codes are returned as addresses in the last page.
+    match ret as isize {
+        code @ -4095..=-1 => {
+            crate::errno::set_errno(-code as c_int);
+            usize::MAX as *mut c_void // MAP_FAILED
+        }
+        _ => ret,
+    }
+}
+
+/// Remove the mappings in the address range.
+#[no_mangle]
+pub unsafe extern "C" fn munmap(addr: *mut c_void, len: ctypes::size_t) -> c_int {
+    e(sys_munmap(addr, len as _))
+}
+
+/// Change the access protections of the mappings in the address range.
+#[no_mangle]
+pub unsafe extern "C" fn mprotect(addr: *mut c_void, len: ctypes::size_t, prot: c_int) -> c_int {
+    e(sys_mprotect(addr, len as _, prot))
+}
+
+/// Write the modifications to shared file mappings back to the files.
+#[no_mangle]
+pub unsafe extern "C" fn msync(addr: *mut c_void, len: ctypes::size_t, flags: c_int) -> c_int {
+    e(sys_msync(addr, len as _, flags))
+}
+
+/// Give advice about the use of memory in the address range.
+#[no_mangle]
+pub unsafe extern "C" fn madvise(addr: *mut c_void, len: ctypes::size_t, advice: c_int) -> c_int {
+    e(sys_madvise(addr, len as _, advice))
+}
@@ -1,6 +1,6 @@
 use crate::{ctypes, utils::e};
 use arceos_posix_api as api;
-use core::ffi::{c_int, c_void};
+use core::ffi::{c_int, c_uint, c_void};
 
 /// Returns the `pthread` struct of current thread.
 #[no_mangle]
@@ -37,6 +37,51 @@ pub unsafe extern "C" fn pthread_join(
     e(api::sys_pthread_join(thread, retval))
 }
 
+/// Set the CPU affinity of the given thread.
+///
+/// Only the current thread is supported for now.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_setaffinity_np(
+    thread: ctypes::pthread_t,
+    cpusetsize: usize,
+    cpuset: *const ctypes::cpu_set_t,
+) -> c_int {
+    e(api::sys_pthread_setaffinity_np(thread, cpusetsize, cpuset))
+}
+
+/// Initialize a mutex attributes object with the default attributes.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_init(attr: *mut ctypes::pthread_mutexattr_t) -> c_int {
+    e(api::sys_pthread_mutexattr_init(attr))
+}
+
+/// Destroy a mutex attributes object.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_destroy(
+    _attr: *mut ctypes::pthread_mutexattr_t,
+) -> c_int {
+    0
+}
+
+/// Set the protocol of a mutex attributes object, `PTHREAD_PRIO_NONE` or
+/// `PTHREAD_PRIO_INHERIT`.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_setprotocol(
+    attr: *mut ctypes::pthread_mutexattr_t,
+    protocol: c_int,
+) -> c_int {
+    e(api::sys_pthread_mutexattr_setprotocol(attr, protocol))
+}
+
+/// Get the protocol of a mutex attributes object.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_getprotocol(
+    attr: *const ctypes::pthread_mutexattr_t,
+    protocol: *mut c_int,
+) -> c_int {
+    e(api::sys_pthread_mutexattr_getprotocol(attr, protocol))
+}
+
 /// Initialize a mutex.
 #[no_mangle]
 pub unsafe extern "C" fn pthread_mutex_init(
@@ -57,3 +102,133 @@ pub unsafe extern "C" fn pthread_mutex_lock(mutex: *mut ctypes::pthread_mutex_t)
 pub unsafe extern "C" fn pthread_mutex_unlock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
     e(api::sys_pthread_mutex_unlock(mutex))
 }
+
+/// Initialize a condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_init(
+    cond: *mut ctypes::pthread_cond_t,
+    attr: *const ctypes::pthread_condattr_t,
+) -> c_int {
+    e(api::sys_pthread_cond_init(cond, attr))
+}
+
+/// Destroy a condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_destroy(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_destroy(cond))
+}
+
+/// Unlock the given mutex and wait on the condition variable, then lock the
+/// mutex again.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    e(api::sys_pthread_cond_wait(cond, mutex))
+}
+
+/// Like [`pthread_cond_wait`], but gives up waiting at the absolute time
+/// `abstime`.
+///
+/// Returns `ETIMEDOUT` (rather than setting `errno`) on timeout, as callers
+/// compare the return value against it.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_timedwait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    -api::sys_pthread_cond_timedwait(cond, mutex, abstime)
+}
+
+/// Wake up one thread waiting on the condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_signal(cond))
+}
+
+/// Wake up all threads waiting on the condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_broadcast(cond))
+}
+
+/// Initialize a reader-writer lock.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_init(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    attr: *const ctypes::pthread_rwlockattr_t,
+) -> c_int {
+    e(api::sys_pthread_rwlock_init(rwlock, attr))
+}
+
+/// Destroy a reader-writer lock.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_destroy(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_destroy(rwlock))
+}
+
+/// Lock the given reader-writer lock for reading.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_rdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_rdlock(rwlock))
+}
+
+/// Try to lock the given reader-writer lock for reading.
+///
+/// Returns `EBUSY` (rather than setting `errno`) if a writer holds the lock.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_tryrdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_tryrdlock(rwlock)
+}
+
+/// Lock the given reader-writer lock for writing.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_wrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_wrlock(rwlock))
+}
+
+/// Try to lock the given reader-writer lock for writing.
+///
+/// Returns `EBUSY` (rather than setting `errno`) if it is already locked.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_trywrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    -api::sys_pthread_rwlock_trywrlock(rwlock)
+}
+
+/// Unlock the given reader-writer lock.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_unlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_unlock(rwlock))
+}
+
+/// Initialize a barrier that blocks until `count` threads are waiting on it.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrier_init(
+    barrier: *mut ctypes::pthread_barrier_t,
+    attr: *const ctypes::pthread_barrierattr_t,
+    count: c_uint,
+) -> c_int {
+    e(api::sys_pthread_barrier_init(barrier, attr, count))
+}
+
+/// Destroy a barrier.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrier_destroy(barrier: *mut ctypes::pthread_barrier_t) -> c_int {
+    e(api::sys_pthread_barrier_destroy(barrier))
+}
+
+/// Wait on the barrier until enough threads have arrived.
+///
+/// Returns `PTHREAD_BARRIER_SERIAL_THREAD` in one (arbitrary) thread, and 0
+/// in the others.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrier_wait(barrier: *mut ctypes::pthread_barrier_t) -> c_int {
+    let ret = api::sys_pthread_barrier_wait(barrier);
+    if ret == ctypes::PTHREAD_BARRIER_SERIAL_THREAD {
+        ret
+    } else {
+        e(ret)
+    }
+}
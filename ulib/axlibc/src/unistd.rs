@@ -1,4 +1,8 @@
-use arceos_posix_api::{sys_exit, sys_getpid};
+use crate::{ctypes, utils::e};
+use arceos_posix_api::{
+    sys_exit, sys_getpid, sys_sched_get_priority_max, sys_sched_get_priority_min,
+    sys_sched_setaffinity, sys_sched_setscheduler,
+};
 use core::ffi::c_int;
 
 /// Get current thread ID.
@@ -7,6 +11,44 @@ pub unsafe extern "C" fn getpid() -> c_int {
     sys_getpid()
 }
 
+/// Set the CPU affinity of the thread `pid`.
+///
+/// Only the current thread is supported, i.e., `pid` must be 0 or the ID of
+/// the current thread.
+#[no_mangle]
+pub unsafe extern "C" fn sched_setaffinity(
+    pid: c_int,
+    cpusetsize: usize,
+    mask: *const ctypes::cpu_set_t,
+) -> c_int {
+    e(sys_sched_setaffinity(pid, cpusetsize, mask))
+}
+
+/// Set the scheduling policy and priority of the thread `pid`.
+///
+/// Only the current thread is supported. `SCHED_FIFO` and `SCHED_RR` require
+/// the `sched_rt` feature.
+#[no_mangle]
+pub unsafe extern "C" fn sched_setscheduler(
+    pid: c_int,
+    policy: c_int,
+    param: *const ctypes::sched_param,
+) -> c_int {
+    e(sys_sched_setscheduler(pid, policy, param))
+}
+
+/// Get the maximum static priority of the scheduling `policy`.
+#[no_mangle]
+pub unsafe extern "C" fn sched_get_priority_max(policy: c_int) -> c_int {
+    e(sys_sched_get_priority_max(policy))
+}
+
+/// Get the minimum static priority of the scheduling `policy`.
+#[no_mangle]
+pub unsafe extern "C" fn sched_get_priority_min(policy: c_int) -> c_int {
+    e(sys_sched_get_priority_min(policy))
+}
+
 /// Abort the current process.
 #[no_mangle]
 pub unsafe extern "C" fn abort() -> ! {
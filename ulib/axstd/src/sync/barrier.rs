@@ -0,0 +1,91 @@
+//! A barrier built on the wait queue.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use arceos_api::task::{self as api, AxWaitQueueHandle};
+
+/// A barrier enables multiple threads to synchronize the beginning of some
+/// computation, similar to
+/// [`std::sync::Barrier`](https://doc.rust-lang.org/std/sync/struct.Barrier.html).
+///
+/// The barrier can be reused after all threads have rendezvoused.
+pub struct Barrier {
+    wq: AxWaitQueueHandle,
+    num_threads: usize,
+    count: AtomicUsize,
+    generation: AtomicUsize,
+}
+
+/// A `BarrierWaitResult` is returned by [`Barrier::wait()`] when all threads
+/// in the [`Barrier`] have rendezvoused.
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` if this thread is the "leader thread" for the call to
+    /// [`Barrier::wait()`].
+    ///
+    /// Only one thread will have `true` returned from their result, all other
+    /// threads will have `false` returned.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Creates a new barrier that can block a given number of threads.
+    ///
+    /// A barrier will block `n`-1 threads which call [`wait()`](Self::wait)
+    /// and then wake up all threads at once when the `n`th thread calls
+    /// [`wait()`](Self::wait).
+    pub const fn new(n: usize) -> Self {
+        Self {
+            wq: AxWaitQueueHandle::new(),
+            num_threads: n,
+            count: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Blocks the current thread until all threads have rendezvoused here.
+    ///
+    /// Barriers are re-usable after all threads have rendezvoused once, and can
+    /// be used continuously.
+    ///
+    /// A single (arbitrary) thread will receive a [`BarrierWaitResult`] that
+    /// returns `true` from [`BarrierWaitResult::is_leader()`] when returning
+    /// from this function, and all other threads will receive a result that
+    /// will return `false` from [`BarrierWaitResult::is_leader()`].
+    pub fn wait(&self) -> BarrierWaitResult {
+        let generation = self.generation.load(Ordering::Acquire);
+        if self.count.fetch_add(1, Ordering::AcqRel) + 1 >= self.num_threads {
+            // All threads of this generation have arrived, so no one else can
+            // touch `count` until the generation changes.
+            self.count.store(0, Ordering::Relaxed);
+            self.generation.fetch_add(1, Ordering::Release);
+            api::ax_wait_queue_wake(&self.wq, u32::MAX);
+            BarrierWaitResult(true)
+        } else {
+            api::ax_wait_queue_wait(
+                &self.wq,
+                || self.generation.load(Ordering::Acquire) != generation,
+                None,
+            );
+            BarrierWaitResult(false)
+        }
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Barrier").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for BarrierWaitResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BarrierWaitResult")
+            .field("is_leader", &self.is_leader())
+            .finish()
+    }
+}
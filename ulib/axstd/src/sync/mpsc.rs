@@ -0,0 +1,615 @@
+//! Multi-producer, single-consumer FIFO queue communication primitives.
+//!
+//! This module provides message-based communication over channels, similar to
+//! [`std::sync::mpsc`](https://doc.rust-lang.org/std/sync/mpsc/index.html).
+//! A channel is one of the two types:
+//!
+//! 1. An asynchronous, infinitely buffered channel created by [`channel`].
+//!    Sending on it will never block.
+//! 2. A synchronous, bounded channel created by [`sync_channel`]. Sending on
+//!    it will block until there is buffer space available. A bound of 0 makes
+//!    it a "rendezvous" channel, where each sender atomically hands off a
+//!    message to a receiver.
+//!
+//! Blocked threads are put into wait queues rather than spinning.
+
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use arceos_api::task::{self as api, AxWaitQueueHandle};
+use spinlock::SpinNoPreempt;
+
+use crate::time::Instant;
+
+struct State<T> {
+    buf: VecDeque<T>,
+    /// Total number of messages ever pushed into `buf`.
+    pushed: usize,
+    /// Total number of messages ever popped from `buf`.
+    popped: usize,
+}
+
+struct Channel<T> {
+    state: SpinNoPreempt<State<T>>,
+    /// `None` for asynchronous channels.
+    bound: Option<usize>,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+    /// Threads blocked in `recv`.
+    recv_wq: AxWaitQueueHandle,
+    /// Threads blocked in `send` of synchronous channels.
+    send_wq: AxWaitQueueHandle,
+}
+
+impl<T> Channel<T> {
+    fn new(bound: Option<usize>) -> Self {
+        Self {
+            state: SpinNoPreempt::new(State {
+                buf: VecDeque::new(),
+                pushed: 0,
+                popped: 0,
+            }),
+            bound,
+            senders: AtomicUsize::new(1),
+            receiver_alive: AtomicBool::new(true),
+            recv_wq: AxWaitQueueHandle::new(),
+            send_wq: AxWaitQueueHandle::new(),
+        }
+    }
+
+    fn is_disconnected_for_sender(&self) -> bool {
+        !self.receiver_alive.load(Ordering::Acquire)
+    }
+
+    fn is_disconnected_for_receiver(&self) -> bool {
+        self.senders.load(Ordering::Acquire) == 0
+    }
+
+    /// The maximum number of messages in the buffer. A rendezvous channel
+    /// still buffers one message, but its sender will wait for it to be
+    /// received.
+    fn capacity(&self) -> usize {
+        self.bound.map_or(usize::MAX, |bound| bound.max(1))
+    }
+
+    /// Tries to push a message into the buffer. Returns the sequence number
+    /// of the message on success.
+    fn try_push(&self, t: T) -> Result<usize, TrySendError<T>> {
+        if self.is_disconnected_for_sender() {
+            return Err(TrySendError::Disconnected(t));
+        }
+        let mut state = self.state.lock();
+        if state.buf.len() >= self.capacity() {
+            return Err(TrySendError::Full(t));
+        }
+        let seq = state.pushed;
+        state.buf.push_back(t);
+        state.pushed += 1;
+        drop(state);
+        api::ax_wait_queue_wake(&self.recv_wq, 1);
+        Ok(seq)
+    }
+
+    /// Pushes a message into the buffer, blocking while it is full.
+    fn send(&self, t: T) -> Result<(), SendError<T>> {
+        let mut t = t;
+        let seq = loop {
+            match self.try_push(t) {
+                Ok(seq) => break seq,
+                Err(TrySendError::Disconnected(t)) => return Err(SendError(t)),
+                Err(TrySendError::Full(ret)) => {
+                    t = ret;
+                    api::ax_wait_queue_wait(
+                        &self.send_wq,
+                        || {
+                            self.is_disconnected_for_sender()
+                                || self.state.lock().buf.len() < self.capacity()
+                        },
+                        None,
+                    );
+                }
+            }
+        };
+        if self.bound == Some(0) {
+            // Rendezvous: wait for the receiver to take the message.
+            api::ax_wait_queue_wait(
+                &self.send_wq,
+                || self.is_disconnected_for_sender() || self.state.lock().popped > seq,
+                None,
+            );
+            let mut state = self.state.lock();
+            if state.popped <= seq {
+                // The receiver is gone before taking the message. As the
+                // buffer holds at most one message, it must be ours.
+                let t = state.buf.pop_back().unwrap();
+                return Err(SendError(t));
+            }
+        }
+        Ok(())
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.state.lock();
+        match state.buf.pop_front() {
+            Some(t) => {
+                state.popped += 1;
+                drop(state);
+                match self.bound {
+                    // Wake up both the rendezvous sender and other senders
+                    // waiting for space.
+                    Some(0) => api::ax_wait_queue_wake(&self.send_wq, u32::MAX),
+                    Some(_) => api::ax_wait_queue_wake(&self.send_wq, 1),
+                    None => {}
+                }
+                Ok(t)
+            }
+            None if self.is_disconnected_for_receiver() => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Waits until a message is available or all senders are gone. Only the
+    /// (single) receiver pops messages, so the message is still there once
+    /// this function returns.
+    ///
+    /// Returns `true` if it has timed out.
+    fn wait_recv(&self, timeout: Option<Duration>) -> bool {
+        api::ax_wait_queue_wait(
+            &self.recv_wq,
+            || self.is_disconnected_for_receiver() || !self.state.lock().buf.is_empty(),
+            timeout,
+        )
+    }
+}
+
+/// The sending-half of an asynchronous channel created by [`channel`].
+///
+/// Messages can be sent through this channel with [`send`](Sender::send).
+/// It can be cloned to send to the same channel multiple times.
+pub struct Sender<T> {
+    inner: Arc<Channel<T>>,
+}
+
+/// The sending-half of a synchronous channel created by [`sync_channel`].
+///
+/// Messages can be sent through this channel with [`send`](SyncSender::send)
+/// or [`try_send`](SyncSender::try_send). `send` will block if there is no
+/// space in the internal buffer.
+pub struct SyncSender<T> {
+    inner: Arc<Channel<T>>,
+}
+
+/// The receiving half of a channel created by [`channel`] or
+/// [`sync_channel`].
+///
+/// Messages sent to the channel can be retrieved using [`recv`](Receiver::recv).
+/// This half can only be owned by one thread, but it can be sent to other
+/// threads.
+pub struct Receiver<T> {
+    inner: Arc<Channel<T>>,
+    // `Receiver` is `Send` but not `Sync`.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+/// Creates a new asynchronous channel, returning the sender/receiver halves.
+///
+/// All data sent on the [`Sender`] will become available on the [`Receiver`]
+/// in the same order as it was sent, and no [`send`](Sender::send) will block
+/// the calling thread. [`recv`](Receiver::recv) will block until a message is
+/// available while there is at least one [`Sender`] alive (including clones).
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Channel::new(None));
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver::new(inner),
+    )
+}
+
+/// Creates a new synchronous, bounded channel.
+///
+/// All data sent on the [`SyncSender`] will become available on the
+/// [`Receiver`] in the same order as it was sent. Like asynchronous
+/// [`channel`]s, the [`Receiver`] will block until a message becomes
+/// available. `sync_channel` differs greatly in the semantics of the sender,
+/// however.
+///
+/// This channel has an internal buffer on which messages will be queued.
+/// `bound` specifies the buffer size. When the internal buffer becomes full,
+/// future sends will *block* waiting for the buffer to open up. Note that a
+/// buffer size of 0 is valid, in which case this becomes "rendezvous channel"
+/// where each [`send`](SyncSender::send) will not return until a
+/// [`recv`](Receiver::recv) is paired with it.
+pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>) {
+    let inner = Arc::new(Channel::new(Some(bound)));
+    (
+        SyncSender {
+            inner: inner.clone(),
+        },
+        Receiver::new(inner),
+    )
+}
+
+impl<T> Sender<T> {
+    /// Attempts to send a value on this channel, returning it back if it could
+    /// not be sent.
+    ///
+    /// A successful send occurs when it is determined that the other end of
+    /// the channel has not hung up already. This method will never block the
+    /// current thread.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.inner.send(t)
+    }
+}
+
+impl<T> SyncSender<T> {
+    /// Sends a value on this synchronous channel.
+    ///
+    /// This function will *block* until space in the internal buffer becomes
+    /// available or a receiver is available to hand off the message to.
+    ///
+    /// An error will be returned if the receiver has hung up, in which case
+    /// the value is returned back.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.inner.send(t)
+    }
+
+    /// Attempts to send a value on this channel without blocking.
+    ///
+    /// This method differs from [`send`](Self::send) by returning immediately
+    /// if the channel's buffer is full or no receiver is waiting to acquire
+    /// some data.
+    pub fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        if self.inner.bound == Some(0) {
+            // A rendezvous channel is always "full" as there is no buffer to
+            // leave the message in.
+            return if self.inner.is_disconnected_for_sender() {
+                Err(TrySendError::Disconnected(t))
+            } else {
+                Err(TrySendError::Full(t))
+            };
+        }
+        self.inner.try_push(t).map(|_| ())
+    }
+}
+
+impl<T> Receiver<T> {
+    fn new(inner: Arc<Channel<T>>) -> Self {
+        Self {
+            inner,
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// Attempts to return a pending value on this receiver without blocking.
+    ///
+    /// This method will never block the caller in order to wait for data to
+    /// become available.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.inner.try_recv()
+    }
+
+    /// Attempts to wait for a value on this receiver, returning an error if
+    /// the corresponding channel has hung up.
+    ///
+    /// This function will always block the current thread if there is no data
+    /// available and it's possible for more data to be sent (at least one
+    /// sender still exists).
+    pub fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            match self.inner.try_recv() {
+                Ok(t) => return Ok(t),
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+                Err(TryRecvError::Empty) => {
+                    self.inner.wait_recv(None);
+                }
+            }
+        }
+    }
+
+    /// Attempts to wait for a value on this receiver, returning an error if
+    /// the corresponding channel has hung up, or if it waits more than
+    /// `timeout`.
+    ///
+    /// If the `irq` feature is not enabled, the timeout is ignored and it
+    /// behaves like [`recv`](Self::recv).
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.inner.try_recv() {
+                Ok(t) => return Ok(t),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {
+                    let remaining = deadline.duration_since(Instant::now());
+                    if remaining.is_zero() || self.inner.wait_recv(Some(remaining)) {
+                        // Check again in case a message arrives just in time.
+                        return self.inner.try_recv().map_err(|e| match e {
+                            TryRecvError::Empty => RecvTimeoutError::Timeout,
+                            TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator that will block waiting for messages, but never
+    /// panic. It will return `None` when the channel has hung up.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns an iterator that will attempt to yield all pending values for
+    /// a receiver, but never block waiting for new ones.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.inner.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Clone for SyncSender<T> {
+    fn clone(&self) -> Self {
+        self.inner.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+fn drop_sender<T>(inner: &Channel<T>) {
+    if inner.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+        // the last sender, wake up the receiver to see the disconnection.
+        api::ax_wait_queue_wake(&inner.recv_wq, u32::MAX);
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        drop_sender(&self.inner);
+    }
+}
+
+impl<T> Drop for SyncSender<T> {
+    fn drop(&mut self) {
+        drop_sender(&self.inner);
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.inner.receiver_alive.store(false, Ordering::Release);
+        api::ax_wait_queue_wake(&self.inner.send_wq, u32::MAX);
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Debug for SyncSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncSender").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+/// An iterator over messages on a [`Receiver`], created by
+/// [`iter`](Receiver::iter).
+#[derive(Debug)]
+pub struct Iter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+
+/// An iterator that attempts to yield all pending values for a [`Receiver`],
+/// created by [`try_iter`](Receiver::try_iter).
+#[derive(Debug)]
+pub struct TryIter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+
+/// An owning iterator over messages on a [`Receiver`], created by
+/// [`into_iter`](Receiver::into_iter).
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> Iterator for TryIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+/// An error returned from the `send` function on a channel.
+///
+/// A send operation can only fail if the receiving end of a channel is
+/// disconnected, implying that the data could never be received. The error
+/// contains the data being sent as a payload so it can be recovered.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendError<T>(pub T);
+
+/// An error returned from the `recv` function on a [`Receiver`].
+///
+/// The `recv` operation can only fail if the sending half of a channel is
+/// disconnected, implying that no further messages will ever be received.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RecvError;
+
+/// This enumeration is the list of the possible reasons that
+/// [`try_recv`](Receiver::try_recv) could not return data when called.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TryRecvError {
+    /// This channel is currently empty, but the sender(s) have not yet
+    /// disconnected, so data may yet become available.
+    Empty,
+    /// The channel's sending half has become disconnected, and there will
+    /// never be any more data received on it.
+    Disconnected,
+}
+
+/// This enumeration is the list of possible errors that made
+/// [`recv_timeout`](Receiver::recv_timeout) unable to return data when
+/// called.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RecvTimeoutError {
+    /// This channel is currently empty, but the sender(s) have not yet
+    /// disconnected, so data may yet become available.
+    Timeout,
+    /// The channel's sending half has become disconnected, and there will
+    /// never be any more data received on it.
+    Disconnected,
+}
+
+/// This enumeration is the list of the possible error outcomes for the
+/// [`try_send`](SyncSender::try_send) method.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrySendError<T> {
+    /// The data could not be sent on the [`sync_channel`] because it would
+    /// require that the callee block to send the data.
+    Full(T),
+    /// This [`sync_channel`]'s receiving half has disconnected, so the data
+    /// could not be sent. The data is returned back to the callee in this
+    /// case.
+    Disconnected(T),
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "sending on a closed channel".fmt(f)
+    }
+}
+
+impl<T> core::error::Error for SendError<T> {}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TrySendError::Full(..) => "Full(..)".fmt(f),
+            TrySendError::Disconnected(..) => "Disconnected(..)".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TrySendError::Full(..) => "sending on a full channel".fmt(f),
+            TrySendError::Disconnected(..) => "sending on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl<T> core::error::Error for TrySendError<T> {}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(err: SendError<T>) -> TrySendError<T> {
+        TrySendError::Disconnected(err.0)
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "receiving on a closed channel".fmt(f)
+    }
+}
+
+impl core::error::Error for RecvError {}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TryRecvError::Empty => "receiving on an empty channel".fmt(f),
+            TryRecvError::Disconnected => "receiving on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for TryRecvError {}
+
+impl From<RecvError> for TryRecvError {
+    fn from(_err: RecvError) -> TryRecvError {
+        TryRecvError::Disconnected
+    }
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RecvTimeoutError::Timeout => "timed out waiting on channel".fmt(f),
+            RecvTimeoutError::Disconnected => "channel is empty and sending half is closed".fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for RecvTimeoutError {}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(_err: RecvError) -> RecvTimeoutError {
+        RecvTimeoutError::Disconnected
+    }
+}
@@ -0,0 +1,112 @@
+//! A counting semaphore built on the wait queue.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use arceos_api::task::{self as api, AxWaitQueueHandle};
+
+/// A counting, blocking semaphore.
+///
+/// Semaphores are a form of atomic counter where access is only granted if
+/// the counter is a positive value. Each acquisition will block the current
+/// thread until the counter is positive, and then decrement it. Each release
+/// will increment the counter and wake up one blocked thread.
+pub struct Semaphore {
+    wq: AxWaitQueueHandle,
+    count: AtomicUsize,
+}
+
+/// An RAII guard which will release a resource acquired from a semaphore when
+/// dropped.
+pub struct SemaphoreGuard<'a> {
+    sem: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the initial count specified.
+    pub const fn new(count: usize) -> Self {
+        Self {
+            wq: AxWaitQueueHandle::new(),
+            count: AtomicUsize::new(count),
+        }
+    }
+
+    /// Returns the current count of the semaphore.
+    ///
+    /// This function provides no synchronization guarantees and so its result
+    /// should be considered 'out of date' the instant it is called.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Acquires a resource of this semaphore, blocking the current thread until
+    /// it can do so.
+    ///
+    /// This method will block until the internal count of the semaphore is
+    /// positive, and then decrement it.
+    pub fn acquire(&self) {
+        if !self.try_acquire() {
+            api::ax_wait_queue_wait(&self.wq, || self.try_acquire(), None);
+        }
+    }
+
+    /// Tries to acquire a resource of this semaphore without blocking.
+    ///
+    /// Returns `true` if the internal count was positive and is decremented.
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.load(Ordering::Relaxed);
+        while count > 0 {
+            match self.count.compare_exchange_weak(
+                count,
+                count - 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(c) => count = c,
+            }
+        }
+        false
+    }
+
+    /// Acquires a resource of this semaphore, blocking the current thread until
+    /// it can do so or the given duration has elapsed.
+    ///
+    /// Returns `true` if a resource is acquired.
+    ///
+    /// The timeout is ignored if the `irq` feature is not enabled.
+    pub fn acquire_timeout(&self, dur: Duration) -> bool {
+        self.try_acquire() || !api::ax_wait_queue_wait(&self.wq, || self.try_acquire(), Some(dur))
+    }
+
+    /// Releases a resource of this semaphore.
+    ///
+    /// This will increment the internal count of the semaphore, and wake up
+    /// one thread waiting for it.
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        api::ax_wait_queue_wake(&self.wq, 1);
+    }
+
+    /// Acquires a resource of this semaphore, returning an RAII guard to
+    /// release the semaphore when dropped.
+    pub fn access(&self) -> SemaphoreGuard {
+        self.acquire();
+        SemaphoreGuard { sem: self }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+impl<'a> Drop for SemaphoreGuard<'a> {
+    fn drop(&mut self) {
+        self.sem.release();
+    }
+}
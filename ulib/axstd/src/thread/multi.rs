@@ -9,6 +9,9 @@ use core::{cell::UnsafeCell, num::NonZeroU64};
 use arceos_api::task::{self as api, AxTaskHandle};
 use axerrno::ax_err_type;
 
+/// A set of CPUs, used as the CPU affinity of threads.
+pub use arceos_api::task::AxCpuMask as CpuMask;
+
 /// A unique identifier for a running thread.
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub struct ThreadId(NonZeroU64);
@@ -49,6 +52,8 @@ pub struct Builder {
     name: Option<String>,
     // The size of the stack for the spawned thread in bytes
     stack_size: Option<usize>,
+    // The set of CPUs that the thread is allowed to run on
+    affinity: Option<CpuMask>,
 }
 
 impl Builder {
@@ -58,6 +63,7 @@ impl Builder {
         Builder {
             name: None,
             stack_size: None,
+            affinity: None,
         }
     }
 
@@ -73,6 +79,15 @@ impl Builder {
         self
     }
 
+    /// Sets the CPU affinity for the new thread, i.e., the set of CPUs that
+    /// it is allowed to run on.
+    ///
+    /// By default, the thread can run on any CPU.
+    pub fn affinity(mut self, cpumask: CpuMask) -> Builder {
+        self.affinity = Some(cpumask);
+        self
+    }
+
     /// Spawns a new thread by taking ownership of the `Builder`, and returns an
     /// [`io::Result`] to its [`JoinHandle`].
     ///
@@ -99,6 +114,10 @@ impl Builder {
         let stack_size = self
             .stack_size
             .unwrap_or(arceos_api::config::TASK_STACK_SIZE);
+        let affinity = self.affinity.unwrap_or_else(CpuMask::full);
+        if affinity.is_empty() {
+            return Err(ax_err_type!(InvalidInput, "empty CPU affinity"));
+        }
 
         let my_packet = Arc::new(Packet {
             result: UnsafeCell::new(None),
@@ -115,7 +134,7 @@ impl Builder {
             drop(their_packet);
         };
 
-        let task = api::ax_spawn(main, name, stack_size);
+        let task = api::ax_spawn_with_affinity(main, name, stack_size, affinity);
         Ok(JoinHandle {
             thread: Thread::from_id(task.id()),
             native: task,